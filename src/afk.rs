@@ -0,0 +1,120 @@
+// Idle detection. A joined player who gives no input for AFK_TIME seconds of
+// a round is marked away, and with bots on a simple bot steers them until
+// they press something again, so Dodge doesn't stall on an empty seat.
+use crate::{events::GameEvent, Bullet, MiniGames, Player, PlayerInput};
+use raylib::prelude::*;
+
+pub const AFK_TIME: f32 = 20.0;
+// the bot jumps for a bullet that reaches it within this many seconds
+const BOT_REACTION: f32 = 0.35;
+// a wandering bot turns around this often
+const BOT_TURN_TIME: f32 = 2.5;
+
+#[derive(Debug)]
+pub struct Afk {
+    pub bots: bool,
+    idle: [f32; 4],
+    away: [bool; 4],
+    // the bot's walking direction, its turn timer and last x to spot walls
+    direction: [f32; 4],
+    turn_timer: [f32; 4],
+    last_x: [f32; 4],
+}
+
+impl Afk {
+    pub fn new(bots: bool) -> Afk {
+        Afk {
+            bots,
+            idle: [0.0; 4],
+            away: [false; 4],
+            direction: [1.0; 4],
+            turn_timer: [BOT_TURN_TIME; 4],
+            last_x: [0.0; 4],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.idle = [0.0; 4];
+        self.away = [false; 4];
+    }
+
+    pub fn is_away(&self, player: usize) -> bool {
+        self.away[player]
+    }
+
+    // Counts idle time from a player's own input; the returned event says
+    // they just went away or came back
+    pub fn update(&mut self, player: usize, input: PlayerInput, dt: f32) -> Option<GameEvent> {
+        if input != PlayerInput::default() {
+            self.idle[player] = 0.0;
+            if self.away[player] {
+                self.away[player] = false;
+                return Some(GameEvent::Returned { player });
+            }
+            return None;
+        }
+        self.idle[player] += dt;
+        if self.idle[player] >= AFK_TIME && !self.away[player] {
+            self.away[player] = true;
+            return Some(GameEvent::WentAfk { player });
+        }
+        None
+    }
+
+    // What the bot presses for an away player. In Dodge it stands still and
+    // jumps the bullets, elsewhere it wanders and hops over what blocks it.
+    pub fn bot_input(
+        &mut self,
+        game: MiniGames,
+        player: &Player,
+        bullets: &[Bullet],
+        dt: f32,
+    ) -> PlayerInput {
+        let index = player.number as usize;
+        let rect = player.get_collision_rect();
+        let threatened = bullets.iter().any(|bullet| {
+            let gap = player.position.x - (bullet.rect.x + bullet.rect.width / 2.0);
+            let arrives = gap / bullet.speed.x;
+            bullet.speed.x != 0.0
+                && arrives > 0.0
+                && arrives < BOT_REACTION
+                && bullet.rect.y < rect.y + rect.height
+                && bullet.rect.y + bullet.rect.height > rect.y
+        });
+        if game == MiniGames::Dodge {
+            return PlayerInput {
+                up: threatened,
+                ..Default::default()
+            };
+        }
+
+        // didn't get anywhere since last frame: jump, and turn around if it
+        // still doesn't help by the time the timer runs out
+        let blocked = (player.position.x - self.last_x[index]).abs() < 0.5;
+        self.last_x[index] = player.position.x;
+        self.turn_timer[index] -= dt;
+        if self.turn_timer[index] <= 0.0 {
+            self.turn_timer[index] = BOT_TURN_TIME;
+            self.direction[index] = -self.direction[index];
+        }
+        PlayerInput {
+            up: threatened || blocked,
+            left: self.direction[index] < 0.0,
+            right: self.direction[index] > 0.0,
+            ..Default::default()
+        }
+    }
+
+    // Tags away players under their feet
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player]) {
+        for (i, player) in players.iter().enumerate() {
+            if !self.away[i] {
+                continue;
+            }
+            let text = if self.bots { "BOT" } else { "AFK" };
+            let x = player.position.x as i32 - d.measure_text(text, 15) / 2;
+            let y = (player.position.y + player.height / 2.0) as i32 + 4;
+            d.draw_text(text, x, y, 15, Color::DARKGRAY);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Camera for the world during a round, driven by the minigame's arena bounds
+// and camera mode from static/minigames.json. The HUD is drawn over it with
+// the plain viewport camera, so it stays put whatever the arena does.
+use crate::{
+    minigame_config::{CameraMode, MiniGameConfig},
+    viewport::Viewport,
+    MiniGames, Player,
+};
+use raylib::prelude::*;
+
+// Room kept around the players when following them
+const FOLLOW_MARGIN: f32 = 150.0;
+const MAX_FOLLOW_ZOOM: f32 = 1.5;
+// How quickly the follow camera catches up, per second
+const FOLLOW_RATE: f32 = 4.0;
+// Zoom of the intro's pan, on top of the zoom that fits the arena
+const INTRO_ZOOM: f32 = 1.6;
+// Share of the intro spent panning, the rest settles on the round's view
+const INTRO_PAN: f32 = 0.7;
+// Zoom toward the focus point, on top of the mode's own zoom
+const FOCUS_ZOOM: f32 = 1.8;
+
+pub struct ArenaCamera {
+    pub target: Vector2,
+    pub zoom: f32,
+    // a decisive moment to zoom toward instead of following the camera mode
+    pub focus: Option<Vector2>,
+    // minigame the camera was last placed for, it snaps when this changes
+    game: Option<MiniGames>,
+}
+
+// Zoom that fits the whole arena on screen
+fn fit_zoom(bounds: Rectangle) -> f32 {
+    let size = Viewport::size();
+    (size.x / bounds.width).min(size.y / bounds.height)
+}
+
+// Keeps the view inside the arena; on an axis where the arena is smaller
+// than the view it is centred instead
+fn clamp_target(target: Vector2, zoom: f32, bounds: Rectangle) -> Vector2 {
+    let half = Viewport::size() * (0.5 / zoom);
+    let axis = |value: f32, min: f32, size: f32, half: f32| {
+        if size <= half * 2.0 {
+            min + size / 2.0
+        } else {
+            value.clamp(min + half, min + size - half)
+        }
+    };
+    Vector2::new(
+        axis(target.x, bounds.x, bounds.width, half.x),
+        axis(target.y, bounds.y, bounds.height, half.y),
+    )
+}
+
+impl ArenaCamera {
+    pub fn new() -> ArenaCamera {
+        ArenaCamera {
+            target: Viewport::size() * 0.5,
+            zoom: 1.0,
+            focus: None,
+            game: None,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        game: MiniGames,
+        config: &MiniGameConfig,
+        round_elapsed: f32,
+        players: &[Player],
+        dt: f32,
+    ) {
+        let bounds = config.bounds;
+        let center = Vector2::new(
+            bounds.x + bounds.width / 2.0,
+            bounds.y + bounds.height / 2.0,
+        );
+        let (target, zoom) = match config.camera {
+            CameraMode::Static => (center, fit_zoom(bounds)),
+            CameraMode::VerticalScroll => {
+                let zoom = Viewport::size().x / bounds.width;
+                let half_height = Viewport::size().y * 0.5 / zoom;
+                let bottom = bounds.y + bounds.height - half_height;
+                (
+                    Vector2::new(center.x, bottom - config.scroll_speed * round_elapsed),
+                    zoom,
+                )
+            }
+            CameraMode::FollowAction => {
+                let mut alive = players.iter().filter(|p| !p.dead).map(|p| p.position);
+                match alive.next() {
+                    Some(first) => {
+                        let (min, max) = alive.fold((first, first), |(min, max), p| {
+                            (
+                                Vector2::new(min.x.min(p.x), min.y.min(p.y)),
+                                Vector2::new(max.x.max(p.x), max.y.max(p.y)),
+                            )
+                        });
+                        let size = max - min + Vector2::one() * (FOLLOW_MARGIN * 2.0);
+                        let zoom = (Viewport::size().x / size.x)
+                            .min(Viewport::size().y / size.y)
+                            .clamp(fit_zoom(bounds), MAX_FOLLOW_ZOOM.max(fit_zoom(bounds)));
+                        ((min + max) * 0.5, zoom)
+                    }
+                    None => (center, fit_zoom(bounds)),
+                }
+            }
+        };
+        let (target, zoom) = match self.focus {
+            Some(focus) => (focus, zoom * FOCUS_ZOOM),
+            None => (target, zoom),
+        };
+        let target = clamp_target(target, zoom, bounds);
+        let eases = config.camera == CameraMode::FollowAction || self.focus.is_some();
+        if self.game == Some(game) && eases {
+            let t = 1.0 - (-FOLLOW_RATE * dt).exp();
+            self.target = self.target.lerp(target, t);
+            self.zoom += (zoom - self.zoom) * t;
+        } else {
+            self.target = target;
+            self.zoom = zoom;
+        }
+        self.game = Some(game);
+    }
+
+    // The match intro's flyby: pans across the arena zoomed in, then settles
+    // on the whole arena. `progress` goes from 0 to 1.
+    pub fn intro(&mut self, bounds: Rectangle, progress: f32) {
+        let smooth = |t: f32| {
+            let t = t.clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        let fit = fit_zoom(bounds);
+        let zoom = fit * INTRO_ZOOM;
+        let middle = bounds.y + bounds.height / 2.0;
+        let from = clamp_target(Vector2::new(bounds.x, middle), zoom, bounds);
+        let to = clamp_target(Vector2::new(bounds.x + bounds.width, middle), zoom, bounds);
+        let settled = clamp_target(
+            Vector2::new(bounds.x + bounds.width / 2.0, middle),
+            fit,
+            bounds,
+        );
+        if progress < INTRO_PAN {
+            self.target = from.lerp(to, smooth(progress / INTRO_PAN));
+            self.zoom = zoom;
+        } else {
+            let t = smooth((progress - INTRO_PAN) / (1.0 - INTRO_PAN));
+            self.target = to.lerp(settled, t);
+            self.zoom = zoom + (fit - zoom) * t;
+        }
+        // the round's camera mode takes over from here without easing in
+        self.game = None;
+    }
+
+    pub fn camera(&self, viewport: &Viewport) -> Camera2D {
+        viewport.world_camera(self.target, self.zoom)
+    }
+
+    // Virtual screen position (e.g. the mouse) to world position
+    pub fn virtual_to_world(&self, point: Vector2) -> Vector2 {
+        (point - Viewport::size() * 0.5) / self.zoom + self.target
+    }
+}
@@ -0,0 +1,147 @@
+// Game assets. Everything in static/ is embedded in the binary, but a file of
+// the same name under ./static/ wins, so art can be swapped without a rebuild.
+// Enabled content packs (mods.rs) win over both.
+use raylib::prelude::*;
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+pub const ASSET_DIR: &str = "./static";
+
+// enabled content pack folders, highest priority first
+static PACKS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+static EMBEDDED: &[(&str, &[u8])] = &[
+    ("level.json", include_bytes!("../static/level.json")),
+    ("level.png", include_bytes!("../static/level.png")),
+    ("minigames.json", include_bytes!("../static/minigames.json")),
+    ("player1.png", include_bytes!("../static/player1.png")),
+    ("player2.png", include_bytes!("../static/player2.png")),
+    ("player3.png", include_bytes!("../static/player3.png")),
+    ("player4.png", include_bytes!("../static/player4.png")),
+    (
+        "transition_left.png",
+        include_bytes!("../static/transition_left.png"),
+    ),
+    (
+        "transition_right.png",
+        include_bytes!("../static/transition_right.png"),
+    ),
+];
+
+fn embedded(name: &str) -> Option<&'static [u8]> {
+    EMBEDDED
+        .iter()
+        .find(|(embedded_name, _)| *embedded_name == name)
+        .map(|(_, bytes)| *bytes)
+}
+
+pub fn use_packs(dirs: Vec<PathBuf>) {
+    if PACKS.set(dirs).is_err() {
+        eprintln!("content packs were already set up");
+    }
+}
+
+fn packs() -> &'static [PathBuf] {
+    PACKS.get().map_or(&[], Vec::as_slice)
+}
+
+// Every enabled pack's copy of a static/ asset, lowest priority first
+pub fn pack_files(name: &str) -> Vec<PathBuf> {
+    packs()
+        .iter()
+        .rev()
+        .map(|dir| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+// Files in a folder of every enabled pack, top pack first
+pub fn pack_folder(folder: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in packs() {
+        let Ok(entries) = fs::read_dir(dir.join(folder)) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    files
+}
+
+// A map shipped in a pack's maps/ folder
+pub fn pack_map(name: &str) -> Option<PathBuf> {
+    packs()
+        .iter()
+        .map(|dir| dir.join("maps").join(name))
+        .find(|path| path.exists())
+}
+
+// Reads `path`; for a static/ asset the top pack's copy comes first
+pub fn read_path(path: &Path) -> Result<Cow<'static, [u8]>, String> {
+    let packed = path.strip_prefix(ASSET_DIR).ok().and_then(|name| {
+        packs()
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|p| p.exists())
+    });
+    match packed {
+        Some(packed) => fs::read(&packed)
+            .map(Cow::Owned)
+            .map_err(|err| format!("could not load {}: {}", packed.display(), err)),
+        None => read_unpacked(path),
+    }
+}
+
+// Reads `path` if it exists, otherwise the embedded asset with the same file name
+fn read_unpacked(path: &Path) -> Result<Cow<'static, [u8]>, String> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Cow::Owned(bytes)),
+        Err(err) => path
+            .file_name()
+            .and_then(|name| embedded(&name.to_string_lossy()))
+            .map(Cow::Borrowed)
+            .ok_or_else(|| format!("could not load {}: {}", path.display(), err)),
+    }
+}
+
+pub fn read(name: &str) -> Result<Cow<'static, [u8]>, String> {
+    read_path(&Path::new(ASSET_DIR).join(name))
+}
+
+pub fn read_to_string(name: &str) -> Result<String, String> {
+    let bytes = read(name)?;
+    String::from_utf8(bytes.into_owned()).map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+// The game's own copy, leaving packs out, for files packs merge into
+pub fn read_base_to_string(name: &str) -> Result<String, String> {
+    let bytes = read_unpacked(&Path::new(ASSET_DIR).join(name))?;
+    String::from_utf8(bytes.into_owned()).map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+pub fn load_image_path(path: &Path) -> Result<Image, String> {
+    let bytes = read_path(path)?;
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_else(|| ".png".to_string());
+    Image::load_image_from_mem(&extension, &bytes)
+}
+
+pub fn load_image(name: &str) -> Image {
+    load_image_path(&Path::new(ASSET_DIR).join(name)).unwrap_or_else(|err| panic!("{}", err))
+}
+
+pub fn load_texture(rl: &mut RaylibHandle, thread: &RaylibThread, name: &str) -> Texture2D {
+    rl.load_texture_from_image(thread, &load_image(name))
+        .unwrap_or_else(|err| panic!("could not upload {}: {}", name, err))
+}
@@ -0,0 +1,394 @@
+// Screenshots, result cards and the automatic end-of-round GIF clip, all
+// written to ./captures
+use raylib::prelude::*;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const CAPTURE_DIR: &str = "captures";
+const CLIP_SECONDS: f32 = 5.0;
+const CLIP_FPS: f32 = 10.0;
+const CLIP_WIDTH: i32 = 300;
+const CLIP_HEIGHT: i32 = 163;
+
+fn capture_path(prefix: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    PathBuf::from(CAPTURE_DIR).join(format!("{}-{}.{}", prefix, millis, extension))
+}
+
+pub fn save_screenshot(image: Image) {
+    save_png("screenshot", image);
+}
+
+// Writes captures/<prefix>-<millis>.png, None when the folder can't be made
+pub fn save_png(prefix: &str, image: Image) -> Option<PathBuf> {
+    let path = capture_path(prefix, "png");
+    if let Err(err) = fs::create_dir_all(CAPTURE_DIR) {
+        eprintln!("could not create {}: {}", CAPTURE_DIR, err);
+        return None;
+    }
+    image.export_image(&path.to_string_lossy());
+    eprintln!("saved {}", path.display());
+    Some(path)
+}
+
+struct ClipFrame {
+    pixels: Vec<[u8; 3]>,
+}
+
+// Keeps the last few seconds of gameplay as small frames so a round's finish
+// can be written out as a GIF once the round is decided.
+#[derive(Default)]
+pub struct ClipRecorder {
+    frames: VecDeque<ClipFrame>,
+    timer: f32,
+}
+
+impl ClipRecorder {
+    // Whether a frame should be grabbed this tick
+    pub fn due(&mut self, dt: f32) -> bool {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return false;
+        }
+        self.timer += 1.0 / CLIP_FPS;
+        if self.timer < 0.0 {
+            self.timer = 1.0 / CLIP_FPS;
+        }
+        true
+    }
+
+    pub fn push(&mut self, mut image: Image) {
+        image.resize(CLIP_WIDTH, CLIP_HEIGHT);
+        let pixels = image
+            .get_image_data()
+            .iter()
+            .map(|c| [c.r, c.g, c.b])
+            .collect();
+        self.frames.push_back(ClipFrame { pixels });
+        while self.frames.len() > (CLIP_SECONDS * CLIP_FPS) as usize {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.timer = 0.0;
+    }
+
+    // Encodes on a background thread so the results screen doesn't hitch
+    pub fn export(&mut self) {
+        if self.frames.is_empty() {
+            return;
+        }
+        let frames: Vec<ClipFrame> = self.frames.drain(..).collect();
+        let path = capture_path("round", "gif");
+        thread::spawn(move || {
+            let delay = (100.0 / CLIP_FPS) as u16;
+            let gif = encode_gif(CLIP_WIDTH as u16, CLIP_HEIGHT as u16, delay, &frames);
+            match fs::create_dir_all(CAPTURE_DIR).and_then(|_| fs::write(&path, gif)) {
+                Ok(_) => eprintln!("saved {}", path.display()),
+                Err(err) => eprintln!("could not write {}: {}", path.display(), err),
+            }
+        });
+    }
+}
+
+// 6x7x6 colour cube; plenty for the flat pastel art
+fn palette_index(rgb: [u8; 3]) -> u8 {
+    let r = (rgb[0] as u32 * 5 + 127) / 255;
+    let g = (rgb[1] as u32 * 6 + 127) / 255;
+    let b = (rgb[2] as u32 * 5 + 127) / 255;
+    (r * 42 + g * 6 + b) as u8
+}
+
+fn palette() -> Vec<u8> {
+    let mut table = Vec::with_capacity(256 * 3);
+    for r in 0..6u32 {
+        for g in 0..7u32 {
+            for b in 0..6u32 {
+                table.push((r * 255 / 5) as u8);
+                table.push((g * 255 / 6) as u8);
+                table.push((b * 255 / 5) as u8);
+            }
+        }
+    }
+    table.resize(256 * 3, 0);
+    table
+}
+
+fn encode_gif(width: u16, height: u16, delay: u16, frames: &[ClipFrame]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&[0xF7, 0, 0]); // 256 entry global colour table
+    out.extend_from_slice(&palette());
+    // loop forever
+    out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        out.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0x00, 0x00]);
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0);
+        out.push(8); // LZW minimum code size
+        let indices: Vec<u8> = frame.pixels.iter().map(|p| palette_index(*p)).collect();
+        for block in lzw_encode(&indices).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3B);
+    out
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4096;
+
+    let mut writer = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+    let mut dictionary = std::collections::HashMap::new();
+    let mut next_code: u16 = 258;
+    // the decoder adds its table entries one code later than we do, so the
+    // width is picked from next_code - 1
+    let width = |next_code: u16| -> u32 {
+        let mut size = 9;
+        while size < 12 && (next_code - 1) >= (1 << size) {
+            size += 1;
+        }
+        size
+    };
+
+    writer.write(CLEAR, 9);
+    let Some((&first, rest)) = indices.split_first() else {
+        writer.write(END, 9);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = dictionary.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, width(next_code));
+        if next_code < MAX_CODE {
+            dictionary.insert((prefix, index), next_code);
+            next_code += 1;
+        } else {
+            writer.write(CLEAR, width(next_code));
+            dictionary.clear();
+            next_code = 258;
+        }
+        prefix = index as u16;
+    }
+    writer.write(prefix, width(next_code));
+    writer.write(END, width(next_code.saturating_add(1).min(MAX_CODE)));
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads `width`-bit codes back the way lzw_encode packs them, lowest
+    // bit first
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        bit: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, width: u32) -> u16 {
+            let mut code = 0;
+            for i in 0..width as usize {
+                let byte = self.bytes[(self.bit + i) / 8];
+                code |= (((byte >> ((self.bit + i) % 8)) & 1) as u16) << i;
+            }
+            self.bit += width as usize;
+            code
+        }
+    }
+
+    // A plain GIF decoder for 8-bit minimum code size. Returns the indices
+    // and every code with the width it was read at.
+    fn lzw_decode(bytes: &[u8]) -> (Vec<u8>, Vec<(u16, u32)>, usize) {
+        let mut reader = BitReader { bytes, bit: 0 };
+        let blank = || -> Vec<Vec<u8>> {
+            (0..=255u8)
+                .map(|i| vec![i])
+                .chain([vec![], vec![]])
+                .collect()
+        };
+        let mut table = blank();
+        let mut width = 9;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        let mut codes = Vec::new();
+        loop {
+            let code = reader.read(width);
+            codes.push((code, width));
+            match code {
+                256 => {
+                    table = blank();
+                    width = 9;
+                    previous = None;
+                    continue;
+                }
+                257 => break,
+                _ => {}
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                // the one code the decoder can't know yet: previous + its
+                // own first index
+                (None, Some(previous)) => {
+                    assert_eq!(code as usize, table.len(), "code out of range");
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                (None, None) => panic!("code {} before any other", code),
+            };
+            if let Some(mut previous) = previous.take() {
+                if table.len() < 4096 {
+                    previous.push(entry[0]);
+                    table.push(previous);
+                }
+            }
+            out.extend(&entry);
+            previous = Some(entry);
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+        }
+        (out, codes, reader.bit)
+    }
+
+    // Deterministic noise over all 256 indices, so the table fills fast
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state: u32 = 12345;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        let inputs = [
+            Vec::new(),
+            vec![7],
+            vec![3; 1000],
+            (0..=255).collect(),
+            noise(300),
+        ];
+        for input in inputs {
+            let (decoded, _, _) = lzw_decode(&lzw_encode(&input));
+            assert_eq!(decoded, input);
+        }
+    }
+
+    #[test]
+    fn lzw_codes_grow_from_9_to_12_bits() {
+        let input = noise(5000);
+        let (decoded, codes, _) = lzw_decode(&lzw_encode(&input));
+        assert_eq!(decoded, input);
+        assert_eq!(codes[0], (256, 9));
+        for width in 9..=12 {
+            assert!(
+                codes.iter().any(|(_, w)| *w == width),
+                "no {}-bit codes",
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn lzw_starts_over_when_the_table_is_full() {
+        let input = noise(60_000);
+        let (decoded, codes, _) = lzw_decode(&lzw_encode(&input));
+        assert_eq!(decoded, input);
+        let clears: Vec<u32> = codes
+            .iter()
+            .filter(|(code, _)| *code == 256)
+            .map(|(_, width)| *width)
+            .collect();
+        assert!(clears.len() > 2, "{} clears", clears.len());
+        // the first clear opens the stream, the rest come with a full table
+        assert!(clears[1..].iter().all(|width| *width == 12));
+    }
+
+    #[test]
+    fn lzw_ends_with_the_end_code_and_padding() {
+        for input in [Vec::new(), vec![1, 2, 3], noise(10_000)] {
+            let bytes = lzw_encode(&input);
+            let (_, codes, bits) = lzw_decode(&bytes);
+            assert_eq!(codes.last().unwrap().0, 257);
+            // nothing after the end code but the last byte's padding
+            assert_eq!(bytes.len(), bits.div_ceil(8));
+        }
+        let (_, codes, _) = lzw_decode(&lzw_encode(&[]));
+        assert_eq!(codes, vec![(256, 9), (257, 9)]);
+    }
+
+    #[test]
+    fn gif_frames_are_framed_by_the_header_and_trailer() {
+        let frame = ClipFrame {
+            pixels: vec![[255, 0, 0]; 4 * 3],
+        };
+        let gif = encode_gif(4, 3, 10, &[frame]);
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(&gif[6..10], &[4, 0, 3, 0]);
+        assert_eq!(*gif.last().unwrap(), 0x3B);
+        // pure red is r = 5, g = 0, b = 0 in the colour cube
+        assert_eq!(palette_index([255, 0, 0]), 210);
+        assert_eq!(&palette()[210 * 3..210 * 3 + 3], &[255, 0, 0]);
+    }
+}
@@ -0,0 +1,213 @@
+// Command-line options. Any match option (--players, --map, --mode,
+// --script, --mutators, --points-to-win, --seed) skips the menus and starts
+// the match directly.
+use crate::{mutators::Mutator, MiniGames};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: project-hashem [options]
+
+  --players <2-4>         number of players
+  --map <file.json>       level layout, looked up in ./static/ if not found as given
+  --mode <name>           play only this minigame (color-the-map, dodge, floor-is-lava)
+  --script <name>         play only this scripted minigame from a content pack
+  --mutators <a,b,...>    rule tweaks for the match: LowGravity, Speedy, BigJumps,
+                          FastBullets, ScatterShot, ShortRounds
+  --points-to-win <n>     points needed to win the match (default 5)
+  --seed <n>              seed for everything random in the match
+  --fullscreen            start in fullscreen
+  --export-stats [dir]    write match stats as JSON/CSV (default ./exports) and exit
+  --golden                render the golden-image scenes, compare them to tests/golden/ and exit
+  --golden-update         render the golden-image scenes into tests/golden/ and exit
+  --dedicated [port]      host online matches without a window (default port 7777)
+  --connect <host[:port]> join an online match on a dedicated host
+  --profile <name>        the saved profile to join with, a guest without one
+  --help                  show this message";
+
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    pub players: Option<usize>,
+    pub map: Option<String>,
+    pub mode: Option<MiniGames>,
+    pub script: Option<String>,
+    pub mutators: Vec<Mutator>,
+    pub points_to_win: Option<u32>,
+    pub seed: Option<u64>,
+    pub fullscreen: bool,
+    pub export_stats: Option<PathBuf>,
+    pub golden: bool,
+    pub golden_update: bool,
+    pub dedicated: Option<u16>,
+    pub connect: Option<String>,
+    pub profile: Option<String>,
+    pub help: bool,
+}
+
+impl CliOptions {
+    pub fn parse(args: &[String]) -> Result<CliOptions, String> {
+        let mut options = CliOptions::default();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .filter(|v| !v.starts_with("--"))
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match arg.as_str() {
+                "--players" => {
+                    let count: usize = value(arg)?
+                        .parse()
+                        .map_err(|_| "--players needs a number".to_string())?;
+                    if !(2..=4).contains(&count) {
+                        return Err("--players must be between 2 and 4".to_string());
+                    }
+                    options.players = Some(count);
+                }
+                "--map" => options.map = Some(value(arg)?.clone()),
+                "--mode" => {
+                    let name = value(arg)?;
+                    options.mode = Some(
+                        MiniGames::from_cli(name)
+                            .ok_or_else(|| format!("unknown --mode {}", name))?,
+                    );
+                }
+                "--script" => options.script = Some(value(arg)?.clone()),
+                "--mutators" => {
+                    for key in value(arg)?.split(',') {
+                        let mutator = Mutator::from_key(key.trim())
+                            .ok_or_else(|| format!("unknown mutator {}", key))?;
+                        if !options.mutators.contains(&mutator) {
+                            options.mutators.push(mutator);
+                        }
+                    }
+                }
+                "--points-to-win" => {
+                    let points: u32 = value(arg)?
+                        .parse()
+                        .map_err(|_| "--points-to-win needs a number".to_string())?;
+                    if points == 0 {
+                        return Err("--points-to-win must be at least 1".to_string());
+                    }
+                    options.points_to_win = Some(points);
+                }
+                "--seed" => {
+                    options.seed = Some(
+                        value(arg)?
+                            .parse()
+                            .map_err(|_| "--seed needs a number".to_string())?,
+                    );
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--export-stats" => {
+                    let dir = match args.peek() {
+                        Some(dir) if !dir.starts_with("--") => args.next().unwrap().as_str(),
+                        _ => crate::export::EXPORT_DIR,
+                    };
+                    options.export_stats = Some(PathBuf::from(dir));
+                }
+                "--golden" => options.golden = true,
+                "--golden-update" => options.golden_update = true,
+                "--dedicated" => {
+                    options.dedicated = Some(match args.peek() {
+                        Some(port) if !port.starts_with("--") => args
+                            .next()
+                            .unwrap()
+                            .parse()
+                            .map_err(|_| "--dedicated needs a port number".to_string())?,
+                        _ => crate::dedicated::DEFAULT_PORT,
+                    });
+                }
+                "--connect" => options.connect = Some(value(arg)?.clone()),
+                "--profile" => options.profile = Some(value(arg)?.clone()),
+                "--help" | "-h" => options.help = true,
+                other => return Err(format!("unknown option {}", other)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn skips_menu(&self) -> bool {
+        self.players.is_some()
+            || self.map.is_some()
+            || self.mode.is_some()
+            || self.script.is_some()
+            || !self.mutators.is_empty()
+            || self.points_to_win.is_some()
+            || self.seed.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<CliOptions, String> {
+        let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+        CliOptions::parse(&args)
+    }
+
+    #[test]
+    fn no_options_open_the_menu() {
+        let options = parse("").unwrap();
+        assert!(!options.skips_menu());
+        assert!(!options.fullscreen && options.dedicated.is_none());
+        // window options don't skip it either
+        assert!(!parse("--fullscreen").unwrap().skips_menu());
+    }
+
+    #[test]
+    fn match_options() {
+        let options = parse(
+            "--players 3 --map lava.json --mode floor-is-lava --points-to-win 2 --seed 42 \
+             --mutators Speedy,BigJumps,Speedy",
+        )
+        .unwrap();
+        assert_eq!(options.players, Some(3));
+        assert_eq!(options.map.as_deref(), Some("lava.json"));
+        assert_eq!(options.mode, Some(MiniGames::FloorIsLava));
+        assert_eq!(options.points_to_win, Some(2));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.mutators, vec![Mutator::Speedy, Mutator::BigJumps]);
+        assert!(options.skips_menu());
+        assert_eq!(
+            parse("--mode ColorTheMap").unwrap().mode,
+            Some(MiniGames::ColorTheMap)
+        );
+    }
+
+    #[test]
+    fn optional_values() {
+        let options = parse("--export-stats --fullscreen").unwrap();
+        assert_eq!(
+            options.export_stats,
+            Some(PathBuf::from(crate::export::EXPORT_DIR))
+        );
+        assert!(options.fullscreen);
+        let options = parse("--export-stats out").unwrap();
+        assert_eq!(options.export_stats, Some(PathBuf::from("out")));
+        let options = parse("--dedicated").unwrap();
+        assert_eq!(options.dedicated, Some(crate::dedicated::DEFAULT_PORT));
+        let options = parse("--dedicated 9000 --connect example.org --profile Ada").unwrap();
+        assert_eq!(options.dedicated, Some(9000));
+        assert_eq!(options.connect.as_deref(), Some("example.org"));
+        assert_eq!(options.profile.as_deref(), Some("Ada"));
+    }
+
+    #[test]
+    fn bad_options_say_what_is_wrong() {
+        let cases = [
+            ("--players", "--players needs a value"),
+            ("--players --fullscreen", "--players needs a value"),
+            ("--players many", "--players needs a number"),
+            ("--players 5", "--players must be between 2 and 4"),
+            ("--mode golf", "unknown --mode golf"),
+            ("--mutators Speedy,Tiny", "unknown mutator Tiny"),
+            ("--points-to-win 0", "--points-to-win must be at least 1"),
+            ("--dedicated http", "--dedicated needs a port number"),
+            ("--colour", "unknown option --colour"),
+        ];
+        for (args, error) in cases {
+            assert_eq!(parse(args).err().as_deref(), Some(error), "{}", args);
+        }
+    }
+}
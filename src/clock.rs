@@ -0,0 +1,38 @@
+// Simulation time. The console sets a lasting time scale; effects such as
+// the final blow's slow motion scale it further for a short while.
+pub struct GameClock {
+    pub scale: f32,
+    slow_motion: f32,
+    // real seconds the slow motion still lasts
+    slow_motion_left: f32,
+}
+
+impl GameClock {
+    pub fn new() -> GameClock {
+        GameClock {
+            scale: 1.0,
+            slow_motion: 1.0,
+            slow_motion_left: 0.0,
+        }
+    }
+
+    pub fn slow_motion(&mut self, scale: f32, seconds: f32) {
+        self.slow_motion = scale;
+        self.slow_motion_left = seconds;
+    }
+
+    pub fn in_slow_motion(&self) -> bool {
+        self.slow_motion_left > 0.0
+    }
+
+    // Simulation time for a frame that took `frame_time`; slow motion runs
+    // out in real time, so it lasts as long whatever the scale
+    pub fn tick(&mut self, frame_time: f32) -> f32 {
+        let mut scale = self.scale;
+        if self.slow_motion_left > 0.0 {
+            self.slow_motion_left -= frame_time;
+            scale *= self.slow_motion;
+        }
+        frame_time * scale
+    }
+}
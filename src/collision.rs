@@ -0,0 +1,163 @@
+// Collision shapes beyond raylib's axis-aligned rectangles, tested against
+// the axis-aligned boxes players use. Level items keep an axis-aligned
+// bounding box for everything that only needs a rough idea of where they
+// are (drawing order, spawn points, paint coverage); their Shape says what
+// players actually bump into inside it.
+use raylib::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shape {
+    // the bounding box itself
+    #[default]
+    Box,
+    // the largest circle that fits the bounding box
+    Circle,
+    // a `width` x `height` rectangle turned by `angle` radians, centred in
+    // the bounding box
+    Rotated {
+        width: f32,
+        height: f32,
+        angle: f32,
+    },
+}
+
+impl Shape {
+    // How far `rect` has to move to get out of the shape of an item with
+    // this bounding box, None when they don't touch. Boxes are left to the
+    // caller's axis-aligned resolution.
+    pub fn push_out(&self, bounds: Rectangle, rect: Rectangle) -> Option<Vector2> {
+        let center = Vector2::new(
+            bounds.x + bounds.width / 2.0,
+            bounds.y + bounds.height / 2.0,
+        );
+        match *self {
+            Shape::Box => None,
+            Shape::Circle => circle_push_out(center, bounds.width.min(bounds.height) / 2.0, rect),
+            Shape::Rotated {
+                width,
+                height,
+                angle,
+            } => OrientedRect {
+                center,
+                half: Vector2::new(width / 2.0, height / 2.0),
+                angle,
+            }
+            .push_out(rect),
+        }
+    }
+}
+
+fn rect_center(rect: Rectangle) -> Vector2 {
+    Vector2::new(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+}
+
+// Pushes `rect` out of a circle along the line from the circle's centre to
+// the closest point of the rectangle
+fn circle_push_out(center: Vector2, radius: f32, rect: Rectangle) -> Option<Vector2> {
+    let closest = Vector2::new(
+        center.x.clamp(rect.x, rect.x + rect.width),
+        center.y.clamp(rect.y, rect.y + rect.height),
+    );
+    let offset = closest - center;
+    let distance = offset.length();
+    if distance >= radius {
+        return None;
+    }
+    if distance > 0.0 {
+        return Some(offset / distance * (radius - distance));
+    }
+    // the centre is inside the rectangle: out through the nearest side
+    let to_left = center.x - rect.x;
+    let to_right = rect.x + rect.width - center.x;
+    let to_top = center.y - rect.y;
+    let to_bottom = rect.y + rect.height - center.y;
+    let nearest = to_left.min(to_right).min(to_top).min(to_bottom);
+    Some(if nearest == to_top {
+        Vector2::new(0.0, to_top + radius)
+    } else if nearest == to_bottom {
+        Vector2::new(0.0, -(to_bottom + radius))
+    } else if nearest == to_left {
+        Vector2::new(to_left + radius, 0.0)
+    } else {
+        Vector2::new(-(to_right + radius), 0.0)
+    })
+}
+
+// A rectangle turned by `angle` radians around its centre
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientedRect {
+    pub center: Vector2,
+    // half the width and height before turning
+    pub half: Vector2,
+    pub angle: f32,
+}
+
+impl OrientedRect {
+    // The rectangle's own x and y axes
+    fn axes(&self) -> [Vector2; 2] {
+        let (sin, cos) = self.angle.sin_cos();
+        [Vector2::new(cos, sin), Vector2::new(-sin, cos)]
+    }
+
+    pub fn corners(&self) -> [Vector2; 4] {
+        let [x, y] = self.axes();
+        let (x, y) = (x * self.half.x, y * self.half.y);
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+
+    // Smallest axis-aligned box around it
+    pub fn bounds(&self) -> Rectangle {
+        let corners = self.corners();
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for corner in &corners[1..] {
+            min = Vector2::new(min.x.min(corner.x), min.y.min(corner.y));
+            max = Vector2::new(max.x.max(corner.x), max.y.max(corner.y));
+        }
+        Rectangle::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    pub fn overlaps_rect(&self, rect: Rectangle) -> bool {
+        self.push_out(rect).is_some()
+    }
+
+    // Separating axis test: the shapes overlap unless their projections come
+    // apart on one of the four edge directions. When they do overlap, the
+    // axis with the least overlap is the shortest way out for `rect`.
+    pub fn push_out(&self, rect: Rectangle) -> Option<Vector2> {
+        let corners = self.corners();
+        let rect_corners = [
+            Vector2::new(rect.x, rect.y),
+            Vector2::new(rect.x + rect.width, rect.y),
+            Vector2::new(rect.x + rect.width, rect.y + rect.height),
+            Vector2::new(rect.x, rect.y + rect.height),
+        ];
+        let [x, y] = self.axes();
+        let axes = [Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0), x, y];
+        let away = rect_center(rect) - self.center;
+        let mut best: Option<Vector2> = None;
+        for axis in axes {
+            let project = |points: &[Vector2]| {
+                points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                    let d = p.dot(axis);
+                    (min.min(d), max.max(d))
+                })
+            };
+            let (a_min, a_max) = project(&corners);
+            let (b_min, b_max) = project(&rect_corners);
+            let overlap = a_max.min(b_max) - a_min.max(b_min);
+            if overlap < 0.0 {
+                return None;
+            }
+            if best.map_or(true, |best| overlap < best.length()) {
+                let direction = if away.dot(axis) < 0.0 { -axis } else { axis };
+                best = Some(direction * overlap);
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,259 @@
+// Developer console, toggled with ~. Each command is registered in COMMANDS
+// with its usage and a parser; a parsed ConsoleCommand is handed back to the
+// game loop, which owns the state the commands act on.
+use crate::{snapshot::QUICK_SNAPSHOT, viewport::SCREEN_WIDTH, MiniGames};
+use raylib::prelude::*;
+
+const MAX_LOG: usize = 100;
+const VISIBLE_LINES: usize = 8;
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 22;
+const MAX_INPUT: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    GivePoints { player: usize, points: u32 },
+    SetMode(MiniGames),
+    SpawnBullet { position: Vector2, speed: Vector2 },
+    TimeScale(f32),
+    ReloadLevel,
+    SaveSnapshot(String),
+    LoadSnapshot(String),
+    // round trip in seconds, loss from 0 to 1
+    NetSim { lag: f32, loss: f32 },
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub usage: &'static str,
+    parse: fn(&[&str]) -> Result<ConsoleCommand, String>,
+}
+
+fn number<T: std::str::FromStr>(args: &[&str], index: usize, what: &str) -> Result<T, String> {
+    args.get(index)
+        .ok_or_else(|| format!("missing {}", what))?
+        .parse()
+        .map_err(|_| format!("{} must be a number", what))
+}
+
+fn no_args(args: &[&str], command: ConsoleCommand) -> Result<ConsoleCommand, String> {
+    match args {
+        [] => Ok(command),
+        _ => Err("takes no arguments".to_string()),
+    }
+}
+
+// Snapshot names become file names
+fn snapshot_name(args: &[&str]) -> Result<String, String> {
+    match args {
+        [] => Ok(QUICK_SNAPSHOT.to_string()),
+        [name]
+            if name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            Ok(name.to_string())
+        }
+        [_] => Err("name may only use letters, digits, - and _".to_string()),
+        _ => Err("takes at most one name".to_string()),
+    }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        usage: "help",
+        parse: |args| no_args(args, ConsoleCommand::Help),
+    },
+    CommandSpec {
+        name: "give_points",
+        usage: "give_points <player 1-4> <points>",
+        parse: |args| {
+            let player: usize = number(args, 0, "player")?;
+            if !(1..=4).contains(&player) {
+                return Err("player must be 1-4".to_string());
+            }
+            Ok(ConsoleCommand::GivePoints {
+                player: player - 1,
+                points: number(args, 1, "points")?,
+            })
+        },
+    },
+    CommandSpec {
+        name: "set_mode",
+        usage: "set_mode <color-the-map|dodge|floor-is-lava>",
+        parse: |args| {
+            let name = args.first().ok_or("missing mode")?;
+            MiniGames::from_cli(name)
+                .map(ConsoleCommand::SetMode)
+                .ok_or_else(|| format!("unknown mode {}", name))
+        },
+    },
+    CommandSpec {
+        name: "spawn_bullet",
+        usage: "spawn_bullet <x> <y> <vx> <vy>",
+        parse: |args| {
+            Ok(ConsoleCommand::SpawnBullet {
+                position: Vector2::new(number(args, 0, "x")?, number(args, 1, "y")?),
+                speed: Vector2::new(number(args, 2, "vx")?, number(args, 3, "vy")?),
+            })
+        },
+    },
+    CommandSpec {
+        name: "timescale",
+        usage: "timescale <factor>",
+        parse: |args| {
+            let scale: f32 = number(args, 0, "factor")?;
+            if !(0.0..=10.0).contains(&scale) {
+                return Err("factor must be between 0 and 10".to_string());
+            }
+            Ok(ConsoleCommand::TimeScale(scale))
+        },
+    },
+    CommandSpec {
+        name: "reload_level",
+        usage: "reload_level",
+        parse: |args| no_args(args, ConsoleCommand::ReloadLevel),
+    },
+    CommandSpec {
+        name: "save_snapshot",
+        usage: "save_snapshot [name]  (F8 saves \"quick\" in debug builds)",
+        parse: |args| snapshot_name(args).map(ConsoleCommand::SaveSnapshot),
+    },
+    CommandSpec {
+        name: "load_snapshot",
+        usage: "load_snapshot [name]  (F9 loads \"quick\" in debug builds)",
+        parse: |args| snapshot_name(args).map(ConsoleCommand::LoadSnapshot),
+    },
+    CommandSpec {
+        name: "net_sim",
+        usage: "net_sim <lag ms> <loss %>  (on the F3 network meter's link)",
+        parse: |args| {
+            let lag: f32 = number(args, 0, "lag")?;
+            let loss: f32 = number(args, 1, "loss")?;
+            if lag < 0.0 || !(0.0..=100.0).contains(&loss) {
+                return Err("lag can't be negative and loss must be 0 to 100".to_string());
+            }
+            Ok(ConsoleCommand::NetSim {
+                lag: lag / 1000.0,
+                loss: loss / 100.0,
+            })
+        },
+    },
+];
+
+pub fn parse(line: &str) -> Result<ConsoleCommand, String> {
+    let mut words = line.split_whitespace();
+    let name = words.next().ok_or("empty command")?;
+    let args: Vec<&str> = words.collect();
+    let spec = COMMANDS
+        .iter()
+        .find(|spec| spec.name == name)
+        .ok_or_else(|| format!("unknown command {}, try help", name))?;
+    (spec.parse)(&args).map_err(|err| format!("{}: {} (usage: {})", name, err, spec.usage))
+}
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    history: Vec<String>,
+    history_index: usize,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > MAX_LOG {
+            self.log.remove(0);
+        }
+    }
+
+    // Handles typing while open; returns a command when Enter is pressed on a valid line
+    pub fn update(&mut self, rl: &mut RaylibHandle) -> Option<ConsoleCommand> {
+        if rl.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+            self.open = !self.open;
+            // drop the ~ that opened it
+            while rl.get_char_pressed().is_some() {}
+            return None;
+        }
+        if !self.open {
+            return None;
+        }
+        while let Some(c) = rl.get_char_pressed() {
+            if c != '`' && c != '~' && !c.is_control() && self.input.len() < MAX_INPUT {
+                self.input.push(c);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.open = false;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.input.pop();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) && self.history_index > 0 {
+            self.history_index -= 1;
+            self.input = self.history[self.history_index].clone();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) && self.history_index < self.history.len() {
+            self.history_index += 1;
+            self.input = self
+                .history
+                .get(self.history_index)
+                .cloned()
+                .unwrap_or_default();
+        }
+        if !rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        self.print(format!("> {}", line));
+        self.history.push(line.to_string());
+        self.history_index = self.history.len();
+        match parse(line) {
+            Ok(command) => Some(command),
+            Err(err) => {
+                self.print(err);
+                None
+            }
+        }
+    }
+
+    pub fn print_help(&mut self) {
+        for spec in COMMANDS {
+            self.print(format!("  {}", spec.usage));
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
+        if !self.open {
+            return;
+        }
+        let height = (VISIBLE_LINES as i32 + 1) * LINE_HEIGHT + 10;
+        d.draw_rectangle(0, 0, SCREEN_WIDTH, height, Color::BLACK.alpha(0.8));
+        let start = self.log.len().saturating_sub(VISIBLE_LINES);
+        for (i, line) in self.log[start..].iter().enumerate() {
+            d.draw_text(
+                line,
+                10,
+                5 + i as i32 * LINE_HEIGHT,
+                FONT_SIZE,
+                Color::LIGHTGRAY,
+            );
+        }
+        d.draw_text(
+            &format!("> {}_", self.input),
+            10,
+            5 + VISIBLE_LINES as i32 * LINE_HEIGHT,
+            FONT_SIZE,
+            Color::WHITE,
+        );
+    }
+}
@@ -0,0 +1,260 @@
+// Unlockable cosmetics: character skins, paint splat styles and victory poses.
+// Unlocks come from profile milestones and are remembered in the profile.
+use crate::{assets, profiles::Profile, MiniGames, Player};
+use raylib::prelude::*;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Milestone {
+    MatchesPlayed(u32),
+    MatchesWon(u32),
+    RoundsWon(u32),
+    RoundsWonIn(MiniGames, u32),
+    Coverage(f32),
+}
+
+impl Milestone {
+    pub fn reached(&self, profile: &Profile) -> bool {
+        match *self {
+            Milestone::MatchesPlayed(n) => profile.matches_played >= n,
+            Milestone::MatchesWon(n) => profile.matches_won >= n,
+            Milestone::RoundsWon(n) => profile.rounds_won.iter().sum::<u32>() >= n,
+            Milestone::RoundsWonIn(game, n) => profile.rounds_won[game.index()] >= n,
+            Milestone::Coverage(total) => profile.total_coverage >= total,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Milestone::MatchesPlayed(n) => format!("play {} matches", n),
+            Milestone::MatchesWon(n) => format!("win {} matches", n),
+            Milestone::RoundsWon(n) => format!("win {} rounds", n),
+            Milestone::RoundsWonIn(game, n) => format!("win {} {} rounds", n, game.name()),
+            Milestone::Coverage(total) => format!("paint {:.0}% in total", total * 100.0),
+        }
+    }
+}
+
+pub trait Cosmetic: Copy + PartialEq + Default + 'static {
+    const ALL: &'static [Self];
+    const KIND: &'static str;
+    fn name(&self) -> &'static str;
+    // None means available from the start
+    fn milestone(&self) -> Option<Milestone>;
+
+    fn key(&self) -> String {
+        format!("{}:{}", Self::KIND, self.name())
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.key() == key)
+    }
+
+    fn is_unlocked(&self, profile: &Profile) -> bool {
+        self.milestone().is_none() || profile.unlocked.contains(&self.key())
+    }
+
+    // Next/previous cosmetic of this kind the profile has unlocked
+    fn cycle(&self, profile: &Profile, forward: bool) -> Self {
+        let available: Vec<Self> = Self::ALL
+            .iter()
+            .copied()
+            .filter(|c| c.is_unlocked(profile))
+            .collect();
+        let position = available.iter().position(|c| c == self).unwrap_or(0);
+        let next = if forward {
+            (position + 1) % available.len()
+        } else {
+            (position + available.len() - 1) % available.len()
+        };
+        available[next]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Skin {
+    #[default]
+    Classic,
+    Shadow,
+    Inverted,
+    Gold,
+}
+
+impl Cosmetic for Skin {
+    const ALL: &'static [Skin] = &[Skin::Classic, Skin::Shadow, Skin::Inverted, Skin::Gold];
+    const KIND: &'static str = "skin";
+
+    fn name(&self) -> &'static str {
+        match self {
+            Skin::Classic => "Classic",
+            Skin::Shadow => "Shadow",
+            Skin::Inverted => "Inverted",
+            Skin::Gold => "Gold",
+        }
+    }
+
+    fn milestone(&self) -> Option<Milestone> {
+        match self {
+            Skin::Classic => None,
+            Skin::Shadow => Some(Milestone::MatchesPlayed(3)),
+            Skin::Inverted => Some(Milestone::RoundsWon(10)),
+            Skin::Gold => Some(Milestone::MatchesWon(5)),
+        }
+    }
+}
+
+impl Skin {
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    fn apply(&self, image: &mut Image) {
+        match self {
+            Skin::Classic => {}
+            Skin::Shadow => image.color_tint(Color::new(90, 90, 130, 255)),
+            Skin::Inverted => image.color_invert(),
+            Skin::Gold => {
+                image.color_grayscale();
+                image.color_tint(Color::GOLD);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplatStyle {
+    #[default]
+    Round,
+    Square,
+    Cross,
+}
+
+impl Cosmetic for SplatStyle {
+    const ALL: &'static [SplatStyle] = &[SplatStyle::Round, SplatStyle::Square, SplatStyle::Cross];
+    const KIND: &'static str = "splat";
+
+    fn name(&self) -> &'static str {
+        match self {
+            SplatStyle::Round => "Round",
+            SplatStyle::Square => "Square",
+            SplatStyle::Cross => "Cross",
+        }
+    }
+
+    fn milestone(&self) -> Option<Milestone> {
+        match self {
+            SplatStyle::Round => None,
+            SplatStyle::Square => Some(Milestone::Coverage(2.0)),
+            SplatStyle::Cross => Some(Milestone::RoundsWonIn(MiniGames::ColorTheMap, 5)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VictoryPose {
+    #[default]
+    Stand,
+    Bounce,
+    Spin,
+    Grow,
+}
+
+impl Cosmetic for VictoryPose {
+    const ALL: &'static [VictoryPose] = &[
+        VictoryPose::Stand,
+        VictoryPose::Bounce,
+        VictoryPose::Spin,
+        VictoryPose::Grow,
+    ];
+    const KIND: &'static str = "pose";
+
+    fn name(&self) -> &'static str {
+        match self {
+            VictoryPose::Stand => "Stand",
+            VictoryPose::Bounce => "Bounce",
+            VictoryPose::Spin => "Spin",
+            VictoryPose::Grow => "Grow",
+        }
+    }
+
+    fn milestone(&self) -> Option<Milestone> {
+        match self {
+            VictoryPose::Stand => None,
+            VictoryPose::Bounce => Some(Milestone::MatchesWon(1)),
+            VictoryPose::Spin => Some(Milestone::RoundsWonIn(MiniGames::Dodge, 5)),
+            VictoryPose::Grow => Some(Milestone::MatchesPlayed(10)),
+        }
+    }
+}
+
+impl VictoryPose {
+    // Draws the winner's sprite centered on `center` with the pose animation
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        texture: &Texture2D,
+        center: Vector2,
+        time: f32,
+    ) {
+        let mut scale = 1.3;
+        let mut rotation = 0.0;
+        let mut offset = Vector2::zero();
+        match self {
+            VictoryPose::Stand => {}
+            VictoryPose::Bounce => offset.y = -(time * 6.0).sin().abs() * 30.0,
+            VictoryPose::Spin => rotation = (time * 180.0) % 360.0,
+            VictoryPose::Grow => scale *= 1.0 + 0.2 * (time * 4.0).sin(),
+        }
+        let size = Vector2::new(texture.width as f32 * scale, texture.height as f32 * scale);
+        d.draw_texture_pro(
+            texture,
+            Rectangle::new(0.0, 0.0, texture.width as f32, texture.height as f32),
+            Rectangle::new(center.x + offset.x, center.y + offset.y, size.x, size.y),
+            size * 0.5,
+            rotation,
+            Color::WHITE,
+        );
+    }
+}
+
+// Every skin variant of every player's sprite, indexed [player][skin]
+pub struct SkinTextures {
+    pub textures: Vec<Vec<Rc<Texture2D>>>,
+}
+
+impl SkinTextures {
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, names: &[&str]) -> SkinTextures {
+        let textures = names
+            .iter()
+            .map(|name| {
+                let base = assets::load_image(name);
+                Skin::ALL
+                    .iter()
+                    .map(|skin| {
+                        let mut image = base.clone();
+                        skin.apply(&mut image);
+                        Rc::new(rl.load_texture_from_image(thread, &image).unwrap())
+                    })
+                    .collect()
+            })
+            .collect();
+        SkinTextures { textures }
+    }
+
+    // Puts the profile's chosen cosmetics on a player; guests get the defaults
+    pub fn apply(&self, player: &mut Player, profile: Option<&Profile>) {
+        let (skin, splat, pose) = match profile {
+            Some(profile) => (profile.skin, profile.splat, profile.pose),
+            None => Default::default(),
+        };
+        player.splat = splat;
+        player.pose = pose;
+        if let Some(texture) = self
+            .textures
+            .get(player.number as usize)
+            .and_then(|skins| skins.get(skin.index()))
+        {
+            player.texture = texture.clone();
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// The area of a map that can actually be painted, so ColorTheMap coverage
+// reads the same on every layout: "35% of the map" means 35% of what the
+// players can reach, not of the screen or of what happens to be painted.
+// Worked out once per map on a coarse grid: the open cells connected to the
+// spawn line are reachable (jumps and falls get everywhere in the open), and
+// paint lands in a band on both sides of where those cells meet solid
+// items. Cells the level's paint mask blocks don't count.
+use crate::{
+    paint_mask::PaintMask,
+    spawn,
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    EnvItem,
+};
+use raylib::prelude::*;
+use std::collections::VecDeque;
+
+// grid cell size, about one paint splat across
+const CELL: i32 = 10;
+
+// Paintable area in virtual screen pixels
+pub fn paintable_area(ops: &[EnvItem], mask: &PaintMask) -> f32 {
+    let columns = SCREEN_WIDTH / CELL;
+    let rows = SCREEN_HEIGHT / CELL;
+    let center = |column: i32, row: i32| {
+        Vector2::new(
+            (column * CELL + CELL / 2) as f32,
+            (row * CELL + CELL / 2) as f32,
+        )
+    };
+    let index = |column: i32, row: i32| (row * columns + column) as usize;
+    let mut solid = vec![false; (columns * rows) as usize];
+    for row in 0..rows {
+        for column in 0..columns {
+            let point = center(column, row);
+            solid[index(column, row)] = ops
+                .iter()
+                .any(|op| op.layer.collides() && op.rect.check_collision_point_rec(point));
+        }
+    }
+
+    let mut reachable = vec![false; solid.len()];
+    let mut queue = VecDeque::new();
+    for number in 0..4 {
+        let start = spawn::spawn_line(number);
+        let (column, row) = (start.x as i32 / CELL, start.y as i32 / CELL);
+        if column < columns && row < rows && !solid[index(column, row)] {
+            reachable[index(column, row)] = true;
+            queue.push_back((column, row));
+        }
+    }
+    let neighbours = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+    while let Some((column, row)) = queue.pop_front() {
+        for (dx, dy) in neighbours {
+            let (next_column, next_row) = (column + dx, row + dy);
+            if next_column < 0 || next_row < 0 || next_column >= columns || next_row >= rows {
+                continue;
+            }
+            let next = index(next_column, next_row);
+            if !solid[next] && !reachable[next] {
+                reachable[next] = true;
+                queue.push_back((next_column, next_row));
+            }
+        }
+    }
+
+    // cells on either side of a reachable surface
+    let mut cells = 0;
+    for row in 0..rows {
+        for column in 0..columns {
+            let here = index(column, row);
+            let on_surface = neighbours.iter().any(|(dx, dy)| {
+                let (next_column, next_row) = (column + dx, row + dy);
+                if next_column < 0 || next_row < 0 || next_column >= columns || next_row >= rows {
+                    return false;
+                }
+                let next = index(next_column, next_row);
+                (reachable[here] && solid[next]) || (solid[here] && reachable[next])
+            });
+            if on_surface && !mask.blocks(center(column, row)) {
+                cells += 1;
+            }
+        }
+    }
+    (cells * CELL * CELL) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collision::Shape, Layer};
+
+    fn item(x: f32, y: f32, width: f32, height: f32, layer: Layer) -> EnvItem {
+        EnvItem {
+            rect: Rectangle::new(x, y, width, height),
+            shape: Shape::Box,
+            color: Color::GRAY,
+            layer,
+            z: 0,
+        }
+    }
+
+    fn floor(layer: Layer) -> EnvItem {
+        item(0.0, 600.0, SCREEN_WIDTH as f32, 50.0, layer)
+    }
+
+    #[test]
+    fn nothing_to_paint_without_solid_items() {
+        assert_eq!(paintable_area(&[], &PaintMask::default()), 0.0);
+        let drawn_only = [floor(Layer::Background), floor(Layer::Foreground)];
+        assert_eq!(paintable_area(&drawn_only, &PaintMask::default()), 0.0);
+    }
+
+    #[test]
+    fn a_floor_is_painted_on_both_sides_of_its_surface() {
+        // the open row above the floor and the floor's top row, 120 cells each
+        let area = 2.0 * 120.0 * (CELL * CELL) as f32;
+        assert_eq!(
+            paintable_area(&[floor(Layer::Platform)], &PaintMask::default()),
+            area
+        );
+        assert_eq!(
+            paintable_area(&[floor(Layer::Collision)], &PaintMask::default()),
+            area
+        );
+    }
+
+    #[test]
+    fn only_the_outside_of_a_block_counts() {
+        let floor_only = paintable_area(&[floor(Layer::Platform)], &PaintMask::default());
+        let ops = [
+            floor(Layer::Platform),
+            item(500.0, 300.0, 100.0, 100.0, Layer::Platform),
+        ];
+        // a 10 x 10 cell block: 40 open cells touch its sides and 36 of its own
+        // cells are on its border, the 64 inside it can't be reached
+        let block = 76.0 * (CELL * CELL) as f32;
+        assert_eq!(
+            paintable_area(&ops, &PaintMask::default()),
+            floor_only + block
+        );
+    }
+
+    #[test]
+    fn sealed_rooms_are_not_reachable() {
+        // a wall from the ceiling down to the floor, right of the spawns
+        let mut ops = vec![
+            floor(Layer::Platform),
+            item(800.0, 0.0, 10.0, 600.0, Layer::Collision),
+        ];
+        let with_wall = paintable_area(&ops, &PaintMask::default());
+        // both sides of the floor left of the wall, 80 cells each, and both
+        // sides of the wall's left face, 60 cells each, less the open corner
+        // cell touching both
+        assert_eq!(
+            with_wall,
+            (2 * 80 + 2 * 60 - 1) as f32 * (CELL * CELL) as f32
+        );
+        // a platform inside the room adds nothing
+        ops.push(item(900.0, 400.0, 200.0, 20.0, Layer::Platform));
+        assert_eq!(paintable_area(&ops, &PaintMask::default()), with_wall);
+    }
+}
@@ -0,0 +1,158 @@
+// Crash handling: a panic writes a report to ./crashes and, once the game has
+// unwound and closed its window, a small error window shows what happened.
+// Without it a build started from a desktop shortcut just vanishes.
+use crate::{history, window};
+use raylib::prelude::*;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    fs,
+    panic::{self, Location},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+pub const CRASH_DIR: &str = "crashes";
+const WINDOW_WIDTH: i32 = 720;
+const WINDOW_HEIGHT: i32 = 320;
+const WRAP_COLUMNS: usize = 70;
+
+pub struct Crash {
+    pub message: String,
+    pub report: Option<PathBuf>,
+}
+
+static LAST_CRASH: Mutex<Option<Crash>> = Mutex::new(None);
+
+fn panic_message(payload: &(dyn Any + Send), location: Option<&Location>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string());
+    match location {
+        Some(location) => format!("{} ({})", message, location),
+        None => message,
+    }
+}
+
+fn write_report(message: &str) -> Option<PathBuf> {
+    let timestamp = history::now();
+    let report = format!(
+        "{} {} crashed on {} (unix time {})\nplatform: {}-{}\n\n{}\n\n{}\n",
+        window::BASE_TITLE,
+        env!("CARGO_PKG_VERSION"),
+        history::format_date(timestamp),
+        timestamp,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        message,
+        Backtrace::force_capture(),
+    );
+    let path = PathBuf::from(CRASH_DIR).join(format!("crash-{}.txt", timestamp));
+    let result = fs::create_dir_all(CRASH_DIR).and_then(|_| fs::write(&path, report));
+    match result {
+        Ok(()) => Some(fs::canonicalize(&path).unwrap_or(path)),
+        Err(err) => {
+            eprintln!("could not write crash report: {}", err);
+            None
+        }
+    }
+}
+
+// Keeps the default hook (message on stderr) and adds the report
+pub fn install_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let message = panic_message(info.payload(), info.location());
+        let report = write_report(&message);
+        if let Ok(mut last) = LAST_CRASH.lock() {
+            *last = Some(Crash { message, report });
+        }
+    }));
+}
+
+pub fn take_last() -> Option<Crash> {
+    LAST_CRASH.lock().ok()?.take()
+}
+
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.len() + word.len() >= WRAP_COLUMNS {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Opens a fresh window with the error; needs the game's window to be closed
+pub fn show_error_window(crash: &Crash) {
+    let (mut rl, thread) = raylib::init()
+        .size(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .title(&format!("{} crashed", window::BASE_TITLE))
+        .build();
+    rl.set_target_fps(30);
+    let report = crash.report.as_ref().map(|path| path.display().to_string());
+    let mut lines = wrap(&crash.message);
+    lines.truncate(6);
+    let mut copied = false;
+
+    while !rl.window_should_close() {
+        let mut d = rl.begin_drawing(&thread);
+        d.clear_background(Color::from_hex("C7DCD0").unwrap());
+        d.draw_text("Sorry, the game crashed.", 20, 20, 30, Color::BLACK);
+        for (i, line) in lines.iter().enumerate() {
+            d.draw_text(line, 20, 65 + i as i32 * 22, 20, Color::MAROON);
+        }
+        match &report {
+            Some(report) => {
+                d.draw_text("A crash report was saved to", 20, 205, 20, Color::DARKGRAY);
+                for (i, line) in wrap(report).iter().enumerate() {
+                    d.draw_text(line, 20, 228 + i as i32 * 18, 15, Color::DARKGRAY);
+                }
+            }
+            None => d.draw_text(
+                "The crash report could not be saved",
+                20,
+                205,
+                20,
+                Color::DARKGRAY,
+            ),
+        }
+        let close = Rectangle::new(
+            (WINDOW_WIDTH - 120) as f32,
+            (WINDOW_HEIGHT - 60) as f32,
+            100.0,
+            40.0,
+        );
+        if d.gui_button(close, Some(rstr!("Close"))) {
+            break;
+        }
+        if let Some(report) = &report {
+            let copy = Rectangle::new(
+                (WINDOW_WIDTH - 280) as f32,
+                (WINDOW_HEIGHT - 60) as f32,
+                150.0,
+                40.0,
+            );
+            let label = if copied {
+                rstr!("Copied")
+            } else {
+                rstr!("Copy report path")
+            };
+            if d.gui_button(copy, Some(label)) {
+                copied = d.set_clipboard_text(report).is_ok();
+            }
+        }
+    }
+}
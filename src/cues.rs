@@ -0,0 +1,134 @@
+// Sounds for the accessibility cues, played through feedback.rs for the
+// seats that turned them on under Settings. The game ships no audio files,
+// so the tones are synthesized when the first cue is needed; raylib-rs 5.0
+// has no safe way to make a sound from samples, hence the ffi calls.
+//
+// Every cue has one alias per seat, so two players' cues can overlap with
+// their own pan and pitch. The seats' base pitches form a major chord, so a
+// cue everyone gets at once (round start) sounds like one jingle.
+use raylib::ffi;
+use std::ffi::c_void;
+
+const SAMPLE_RATE: u32 = 44100;
+const VOLUME: f32 = 0.3;
+// fade in and out of every note, avoids clicks
+const RAMP: f32 = 0.005;
+const SEAT_PITCH: [f32; 4] = [1.0, 1.26, 1.5, 2.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    // a bullet is closing in, repeated faster and higher the closer it gets
+    Approach,
+    RoundStart,
+    RoundOver,
+    Won,
+    Out,
+    // picked up or set off a paint bomb
+    Blip,
+}
+
+impl Cue {
+    const ALL: [Cue; 6] = [
+        Cue::Approach,
+        Cue::RoundStart,
+        Cue::RoundOver,
+        Cue::Won,
+        Cue::Out,
+        Cue::Blip,
+    ];
+
+    // (frequency in Hz, seconds) played one after the other
+    fn notes(&self) -> &'static [(f32, f32)] {
+        match self {
+            Cue::Approach => &[(880.0, 0.06)],
+            Cue::RoundStart => &[(523.0, 0.1), (659.0, 0.1), (784.0, 0.2)],
+            Cue::RoundOver => &[(784.0, 0.1), (659.0, 0.1), (523.0, 0.25)],
+            Cue::Won => &[(523.0, 0.08), (659.0, 0.08), (784.0, 0.08), (1047.0, 0.3)],
+            Cue::Out => &[(220.0, 0.15), (165.0, 0.3)],
+            Cue::Blip => &[(1320.0, 0.05)],
+        }
+    }
+}
+
+fn synthesize(notes: &[(f32, f32)]) -> Vec<i16> {
+    let rate = SAMPLE_RATE as f32;
+    let mut samples = Vec::new();
+    for (frequency, seconds) in notes {
+        let count = (seconds * rate) as usize;
+        for i in 0..count {
+            let t = i as f32 / rate;
+            let envelope = (t / RAMP).min((seconds - t) / RAMP).clamp(0.0, 1.0);
+            let wave = (t * frequency * std::f32::consts::TAU).sin();
+            samples.push((wave * envelope * VOLUME * i16::MAX as f32) as i16);
+        }
+    }
+    samples
+}
+
+struct Voice {
+    sound: ffi::Sound,
+    seats: [ffi::Sound; 4],
+}
+
+pub struct CueSounds {
+    voices: Vec<Voice>,
+}
+
+impl CueSounds {
+    // None when there is no audio device to play on
+    pub fn open() -> Option<CueSounds> {
+        unsafe {
+            ffi::InitAudioDevice();
+            if !ffi::IsAudioDeviceReady() {
+                eprintln!("no audio device, the audio cues stay silent");
+                return None;
+            }
+        }
+        let voices = Cue::ALL
+            .iter()
+            .map(|cue| {
+                let mut samples = synthesize(cue.notes());
+                let wave = ffi::Wave {
+                    frameCount: samples.len() as u32,
+                    sampleRate: SAMPLE_RATE,
+                    sampleSize: 16,
+                    channels: 1,
+                    data: samples.as_mut_ptr() as *mut c_void,
+                };
+                // raylib copies the samples, they can go after this
+                let sound = unsafe { ffi::LoadSoundFromWave(wave) };
+                Voice {
+                    sound,
+                    seats: [(); 4].map(|_| unsafe { ffi::LoadSoundAlias(sound) }),
+                }
+            })
+            .collect();
+        Some(CueSounds { voices })
+    }
+
+    // `pan` runs from 0 at the left edge of the screen to 1 at the right,
+    // `pitch` scales the seat's own pitch
+    pub fn play(&self, cue: Cue, seat: usize, pan: f32, pitch: f32) {
+        let sound = self.voices[cue as usize].seats[seat % 4];
+        unsafe {
+            ffi::SetSoundPitch(sound, SEAT_PITCH[seat % 4] * pitch);
+            // raylib 5.0 pans from 1 (left) to 0 (right)
+            ffi::SetSoundPan(sound, 1.0 - pan.clamp(0.0, 1.0));
+            ffi::PlaySound(sound);
+        }
+    }
+}
+
+impl Drop for CueSounds {
+    fn drop(&mut self) {
+        unsafe {
+            for voice in &self.voices {
+                for alias in voice.seats {
+                    ffi::UnloadSoundAlias(alias);
+                }
+                ffi::UnloadSound(voice.sound);
+            }
+            ffi::CloseAudioDevice();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Daily challenge: the date picks the seed, minigame order and mutators, so
+// everyone playing on the same day gets the same match. Results go to
+// save/daily.json.
+use crate::{
+    history::{self, MatchPlayer},
+    json::Json,
+    mutators::Mutator,
+    rng::Rng,
+    storage, MiniGames,
+};
+
+pub const DAILY_FILE: &str = "daily.json";
+const DAILY_MUTATORS: usize = 2;
+// more than a match can last at 5 points to win
+const DAILY_ROUNDS: usize = 24;
+
+#[derive(Debug, Clone)]
+pub struct DailyChallenge {
+    pub day: u64, // days since the unix epoch, UTC
+    pub seed: u64,
+    pub order: Vec<MiniGames>,
+    pub mutators: Vec<Mutator>,
+}
+
+impl DailyChallenge {
+    pub fn for_day(day: u64) -> DailyChallenge {
+        let (year, month, date) = history::civil_date(day * 86400);
+        let seed = Rng::new(year as u64 * 10000 + month as u64 * 100 + date as u64).next_u64();
+        let mut rng = Rng::new(seed);
+
+        let playable = [MiniGames::ColorTheMap, MiniGames::Dodge];
+        let order = (0..DAILY_ROUNDS)
+            .map(|_| playable[rng.index(playable.len())])
+            .collect();
+        let mut mutators = Mutator::ALL.to_vec();
+        rng.shuffle(&mut mutators);
+        mutators.truncate(DAILY_MUTATORS);
+
+        DailyChallenge {
+            day,
+            seed,
+            order,
+            mutators,
+        }
+    }
+
+    pub fn today() -> DailyChallenge {
+        DailyChallenge::for_day(history::now() / 86400)
+    }
+
+    pub fn date(&self) -> String {
+        history::format_date(self.day * 86400)
+    }
+
+    // Minigame for the round after `rounds_played` rounds
+    pub fn game_for_round(&self, rounds_played: usize) -> MiniGames {
+        self.order[rounds_played % self.order.len()]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DailyResult {
+    pub day: u64,
+    pub timestamp: u64,
+    pub players: Vec<MatchPlayer>,
+}
+
+impl DailyResult {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("day", self.day.into()),
+            ("timestamp", self.timestamp.into()),
+            (
+                "players",
+                Json::Array(
+                    self.players
+                        .iter()
+                        .map(|p| {
+                            Json::object(vec![
+                                ("name", p.name.as_str().into()),
+                                ("has_profile", p.has_profile.into()),
+                                ("points", p.points.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<DailyResult> {
+        Some(DailyResult {
+            day: json.get("day")?.as_u64()?,
+            timestamp: json.get("timestamp")?.as_u64()?,
+            players: json
+                .array("players")
+                .iter()
+                .map(|p| MatchPlayer {
+                    name: p.str_or("name", "?").to_string(),
+                    has_profile: p.bool_or("has_profile", false),
+                    points: p.u32_or("points", 0),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DailyLog {
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyLog {
+    pub fn load() -> Self {
+        let results = storage::read_json(DAILY_FILE)
+            .map(|json| {
+                json.array("results")
+                    .iter()
+                    .filter_map(DailyResult::from_json)
+                    .collect()
+            })
+            .unwrap_or_default();
+        DailyLog { results }
+    }
+
+    pub fn save(&self) {
+        let results: Vec<Json> = self.results.iter().map(DailyResult::to_json).collect();
+        storage::write_json(DAILY_FILE, &Json::object(vec![("results", results.into())]));
+    }
+
+    pub fn push(&mut self, result: DailyResult) {
+        self.results.push(result);
+    }
+
+    pub fn played_on(&self, day: u64) -> usize {
+        self.results.iter().filter(|r| r.day == day).count()
+    }
+
+    // Highest single score posted on that day, with who posted it
+    pub fn best_on(&self, day: u64) -> Option<&MatchPlayer> {
+        self.results
+            .iter()
+            .filter(|r| r.day == day)
+            .flat_map(|r| r.players.iter())
+            .max_by_key(|p| p.points)
+    }
+}
@@ -0,0 +1,273 @@
+// F3 debug overlay: hitboxes, contact normals and velocities drawn over the
+// match, plus a side panel showing the fields of whatever was clicked.
+// Debug builds also get time controls for catching fast collision bugs.
+use crate::{
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    Bullet, Contact, EnvItem, Player,
+};
+use raylib::prelude::*;
+
+const PANEL_WIDTH: i32 = 280;
+const FONT_SIZE: i32 = 18;
+// seconds of travel shown by a velocity arrow
+const VELOCITY_SCALE: f32 = 0.1;
+const NORMAL_LENGTH: f32 = 20.0;
+// what one frame-step advances
+pub const FIXED_TICK: f32 = 1.0 / 60.0;
+const SPEEDS: [f32; 3] = [1.0, 0.5, 0.1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity {
+    Player(usize),
+    Bullet(usize),
+    EnvItem(usize),
+}
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+    pub selected: Option<Entity>,
+    contacts: Vec<(Vector2, Vector2)>,
+}
+
+fn draw_arrow(
+    d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+    from: Vector2,
+    to: Vector2,
+    color: Color,
+) {
+    d.draw_line_ex(from, to, 2.0, color);
+    let dir = to - from;
+    if dir.length() < 1.0 {
+        return;
+    }
+    let dir = dir.normalized();
+    let side = Vector2::new(-dir.y, dir.x);
+    d.draw_triangle(
+        to,
+        to - dir * 8.0 - side * 4.0,
+        to - dir * 8.0 + side * 4.0,
+        color,
+    );
+}
+
+impl DebugOverlay {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        if !self.enabled {
+            self.selected = None;
+        }
+    }
+
+    // Contacts are gathered while simulating and drawn with the frame
+    pub fn clear_contacts(&mut self) {
+        self.contacts.clear();
+    }
+
+    pub fn record_contact(&mut self, contact: &Contact) {
+        if self.enabled {
+            self.contacts.push((contact.point, contact.normal));
+        }
+    }
+
+    // Selects the top-most entity under `point`, players first
+    pub fn click(
+        &mut self,
+        point: Vector2,
+        players: &[Player],
+        bullets: &[Bullet],
+        ops: &[EnvItem],
+    ) {
+        self.selected = players
+            .iter()
+            .position(|p| p.get_collision_rect().check_collision_point_rec(point))
+            .map(Entity::Player)
+            .or_else(|| {
+                bullets
+                    .iter()
+                    .position(|b| b.rect.check_collision_point_rec(point))
+                    .map(Entity::Bullet)
+            })
+            .or_else(|| {
+                ops.iter()
+                    .position(|op| op.rect.check_collision_point_rec(point))
+                    .map(Entity::EnvItem)
+            });
+    }
+
+    pub fn draw(
+        &mut self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        players: &[Player],
+        bullets: &[Bullet],
+        ops: &[EnvItem],
+    ) {
+        if !self.enabled {
+            return;
+        }
+        for op in ops {
+            d.draw_rectangle_rec(op.rect, op.color);
+            d.draw_rectangle_lines_ex(op.rect, 1.0, Color::DARKGRAY);
+        }
+        for player in players {
+            d.draw_rectangle_lines_ex(player.get_collision_rect(), 2.0, Color::LIME);
+            draw_arrow(
+                d,
+                player.position,
+                player.position + player.velocity * VELOCITY_SCALE,
+                Color::BLUE,
+            );
+        }
+        for bullet in bullets {
+            d.draw_rectangle_lines_ex(bullet.rect, 2.0, Color::ORANGE);
+            draw_arrow(
+                d,
+                bullet.center(),
+                bullet.center() + bullet.speed * VELOCITY_SCALE,
+                Color::BLUE,
+            );
+        }
+        for (point, normal) in &self.contacts {
+            d.draw_circle_v(*point, 3.0, Color::MAGENTA);
+            draw_arrow(d, *point, *point + *normal * NORMAL_LENGTH, Color::MAGENTA);
+        }
+
+        let Some(lines) = self.describe_selected(players, bullets, ops) else {
+            return;
+        };
+        let outline = match self.selected {
+            Some(Entity::Player(i)) => players[i].get_collision_rect(),
+            Some(Entity::Bullet(i)) => bullets[i].rect,
+            Some(Entity::EnvItem(i)) => ops[i].rect,
+            None => return,
+        };
+        d.draw_rectangle_lines_ex(outline, 3.0, Color::YELLOW);
+
+        let x = SCREEN_WIDTH - PANEL_WIDTH;
+        let height = 20 + lines.len() as i32 * (FONT_SIZE + 4);
+        d.draw_rectangle(x, 60, PANEL_WIDTH, height, Color::BLACK.alpha(0.75));
+        for (i, line) in lines.iter().enumerate() {
+            d.draw_text(
+                line,
+                x + 10,
+                70 + i as i32 * (FONT_SIZE + 4),
+                FONT_SIZE,
+                Color::WHITE,
+            );
+        }
+    }
+
+    // Field dump for the panel; drops the selection once the entity is gone
+    fn describe_selected(
+        &mut self,
+        players: &[Player],
+        bullets: &[Bullet],
+        ops: &[EnvItem],
+    ) -> Option<Vec<String>> {
+        let vec2 = |v: Vector2| format!("({:.1}, {:.1})", v.x, v.y);
+        let rect =
+            |r: Rectangle| format!("({:.0}, {:.0}) {:.0}x{:.0}", r.x, r.y, r.width, r.height);
+        let lines = match self.selected? {
+            Entity::Player(i) if i < players.len() => {
+                let p = &players[i];
+                vec![
+                    format!("Player {}", p.number + 1),
+                    format!("position {}", vec2(p.position)),
+                    format!("velocity {}", vec2(p.velocity)),
+                    format!("on ground {}", p.is_on_ground),
+                    format!("jumping {} ({:.2}s)", p.is_jumping, p.jump_time),
+                    format!("dead {}", p.dead),
+                    format!("invulnerable {:.2}s", p.invulnerable),
+                    format!("spawn protection {:.2}s", p.spawn_protection),
+                    format!("hit flash {:.2}s", p.hit_flash),
+                    format!("points {}", p.points),
+                    format!("game {}", p.game.name()),
+                    format!("speed {:.0} jump {:.0}", p.speed, p.jump_force),
+                    format!("gravity {:.1}", p.gravity),
+                    format!("profile {:?}", p.profile),
+                ]
+            }
+            Entity::Bullet(i) if i < bullets.len() => {
+                let b = &bullets[i];
+                vec![
+                    format!("Bullet {}", i),
+                    format!("rect {}", rect(b.rect)),
+                    format!("speed {}", vec2(b.speed)),
+                    format!("gravity {:.1}", b.gravity),
+                    format!("time to live {:.2}s", b.time_to_live),
+                    format!("source {}", b.source.describe()),
+                ]
+            }
+            Entity::EnvItem(i) if i < ops.len() => {
+                let op = &ops[i];
+                vec![
+                    format!("EnvItem {}", i),
+                    format!("rect {}", rect(op.rect)),
+                    format!("shape {:?}", op.shape),
+                    format!("layer {} z {}", op.layer.key(), op.z),
+                    format!(
+                        "color {} {} {} {}",
+                        op.color.r, op.color.g, op.color.b, op.color.a
+                    ),
+                ]
+            }
+            _ => {
+                self.selected = None;
+                return None;
+            }
+        };
+        Some(lines)
+    }
+}
+
+// F5 freezes the simulation, F6 advances it by one FIXED_TICK while frozen and
+// F7 cycles 1x/0.5x/0.1x. Only active in debug builds.
+#[derive(Default)]
+pub struct TimeControl {
+    frozen: bool,
+    step: bool,
+    speed: usize, // index into SPEEDS
+}
+
+impl TimeControl {
+    pub fn update(&mut self, rl: &RaylibHandle) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+            self.frozen = !self.frozen;
+        }
+        self.step = self.frozen && rl.is_key_pressed(KeyboardKey::KEY_F6);
+        if rl.is_key_pressed(KeyboardKey::KEY_F7) {
+            self.speed = (self.speed + 1) % SPEEDS.len();
+        }
+    }
+
+    pub fn freeze(&mut self) {
+        if cfg!(debug_assertions) {
+            self.frozen = true;
+        }
+    }
+
+    // Simulation time for this frame
+    pub fn scale(&self, frame_time: f32) -> f32 {
+        if self.step {
+            FIXED_TICK
+        } else if self.frozen {
+            0.0
+        } else {
+            frame_time * SPEEDS[self.speed]
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
+        let label = if self.frozen {
+            "FROZEN - F6 steps, F5 resumes".to_string()
+        } else if self.speed != 0 {
+            format!("{}x speed", SPEEDS[self.speed])
+        } else {
+            return;
+        };
+        d.draw_text(&label, 10, SCREEN_HEIGHT - 30, 20, Color::RED);
+    }
+}
@@ -0,0 +1,324 @@
+// Headless host for online matches, `--dedicated [port]`: no window and no
+// textures, only the simulation and the netcode, so a Raspberry Pi or a VPS
+// can run it. Clients take seats as they say hello over UDP (see join.rs).
+// Rounds run back to back while at least two are seated, picked like the
+// game picks them: --mode, --script and --mutators apply here too. The first
+// to --points-to-win takes the match, every client gets it for its history
+// and the scores start over. Type `quit` to stop hosting.
+//
+// Rounds are played through sim.rs, the same step the game loop uses; the
+// paint map is kept as a CPU-side image.
+use crate::{
+    blank_paint_map,
+    cli::CliOptions,
+    coverage,
+    history::{self, MatchPlayer, MatchRecord, RoundRecord},
+    level::Level,
+    mutators,
+    netcode::{MAX_PACKET, SNAPSHOT_RATE},
+    on_round_start,
+    online::{Host, RoundInfo},
+    orchestrator::MatchOrchestrator,
+    pickups::Pickups,
+    rng::Rng,
+    scripted::ScriptedGame,
+    setpieces::SetPieces,
+    settings::{Quality, Settings},
+    sim::{self, Round, Stage},
+    spawn::{self, spawn_line},
+    spawners::Spawners,
+    Bullet, InputType, MiniGames, Player, JUMP_FORCE, PLAYER_COLORS, PLAYER_SPEED, POINTS_TO_WIN,
+};
+use raylib::prelude::*;
+use std::{
+    io::{self, BufRead},
+    net::{SocketAddr, UdpSocket},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_PORT: u16 = 7777;
+const TICK: f32 = 1.0 / 60.0;
+// a client not heard from for this long gives up its seat
+const CLIENT_TIMEOUT: f32 = 10.0;
+// seconds between a round's result and the next round
+const ROUND_BREAK: f32 = 5.0;
+
+// Players are never drawn here. raylib skips unloading a texture with id 0,
+// so this is safe to drop without a GPU.
+fn no_texture() -> Texture2D {
+    unsafe {
+        Texture2D::from_raw(raylib::ffi::Texture {
+            id: 0,
+            width: 0,
+            height: 0,
+            mipmaps: 0,
+            format: 0,
+        })
+    }
+}
+
+// The seat's profile name, or P<n>
+fn name(host: &Host<SocketAddr>, seat: usize) -> String {
+    host.profile(seat)
+        .map_or_else(|| format!("P{}", seat + 1), |p| p.name.clone())
+}
+
+// Lines typed on the console, read on their own thread so the tick loop
+// never waits for them
+fn console_lines() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+pub fn run(
+    options: &CliOptions,
+    port: u16,
+    level: Level,
+    mut script: Option<ScriptedGame>,
+) -> Result<(), String> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .map_err(|err| format!("could not listen on port {}: {}", port, err))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|err| format!("could not set up the socket: {}", err))?;
+    let seats = options.players.unwrap_or(4);
+    let points_to_win = options.points_to_win.unwrap_or(POINTS_TO_WIN);
+    // local round times are this machine's business, not the players'
+    let settings = Settings::default();
+    let mutators = options.mutators.clone();
+    let mut orchestrator =
+        MatchOrchestrator::new(script.as_ref().map_or(options.mode, |s| Some(s.base)));
+    let mut rng = Rng::new(options.seed.unwrap_or_else(history::now));
+    let mut game = orchestrator.first_game(MiniGames::ColorTheMap, &mut rng);
+
+    let mut paint_mask = level.load_paint_mask()?;
+    let mut ops = level.items;
+    let paint_area = coverage::paintable_area(&ops, &paint_mask);
+    let mut setpieces = SetPieces::new(level.setpieces);
+    let mut spawners = Spawners::new(level.spawners);
+    let mut pickups = Pickups::default();
+    let mut bullets: Vec<Bullet> = Vec::new();
+    let mut players: Vec<Player> = (0..seats)
+        .map(|i| {
+            Player::new(
+                spawn_line(i as u32),
+                0.0,
+                PLAYER_SPEED,
+                Color::from_hex(PLAYER_COLORS[i]).unwrap(),
+                InputType::Controller(i),
+                Box::new(game),
+                50.0,
+                50.0,
+                JUMP_FORCE,
+                no_texture(),
+                i as u32,
+            )
+        })
+        .collect();
+    mutators::apply_to_players(&mutators, &mut players);
+    let mut paint = blank_paint_map(Quality::High);
+    let mut host: Host<SocketAddr> = Host::new(0..seats, &paint);
+    let console = console_lines();
+    eprintln!(
+        "hosting {} for {} players on port {}, {}, type quit to stop",
+        level.name,
+        seats,
+        port,
+        mutators::describe(&mutators)
+    );
+
+    let mut round = Round::default();
+    // false while waiting for players
+    let mut playing = false;
+    let mut break_timer = 0.0;
+    // the round that just ended was the match's last
+    let mut match_over = false;
+    let mut rounds: Vec<RoundRecord> = Vec::new();
+    let mut stalled: Vec<usize> = Vec::new();
+    let started = Instant::now();
+    let mut next_tick = started;
+    let mut ticks: u64 = 0;
+    let snapshot_every = (1.0 / (SNAPSHOT_RATE * TICK)).round() as u64;
+    let mut buffer = [0; MAX_PACKET];
+
+    // A round of `game` from the top, with the board wiped
+    macro_rules! start_round {
+        () => {
+            let config = orchestrator.configs.get(game);
+            round.time_left = orchestrator.round_time(game, &settings, &mutators);
+            round.reset();
+            paint = blank_paint_map(Quality::High);
+            bullets.clear();
+            setpieces.reset(&mut ops);
+            spawners.reset();
+            pickups.reset(config.paint_bomb_interval);
+            spawn::respawn_all(&mut players, &bullets, &ops, config.bounds);
+            on_round_start(&mut players);
+            eprintln!("{} started", game.name());
+        };
+    }
+
+    loop {
+        match console.try_recv().as_deref().map(str::trim) {
+            Ok("quit") => break,
+            Ok("") | Err(mpsc::TryRecvError::Empty) => {}
+            Ok(other) => eprintln!("unknown command {}, type quit to stop", other),
+            // no console to read from, e.g. started from a service
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        let now = started.elapsed().as_secs_f32();
+        while let Ok((len, from)) = socket.recv_from(&mut buffer) {
+            let seated = host.seated();
+            if let Some(reply) = host.receive(from, &buffer[..len], now) {
+                let _ = socket.send_to(&reply, from);
+            }
+            if let Some(seat) = host.seat_of(&from).filter(|_| host.seated() > seated) {
+                eprintln!(
+                    "{} joined from {}, {} seated",
+                    name(&host, seat),
+                    from,
+                    host.seated()
+                );
+            }
+        }
+        for seat in host.drop_silent(now, CLIENT_TIMEOUT) {
+            eprintln!("P{} timed out, {} seated", seat + 1, host.seated());
+        }
+        let stats = host.stats(now);
+        for connection in &stats {
+            let was_stalled = stalled.contains(&connection.seat);
+            if connection.stalled && !was_stalled {
+                eprintln!("{} connection unstable", connection.name);
+            } else if !connection.stalled && was_stalled {
+                eprintln!(
+                    "{} back, {:.0} ms ping",
+                    connection.name,
+                    connection.ping * 1000.0
+                );
+            }
+        }
+        stalled = stats.iter().filter(|c| c.stalled).map(|c| c.seat).collect();
+
+        if host.seated() < 2 {
+            if playing {
+                eprintln!("waiting for players");
+            }
+            playing = false;
+        } else if !playing {
+            playing = true;
+            start_round!();
+        }
+        if playing {
+            let inputs: Vec<_> = (0..seats).map(|seat| host.input(seat)).collect();
+            for (player, input) in players.iter_mut().zip(&inputs) {
+                player.tick_timers(TICK);
+                // free seats sit the round out as if knocked out
+                if input.is_none() {
+                    player.dead = true;
+                }
+            }
+            let mut stage = Stage {
+                game,
+                config: orchestrator.configs.get(game),
+                mutators: &mutators,
+                players: &mut players,
+                bullets: &mut bullets,
+                paint: &mut paint,
+                paint_mask: &mut paint_mask,
+                paint_area,
+                ops: &mut ops,
+                setpieces: &mut setpieces,
+                spawners: &mut spawners,
+                pickups: &mut pickups,
+                rng: &mut rng,
+                script: script.as_mut(),
+            };
+            let step = sim::step(&mut stage, &mut round, &inputs, false, TICK);
+            for message in step.messages {
+                eprintln!("{}", message);
+            }
+            if let Some(end) = step.ended {
+                let winners: Vec<String> = end.winners.iter().map(|s| name(&host, *s)).collect();
+                match winners.as_slice() {
+                    [] => eprintln!("nobody took the round"),
+                    _ => eprintln!("{} took the round", winners.join(" and ")),
+                }
+                rounds.push(RoundRecord {
+                    game,
+                    winners: end.winners,
+                });
+                break_timer = ROUND_BREAK;
+                if let Some(winner) = (0..seats).find(|s| players[*s].points >= points_to_win) {
+                    eprintln!("{} won the match", name(&host, winner));
+                    let seated: Vec<usize> =
+                        (0..seats).filter(|s| host.input(*s).is_some()).collect();
+                    // winners are seats, a record's are places in its players
+                    let place = |seat: &usize| seated.iter().position(|s| s == seat);
+                    let record = MatchRecord {
+                        timestamp: history::now(),
+                        players: seated
+                            .iter()
+                            .map(|seat| MatchPlayer {
+                                name: name(&host, *seat),
+                                has_profile: host.profile(*seat).is_some(),
+                                points: players[*seat].points,
+                            })
+                            .collect(),
+                        rounds: rounds
+                            .drain(..)
+                            .map(|round| RoundRecord {
+                                game: round.game,
+                                winners: round.winners.iter().filter_map(place).collect(),
+                            })
+                            .collect(),
+                    };
+                    host.finish_match(&record);
+                    for player in &mut players {
+                        player.points = 0;
+                    }
+                    match_over = true;
+                }
+            }
+            if round.done {
+                break_timer -= TICK;
+                if break_timer <= 0.0 {
+                    game = if match_over {
+                        orchestrator.rematch(game, &mut rng)
+                    } else {
+                        orchestrator.next_game(game, rounds.len(), &mut rng)
+                    };
+                    match_over = false;
+                    start_round!();
+                }
+            }
+        }
+        host.announce_round(RoundInfo {
+            game,
+            time_left: round.time_left.max(0.0),
+            done: round.done || !playing,
+        });
+
+        if ticks % snapshot_every == 0 {
+            for (to, packet) in host.broadcast(&players, &bullets, &mut paint, now) {
+                let _ = socket.send_to(&packet, to);
+            }
+        }
+        ticks += 1;
+        next_tick += Duration::from_secs_f32(TICK);
+        // a host that fell behind catches up rather than sleeping
+        if let Some(wait) = next_tick.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+    }
+    eprintln!("stopped hosting");
+    Ok(())
+}
@@ -0,0 +1,191 @@
+// Director view for people streaming a local tournament, toggled with F10.
+// It takes the camera away from the minigame: 1-4 follow a player, right
+// drag pans and the wheel zooms a free camera, 0 hands the shot back to the
+// game. A scoreboard sized to stay readable on a scaled-down stream runs
+// along the bottom, and debug overlays stay off the feed unless F4 lets
+// them back in.
+use crate::{
+    arena::ArenaCamera,
+    viewport::{Viewport, SCREEN_HEIGHT, SCREEN_WIDTH},
+    MiniGames, Player,
+};
+use raylib::prelude::*;
+
+const FOLLOW_ZOOM: f32 = 1.6;
+// How quickly the follow shot catches up, per second
+const FOLLOW_RATE: f32 = 5.0;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 4.0;
+const BOARD_HEIGHT: i32 = 70;
+const NAME_SIZE: i32 = 26;
+const POINTS_SIZE: i32 = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shot {
+    // whatever the minigame's camera does
+    Game,
+    Follow(usize),
+    Free,
+}
+
+pub struct Director {
+    pub enabled: bool,
+    // debug overlays drawn over the feed anyway
+    pub show_debug: bool,
+    shot: Shot,
+    target: Vector2,
+    zoom: f32,
+}
+
+impl Default for Director {
+    fn default() -> Director {
+        Director {
+            enabled: false,
+            show_debug: false,
+            shot: Shot::Game,
+            target: Viewport::size() * 0.5,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Director {
+    // Whether debug overlays belong on screen this frame
+    pub fn shows_debug(&self) -> bool {
+        !self.enabled || self.show_debug
+    }
+
+    pub fn update(&mut self, rl: &RaylibHandle, arena: &ArenaCamera, players: &[Player], dt: f32) {
+        if rl.is_key_pressed(KeyboardKey::KEY_F10) {
+            self.enabled = !self.enabled;
+            self.shot = Shot::Game;
+        }
+        if !self.enabled {
+            return;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_F4) {
+            self.show_debug = !self.show_debug;
+        }
+        let keys = [
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_FOUR,
+        ];
+        for (i, key) in keys.into_iter().enumerate() {
+            if i < players.len() && rl.is_key_pressed(key) {
+                self.shot = Shot::Follow(i);
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ZERO) {
+            self.shot = Shot::Game;
+        }
+
+        // grabbing the camera starts a free shot from wherever it was
+        let drag = rl.get_mouse_delta();
+        let dragging = rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT);
+        let wheel = rl.get_mouse_wheel_move();
+        if (dragging && drag != Vector2::zero()) || wheel != 0.0 {
+            if self.shot == Shot::Game {
+                self.target = arena.target;
+                self.zoom = arena.zoom;
+            }
+            self.shot = Shot::Free;
+        }
+        match self.shot {
+            Shot::Game => {
+                self.target = arena.target;
+                self.zoom = arena.zoom;
+            }
+            Shot::Follow(i) => match players.get(i) {
+                Some(player) => {
+                    let t = 1.0 - (-FOLLOW_RATE * dt).exp();
+                    self.target = self.target.lerp(player.position, t);
+                    self.zoom += (FOLLOW_ZOOM - self.zoom) * t;
+                }
+                // the player left the match
+                None => self.shot = Shot::Game,
+            },
+            Shot::Free => {
+                if dragging {
+                    self.target -= drag / self.zoom;
+                }
+                self.zoom = (self.zoom * (1.0 + wheel * 0.1)).clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+        }
+    }
+
+    pub fn camera(&self, arena: &ArenaCamera, viewport: &Viewport) -> Camera2D {
+        if self.enabled {
+            viewport.world_camera(self.target, self.zoom)
+        } else {
+            arena.camera(viewport)
+        }
+    }
+
+    // Virtual screen position (e.g. the mouse) to world position
+    pub fn virtual_to_world(&self, arena: &ArenaCamera, point: Vector2) -> Vector2 {
+        if self.enabled {
+            (point - Viewport::size() * 0.5) / self.zoom + self.target
+        } else {
+            arena.virtual_to_world(point)
+        }
+    }
+
+    // The scoreboard along the bottom, plus the minigame and clock up top
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        game: MiniGames,
+        time_left: f32,
+        players: &[Player],
+        names: &[String],
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let title = format!("{}  {}", game.name(), time_left.max(0.0).ceil() as i32);
+        let width = d.measure_text(&title, NAME_SIZE);
+        d.draw_rectangle(
+            SCREEN_WIDTH / 2 - width / 2 - 16,
+            0,
+            width + 32,
+            NAME_SIZE + 16,
+            Color::BLACK.alpha(0.75),
+        );
+        d.draw_text(
+            &title,
+            SCREEN_WIDTH / 2 - width / 2,
+            8,
+            NAME_SIZE,
+            Color::WHITE,
+        );
+
+        let y = SCREEN_HEIGHT - BOARD_HEIGHT;
+        d.draw_rectangle(0, y, SCREEN_WIDTH, BOARD_HEIGHT, Color::BLACK.alpha(0.75));
+        let slot = SCREEN_WIDTH / players.len().max(1) as i32;
+        for (i, (player, name)) in players.iter().zip(names).enumerate() {
+            let x = slot * i as i32;
+            let followed = self.shot == Shot::Follow(i);
+            d.draw_rectangle(x + 12, y + 12, 10, BOARD_HEIGHT - 24, player.color);
+            if followed {
+                d.draw_rectangle_lines(x + 4, y + 4, slot - 8, BOARD_HEIGHT - 8, Color::WHITE);
+            }
+            let name_color = if player.dead {
+                Color::GRAY
+            } else {
+                Color::WHITE
+            };
+            d.draw_text(name, x + 32, y + 22, NAME_SIZE, name_color);
+            let points = player.points.to_string();
+            let points_width = d.measure_text(&points, POINTS_SIZE);
+            d.draw_text(
+                &points,
+                x + slot - points_width - 20,
+                y + (BOARD_HEIGHT - POINTS_SIZE) / 2,
+                POINTS_SIZE,
+                player.color,
+            );
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// Gameplay events. Systems push what happened during the frame and they are
+// handled in order, so a hit is resolved in one place no matter what
+// detected it: sim.rs despawns and knocks out, the game loop keeps the
+// stats, the feed and the cues. Handling an event may push follow-up
+// events, which are handled in the same frame.
+use crate::{pickups::PickupKind, BulletSource};
+use raylib::prelude::*;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    // bullet and player are indexes into the frame's bullets and players
+    BulletHit {
+        bullet: usize,
+        player: usize,
+    },
+    // `player` touched a setpiece's lava
+    Burned {
+        player: usize,
+    },
+    // a setpiece's beam swept `player`: it knocks them out, or shoves them
+    // at `knockback` when it has one
+    Swept {
+        player: usize,
+        knockback: Option<Vector2>,
+    },
+    // a player was knocked out this frame, `by` gets the credit
+    Eliminated {
+        player: usize,
+        by: BulletSource,
+    },
+    // `player` touched a pickup
+    PickedUp {
+        player: usize,
+        kind: PickupKind,
+    },
+    // a paint bomb carried by `player` went off at `position`
+    PaintBomb {
+        player: usize,
+        position: Vector2,
+    },
+    // `player` gave no input for afk::AFK_TIME seconds
+    WentAfk {
+        player: usize,
+    },
+    // an away player pressed something again
+    Returned {
+        player: usize,
+    },
+    // `player` sent quickchat::MESSAGES[message]
+    QuickChat {
+        player: usize,
+        message: usize,
+    },
+    // a round was decided; no winners for a round nobody won
+    RoundWon {
+        winners: Vec<usize>,
+    },
+    // `player` won another round in a row
+    StreakExtended {
+        player: usize,
+        streak: u32,
+    },
+    // `player` won the round that ended the `victim`'s streak
+    Shutdown {
+        player: usize,
+        victim: usize,
+        streak: u32,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<GameEvent> {
+        self.events.pop_front()
+    }
+}
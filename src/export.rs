@@ -0,0 +1,142 @@
+// Dumps match history as JSON and CSV for external standings sheets. Run from
+// the History screen or with `--export-stats [dir]`.
+use crate::{
+    history::{self, MatchHistory, MatchRecord},
+    json::Json,
+};
+use std::{fs, io, path::Path};
+
+pub const EXPORT_DIR: &str = "exports";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+    fields.join(",") + "\n"
+}
+
+fn winner_name(record: &MatchRecord) -> String {
+    record
+        .winner()
+        .map(|i| record.players[i].name.clone())
+        .unwrap_or_default()
+}
+
+fn match_json(id: usize, record: &MatchRecord) -> Json {
+    Json::object(vec![
+        ("match", id.into()),
+        ("timestamp", record.timestamp.into()),
+        ("date", history::format_date(record.timestamp).into()),
+        ("winner", winner_name(record).into()),
+        (
+            "players",
+            Json::Array(
+                record
+                    .players
+                    .iter()
+                    .map(|p| {
+                        Json::object(vec![
+                            ("name", p.name.as_str().into()),
+                            ("has_profile", p.has_profile.into()),
+                            ("points", p.points.into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "rounds",
+            Json::Array(
+                record
+                    .rounds
+                    .iter()
+                    .enumerate()
+                    .map(|(i, round)| {
+                        let winners: Vec<&str> = round
+                            .winners
+                            .iter()
+                            .filter_map(|w| record.players.get(*w))
+                            .map(|p| p.name.as_str())
+                            .collect();
+                        Json::object(vec![
+                            ("round", (i + 1).into()),
+                            ("game", round.game.name().into()),
+                            ("winners", winners.into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+// Writes stats.json, matches.csv (one row per player per match) and
+// rounds.csv (one row per round) into `dir`
+pub fn export_stats(history: &MatchHistory, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let matches: Vec<Json> = history
+        .matches
+        .iter()
+        .enumerate()
+        .map(|(i, record)| match_json(i + 1, record))
+        .collect();
+    fs::write(
+        dir.join("stats.json"),
+        Json::object(vec![("matches", matches.into())]).to_string(),
+    )?;
+
+    let mut matches_csv = csv_row(&[
+        "match".into(),
+        "date".into(),
+        "timestamp".into(),
+        "player".into(),
+        "has_profile".into(),
+        "points".into(),
+        "won".into(),
+    ]);
+    let mut rounds_csv = csv_row(&[
+        "match".into(),
+        "round".into(),
+        "game".into(),
+        "winners".into(),
+    ]);
+    for (i, record) in history.matches.iter().enumerate() {
+        let id = (i + 1).to_string();
+        let winner = record.winner();
+        for (p, player) in record.players.iter().enumerate() {
+            matches_csv += &csv_row(&[
+                id.clone(),
+                history::format_date(record.timestamp),
+                record.timestamp.to_string(),
+                player.name.clone(),
+                player.has_profile.to_string(),
+                player.points.to_string(),
+                (winner == Some(p)).to_string(),
+            ]);
+        }
+        for (r, round) in record.rounds.iter().enumerate() {
+            let winners: Vec<&str> = round
+                .winners
+                .iter()
+                .filter_map(|w| record.players.get(*w))
+                .map(|p| p.name.as_str())
+                .collect();
+            rounds_csv += &csv_row(&[
+                id.clone(),
+                (r + 1).to_string(),
+                round.game.name().to_string(),
+                winners.join(";"),
+            ]);
+        }
+    }
+    fs::write(dir.join("matches.csv"), matches_csv)?;
+    fs::write(dir.join("rounds.csv"), rounds_csv)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// Short-lived lines in the top-right corner announcing what just happened in
+// the match (streaks, shutdowns, ...). Filled from the game event queue.
+use crate::viewport::SCREEN_WIDTH;
+use raylib::prelude::*;
+
+const ENTRY_TIME: f32 = 4.0;
+const FADE_TIME: f32 = 0.5;
+const MAX_ENTRIES: usize = 5;
+
+struct Entry {
+    text: String,
+    color: Color,
+    time_left: f32,
+}
+
+#[derive(Default)]
+pub struct EventFeed {
+    entries: Vec<Entry>,
+}
+
+impl EventFeed {
+    pub fn push(&mut self, text: String, color: Color) {
+        self.entries.push(Entry {
+            text,
+            color,
+            time_left: ENTRY_TIME,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for entry in &mut self.entries {
+            entry.time_left -= dt;
+        }
+        self.entries.retain(|entry| entry.time_left > 0.0);
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
+        for (i, entry) in self.entries.iter().enumerate() {
+            let alpha = (entry.time_left / FADE_TIME).min(1.0);
+            let width = d.measure_text(&entry.text, 20);
+            let x = SCREEN_WIDTH - width - 20;
+            let y = 20 + i as i32 * 28;
+            d.draw_rectangle(
+                x - 8,
+                y - 4,
+                width + 16,
+                28,
+                Color::BLACK.alpha(0.4 * alpha),
+            );
+            d.draw_text(&entry.text, x, y, 20, entry.color.alpha(alpha));
+        }
+    }
+}
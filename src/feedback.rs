@@ -0,0 +1,191 @@
+// Feedback that concerns a single player. Four people share one screen, so
+// nothing here is global: each effect is routed to the player it is about.
+// Their own sprite shakes (raylib 5.0 has no gamepad vibration, so this
+// stands in for the rumble) and a popup in their colour rises over their
+// head. The hit flash is drawn on the sprite itself, see Player::draw.
+//
+// Seats with audio cues on (Settings, for low-vision players) also hear
+// each effect, panned to where their player is on screen, plus a beep that
+// speeds up and rises as a bullet closes in on them; a close one shakes the
+// sprite too. See cues.rs for the sounds.
+use crate::{
+    cues::{Cue, CueSounds},
+    viewport::SCREEN_WIDTH,
+    Bullet, BulletSource, Player,
+};
+use raylib::prelude::*;
+
+const POPUP_TIME: f32 = 1.2;
+// how far a popup rises over its lifetime
+const POPUP_RISE: f32 = 40.0;
+const POPUP_SIZE: i32 = 22;
+// bullets heading for a player are warned about from this far
+const WARN_RANGE: f32 = 350.0;
+const SHAKE_RANGE: f32 = 120.0;
+// seconds between approach beeps, far and close
+const BEEP_SLOWEST: f32 = 0.5;
+const BEEP_FASTEST: f32 = 0.08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    // won the round, worth a point
+    Scored,
+    Eliminated,
+    Grabbed,
+    // their paint bomb went off
+    Splat,
+    // no popup for these two, they only have a cue
+    RoundStart,
+    // the round ended without them winning it
+    RoundOver,
+}
+
+impl Feedback {
+    fn text(&self) -> Option<&'static str> {
+        match self {
+            Feedback::Scored => Some("+1"),
+            Feedback::Eliminated => Some("OUT"),
+            Feedback::Grabbed => Some("BOMB!"),
+            Feedback::Splat => Some("SPLAT!"),
+            Feedback::RoundStart | Feedback::RoundOver => None,
+        }
+    }
+
+    fn cue(&self) -> Cue {
+        match self {
+            Feedback::Scored => Cue::Won,
+            Feedback::Eliminated => Cue::Out,
+            Feedback::Grabbed | Feedback::Splat => Cue::Blip,
+            Feedback::RoundStart => Cue::RoundStart,
+            Feedback::RoundOver => Cue::RoundOver,
+        }
+    }
+
+    // seconds the player's sprite shakes for
+    fn shake(&self) -> f32 {
+        match self {
+            Feedback::Scored | Feedback::Grabbed => 0.0,
+            Feedback::RoundStart | Feedback::RoundOver => 0.0,
+            Feedback::Eliminated => 0.4,
+            Feedback::Splat => 0.25,
+        }
+    }
+}
+
+struct Popup {
+    player: usize,
+    text: &'static str,
+    time_left: f32,
+}
+
+#[derive(Default)]
+pub struct FeedbackRouter {
+    popups: Vec<Popup>,
+    // seats that hear audio cues, from the settings
+    pub cues: [bool; 4],
+    // opened with the first cue, so nobody without cues needs audio
+    sounds: Option<CueSounds>,
+    no_audio: bool,
+    // seconds to the next approach beep, per seat
+    beep_in: [f32; 4],
+}
+
+impl FeedbackRouter {
+    pub fn send(&mut self, players: &mut [Player], player: usize, feedback: Feedback) {
+        let Some(target) = players.get_mut(player) else {
+            return;
+        };
+        target.shake = target.shake.max(feedback.shake());
+        let position = target.position;
+        if let Some(text) = feedback.text() {
+            self.popups.push(Popup {
+                player,
+                text,
+                time_left: POPUP_TIME,
+            });
+        }
+        self.play(player, position, feedback.cue(), 1.0);
+    }
+
+    fn play(&mut self, seat: usize, position: Vector2, cue: Cue, pitch: f32) {
+        if !self.cues[seat % 4] || self.no_audio {
+            return;
+        }
+        if self.sounds.is_none() {
+            self.sounds = CueSounds::open();
+            self.no_audio = self.sounds.is_none();
+        }
+        if let Some(sounds) = &self.sounds {
+            sounds.play(cue, seat, position.x / SCREEN_WIDTH as f32, pitch);
+        }
+    }
+
+    // Beeps for each cue seat the nearest bullet heading its way
+    pub fn warn(&mut self, players: &mut [Player], bullets: &[Bullet], dt: f32) {
+        for (seat, player) in players.iter_mut().enumerate() {
+            if !self.cues[seat % 4] || player.dead {
+                continue;
+            }
+            self.beep_in[seat % 4] -= dt;
+            let closest = bullets
+                .iter()
+                .filter(|bullet| bullet.source != BulletSource::Thrown(seat))
+                .filter_map(|bullet| {
+                    let center = Vector2::new(
+                        bullet.rect.x + bullet.rect.width / 2.0,
+                        bullet.rect.y + bullet.rect.height / 2.0,
+                    );
+                    let to_player = player.position - center;
+                    let distance = to_player.length();
+                    (distance < WARN_RANGE && bullet.speed.dot(to_player) > 0.0).then_some(distance)
+                })
+                .min_by(|a, b| a.total_cmp(b));
+            let Some(distance) = closest else {
+                continue;
+            };
+            let closeness = 1.0 - distance / WARN_RANGE;
+            if distance < SHAKE_RANGE {
+                player.shake = player.shake.max(0.1);
+            }
+            if self.beep_in[seat % 4] <= 0.0 {
+                self.beep_in[seat % 4] = BEEP_SLOWEST + (BEEP_FASTEST - BEEP_SLOWEST) * closeness;
+                self.play(seat, player.position, Cue::Approach, 1.0 + closeness);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.popups.clear();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for popup in &mut self.popups {
+            popup.time_left -= dt;
+        }
+        self.popups.retain(|popup| popup.time_left > 0.0);
+    }
+
+    // Popups follow their player, newer ones stack above older ones
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player]) {
+        let mut stacked = [0; 4];
+        for popup in self.popups.iter().rev() {
+            let Some(player) = players.get(popup.player) else {
+                continue;
+            };
+            let progress = 1.0 - popup.time_left / POPUP_TIME;
+            let x = player.position.x as i32 - d.measure_text(popup.text, POPUP_SIZE) / 2;
+            let y = player.position.y - player.height / 2.0 - 30.0 - progress * POPUP_RISE;
+            let y = y as i32 - stacked[popup.player % 4] * POPUP_SIZE;
+            stacked[popup.player % 4] += 1;
+            let alpha = (popup.time_left / (POPUP_TIME / 3.0)).min(1.0);
+            d.draw_text(
+                popup.text,
+                x + 2,
+                y + 2,
+                POPUP_SIZE,
+                Color::BLACK.alpha(alpha),
+            );
+            d.draw_text(popup.text, x, y, POPUP_SIZE, player.color.alpha(alpha));
+        }
+    }
+}
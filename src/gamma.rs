@@ -0,0 +1,181 @@
+// Brightness calibration. Every frame is drawn into a render texture first,
+// then copied to the window through a gamma shader, so the setting covers
+// the whole picture (the pastel level art is hard to read on some TVs).
+// The calibration screen shows a test pattern and the gamma slider; it opens
+// on the first run and again from Settings.
+use crate::{
+    settings::{GAMMA_MAX, GAMMA_MIN},
+    viewport::{Viewport, SCREEN_WIDTH},
+};
+use raylib::prelude::*;
+
+const GAMMA_SHADER: &str = r#"#version 330
+in vec2 fragTexCoord;
+in vec4 fragColor;
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+uniform float gamma;
+out vec4 finalColor;
+
+void main() {
+    vec4 color = texture(texture0, fragTexCoord) * colDiffuse * fragColor;
+    finalColor = vec4(pow(color.rgb, vec3(1.0 / gamma)), color.a);
+}
+"#;
+
+// The background the game is drawn on and the player colours that have to
+// stand apart on it
+const PALETTE: [&str; 5] = ["C7DCD0", "FBB954", "A884F3", "1EBC73", "E83B3B"];
+
+pub struct PostProcess {
+    pub target: RenderTexture2D,
+    shader: Shader,
+    gamma_location: i32,
+    gamma: f32,
+}
+
+impl PostProcess {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        viewport: &Viewport,
+        gamma: f32,
+    ) -> Result<PostProcess, String> {
+        let target = load_target(rl, thread, viewport)?;
+        let mut shader = rl.load_shader_from_memory(thread, None, Some(GAMMA_SHADER));
+        let gamma_location = shader.get_shader_location("gamma");
+        shader.set_shader_value(gamma_location, gamma);
+        Ok(PostProcess {
+            target,
+            shader,
+            gamma_location,
+            gamma,
+        })
+    }
+
+    // Re-makes the render texture when the window changed size
+    pub fn fit(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, viewport: &Viewport) {
+        if self.target.texture.width == viewport.window.x as i32
+            && self.target.texture.height == viewport.window.y as i32
+        {
+            return;
+        }
+        match load_target(rl, thread, viewport) {
+            Ok(target) => self.target = target,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    pub fn set_gamma(&mut self, gamma: f32) {
+        if gamma != self.gamma {
+            self.gamma = gamma;
+            self.shader.set_shader_value(self.gamma_location, gamma);
+        }
+    }
+
+    // Copies the finished frame to the window
+    pub fn present(&self, d: &mut RaylibDrawHandle) {
+        let width = self.target.texture.width as f32;
+        let height = self.target.texture.height as f32;
+        let mut d = d.begin_shader_mode(&self.shader);
+        // render textures are stored upside down
+        d.draw_texture_rec(
+            &self.target,
+            Rectangle::new(0.0, 0.0, width, -height),
+            Vector2::zero(),
+            Color::WHITE,
+        );
+    }
+}
+
+fn load_target(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    viewport: &Viewport,
+) -> Result<RenderTexture2D, String> {
+    rl.load_render_texture(
+        thread,
+        viewport.window.x.max(1.0) as u32,
+        viewport.window.y.max(1.0) as u32,
+    )
+}
+
+// The test pattern and slider; returns the gamma picked this frame
+pub fn draw_calibration(d: &mut RaylibMode2D<'_, RaylibDrawHandle>, gamma: f32) -> f32 {
+    d.clear_background(Color::BLACK);
+    let title = "Brightness";
+    d.draw_text(
+        title,
+        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+        30,
+        35,
+        Color::WHITE,
+    );
+    let hint = "Move the slider until every square in both rows is just visible";
+    d.draw_text(
+        hint,
+        SCREEN_WIDTH / 2 - d.measure_text(hint, 20) / 2,
+        80,
+        20,
+        Color::LIGHTGRAY,
+    );
+
+    // near-black squares on black, near-white ones on white
+    let size = 70;
+    let x = SCREEN_WIDTH / 2 - size * 4;
+    for i in 0..8 {
+        let dark = (i * 6 + 4) as u8;
+        d.draw_rectangle(
+            x + i * size,
+            130,
+            size,
+            size,
+            Color::new(dark, dark, dark, 255),
+        );
+    }
+    d.draw_rectangle(x, 220, size * 8, size, Color::WHITE);
+    for i in 0..8 {
+        let light = (250 - i * 6) as u8;
+        d.draw_rectangle(
+            x + i * size + 10,
+            230,
+            size - 20,
+            size - 20,
+            Color::new(light, light, light, 255),
+        );
+    }
+    // the full grey ramp and the palette the game is drawn in
+    for i in 0..16 {
+        let grey = (i * 17) as u8;
+        d.draw_rectangle(
+            x + i * size / 2,
+            320,
+            size / 2,
+            40,
+            Color::new(grey, grey, grey, 255),
+        );
+    }
+    let swatch = size * 8 / PALETTE.len() as i32;
+    for (i, hex) in PALETTE.iter().enumerate() {
+        let color = Color::from_hex(hex).unwrap();
+        d.draw_rectangle(x + i as i32 * swatch, 380, swatch, 60, color);
+    }
+
+    let gamma = d.gui_slider_bar(
+        Rectangle::new((SCREEN_WIDTH / 2 - 200) as f32, 470.0, 400.0, 30.0),
+        Some(rstr!("Darker")),
+        Some(rstr!("Brighter")),
+        gamma,
+        GAMMA_MIN,
+        GAMMA_MAX,
+    );
+    let value = format!("gamma {:.2}", gamma);
+    d.draw_text(
+        &value,
+        SCREEN_WIDTH / 2 - d.measure_text(&value, 20) / 2,
+        510,
+        20,
+        Color::WHITE,
+    );
+    gamma
+}
@@ -0,0 +1,161 @@
+// Golden-image checks (`--golden`): renders a few fixed scenes through the
+// normal draw code, frozen in time, and compares each frame against the
+// reference PNG in tests/golden/. `--golden-update` rewrites the references
+// instead. Failing frames are written to target/golden/ with a diff image.
+use raylib::prelude::*;
+use std::{fs, path::PathBuf};
+
+pub const REFERENCE_DIR: &str = "tests/golden";
+pub const OUTPUT_DIR: &str = "target/golden";
+// day the menu's Daily line is pinned to, 2024-01-01
+pub const DAY: u64 = 19723;
+// frames a scene is drawn before it is captured, so textures are up to date
+const SETTLE_FRAMES: u32 = 2;
+// per-channel difference that still counts as the same pixel (driver rounding)
+const TOLERANCE: u8 = 2;
+// share of pixels allowed to differ before a scene fails
+const MAX_DIFFERENT: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    Menu,
+    ColorTheMap,
+    Dodge,
+}
+
+impl Scene {
+    pub const ALL: [Scene; 3] = [Scene::Menu, Scene::ColorTheMap, Scene::Dodge];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scene::Menu => "menu",
+            Scene::ColorTheMap => "color-the-map",
+            Scene::Dodge => "dodge",
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.png", self.name())
+    }
+}
+
+pub struct GoldenRun {
+    update: bool,
+    scene: usize,
+    frames: u32,
+    failures: Vec<String>,
+}
+
+fn same(a: Color, b: Color) -> bool {
+    a.r.abs_diff(b.r) <= TOLERANCE
+        && a.g.abs_diff(b.g) <= TOLERANCE
+        && a.b.abs_diff(b.b) <= TOLERANCE
+        && a.a.abs_diff(b.a) <= TOLERANCE
+}
+
+impl GoldenRun {
+    pub fn new(update: bool) -> GoldenRun {
+        GoldenRun {
+            update,
+            scene: 0,
+            frames: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    // None once every scene is done
+    pub fn scene(&self) -> Option<Scene> {
+        Scene::ALL.get(self.scene).copied()
+    }
+
+    // True on the first frame of a scene, when its state should be set up
+    pub fn starting(&self) -> bool {
+        self.frames == 0 && self.scene().is_some()
+    }
+
+    // Counts a drawn frame; true when this frame should be captured
+    pub fn frame_drawn(&mut self) -> bool {
+        self.frames += 1;
+        self.scene().is_some() && self.frames > SETTLE_FRAMES
+    }
+
+    pub fn finish_scene(&mut self, mut frame: Image) {
+        let Some(scene) = self.scene() else {
+            return;
+        };
+        self.scene += 1;
+        self.frames = 0;
+        let reference_path = PathBuf::from(REFERENCE_DIR).join(scene.file_name());
+
+        if self.update {
+            let _ = fs::create_dir_all(REFERENCE_DIR);
+            frame.export_image(&reference_path.to_string_lossy());
+            eprintln!("golden: wrote {}", reference_path.display());
+            return;
+        }
+        let result = match Image::load_image(&reference_path.to_string_lossy()) {
+            Ok(mut reference) => self.compare(scene, &mut frame, &mut reference),
+            Err(_) => Err(format!(
+                "no reference at {}, run with --golden-update",
+                reference_path.display()
+            )),
+        };
+        match result {
+            Ok(()) => eprintln!("golden: {} ok", scene.name()),
+            Err(err) => {
+                let _ = fs::create_dir_all(OUTPUT_DIR);
+                let actual = PathBuf::from(OUTPUT_DIR).join(scene.file_name());
+                frame.export_image(&actual.to_string_lossy());
+                eprintln!("golden: {} FAILED: {}", scene.name(), err);
+                self.failures.push(scene.name().to_string());
+            }
+        }
+    }
+
+    fn compare(
+        &self,
+        scene: Scene,
+        frame: &mut Image,
+        reference: &mut Image,
+    ) -> Result<(), String> {
+        if (frame.width, frame.height) != (reference.width, reference.height) {
+            return Err(format!(
+                "frame is {}x{}, reference is {}x{}",
+                frame.width, frame.height, reference.width, reference.height
+            ));
+        }
+        let mut diff = Image::gen_image_color(frame.width, frame.height, Color::BLACK);
+        let mut different = 0;
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                if !same(frame.get_color(x, y), reference.get_color(x, y)) {
+                    different += 1;
+                    diff.draw_pixel(x, y, Color::RED);
+                }
+            }
+        }
+        let share = different as f32 / (frame.width * frame.height) as f32;
+        if share <= MAX_DIFFERENT {
+            return Ok(());
+        }
+        let _ = fs::create_dir_all(OUTPUT_DIR);
+        let diff_path = PathBuf::from(OUTPUT_DIR).join(format!("{}-diff.png", scene.name()));
+        diff.export_image(&diff_path.to_string_lossy());
+        Err(format!(
+            "{} pixels ({:.2}%) differ, see {}",
+            different,
+            share * 100.0,
+            diff_path.display()
+        ))
+    }
+
+    // Process exit code for the run
+    pub fn exit_code(&self) -> i32 {
+        if self.failures.is_empty() {
+            0
+        } else {
+            eprintln!("golden: failed {}", self.failures.join(", "));
+            1
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// Finished matches, kept in save/history.json for the History screen
+use crate::{json::Json, storage, MiniGames};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE: &str = "history.json";
+pub const MAX_MATCHES: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct MatchPlayer {
+    pub name: String,
+    pub has_profile: bool,
+    pub points: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoundRecord {
+    pub game: MiniGames,
+    pub winners: Vec<usize>, // indexes into MatchRecord::players, empty on a no-winner round
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchRecord {
+    pub timestamp: u64,
+    pub players: Vec<MatchPlayer>,
+    pub rounds: Vec<RoundRecord>,
+}
+
+impl RoundRecord {
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("game", format!("{:?}", self.game).into()),
+            ("winners", self.winners.clone().into()),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Option<RoundRecord> {
+        Some(RoundRecord {
+            game: MiniGames::from_key(json.get("game")?.as_str()?)?,
+            winners: json
+                .array("winners")
+                .iter()
+                .filter_map(|w| w.as_u32().map(|w| w as usize))
+                .collect(),
+        })
+    }
+}
+
+impl MatchRecord {
+    pub fn winner(&self) -> Option<usize> {
+        let best = self.players.iter().map(|p| p.points).max()?;
+        let mut leaders = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.points == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((index, _)), None) => Some(index),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("timestamp", self.timestamp.into()),
+            (
+                "players",
+                Json::Array(
+                    self.players
+                        .iter()
+                        .map(|p| {
+                            Json::object(vec![
+                                ("name", p.name.as_str().into()),
+                                ("has_profile", p.has_profile.into()),
+                                ("points", p.points.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "rounds",
+                Json::Array(self.rounds.iter().map(RoundRecord::to_json).collect()),
+            ),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Option<MatchRecord> {
+        let players = json
+            .array("players")
+            .iter()
+            .map(|p| MatchPlayer {
+                name: p.str_or("name", "?").to_string(),
+                has_profile: p.bool_or("has_profile", false),
+                points: p.u32_or("points", 0),
+            })
+            .collect();
+        let rounds = json
+            .array("rounds")
+            .iter()
+            .filter_map(RoundRecord::from_json)
+            .collect();
+        Some(MatchRecord {
+            timestamp: json.get("timestamp")?.as_u64()?,
+            players,
+            rounds,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadToHead {
+    pub a: String,
+    pub b: String,
+    pub a_wins: u32,
+    pub b_wins: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct MatchHistory {
+    pub matches: Vec<MatchRecord>,
+}
+
+impl MatchHistory {
+    pub fn load() -> Self {
+        let matches = storage::read_json(HISTORY_FILE)
+            .map(|json| {
+                json.array("matches")
+                    .iter()
+                    .filter_map(MatchRecord::from_json)
+                    .collect()
+            })
+            .unwrap_or_default();
+        MatchHistory { matches }
+    }
+
+    pub fn save(&self) {
+        let matches: Vec<Json> = self.matches.iter().map(MatchRecord::to_json).collect();
+        storage::write_json(
+            HISTORY_FILE,
+            &Json::object(vec![("matches", matches.into())]),
+        );
+    }
+
+    pub fn push(&mut self, record: MatchRecord) {
+        self.matches.push(record);
+        if self.matches.len() > MAX_MATCHES {
+            self.matches.remove(0);
+        }
+    }
+
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &MatchRecord> {
+        self.matches.iter().rev().take(count)
+    }
+
+    // Match wins between every pair of profiles that played each other,
+    // most played pairs first
+    pub fn head_to_head(&self) -> Vec<HeadToHead> {
+        let mut table: Vec<HeadToHead> = Vec::new();
+        for record in &self.matches {
+            let Some(winner) = record.winner() else {
+                continue;
+            };
+            if !record.players[winner].has_profile {
+                continue;
+            }
+            let winner_name = &record.players[winner].name;
+            for (i, loser) in record.players.iter().enumerate() {
+                if i == winner || !loser.has_profile || loser.name == *winner_name {
+                    continue;
+                }
+                let (a, b) = if *winner_name < loser.name {
+                    (winner_name, &loser.name)
+                } else {
+                    (&loser.name, winner_name)
+                };
+                let index = match table.iter().position(|h| h.a == *a && h.b == *b) {
+                    Some(index) => index,
+                    None => {
+                        table.push(HeadToHead {
+                            a: a.clone(),
+                            b: b.clone(),
+                            a_wins: 0,
+                            b_wins: 0,
+                        });
+                        table.len() - 1
+                    }
+                };
+                if table[index].a == *winner_name {
+                    table[index].a_wins += 1;
+                } else {
+                    table[index].b_wins += 1;
+                }
+            }
+        }
+        table.sort_by_key(|h| std::cmp::Reverse(h.a_wins + h.b_wins));
+        table
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// (year, month, day) in UTC for a unix timestamp
+pub fn civil_date(timestamp: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's days-to-civil algorithm
+    let z = (timestamp / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(players: &[(&str, bool, u32)]) -> MatchRecord {
+        MatchRecord {
+            timestamp: 0,
+            players: players
+                .iter()
+                .map(|(name, has_profile, points)| MatchPlayer {
+                    name: name.to_string(),
+                    has_profile: *has_profile,
+                    points: *points,
+                })
+                .collect(),
+            rounds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(86399), (1970, 1, 1));
+        assert_eq!(civil_date(86400), (1970, 1, 2));
+        // leap days, including the century rules
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date(1_709_164_800), (2024, 2, 29));
+        assert_eq!(civil_date(1_709_251_200), (2024, 3, 1));
+        assert_eq!(civil_date(4_107_542_400), (2100, 3, 1));
+        assert_eq!(civil_date(1_704_067_199), (2023, 12, 31));
+        assert_eq!(format_date(1_704_067_200), "2024-01-01");
+    }
+
+    #[test]
+    fn head_to_head_counts_profiles_only() {
+        let history = MatchHistory {
+            matches: vec![
+                record(&[("Bo", true, 3), ("Ada", true, 1)]),
+                record(&[("Ada", true, 3), ("Bo", true, 2), ("P3", false, 0)]),
+                record(&[("Bo", true, 3), ("Ada", true, 0), ("Cy", true, 1)]),
+                // a guest's win and a tie count for nobody
+                record(&[("P1", false, 3), ("Ada", true, 1)]),
+                record(&[("Ada", true, 2), ("Bo", true, 2)]),
+            ],
+        };
+        let table = history.head_to_head();
+        assert_eq!(
+            table,
+            vec![
+                HeadToHead {
+                    a: "Ada".to_string(),
+                    b: "Bo".to_string(),
+                    a_wins: 1,
+                    b_wins: 2,
+                },
+                HeadToHead {
+                    a: "Bo".to_string(),
+                    b: "Cy".to_string(),
+                    a_wins: 1,
+                    b_wins: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut original = record(&[("Ada", true, 3), ("P2", false, 1)]);
+        original.timestamp = 1_704_067_200;
+        original.rounds = vec![RoundRecord {
+            game: MiniGames::Dodge,
+            winners: vec![0],
+        }];
+        let json = Json::parse(&original.to_json().to_string()).unwrap();
+        let back = MatchRecord::from_json(&json).unwrap();
+        assert_eq!(back.timestamp, original.timestamp);
+        assert_eq!(back.winner(), Some(0));
+        assert_eq!(back.players[1].name, "P2");
+        assert!(!back.players[1].has_profile);
+        assert_eq!(back.rounds[0].game, MiniGames::Dodge);
+        assert_eq!(back.rounds[0].winners, vec![0]);
+    }
+}
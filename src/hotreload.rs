@@ -0,0 +1,76 @@
+// Development-only (`--features dev`) watcher that polls file modification
+// times so art and level edits show up without restarting the game.
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const POLL_INTERVAL: f32 = 0.5;
+
+pub struct AssetWatcher {
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    seen: HashMap<PathBuf, SystemTime>,
+    timer: f32,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl AssetWatcher {
+    pub fn new() -> Self {
+        AssetWatcher {
+            dirs: Vec::new(),
+            files: Vec::new(),
+            seen: HashMap::new(),
+            timer: 0.0,
+        }
+    }
+
+    pub fn watch_dir(&mut self, dir: &Path) {
+        self.dirs.push(dir.to_path_buf());
+        self.scan();
+    }
+
+    pub fn watch_file(&mut self, file: &Path) {
+        self.files.push(file.to_path_buf());
+        self.scan();
+    }
+
+    fn watched(&self) -> Vec<PathBuf> {
+        let mut paths = self.files.clone();
+        for dir in &self.dirs {
+            if let Ok(entries) = fs::read_dir(dir) {
+                paths.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_file()));
+            }
+        }
+        paths
+    }
+
+    // Records current times, returning the files that are new or changed
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for path in self.watched() {
+            let Some(time) = modified(&path) else {
+                continue;
+            };
+            if self.seen.insert(path.clone(), time) != Some(time) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+
+    // Files changed since the last poll; checks the disk a couple of times a second
+    pub fn poll(&mut self, dt: f32) -> Vec<PathBuf> {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return Vec::new();
+        }
+        self.timer = POLL_INTERVAL;
+        self.scan()
+    }
+}
@@ -0,0 +1,69 @@
+// Hot-seat: more profiles than seats pass the controllers around. The
+// profiles not seated when the match starts queue up, and after each round
+// the seated profile with the fewest points sits out for the one at the
+// front of the queue. A benched player keeps their points for when their
+// turn comes back. Guests always stay seated, there'd be no telling them
+// apart in the queue.
+use crate::{profiles::ProfileStore, viewport::SCREEN_HEIGHT, Player};
+use raylib::prelude::*;
+use std::collections::VecDeque;
+
+#[derive(Debug, Default)]
+pub struct HotSeat {
+    pub enabled: bool,
+    // waiting profiles and the points they had when they got up
+    queue: VecDeque<(usize, u32)>,
+}
+
+impl HotSeat {
+    // Queues every profile that isn't seated, in the order of the store
+    pub fn start(&mut self, seated: &[Player], profile_count: usize) {
+        self.queue.clear();
+        if !self.enabled {
+            return;
+        }
+        for profile in 0..profile_count {
+            if !seated.iter().any(|p| p.profile == Some(profile)) {
+                self.queue.push_back((profile, 0));
+            }
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.enabled && !self.queue.is_empty()
+    }
+
+    // After a round: the lowest scorer swaps with the front of the queue.
+    // Returns the seat that changed hands and the profile that got up.
+    pub fn rotate(&mut self, seated: &mut [Player]) -> Option<(usize, usize)> {
+        if !self.active() {
+            return None;
+        }
+        let seat = seated
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.profile.is_some())
+            .min_by_key(|(_, p)| p.points)
+            .map(|(seat, _)| seat)?;
+        let (profile, points) = self.queue.pop_front()?;
+        let player = &mut seated[seat];
+        let benched = player.profile.replace(profile)?;
+        self.queue
+            .push_back((benched, std::mem::replace(&mut player.points, points)));
+        Some((seat, benched))
+    }
+
+    // The queue along the bottom of the round's HUD
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, profiles: &ProfileStore) {
+        if !self.active() {
+            return;
+        }
+        let names: Vec<String> = self
+            .queue
+            .iter()
+            .map(|(profile, points)| format!("{} ({})", profiles.name(Some(*profile)), points))
+            .collect();
+        let text = format!("Up next: {}", names.join(", "));
+        d.draw_text(&text, 20, SCREEN_HEIGHT - 30, 20, Color::DARKGRAY);
+    }
+}
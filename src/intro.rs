@@ -0,0 +1,104 @@
+// Match intro, played once before the first round: the arena camera pans
+// across the map while each player's character and name card slide in one
+// after the other. Any key or button skips it.
+use crate::{
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    Player,
+};
+use raylib::prelude::*;
+
+const PAN_TIME: f32 = 2.5;
+// when the first card starts sliding in, and the gap between cards
+const CARD_START: f32 = 0.4;
+const CARD_DELAY: f32 = 0.5;
+const CARD_SLIDE: f32 = 0.35;
+// time on the finished line-up before the round starts
+const HOLD_TIME: f32 = 1.2;
+const CARD_WIDTH: f32 = 300.0;
+const CARD_HEIGHT: f32 = 90.0;
+
+pub struct Intro {
+    time: f32,
+    cards: usize,
+}
+
+impl Intro {
+    pub fn new(cards: usize) -> Intro {
+        Intro { time: 0.0, cards }
+    }
+
+    fn duration(&self) -> f32 {
+        let last_card = CARD_START + self.cards as f32 * CARD_DELAY + CARD_SLIDE;
+        PAN_TIME.max(last_card) + HOLD_TIME
+    }
+
+    // How far along the camera flyby is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        (self.time / self.duration()).min(1.0)
+    }
+
+    // Returns true once the intro is over
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time += dt;
+        self.time >= self.duration()
+    }
+
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        players: &[Player],
+        names: &[String],
+    ) {
+        let title = "Get ready!";
+        d.draw_text(
+            title,
+            SCREEN_WIDTH / 2 - d.measure_text(title, 40) / 2,
+            30,
+            40,
+            Color::BLACK,
+        );
+        for (i, (player, name)) in players.iter().zip(names).take(self.cards).enumerate() {
+            let start = CARD_START + i as f32 * CARD_DELAY;
+            let t = ((self.time - start) / CARD_SLIDE).clamp(0.0, 1.0);
+            if t <= 0.0 {
+                continue;
+            }
+            // eases out, overshooting a little before it settles
+            let eased = 1.0 + 2.7 * (t - 1.0).powi(3) + 1.7 * (t - 1.0).powi(2);
+            let x = -CARD_WIDTH + eased * (CARD_WIDTH + 40.0);
+            let y = 110.0 + i as f32 * (CARD_HEIGHT + 20.0);
+            let card = Rectangle::new(x, y, CARD_WIDTH, CARD_HEIGHT);
+            d.draw_rectangle_rec(card, player.color.alpha(0.9));
+            d.draw_rectangle_lines_ex(card, 3.0, Color::BLACK);
+            d.draw_texture_ex(
+                player.texture.as_ref(),
+                Vector2::new(x + 15.0, y + 10.0),
+                0.0,
+                0.65,
+                Color::WHITE,
+            );
+            d.draw_text(
+                &format!("P{}", i + 1),
+                (x + 110.0) as i32,
+                (y + 12.0) as i32,
+                20,
+                Color::BLACK,
+            );
+            d.draw_text(
+                name,
+                (x + 110.0) as i32,
+                (y + 40.0) as i32,
+                30,
+                Color::BLACK,
+            );
+        }
+        let hint = "Press any button to skip";
+        d.draw_text(
+            hint,
+            SCREEN_WIDTH - d.measure_text(hint, 20) - 30,
+            SCREEN_HEIGHT - 40,
+            20,
+            Color::DARKGRAY,
+        );
+    }
+}
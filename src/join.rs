@@ -0,0 +1,176 @@
+// Playing on a dedicated host, `--connect <host[:port]>`. The host runs the
+// rounds; the window shows what it sends back and sends it what the local
+// player presses, with P1's controls whichever seat the host hands out. Our
+// own seat is played ahead of the host so moving and painting feel local.
+use crate::{
+    dedicated::DEFAULT_PORT,
+    netcode::MAX_PACKET,
+    online::{Client, Received},
+    profiles::Profile,
+    Bullet, BulletSource, EnvItem, Player, PlayerInput,
+};
+use raylib::prelude::*;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+// seconds between hellos until the host answers
+const HELLO_INTERVAL: f32 = 0.5;
+// how far the host may put our own player from where it was predicted
+// before the prediction is given up; a round trip at full speed is well
+// under this
+const RECONCILE_DISTANCE: f32 = 80.0;
+
+pub struct Session {
+    socket: UdpSocket,
+    pub host: SocketAddr,
+    pub client: Client,
+    hello_timer: f32,
+    // the host has no seat left
+    pub full: bool,
+}
+
+impl Session {
+    // `address` is a host name or IP, with DEFAULT_PORT when it names none
+    pub fn connect(
+        address: &str,
+        paint: &Image,
+        profile: Option<&Profile>,
+    ) -> Result<Session, String> {
+        let host = address
+            .to_socket_addrs()
+            .or_else(|_| (address, DEFAULT_PORT).to_socket_addrs())
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("could not find the host {}", address))?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))
+            .and_then(|socket| {
+                socket.connect(host)?;
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            })
+            .map_err(|err| format!("could not reach {}: {}", host, err))?;
+        Ok(Session {
+            socket,
+            host,
+            client: Client::new(paint, profile),
+            hello_timer: 0.0,
+            full: false,
+        })
+    }
+
+    // Sends this frame's input, or a hello until the host seats us, then
+    // takes in everything the host sent since the last frame
+    pub fn update(
+        &mut self,
+        input: PlayerInput,
+        players: &[Player],
+        map: &mut Image,
+        dt: f32,
+    ) -> Vec<Received> {
+        self.client.extrapolate(dt);
+        // an unreachable host shows up as silence, retrying is all there is
+        if self.client.seat.is_some() {
+            let _ = self.socket.send(&self.client.input(input));
+        } else if !self.full {
+            self.hello_timer -= dt;
+            if self.hello_timer <= 0.0 {
+                self.hello_timer = HELLO_INTERVAL;
+                let _ = self.socket.send(&self.client.hello());
+            }
+        }
+        let mut received = Vec::new();
+        let mut buffer = [0; MAX_PACKET];
+        while let Ok(len) = self.socket.recv(&mut buffer) {
+            match self.client.receive(&buffer[..len], players, map) {
+                Received::Nothing => {}
+                Received::Full => self.full = true,
+                other => received.push(other),
+            }
+        }
+        received
+    }
+
+    // Sends one of quickchat::MESSAGES for our seat, the host passes it on
+    pub fn chat(&self, message: usize) {
+        if let Some(packet) = self.client.chat(message) {
+            let _ = self.socket.send(&packet);
+        }
+    }
+
+    // Puts the host's latest snapshot on the local players and bullets,
+    // returns how many seats the host has. Our own seat keeps its predicted
+    // movement unless the host disagrees by more than RECONCILE_DISTANCE.
+    pub fn show(&self, players: &mut [Player], bullets: &mut Vec<Bullet>) -> Option<usize> {
+        let view = self.client.view.as_ref()?;
+        for (seat, (player, state)) in players.iter_mut().zip(&view.players).enumerate() {
+            let predicted = self.client.seat == Some(seat)
+                && !state.dead
+                && !player.dead
+                && player.position.distance_to(state.position) < RECONCILE_DISTANCE;
+            if !predicted {
+                state.restore(player);
+                continue;
+            }
+            let mut state = state.clone();
+            state.position = player.position;
+            state.velocity = player.velocity;
+            state.facing = player.facing;
+            state.is_on_ground = player.is_on_ground;
+            state.is_jumping = player.is_jumping;
+            state.jump_time = player.jump_time;
+            state.input = player.input;
+            state.restore(player);
+        }
+        *bullets = view
+            .bullets
+            .iter()
+            .map(|bullet| Bullet {
+                rect: bullet.rect,
+                color: bullet.color,
+                speed: bullet.speed,
+                gravity: bullet.gravity,
+                time_to_live: 1.0,
+                // the host keeps the score, the credit doesn't matter here
+                source: BulletSource::Script,
+            })
+            .collect();
+        Some(view.players.len().min(players.len()))
+    }
+
+    // Moves our own seat with this frame's input the way sim.rs would and
+    // paints its splats straight away; they stay on the map until the host's
+    // paint for the same input arrives
+    pub fn predict(
+        &mut self,
+        players: &mut [Player],
+        input: PlayerInput,
+        ops: &Vec<EnvItem>,
+        bounds: Rectangle,
+        map: &mut Image,
+        dt: f32,
+    ) {
+        let Some(seat) = self.client.seat.filter(|seat| *seat < players.len()) else {
+            return;
+        };
+        if players[seat].dead {
+            return;
+        }
+        let seated = self.client.seated();
+        let before = players.to_vec();
+        let others: Vec<&Player> = before
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != seat && seated.contains(i))
+            .map(|(_, p)| p)
+            .collect();
+        let player = &mut players[seat];
+        player.update(input, dt);
+        player.keep_inside(bounds);
+        let contacts = player.handle_collision(ops, others);
+        if contacts.is_empty() {
+            player.is_on_ground = false;
+        }
+        for point in contacts.iter().flat_map(|contact| contact.points.iter()) {
+            self.client.predict(map, player, *point);
+        }
+    }
+}
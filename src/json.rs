@@ -0,0 +1,481 @@
+// Tiny JSON reader/writer for save files, level files and exports.
+// Numbers are kept as f64 and objects keep their key order.
+
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(src: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: src.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(format!("trailing characters at {}", parser.pos));
+        }
+        Ok(value)
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|n| n as f32)
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        self.as_f64().map(|n| n.max(0.0) as u32)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_f64().map(|n| n.max(0.0) as u64)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    // Shorthands for reading optional fields with a fallback
+    pub fn f32_or(&self, key: &str, default: f32) -> f32 {
+        self.get(key).and_then(Json::as_f32).unwrap_or(default)
+    }
+
+    pub fn u32_or(&self, key: &str, default: u32) -> u32 {
+        self.get(key).and_then(Json::as_u32).unwrap_or(default)
+    }
+
+    pub fn bool_or(&self, key: &str, default: bool) -> bool {
+        self.get(key).and_then(Json::as_bool).unwrap_or(default)
+    }
+
+    pub fn str_or<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        self.get(key).and_then(Json::as_str).unwrap_or(default)
+    }
+
+    pub fn array(&self, key: &str) -> &[Json] {
+        self.get(key).map(Json::as_array).unwrap_or(&[])
+    }
+}
+
+impl From<f32> for Json {
+    fn from(n: f32) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(n: u32) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Json {
+        Json::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => {
+                if n.is_finite() {
+                    write!(f, "{}", n)
+                } else {
+                    f.write_str("null")
+                }
+            }
+            Json::String(s) => write_escaped(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", c, self.pos))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("unexpected '{}' at {}", c, self.pos)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            let c = self.peek().ok_or("unterminated string")?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escaped = self.peek().ok_or("unterminated escape")?;
+                    self.pos += 1;
+                    match escaped {
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars[self.pos..].iter().take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| format!("bad unicode escape at {}", self.pos))?;
+                            out.push(char::from_u32(code).unwrap_or('?'));
+                            self.pos += 4;
+                        }
+                        c => out.push(c),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| format!("bad number '{}' at {}", text, start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(json: &Json) -> Json {
+        Json::parse(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn writes_and_reads_back_a_save_file() {
+        let json = Json::object(vec![
+            ("name", "Ada".into()),
+            ("wins", 3u32.into()),
+            ("coverage", 0.25f32.into()),
+            ("guest", false.into()),
+            ("profile", Json::Null),
+            ("rounds", vec![1u32, 2, 3].into()),
+            (
+                "nested",
+                Json::object(vec![("list", Json::Array(vec![Json::object(vec![])]))]),
+            ),
+        ]);
+        assert_eq!(round_trip(&json), json);
+    }
+
+    #[test]
+    fn objects_keep_their_key_order() {
+        let json = Json::parse(r#"{"b": 1, "a": 2, "c": 3}"#).unwrap();
+        let Json::Object(fields) = &json else {
+            panic!("not an object: {:?}", json);
+        };
+        let keys: Vec<&str> = fields.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["b", "a", "c"]);
+        assert_eq!(json.to_string(), r#"{"b":1,"a":2,"c":3}"#);
+    }
+
+    #[test]
+    fn escapes_survive_a_round_trip() {
+        let text = "quote \" backslash \\ newline \n return \r tab \t bell \u{7} é 🎨";
+        let json = Json::String(text.to_string());
+        assert_eq!(json.to_string().lines().count(), 1);
+        assert!(json.to_string().contains("\\u0007"));
+        assert_eq!(round_trip(&json), json);
+    }
+
+    #[test]
+    fn reads_escape_sequences() {
+        let json = Json::parse(r#""a\nb\t\"c\"\\\/\b\féA""#).unwrap();
+        assert_eq!(json.as_str(), Some("a\nb\t\"c\"\\/\u{8}\u{c}éA"));
+    }
+
+    #[test]
+    fn reads_numbers() {
+        let cases = [
+            ("0", 0.0),
+            ("-7", -7.0),
+            ("3.25", 3.25),
+            ("1e3", 1000.0),
+            ("-2.5E-2", -0.025),
+            ("1700000000123", 1700000000123.0),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(
+                Json::parse(text).unwrap().as_f64(),
+                Some(expected),
+                "{}",
+                text
+            );
+        }
+        // whole numbers are written without a fraction
+        assert_eq!(Json::from(42u32).to_string(), "42");
+        assert_eq!(
+            Json::from(u64::from(u32::MAX) + 1).to_string(),
+            "4294967296"
+        );
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+    }
+
+    #[test]
+    fn accessors_fall_back_on_missing_or_wrong_types() {
+        let json = Json::parse(r#"{"n": -4, "s": "x", "list": [true]}"#).unwrap();
+        assert_eq!(json.u32_or("n", 9), 0);
+        assert_eq!(json.u32_or("missing", 9), 9);
+        assert_eq!(json.str_or("n", "fallback"), "fallback");
+        assert_eq!(json.array("list").len(), 1);
+        assert!(json.array("s").is_empty());
+        assert!(json.bool_or("list", true));
+        assert_eq!(Json::Null.get("n"), None);
+    }
+
+    #[test]
+    fn reads_nested_values_with_whitespace() {
+        let json = Json::parse(" {\n\t\"a\" : [ 1 , { \"b\" : [ ] } , null ] \r\n} ").unwrap();
+        let items = json.array("a");
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_f64(), Some(1.0));
+        assert_eq!(items[1].get("b"), Some(&Json::Array(Vec::new())));
+        assert_eq!(items[2], Json::Null);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let bad = [
+            "",
+            "   ",
+            "{",
+            "[1, 2",
+            "[1 2]",
+            "[1,]",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            r#"{a: 1}"#,
+            r#""unterminated"#,
+            r#""bad escape \u12""#,
+            r#""\uzzzz""#,
+            "tru",
+            "nul",
+            "-",
+            "1.2.3",
+            "+1",
+            "{} {}",
+            "[1] x",
+        ];
+        for text in bad {
+            assert!(Json::parse(text).is_err(), "accepted {:?}", text);
+        }
+    }
+}
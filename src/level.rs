@@ -0,0 +1,256 @@
+// Level layouts loaded from JSON, e.g. static/level.json:
+// { "name": ..., "background": "level.png", "items": [{ "rect": [x, y, w, h], "color": [r, g, b, a] }] }
+// The background path is relative to the level file. Items can also set
+// "layer" ("collision", the default, "platform", "background" or
+// "foreground") and "z", their draw order within the layer. A "rect" can be
+// turned by "angle" degrees around its centre, or an item can be a
+// "circle": [x, y, radius] instead, see collision.rs. An optional
+// "setpieces" list schedules events during rounds, see setpieces.rs, and
+// "spawners" places Dodge's bullet spawners, see spawners.rs. "paint_mask"
+// names an image of the parts that can't take paint, see paint_mask.rs.
+use crate::{
+    assets,
+    collision::{OrientedRect, Shape},
+    json::Json,
+    paint_mask::PaintMask,
+    setpieces::{SetPiece, SetPieceAction, DEFAULT_WARNING},
+    spawners::{BulletSpawner, Pattern, DEFAULT_COUNT},
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    EnvItem, Layer, MiniGames,
+};
+use raylib::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_LEVEL: &str = "level.json";
+
+pub struct Level {
+    pub name: String,
+    pub background: PathBuf,
+    pub paint_mask: Option<PathBuf>,
+    pub items: Vec<EnvItem>,
+    pub setpieces: Vec<SetPiece>,
+    pub spawners: Vec<BulletSpawner>,
+}
+
+fn read_numbers<const N: usize>(json: &Json, key: &str) -> Option<[f32; N]> {
+    let values = json.get(key)?.as_array();
+    if values.len() != N {
+        return None;
+    }
+    let mut out = [0.0; N];
+    for (slot, value) in out.iter_mut().zip(values) {
+        *slot = value.as_f32()?;
+    }
+    Some(out)
+}
+
+fn parse_setpiece(json: &Json, item_count: usize) -> Result<SetPiece, String> {
+    let at = json
+        .get("at")
+        .and_then(Json::as_f32)
+        .ok_or("needs \"at\": seconds into the round")?;
+    let action = if let Some(item) = json.get("collapse") {
+        let item = item
+            .as_f32()
+            .map(|item| item as usize)
+            .filter(|item| *item < item_count)
+            .ok_or("\"collapse\" needs the index of a level item")?;
+        SetPieceAction::Collapse { item }
+    } else if json.get("turret").is_some() {
+        let [x, y] = read_numbers::<2>(json, "turret").ok_or("\"turret\" needs [x, y]")?;
+        let [speed_x, speed_y] =
+            read_numbers::<2>(json, "speed").ok_or("needs the turret's \"speed\": [x, y]")?;
+        SetPieceAction::Turret {
+            position: Vector2::new(x, y),
+            speed: Vector2::new(speed_x, speed_y),
+            interval: json.f32_or("interval", 2.0).max(0.1),
+        }
+    } else if let Some(step) = json.get("lava").and_then(Json::as_f32) {
+        SetPieceAction::RaiseLava { step }
+    } else if json.get("beam").is_some() {
+        let [x, y] = read_numbers::<2>(json, "beam").ok_or("\"beam\" needs its pivot [x, y]")?;
+        SetPieceAction::Beam {
+            pivot: Vector2::new(x, y),
+            length: json.f32_or("length", 400.0).max(1.0),
+            speed: json.f32_or("speed", 1.0),
+            knockback: json.get("knockback").and_then(Json::as_f32),
+        }
+    } else {
+        return Err("needs one of \"collapse\", \"turret\", \"lava\" or \"beam\"".to_string());
+    };
+    let mut modes = Vec::new();
+    for mode in json.array("modes") {
+        let name = mode.as_str().unwrap_or_default();
+        modes.push(MiniGames::from_cli(name).ok_or_else(|| format!("unknown mode {}", name))?);
+    }
+    Ok(SetPiece {
+        at,
+        warning: json.f32_or("warning", DEFAULT_WARNING),
+        modes,
+        action,
+    })
+}
+
+fn parse_spawner(json: &Json) -> Result<BulletSpawner, String> {
+    let [x, y] = read_numbers::<2>(json, "position").ok_or("needs \"position\": [x, y]")?;
+    let [dx, dy] = read_numbers::<2>(json, "direction").ok_or("needs \"direction\": [x, y]")?;
+    let direction = Vector2::new(dx, dy);
+    if direction.length() == 0.0 {
+        return Err("has no \"direction\"".to_string());
+    }
+    let pattern = match json.get("pattern").and_then(Json::as_str) {
+        Some(key) => {
+            Pattern::from_key(key).ok_or_else(|| format!("has unknown pattern {}", key))?
+        }
+        None => Pattern::Single,
+    };
+    Ok(BulletSpawner {
+        position: Vector2::new(x, y),
+        direction: direction.normalized(),
+        interval: json
+            .get("interval")
+            .and_then(Json::as_f32)
+            .map(|interval| interval.max(0.1)),
+        pattern,
+        count: json.f32_or("count", DEFAULT_COUNT as f32).max(1.0) as u32,
+    })
+}
+
+impl Level {
+    pub fn load(path: &Path) -> Result<Level, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        Level::parse(&text, path)
+    }
+
+    // The built-in level, or its override in ./static/
+    pub fn load_default() -> Result<Level, String> {
+        let text = assets::read_to_string(DEFAULT_LEVEL)?;
+        Level::parse(&text, &Path::new(assets::ASSET_DIR).join(DEFAULT_LEVEL))
+    }
+
+    fn parse(text: &str, path: &Path) -> Result<Level, String> {
+        let json = Json::parse(text)
+            .map_err(|err| format!("{} is not valid JSON: {}", path.display(), err))?;
+
+        let mut items = Vec::new();
+        for (i, item) in json.array("items").iter().enumerate() {
+            let (rect, shape) = match read_numbers::<3>(item, "circle") {
+                Some([x, y, radius]) => (
+                    Rectangle::new(x - radius, y - radius, radius * 2.0, radius * 2.0),
+                    Shape::Circle,
+                ),
+                None => {
+                    let [x, y, width, height] =
+                        read_numbers::<4>(item, "rect").ok_or_else(|| {
+                            format!(
+                                "{}: item {} needs \"rect\": [x, y, w, h] or \"circle\": [x, y, r]",
+                                path.display(),
+                                i
+                            )
+                        })?;
+                    let rect = Rectangle::new(x, y, width, height);
+                    match item.f32_or("angle", 0.0).to_radians() {
+                        angle if angle == 0.0 => (rect, Shape::Box),
+                        angle => {
+                            let turned = OrientedRect {
+                                center: Vector2::new(x + width / 2.0, y + height / 2.0),
+                                half: Vector2::new(width / 2.0, height / 2.0),
+                                angle,
+                            };
+                            (
+                                turned.bounds(),
+                                Shape::Rotated {
+                                    width,
+                                    height,
+                                    angle,
+                                },
+                            )
+                        }
+                    }
+                }
+            };
+            let color = match read_numbers::<4>(item, "color") {
+                Some([r, g, b, a]) => Color::new(r as u8, g as u8, b as u8, a as u8),
+                None => Color::RED.alpha(0.5),
+            };
+            let layer = match item.get("layer").and_then(Json::as_str) {
+                Some(key) => Layer::from_key(key).ok_or_else(|| {
+                    format!("{}: item {} has unknown layer {}", path.display(), i, key)
+                })?,
+                None => Layer::default(),
+            };
+            items.push(EnvItem {
+                rect,
+                shape,
+                color,
+                layer,
+                z: item.f32_or("z", 0.0) as i32,
+            });
+        }
+
+        let mut setpieces = Vec::new();
+        for (i, piece) in json.array("setpieces").iter().enumerate() {
+            setpieces.push(
+                parse_setpiece(piece, items.len())
+                    .map_err(|err| format!("{}: setpiece {} {}", path.display(), i, err))?,
+            );
+        }
+
+        let mut spawners = Vec::new();
+        for (i, spawner) in json.array("spawners").iter().enumerate() {
+            spawners.push(
+                parse_spawner(spawner)
+                    .map_err(|err| format!("{}: spawner {} {}", path.display(), i, err))?,
+            );
+        }
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Ok(Level {
+            name: json.str_or("name", "Untitled").to_string(),
+            background: dir.join(json.str_or("background", "level.png")),
+            paint_mask: json
+                .get("paint_mask")
+                .and_then(Json::as_str)
+                .map(|mask| dir.join(mask)),
+            items,
+            setpieces,
+            spawners,
+        })
+    }
+
+    // The background image scaled to the virtual screen
+    pub fn load_background(
+        &self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+    ) -> Result<Texture2D, String> {
+        let mut image = assets::load_image_path(&self.background)?;
+        image.resize(SCREEN_WIDTH, SCREEN_HEIGHT);
+        rl.load_texture_from_image(thread, &image)
+    }
+
+    // The unpaintable parts, none when the level has no mask
+    pub fn load_paint_mask(&self) -> Result<PaintMask, String> {
+        match &self.paint_mask {
+            Some(path) => Ok(PaintMask::new(assets::load_image_path(path)?)),
+            None => Ok(PaintMask::default()),
+        }
+    }
+
+    // Finds a level given on the command line, trying the content packs'
+    // maps and then ./static/ when the path on its own doesn't exist
+    pub fn resolve(name: &str) -> PathBuf {
+        let path = PathBuf::from(name);
+        if path.exists() {
+            return path;
+        }
+        if let Some(path) = assets::pack_map(name) {
+            return path;
+        }
+        Path::new(assets::ASSET_DIR).join(name)
+    }
+}
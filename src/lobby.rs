@@ -0,0 +1,42 @@
+// Default lobby seating. Connected gamepads claim the first seats, then the
+// two keyboard schemes; seats left over wait for pads that aren't plugged in
+// yet. The main menu seats everyone when it first opens and re-seats them
+// when a pad has come or gone since.
+use crate::{InputType, KeyboardControls};
+use raylib::prelude::*;
+
+const MAX_PADS: usize = 4;
+
+// Indexes of the gamepads raylib sees right now
+pub fn connected_pads(rl: &RaylibHandle) -> Vec<usize> {
+    (0..MAX_PADS)
+        .filter(|pad| rl.is_gamepad_available(*pad as i32))
+        .collect()
+}
+
+pub fn seat_controls(pads: &[usize]) -> [InputType; 4] {
+    let spare_pads = (0..MAX_PADS).filter(|pad| !pads.contains(pad));
+    let mut seats = pads
+        .iter()
+        .copied()
+        .map(InputType::Controller)
+        .chain([
+            InputType::Keyboard(KeyboardControls::WASD),
+            InputType::Keyboard(KeyboardControls::ArrowKeys),
+        ])
+        .chain(spare_pads.map(InputType::Controller));
+    [(); 4].map(|_| seats.next().unwrap())
+}
+
+// Everyone holding a pad plays, and never fewer than the two keyboard seats
+pub fn seat_count(pads: &[usize]) -> usize {
+    pads.len().clamp(2, 4)
+}
+
+pub fn describe(controls: InputType) -> String {
+    match controls {
+        InputType::Keyboard(KeyboardControls::WASD) => "WASD".to_string(),
+        InputType::Keyboard(KeyboardControls::ArrowKeys) => "Arrows".to_string(),
+        InputType::Controller(pad) => format!("Pad {}", pad + 1),
+    }
+}
@@ -1,10 +1,138 @@
+mod afk;
+mod arena;
+mod assets;
+mod capture;
+mod cli;
+mod clock;
+mod collision;
+mod console;
+mod cosmetics;
+mod coverage;
+mod crash;
+mod cues;
+mod daily;
+mod debug;
+mod dedicated;
+mod director;
+mod events;
+mod export;
+mod feed;
+mod feedback;
+mod gamma;
+mod golden;
+mod history;
+#[cfg(feature = "dev")]
+mod hotreload;
+mod hotseat;
+mod intro;
+mod join;
+mod json;
+mod level;
+mod lobby;
+mod minigame_config;
+mod mods;
+mod mutators;
+mod net_hud;
+mod netcode;
+mod online;
+mod orchestrator;
+mod padwatch;
+mod paint_mask;
+mod pickups;
+mod profiles;
+mod quickchat;
+mod replay;
+mod result_card;
+mod rng;
+mod script;
+mod scripted;
+mod setpieces;
+mod settings;
+mod sim;
+mod snapshot;
+mod spawn;
+mod spawners;
+mod stats_hud;
+mod storage;
+mod streaks;
+mod suspend;
+mod trails;
+mod tutorial;
+mod viewport;
+mod window;
+
+use afk::Afk;
+use arena::ArenaCamera;
+use capture::ClipRecorder;
+use cli::CliOptions;
+use clock::GameClock;
+use collision::Shape;
+use console::{Console, ConsoleCommand};
+use cosmetics::{Cosmetic, SkinTextures, SplatStyle, VictoryPose};
+use daily::{DailyChallenge, DailyLog, DailyResult};
+use debug::{DebugOverlay, TimeControl};
+use director::Director;
+use events::{EventQueue, GameEvent};
+use feed::EventFeed;
+use feedback::{Feedback, FeedbackRouter};
+use gamma::PostProcess;
+use golden::{GoldenRun, Scene};
+use history::{MatchHistory, MatchPlayer, MatchRecord, RoundRecord};
+use hotseat::HotSeat;
+use intro::Intro;
+use join::Session;
+use level::Level;
+use mods::ContentPacks;
+use mutators::Mutator;
+use online::{ConnectionStats, LinkConditions, NetMeter, Received};
+use orchestrator::{MatchOrchestrator, RotationPolicy};
+use padwatch::PadWatch;
+use pickups::Pickups;
+use profiles::ProfileStore;
+use quickchat::QuickChat;
 use raylib::prelude::*;
 use raylib_sys::TraceLogLevel;
-use std::{cell::OnceCell, ffi::CString, rc::Rc};
-
-const SCREEN_WIDTH: i32 = 1200;
-const SCREEN_HEIGHT: i32 = 650;
-const PAINT_RADIUS: f32 = 5.0; // Radius of the paint splat
+use replay::{Replay, ReplayRecorder, ReplayViewer};
+use result_card::Card;
+use rng::Rng;
+use setpieces::SetPieces;
+use settings::{Quality, Settings};
+use sim::{Round, Stage};
+use snapshot::Snapshot;
+use spawners::Spawners;
+use stats_hud::StatsHud;
+use std::{
+    cell::OnceCell,
+    ffi::CString,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use streaks::Streaks;
+use suspend::SuspendedMatch;
+use trails::Trails;
+use tutorial::Tutorial;
+use viewport::{Viewport, SCREEN_HEIGHT, SCREEN_WIDTH};
+use window::WindowTitle;
+
+const PAINT_RADIUS: f32 = 5.0; // Radius of the paint splat, before any player's changes
+const GRAVITY: f32 = 980.8;
+const PLAYER_SPEED: f32 = 300.0;
+const JUMP_FORCE: f32 = 700.0;
+const POINTS_TO_WIN: u32 = 5;
+const PLAYER_SPRITES: [&str; 4] = ["player1.png", "player2.png", "player3.png", "player4.png"];
+const PLAYER_COLORS: [&str; 4] = ["FBB954", "A884F3", "1EBC73", "E83B3B"];
+const THROW_SPEED: f32 = 650.0;
+const THROW_COOLDOWN: f32 = 1.5;
+const AIM_PREVIEW_TIME: f32 = 1.2; // How far ahead (in seconds) the aim arc is simulated
+const AIM_PREVIEW_STEP: f32 = 1.0 / 30.0;
+const HIT_FLASH_TIME: f32 = 0.15;
+// how far the sprite moves side to side while it shakes
+const SHAKE_AMOUNT: f32 = 4.0;
+const RESPAWN_INVULNERABILITY: f32 = 1.0;
+const SPAWN_PROTECTION_TIME: f32 = 1.5;
+// A close finish is played out in slow motion, see sim.rs
+const FINAL_BLOW_SCALE: f32 = 0.3;
+const FINAL_BLOW_TIME: f32 = 0.5;
 
 // global counter
 
@@ -21,6 +149,7 @@ pub struct Player {
     pub width: f32,
     pub height: f32,
     pub jump_force: f32,
+    pub gravity: f32,
     pub texture: Rc<Texture2D>,
     pub is_jumping: bool,
     pub jump_time: f32,
@@ -29,6 +158,61 @@ pub struct Player {
     pub points: u32,
     pub number: u32,
     pub dead: bool,
+    pub input: PlayerInput,
+    pub facing: f32,
+    pub hit_flash: f32,
+    pub invulnerable: f32,
+    pub profile: Option<usize>,
+    pub splat: SplatStyle,
+    pub pose: VictoryPose,
+    // radius of this player's paint splats and the spacing of their contact
+    // points; pickups and overtime change it for the round
+    pub paint_radius: f32,
+    // left of the round start grace: no player collisions and no bullet hits
+    pub spawn_protection: f32,
+    // seconds left of this player's own shake, see feedback.rs
+    pub shake: f32,
+    // seconds until the player can throw again
+    pub throw_cooldown: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub primary: bool,
+    pub secondary: bool,
+}
+
+impl PlayerInput {
+    // Packed form used by replays
+    pub fn to_bits(&self) -> u8 {
+        [
+            self.up,
+            self.down,
+            self.left,
+            self.right,
+            self.primary,
+            self.secondary,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, held)| bits | ((*held as u8) << i))
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        let held = |i: u8| bits & (1 << i) != 0;
+        PlayerInput {
+            up: held(0),
+            down: held(1),
+            left: held(2),
+            right: held(3),
+            primary: held(4),
+            secondary: held(5),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -60,12 +244,79 @@ pub enum MiniGames {
     Dodge,
     FloorIsLava,
 }
+impl MiniGames {
+    pub const ALL: [MiniGames; 3] = [
+        MiniGames::ColorTheMap,
+        MiniGames::Dodge,
+        MiniGames::FloorIsLava,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MiniGames::ColorTheMap => "Color The Map",
+            MiniGames::Dodge => "Dodge",
+            MiniGames::FloorIsLava => "Floor Is Lava",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    // Inverse of the `{:?}` name used as key in save files
+    pub fn from_key(key: &str) -> Option<MiniGames> {
+        MiniGames::ALL
+            .into_iter()
+            .find(|game| format!("{:?}", game) == key)
+    }
+
+    // Lenient match for --mode: case, spaces, dashes and underscores are ignored
+    pub fn from_cli(name: &str) -> Option<MiniGames> {
+        let normalize = |s: &str| {
+            s.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let wanted = normalize(name);
+        MiniGames::ALL
+            .into_iter()
+            .find(|game| normalize(game.name()) == wanted)
+    }
+
+    // The minigame played after this one
+    pub fn next_in_rotation(&self) -> MiniGames {
+        match self {
+            MiniGames::ColorTheMap => MiniGames::Dodge,
+            MiniGames::Dodge => MiniGames::ColorTheMap,
+            MiniGames::FloorIsLava => MiniGames::FloorIsLava,
+        }
+    }
+
+    // Modes where holding `primary` aims a throw and letting go throws it
+    pub fn uses_throwing(&self) -> bool {
+        match self {
+            MiniGames::Dodge => true,
+            MiniGames::ColorTheMap | MiniGames::FloorIsLava => false,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 
 pub enum GameMode {
     MainMenu,
     Game,
     WinScreen,
+    Profiles,
+    History,
+    Replays,
+    ReplayViewer,
+    Settings,
+    Tutorial,
+    Calibration,
+    Intro,
+    Mods,
 }
 
 pub struct KeyboardInput {
@@ -117,6 +368,7 @@ impl Player {
             width,
             height,
             jump_force,
+            gravity: GRAVITY,
             texture: Rc::new(texture),
             is_jumping: false,
             jump_time: 0.0,
@@ -125,14 +377,53 @@ impl Player {
             points: 0,
             number,
             dead: false,
+            input: PlayerInput::default(),
+            facing: 1.0,
+            hit_flash: 0.0,
+            invulnerable: 0.0,
+            profile: None,
+            splat: SplatStyle::default(),
+            pose: VictoryPose::default(),
+            paint_radius: PAINT_RADIUS,
+            spawn_protection: 0.0,
+            shake: 0.0,
+            throw_cooldown: 0.0,
         }
     }
 
-    pub fn update(&mut self, rl: &RaylibHandle, dt: f32) {
-        let keys: ControlsType;
-        if (self.dead) {
-            return;
+    // Feedback timers keep running while dead, so they are ticked apart from update()
+    pub fn tick_timers(&mut self, dt: f32) {
+        self.hit_flash = (self.hit_flash - dt).max(0.0);
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        self.spawn_protection = (self.spawn_protection - dt).max(0.0);
+        self.shake = (self.shake - dt).max(0.0);
+        self.throw_cooldown = (self.throw_cooldown - dt).max(0.0);
+    }
+
+    pub fn can_be_hit(&self) -> bool {
+        !self.dead && self.invulnerable <= 0.0 && self.spawn_protection <= 0.0
+    }
+
+    // Returns false when the hit was ignored (already dead or invulnerable)
+    pub fn hit(&mut self) -> bool {
+        if !self.can_be_hit() {
+            return false;
         }
+        self.dead = true;
+        self.hit_flash = HIT_FLASH_TIME;
+        true
+    }
+
+    pub fn respawn(&mut self, position: Vector2) {
+        self.dead = false;
+        self.position = position;
+        self.velocity = Vector2::zero();
+        self.hit_flash = HIT_FLASH_TIME;
+        self.invulnerable = RESPAWN_INVULNERABILITY;
+    }
+
+    pub fn read_input(&self, rl: &RaylibHandle) -> PlayerInput {
+        let keys: ControlsType;
         match self.controls {
             InputType::Keyboard(input) => match input {
                 KeyboardControls::WASD => {
@@ -156,102 +447,57 @@ impl Player {
                     });
                 }
             },
-            InputType::Controller(number) => {
+            InputType::Controller(_) => {
                 keys = ControlsType::Gamepad(GamepadInput {
                     up: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
                     down: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
                     left: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
                     right: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
-                    primary: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
-                    secondary: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
+                    // face buttons, the d-pad is already moving the player
+                    primary: consts::GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+                    secondary: consts::GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
                 });
-            } // Controls::WASD => {
-              //     keys = Input {
-              //         up: consts::KeyboardKey::KEY_W,
-              //         down: consts::KeyboardKey::KEY_S,
-              //         left: consts::KeyboardKey::KEY_A,
-              //         right: consts::KeyboardKey::KEY_D,
-              //         primary: consts::KeyboardKey::KEY_F,
-              //         secondary: consts::KeyboardKey::KEY_G,
-              //     };
-              // }
-
-              // Controls::ArrowKeys => {
-              //     keys = Input {
-              //         up: consts::KeyboardKey::KEY_UP,
-              //         down: consts::KeyboardKey::KEY_DOWN,
-              //         left: consts::KeyboardKey::KEY_LEFT,
-              //         right: consts::KeyboardKey::KEY_RIGHT,
-              //         primary: consts::KeyboardKey::KEY_J,
-              //         secondary: consts::KeyboardKey::KEY_K,
-              //     };
-              // }
-              // Controls::Controller(index) => {
-              //     keys = Input {
-              //         up: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
-              //         down: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
-              //         left: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
-              //         right: consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
-              //         primary: consts::GamepadButton::A as usize,
-              //         secondary: consts::GamepadButton::B as usize,
-              //     };
-              // }
-        }
-        // consts::GamepadButton::UP
-        // Apply gravity.  This happens *before* jump input.
-        if !self.is_on_ground {
-            self.velocity.y += 980.8 * dt;
+            }
         }
-        // New jump logic
-        let mut up = false;
-        let mut down = false;
-        let mut left = false;
-        let mut right = false;
-        let mut primary = false;
-        let mut secondary = false;
+        let mut input = PlayerInput::default();
 
         match keys {
             ControlsType::Gamepad(keys) => {
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.up) {
-                    up = true;
-                }
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.down) {
-                    down = true;
-                }
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.left) {
-                    left = true;
-                }
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.right) {
-                    right = true;
-                }
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.primary) {
-                    primary = true;
-                }
-                if rl.is_gamepad_button_down(self.number as i32 - 2, keys.secondary) {
-                    secondary = true;
-                }
+                let pad = match self.controls {
+                    InputType::Controller(pad) => pad as i32,
+                    InputType::Keyboard(_) => 0,
+                };
+                input.up = rl.is_gamepad_button_down(pad, keys.up);
+                input.down = rl.is_gamepad_button_down(pad, keys.down);
+                input.left = rl.is_gamepad_button_down(pad, keys.left);
+                input.right = rl.is_gamepad_button_down(pad, keys.right);
+                input.primary = rl.is_gamepad_button_down(pad, keys.primary);
+                input.secondary = rl.is_gamepad_button_down(pad, keys.secondary);
             }
             ControlsType::Keyboard(keys) => {
-                if rl.is_key_down(keys.up) {
-                    up = true;
-                }
-                if rl.is_key_down(keys.down) {
-                    down = true;
-                }
-                if rl.is_key_down(keys.left) {
-                    left = true;
-                }
-                if rl.is_key_down(keys.right) {
-                    right = true;
-                }
-                if rl.is_key_down(keys.primary) {
-                    primary = true;
-                }
-                if rl.is_key_down(keys.secondary) {
-                    secondary = true;
-                }
+                input.up = rl.is_key_down(keys.up);
+                input.down = rl.is_key_down(keys.down);
+                input.left = rl.is_key_down(keys.left);
+                input.right = rl.is_key_down(keys.right);
+                input.primary = rl.is_key_down(keys.primary);
+                input.secondary = rl.is_key_down(keys.secondary);
             }
         }
+        input
+    }
+
+    pub fn update(&mut self, input: PlayerInput, dt: f32) {
+        if (self.dead) {
+            return;
+        }
+        self.input = input;
+        // Apply gravity.  This happens *before* jump input.
+        if !self.is_on_ground {
+            self.velocity.y += self.gravity * dt;
+        }
+        let PlayerInput {
+            up, left, right, ..
+        } = input;
         if up && self.is_on_ground && !self.is_jumping {
             self.velocity.y = -self.jump_force;
             self.is_jumping = true;
@@ -278,6 +524,9 @@ impl Player {
         if left {
             horizontal_input -= 1.0;
         }
+        if horizontal_input != 0.0 {
+            self.facing = horizontal_input;
+        }
 
         match *self.game {
             MiniGames::ColorTheMap => {
@@ -289,26 +538,53 @@ impl Player {
 
         self.position += self.velocity * dt;
     }
-    pub fn handle_collision(
-        &mut self,
-        ops: &Vec<EnvItem>,
-        players: Vec<&Player>,
-    ) -> Vec<(Rectangle, Vec<Vector2>)> {
+    pub fn handle_collision(&mut self, ops: &Vec<EnvItem>, players: Vec<&Player>) -> Vec<Contact> {
         let player_rect = self.get_collision_rect();
         let mut collisions = Vec::new();
 
-        for op in ops {
+        for op in ops.iter().filter(|op| op.layer.collides()) {
+            if op.shape != Shape::Box {
+                let Some(push) = op.shape.push_out(op.rect, player_rect) else {
+                    continue;
+                };
+                self.position += push;
+                let normal = push.normalized();
+                // stop moving into the surface, keep sliding along it
+                let into = self.velocity.dot(normal);
+                if into < 0.0 {
+                    self.velocity -= normal * into;
+                }
+                if normal.y < -0.5 {
+                    self.is_on_ground = true;
+                }
+                // the player's edge that touched, paint points are offset
+                // by the radius like the box ones below
+                let point = Vector2::new(
+                    player_rect.x + player_rect.width / 2.0 * (1.0 - normal.x),
+                    player_rect.y + player_rect.height / 2.0 * (1.0 - normal.y),
+                );
+                collisions.push(Contact {
+                    rect: op.rect,
+                    point,
+                    normal,
+                    points: vec![point + Vector2::new(self.paint_radius, self.paint_radius)],
+                });
+                continue;
+            }
             if let Some(collision) = player_rect.get_collision_rec(&op.rect) {
                 // Resolve collision
                 let dx = collision.width;
                 let dy = collision.height;
+                let normal;
 
                 if dx < dy {
                     // X-axis collision
                     if player_rect.x < op.rect.x {
                         self.position.x -= dx;
+                        normal = Vector2::new(-1.0, 0.0);
                     } else {
                         self.position.x += dx;
+                        normal = Vector2::new(1.0, 0.0);
                     }
                     self.velocity.x = 0.0;
                 } else {
@@ -317,15 +593,17 @@ impl Player {
                         self.position.y -= dy;
                         self.velocity.y = 0.0;
                         self.is_on_ground = true;
+                        normal = Vector2::new(0.0, -1.0);
                     } else {
                         self.position.y += dy;
                         self.velocity.y = 0.0;
+                        normal = Vector2::new(0.0, 1.0);
                     }
                 }
 
                 // Generate collision points
                 let mut points = Vec::new();
-                let step = PAINT_RADIUS * 1.0;
+                let step = self.paint_radius;
 
                 let start_x = collision.x;
                 let end_x = collision.x + collision.width;
@@ -336,8 +614,8 @@ impl Player {
                 while x < end_x {
                     let mut y = start_y;
                     while y < end_y {
-                        let adjusted_x = x + PAINT_RADIUS;
-                        let adjusted_y = y + PAINT_RADIUS;
+                        let adjusted_x = x + self.paint_radius;
+                        let adjusted_y = y + self.paint_radius;
                         points.push(Vector2::new(adjusted_x, adjusted_y));
                         y += step;
                     }
@@ -346,15 +624,26 @@ impl Player {
 
                 // Ensure at least one point for small collisions
                 if points.is_empty() {
-                    let center_x = collision.x + collision.width / 2.0 + PAINT_RADIUS;
-                    let center_y = collision.y + collision.height / 2.0 + PAINT_RADIUS;
+                    let center_x = collision.x + collision.width / 2.0 + self.paint_radius;
+                    let center_y = collision.y + collision.height / 2.0 + self.paint_radius;
                     points.push(Vector2::new(center_x, center_y));
                 }
 
-                collisions.push((op.rect.clone(), points));
+                collisions.push(Contact {
+                    rect: op.rect.clone(),
+                    point: Vector2::new(
+                        collision.x + collision.width / 2.0,
+                        collision.y + collision.height / 2.0,
+                    ),
+                    normal,
+                    points,
+                });
             }
         }
         for player in players {
+            if self.spawn_protection > 0.0 || player.spawn_protection > 0.0 {
+                continue;
+            }
             let rect = player.get_collision_rect();
             if let Some(collision) = rect.get_collision_rec(&player_rect) {
                 // Resolve collision
@@ -386,6 +675,19 @@ impl Player {
         collisions
     }
 
+    // Stops the player at the sides of the arena
+    pub fn keep_inside(&mut self, bounds: Rectangle) {
+        let half_width = self.width / 2.0;
+        let x = self
+            .position
+            .x
+            .clamp(bounds.x + half_width, bounds.x + bounds.width - half_width);
+        if x != self.position.x {
+            self.position.x = x;
+            self.velocity.x = 0.0;
+        }
+    }
+
     pub fn get_collision_rect(&self) -> Rectangle {
         Rectangle {
             x: self.position.x - self.width / 2.0,
@@ -407,366 +709,1048 @@ impl Player {
         //     self.rotation,
         //     self.color,
         // );
+        // blink at ~8Hz while invulnerable or spawn protected
+        let protected = self.invulnerable.max(self.spawn_protection);
+        if protected > 0.0 && (protected * 16.0) as i32 % 2 == 0 {
+            return;
+        }
         let tint = if self.dead { Color::GRAY } else { Color::WHITE };
-        d.draw_texture_ex(
-            &self.texture.as_ref(),
-            Vector2::new(
-                self.position.x - self.width / 2.,
-                self.position.y - self.height / 2.,
-            ),
-            self.rotation,
-            0.65,
-            tint,
+        let shake = (self.shake * 60.0).sin() * SHAKE_AMOUNT * self.shake.min(0.2) / 0.2;
+        let position = Vector2::new(
+            self.position.x - self.width / 2. + shake,
+            self.position.y - self.height / 2.,
         );
+        d.draw_texture_ex(&self.texture.as_ref(), position, self.rotation, 0.65, tint);
+        if self.hit_flash > 0.0 {
+            {
+                // drawing the sprite a second time additively washes it out to white
+                let mut d = d.begin_blend_mode(BlendMode::BLEND_ADDITIVE);
+                d.draw_texture_ex(
+                    &self.texture.as_ref(),
+                    position,
+                    self.rotation,
+                    0.65,
+                    Color::WHITE,
+                );
+            }
+            // outlined in the player's colour so it's clear whose sprite flashed
+            d.draw_rectangle_lines_ex(
+                Rectangle::new(
+                    position.x - 4.0,
+                    position.y - 4.0,
+                    self.width + 8.0,
+                    self.height + 8.0,
+                ),
+                3.0,
+                self.color,
+            );
+        }
     }
     // Modified paint function
     pub fn paint(&self, image: &mut Image, collision_point: Vector2) {
-        // Use the collision point for drawing.  Offset by radius to center the circle.
-        let image_x = (collision_point.x - PAINT_RADIUS).round() as i32;
-        let image_y = (collision_point.y - PAINT_RADIUS).round() as i32;
-        image.draw_circle(image_x, image_y, PAINT_RADIUS as i32, self.color);
+        paint_splat(
+            image,
+            collision_point,
+            self.color,
+            self.splat,
+            self.paint_radius,
+        );
+    }
+
+    // Launch velocity of a throw, aimed up/forward/down with the movement keys
+    pub fn throw_velocity(&self) -> Vector2 {
+        let mut dir = Vector2::new(self.facing, -1.0);
+        if self.input.up {
+            dir.y = -2.0;
+        }
+        if self.input.down {
+            dir.y = 0.0;
+        }
+        dir.normalized() * THROW_SPEED
+    }
+
+    // Called with this frame's input before update(): letting go of
+    // `primary` throws along the arc that was aimed while it was held
+    pub fn release_throw(&mut self, input: PlayerInput) -> Option<Bullet> {
+        if self.dead || !self.input.primary || input.primary || self.throw_cooldown > 0.0 {
+            return None;
+        }
+        self.throw_cooldown = THROW_COOLDOWN;
+        Some(self.throw())
+    }
+
+    pub fn throw(&self) -> Bullet {
+        Bullet {
+            rect: Rectangle::new(self.position.x - 5.0, self.position.y - 5.0, 10.0, 10.0),
+            color: self.color,
+            speed: self.throw_velocity(),
+            gravity: GRAVITY,
+            time_to_live: 5.0,
+            source: BulletSource::Thrown(self.number as usize),
+        }
+    }
+
+    // Dotted arc of where a throw would land, made by stepping a throwaway
+    // projectile with the same physics the real one uses.
+    pub fn draw_aim(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, ops: &Vec<EnvItem>) {
+        let mut projectile = self.throw();
+        let mut t = 0.0;
+        let mut i = 0;
+        while t < AIM_PREVIEW_TIME {
+            projectile.step(AIM_PREVIEW_STEP);
+            t += AIM_PREVIEW_STEP;
+            i += 1;
+            if ops
+                .iter()
+                .any(|op| op.layer.collides() && op.rect.check_collision_recs(&projectile.rect))
+            {
+                break;
+            }
+            if i % 2 == 0 {
+                let alpha = 1.0 - t / AIM_PREVIEW_TIME;
+                d.draw_circle_v(projectile.center(), 3.0, self.color.alpha(alpha));
+            }
+        }
+    }
+}
+
+// A player touching an EnvItem this frame
+pub struct Contact {
+    pub rect: Rectangle,
+    pub point: Vector2,       // middle of the overlap
+    pub normal: Vector2,      // direction the player was pushed out
+    pub points: Vec<Vector2>, // where to paint
+}
+
+// Where a level item sits. Only collision and platform items are solid, the
+// rest is decoration drawn behind or in front of the players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layer {
+    // solid but not drawn, the level background shows it
+    #[default]
+    Collision,
+    // solid and drawn in its colour behind the players
+    Platform,
+    Background,
+    Foreground,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 4] = [
+        Layer::Collision,
+        Layer::Platform,
+        Layer::Background,
+        Layer::Foreground,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Layer::Collision => "collision",
+            Layer::Platform => "platform",
+            Layer::Background => "background",
+            Layer::Foreground => "foreground",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Layer> {
+        Layer::ALL.into_iter().find(|layer| layer.key() == key)
+    }
+
+    pub fn collides(&self) -> bool {
+        matches!(self, Layer::Collision | Layer::Platform)
     }
 }
 
 pub struct EnvItem {
+    // bounding box, see `shape` for what players collide with
     pub rect: Rectangle,
+    pub shape: Shape,
     pub color: Color,
+    pub layer: Layer,
+    // draw order within the layer, higher is drawn later
+    pub z: i32,
 }
 
+// Draws the items on `layer`, lowest z first
+pub fn draw_env_items(d: &mut RaylibMode2D<'_, RaylibDrawHandle>, ops: &[EnvItem], layer: Layer) {
+    let mut items: Vec<&EnvItem> = ops.iter().filter(|op| op.layer == layer).collect();
+    items.sort_by_key(|op| op.z);
+    for op in items {
+        let center = Vector2::new(
+            op.rect.x + op.rect.width / 2.0,
+            op.rect.y + op.rect.height / 2.0,
+        );
+        match op.shape {
+            Shape::Box => d.draw_rectangle_rec(op.rect, op.color),
+            Shape::Circle => {
+                d.draw_circle_v(center, op.rect.width.min(op.rect.height) / 2.0, op.color)
+            }
+            Shape::Rotated {
+                width,
+                height,
+                angle,
+            } => d.draw_rectangle_pro(
+                Rectangle::new(center.x, center.y, width, height),
+                Vector2::new(width / 2.0, height / 2.0),
+                angle.to_degrees(),
+                op.color,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Bullet {
     pub rect: Rectangle,
     pub color: Color,
     pub speed: Vector2,
+    pub gravity: f32,
     pub time_to_live: f32,
+    pub source: BulletSource,
+}
+
+// Who gets the credit when a bullet knocks someone out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulletSource {
+    // a Dodge wave, counted from 1 each round
+    Wave(u32),
+    // thrown by the player with this index
+    Thrown(usize),
+    // spawned from the dev console
+    Console,
+    // fired by a level's turret setpiece
+    Turret,
+    // not a bullet at all: the player touched a setpiece's lava
+    Lava,
+    // or was swept by a setpiece's beam
+    Beam,
+    // spawned or knocked out by a scripted minigame
+    Script,
+}
+
+impl BulletSource {
+    // "P2 was eliminated by ..."
+    pub fn describe(&self) -> String {
+        match self {
+            BulletSource::Wave(wave) => format!("wave {}", wave),
+            BulletSource::Thrown(player) => format!("P{}'s throw", player + 1),
+            BulletSource::Console => "a console bullet".to_string(),
+            BulletSource::Turret => "a turret".to_string(),
+            BulletSource::Lava => "the lava".to_string(),
+            BulletSource::Beam => "the beam".to_string(),
+            BulletSource::Script => "the rules".to_string(),
+        }
+    }
+
+    // Key used in save files, e.g. "wave:3", "thrown:1" or "console"
+    pub fn key(&self) -> String {
+        match self {
+            BulletSource::Wave(wave) => format!("wave:{}", wave),
+            BulletSource::Thrown(player) => format!("thrown:{}", player),
+            BulletSource::Console => "console".to_string(),
+            BulletSource::Turret => "turret".to_string(),
+            BulletSource::Lava => "lava".to_string(),
+            BulletSource::Beam => "beam".to_string(),
+            BulletSource::Script => "script".to_string(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<BulletSource> {
+        match key.split_once(':') {
+            Some(("wave", wave)) => wave.parse().ok().map(BulletSource::Wave),
+            Some(("thrown", player)) => player.parse().ok().map(BulletSource::Thrown),
+            None if key == "console" => Some(BulletSource::Console),
+            None if key == "turret" => Some(BulletSource::Turret),
+            None if key == "lava" => Some(BulletSource::Lava),
+            None if key == "beam" => Some(BulletSource::Beam),
+            None if key == "script" => Some(BulletSource::Script),
+            _ => None,
+        }
+    }
+}
+
+impl Bullet {
+    pub fn step(&mut self, dt: f32) {
+        self.speed.y += self.gravity * dt;
+        self.rect.x += self.speed.x * dt;
+        self.rect.y += self.speed.y * dt;
+        self.time_to_live -= dt;
+    }
+
+    pub fn center(&self) -> Vector2 {
+        Vector2::new(
+            self.rect.x + self.rect.width / 2.0,
+            self.rect.y + self.rect.height / 2.0,
+        )
+    }
+}
+
+pub fn paint_splat(
+    image: &mut Image,
+    collision_point: Vector2,
+    color: Color,
+    style: SplatStyle,
+    paint_radius: f32,
+) {
+    // The image may be smaller than the screen on lower quality settings
+    let scale = image.width as f32 / SCREEN_WIDTH as f32;
+    // Use the collision point for drawing.  Offset by radius to center the circle.
+    let image_x = ((collision_point.x - paint_radius) * scale).round() as i32;
+    let image_y = ((collision_point.y - paint_radius) * scale).round() as i32;
+    let radius = ((paint_radius * scale).round() as i32).max(1);
+    match style {
+        SplatStyle::Round => image.draw_circle(image_x, image_y, radius, color),
+        SplatStyle::Square => image.draw_rectangle(
+            image_x - radius,
+            image_y - radius,
+            radius * 2,
+            radius * 2,
+            color,
+        ),
+        SplatStyle::Cross => {
+            image.draw_rectangle(
+                image_x - radius,
+                image_y - radius / 3,
+                radius * 2,
+                radius * 2 / 3,
+                color,
+            );
+            image.draw_rectangle(
+                image_x - radius / 3,
+                image_y - radius,
+                radius * 2 / 3,
+                radius * 2,
+                color,
+            );
+        }
+    }
+}
+
+// Per-player state every round starts from
+pub fn on_round_start(players: &mut [Player]) {
+    for player in players {
+        player.spawn_protection = SPAWN_PROTECTION_TIME;
+        player.paint_radius = PAINT_RADIUS;
+    }
+}
+
+// Empty paint map at the resolution `quality` paints at
+pub fn blank_paint_map(quality: Quality) -> Image {
+    let scale = quality.paint_scale();
+    Image::gen_image_color(
+        (SCREEN_WIDTH as f32 * scale) as i32,
+        (SCREEN_HEIGHT as f32 * scale) as i32,
+        Color::WHITE.alpha(0.0),
+    )
+}
+
+// Raw pixel bytes of an image, for Texture2D::update_texture
+pub fn image_bytes(image: &Image) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(
+            image.data as *const u8,
+            raylib::texture::get_pixel_data_size(image.width, image.height, image.format())
+                .try_into()
+                .unwrap(),
+        )
+    }
 }
 
 fn main() {
+    crash::install_hook();
+    if std::panic::catch_unwind(run).is_err() {
+        // the game's window is closed by now, the hook left the details
+        if let Some(crash) = crash::take_last() {
+            if std::panic::catch_unwind(|| crash::show_error_window(&crash)).is_err() {
+                eprintln!("could not open the error window either");
+            }
+        }
+        std::process::exit(101);
+    }
+}
+
+fn run() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match CliOptions::parse(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if let Some(dir) = &options.export_stats {
+        let history = MatchHistory::load();
+        match export::export_stats(&history, dir) {
+            Ok(()) => eprintln!(
+                "exported {} matches to {}",
+                history.matches.len(),
+                dir.display()
+            ),
+            Err(err) => {
+                eprintln!("could not export stats to {}: {}", dir.display(), err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // golden runs compare against the game's own art
+    let mut content_packs = ContentPacks::load();
+    if !options.golden && !options.golden_update {
+        content_packs.install();
+    }
+    let mut scripted_games = scripted::load_all();
+    // the scripted minigame every round is played as, None for the built-ins
+    let mut active_script = match &options.script {
+        Some(name) => match scripted_games
+            .iter()
+            .position(|game| game.name.eq_ignore_ascii_case(name))
+        {
+            Some(i) => Some(i),
+            None => {
+                eprintln!("no scripted minigame called {} in the enabled packs", name);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let level_file = match &options.map {
+        Some(name) => Level::resolve(name),
+        None => Path::new(assets::ASSET_DIR).join(level::DEFAULT_LEVEL),
+    };
+    let load_level = || match &options.map {
+        Some(_) => Level::load(&level_file),
+        None => Level::load_default(),
+    };
+    let level = match load_level() {
+        Ok(level) => level,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(port) = options.dedicated {
+        let script = active_script.map(|i| scripted_games.swap_remove(i));
+        if let Err(err) = dedicated::run(&options, port, level, script) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let (mut rl, thread) = raylib::init()
         .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("Color The Map")
+        .title(window::BASE_TITLE)
         .resizable()
         .build();
-    let mut trantition_right_image = Image::load_image("./static/transition_right.png").unwrap();
+    if options.fullscreen {
+        rl.toggle_fullscreen();
+    }
+    window::set_icon(&mut rl);
+    let base_title = match &options.map {
+        Some(_) => format!("{} - {}", window::BASE_TITLE, level.name),
+        None => window::BASE_TITLE.to_string(),
+    };
+    let mut window_title = WindowTitle::default();
+    let mut trantition_right_image = assets::load_image("transition_right.png");
     trantition_right_image.resize(SCREEN_WIDTH / 2, SCREEN_HEIGHT);
 
-    let mut level_timer = 60.0;
+    let mut round = Round::default();
     let trantition_right_texture = rl
         .load_texture_from_image(&thread, &trantition_right_image)
         .unwrap();
-    let mut trantition_left_image = Image::load_image("./static/transition_left.png").unwrap(); // Load image data into CPU memory (RAM)
+    let mut trantition_left_image = assets::load_image("transition_left.png"); // Load image data into CPU memory (RAM)
     trantition_left_image.resize(SCREEN_WIDTH / 2, SCREEN_HEIGHT);
     let trantition_left_texture = rl
         .load_texture_from_image(&thread, &trantition_left_image)
         .unwrap();
-    let mut player1_texture = rl.load_texture(&thread, "./static/player1.png").unwrap();
-    let mut player2_texture = rl.load_texture(&thread, "./static/player2.png").unwrap();
-    let mut player3_texture = rl.load_texture(&thread, "./static/player3.png").unwrap();
-    let mut player4_texture = rl.load_texture(&thread, "./static/player4.png").unwrap();
-
-    let mut level_image = Image::load_image("./static/level.png").unwrap();
-    level_image.resize(SCREEN_WIDTH, SCREEN_HEIGHT);
-    let mut level_texture = rl.load_texture_from_image(&thread, &level_image).unwrap();
+    let mut player1_texture = assets::load_texture(&mut rl, &thread, "player1.png");
+    let mut player2_texture = assets::load_texture(&mut rl, &thread, "player2.png");
+    let mut player3_texture = assets::load_texture(&mut rl, &thread, "player3.png");
+    let mut player4_texture = assets::load_texture(&mut rl, &thread, "player4.png");
+    #[cfg_attr(not(feature = "dev"), allow(unused_mut))]
+    let mut skin_textures = SkinTextures::load(&mut rl, &thread, &PLAYER_SPRITES);
+
+    let mut level_texture = level
+        .load_background(&mut rl, &thread)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let mut paint_mask = level
+        .load_paint_mask()
+        .unwrap_or_else(|err| panic!("{}", err));
+    #[cfg(feature = "dev")]
+    let mut watcher = {
+        let mut watcher = hotreload::AssetWatcher::new();
+        watcher.watch_dir(Path::new(assets::ASSET_DIR));
+        watcher.watch_file(&level_file);
+        watcher.watch_file(&level.background);
+        watcher
+    };
+    #[cfg(feature = "dev")]
+    let mut level_background = level.background.clone();
     let mut trantition_progress = 0.0;
     let mut transitioning = false;
+    // a new match opens with the intro once the transition closes
+    let mut intro_pending = false;
+    let mut intro: Option<Intro> = None;
+    // the gamepads the lobby was last seated for, None until the menu first
+    // opens
+    let mut lobby_pads: Option<Vec<usize>> = None;
     let mut reversing = false;
     let mut in_game = false;
     let mut delay_timer = 0.0;
     let mut head_msg: Option<String> = None;
-    let mut level_done = false;
     let mut level_end_timer = 5.0;
-    let mut spawn_timer = 5.0;
-    let mut players_count = 2;
-
-    let mut game_type = Box::new(MiniGames::ColorTheMap);
+    let mut arena_camera = ArenaCamera::new();
+    let mut director = Director::default();
+    let mut players_count = options.players.unwrap_or(2);
+    let points_to_win = options.points_to_win.unwrap_or(POINTS_TO_WIN);
+    // --mode keeps every round on the one minigame
+    let mut orchestrator = MatchOrchestrator::new(
+        active_script.map_or(options.mode, |i| Some(scripted_games[i].base)),
+    );
+    // golden runs ignore everything saved on this machine
+    let mut golden =
+        (options.golden || options.golden_update).then(|| GoldenRun::new(options.golden_update));
+    let mut settings = if golden.is_some() {
+        Settings::default()
+    } else {
+        Settings::load()
+    };
+    let mut profiles = ProfileStore::load();
+    let mut new_profile_name = String::new();
+    let mut history = MatchHistory::load();
+    let mut export_status: Option<String> = None;
+    let mut current_rounds: Vec<RoundRecord> = Vec::new();
+    let mut new_unlocks: Vec<String> = Vec::new();
+    let mut suspended = if golden.is_some() {
+        None
+    } else {
+        SuspendedMatch::load()
+    };
+    // Some(reason) while a match is paused
+    let mut pause_reason: Option<&str> = None;
+    // holds the round while a seat's gamepad is gone
+    let mut pad_watch = PadWatch::default();
+    let mut quit = false;
+    let mut active_mutators: Vec<Mutator> = options.mutators.clone();
+    let mut daily_log = if golden.is_some() {
+        DailyLog::default()
+    } else {
+        DailyLog::load()
+    };
+    let today = match golden {
+        Some(_) => DailyChallenge::for_day(golden::DAY),
+        None => DailyChallenge::today(),
+    };
+    let mut rng = Rng::new(options.seed.unwrap_or_else(history::now));
+    let mut recorder = ReplayRecorder::default();
+    let mut replay_saved = false;
+    // the WinScreen asked for a result card, it is made at the next frame
+    let mut save_card = false;
+    let mut card_path: Option<PathBuf> = None;
+    let mut replay_files: Vec<String> = Vec::new();
+    let mut replay_to_load: Option<String> = None;
+    let mut replay_viewer: Option<ReplayViewer> = None;
+    let mut tutorial: Option<Tutorial> = None;
+    let mut tutorial_to_open: Option<MiniGames> = None;
+    let mut clip = ClipRecorder::default();
+    let mut debug = DebugOverlay::default();
+    let mut net_meter = NetMeter::default();
+    let mut console = Console::default();
+    // simulation speed, changed from the console and slowed for a final blow
+    let mut clock = GameClock::new();
+    let mut time_control = TimeControl::default();
+
+    let mut game_type = Box::new(orchestrator.locked_mode.unwrap_or(MiniGames::ColorTheMap));
     let mut game_mode = GameMode::MainMenu;
     let mut bullets: Vec<Bullet> = Vec::new();
-
-    let mut camera = Camera2D {
-        offset: Vector2::new(
-            (rl.get_screen_width() as f32 / 2.0) - SCREEN_WIDTH as f32 / 2.,
-            (rl.get_screen_height() as f32 / 2.0) - SCREEN_HEIGHT as f32 / 2.,
-        ),
-        zoom: 1.0,
-        ..Default::default()
-    };
-
-    let mut ops: Vec<EnvItem> = vec![
-        EnvItem {
-            rect: Rectangle {
-                x: 0.0,
-                y: 0.0,
-                width: SCREEN_WIDTH as f32,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: SCREEN_WIDTH as f32 - 15.0,
-                y: 50.0,
-                width: 15.0,
-                height: 120.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: SCREEN_WIDTH as f32 - 15.0,
-                y: 240.0,
-                width: 15.0,
-                height: 120.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: SCREEN_WIDTH as f32 - 15.0,
-                y: 425.0,
-                width: 15.0,
-                height: 90.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 0.0,
-                y: 45.0,
-                width: 15.0,
-                height: 45.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 0.0,
-                y: 160.0,
-                width: 15.0,
-                height: 30.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 0.0,
-                y: 260.0,
-                width: 15.0,
-                height: 153.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 0.0,
-                y: 480.0,
-                width: 15.0,
-                height: 95.,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 1010.,
-                y: 185.,
-                width: 182.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 9.,
-                y: 119.,
-                width: 117.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 9.,
-                y: 209.,
-                width: 217.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 725.,
-                y: 210.,
-                width: 45.0,
-                height: 60.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 590.,
-                y: 210.,
-                width: 40.0,
-                height: 60.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 450.,
-                y: 260.,
-                width: 460.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 130.,
-                y: 320.,
-                width: 220.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 975.,
-                y: 330.,
-                width: 40.0,
-                height: 60.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 907.,
-                y: 370.,
-                width: 285.,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 9.,
-                y: 439.,
-                width: 493.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 655.,
-                y: 485.,
-                width: 395.0,
-                height: 30.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: SCREEN_WIDTH as f32 - 20.0 - 30.0,
-                y: SCREEN_HEIGHT as f32 - 115.,
-                width: 35.0,
-                height: 60.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 345.0,
-                y: SCREEN_HEIGHT as f32 - 115.,
-                width: 50.0,
-                height: 60.0,
-            },
-            color: Color::RED.alpha(0.5),
-        },
-        EnvItem {
-            rect: Rectangle {
-                x: 10.0,
-                y: SCREEN_HEIGHT as f32 - 60.0,
-                width: SCREEN_WIDTH as f32 - 20.0,
-                height: 60.0,
-            },
-            color: Color::BLUE.alpha(0.5),
-        },
-    ];
+    let mut events = EventQueue::default();
+    let mut feed = EventFeed::default();
+    let mut feedback = FeedbackRouter::default();
+    feedback.cues = settings.audio_cues;
+    let mut pickups = Pickups::default();
+    let mut streaks = Streaks::new(true);
+    let mut afk = Afk::new(true);
+    let mut trails = Trails::default();
+    let mut quick_chat = QuickChat::default();
+    let mut stats_hud = StatsHud::default();
+    let mut hotseat = HotSeat::default();
+
+    let mut viewport = Viewport::new(&mut rl);
+    let mut post = PostProcess::new(&mut rl, &thread, &viewport, settings.gamma).unwrap();
+
+    let mut setpieces = SetPieces::new(level.setpieces);
+    let mut spawners = Spawners::new(level.spawners);
+    let mut ops: Vec<EnvItem> = level.items;
+    // what the players can paint on this map, ColorTheMap coverage is a share of it
+    let mut paint_area = coverage::paintable_area(&ops, &paint_mask);
 
     let mut players: [Player; 4] = [
         Player::new(
             Vector2::new(100.0, 100.0),
             0.0,
-            300.0,
-            Color::from_hex("FBB954").unwrap(),
+            PLAYER_SPEED,
+            Color::from_hex(PLAYER_COLORS[0]).unwrap(),
             InputType::Keyboard(KeyboardControls::WASD),
             game_type.clone(),
             50.0,
             50.0,
-            700.0,
+            JUMP_FORCE,
             player1_texture,
             0,
         ),
         Player::new(
             Vector2::new(200.0, 100.0),
             0.0,
-            300.0,
-            Color::from_hex("A884F3").unwrap(),
+            PLAYER_SPEED,
+            Color::from_hex(PLAYER_COLORS[1]).unwrap(),
             InputType::Keyboard(KeyboardControls::ArrowKeys),
             game_type.clone(),
             50.0,
             50.0,
-            700.0,
+            JUMP_FORCE,
             player2_texture,
             1,
         ),
         Player::new(
             Vector2::new(300.0, 100.0),
             0.0,
-            300.0,
-            Color::from_hex("1EBC73").unwrap(),
-            InputType::Controller(2),
+            PLAYER_SPEED,
+            Color::from_hex(PLAYER_COLORS[2]).unwrap(),
+            InputType::Controller(0),
             game_type.clone(),
             50.0,
             50.0,
-            700.0,
+            JUMP_FORCE,
             player3_texture,
             2,
         ),
         Player::new(
             Vector2::new(400.0, 100.0),
             0.0,
-            300.0,
-            Color::from_hex("E83B3B").unwrap(),
-            InputType::Controller(3),
+            PLAYER_SPEED,
+            Color::from_hex(PLAYER_COLORS[3]).unwrap(),
+            InputType::Controller(1),
             game_type.clone(),
             50.0,
             50.0,
-            700.0,
+            JUMP_FORCE,
             player4_texture,
             3,
         ),
     ];
 
-    let mut map_image =
-        Image::gen_image_color(SCREEN_WIDTH, SCREEN_HEIGHT, Color::WHITE.alpha(0.0));
+    let mut map_image = blank_paint_map(settings.quality);
     let mut map_texture = rl.load_texture_from_image(&thread, &map_image).unwrap();
 
-    rl.set_target_fps(60);
-    let mut persents: [f32; 4] = [0.0; 4];
+    // What every round starts from, however it was started: the last
+    // round's leftovers cleared and spawn protection handed out. Which
+    // minigame it is, its timer and where the players stand are set before,
+    // a resumed match keeps all three.
+    macro_rules! reset_round {
+        () => {
+            round.reset();
+            level_end_timer = 5.0;
+            head_msg = None;
+            trails.clear();
+            clip.clear();
+            stats_hud.reset_round();
+            setpieces.reset(&mut ops);
+            spawners.reset();
+            on_round_start(&mut players);
+            pickups.reset(orchestrator.configs.get(*game_type).paint_bomb_interval);
+        };
+    }
+    // A new match for the same lobby: the per-match tallies, feeds and
+    // recording start over, then its first round
+    macro_rules! reset_match {
+        () => {
+            mutators::apply_to_players(&active_mutators, &mut players);
+            streaks.reset();
+            afk.reset();
+            feed.clear();
+            feedback.clear();
+            stats_hud.reset_match();
+            hotseat.start(&players[0..players_count], profiles.profiles.len());
+            recorder.start();
+            replay_saved = false;
+            new_unlocks.clear();
+            card_path = None;
+            reset_round!();
+        };
+    }
 
-    while !rl.window_should_close() {
-        let dt = rl.get_frame_time();
+    // the first run starts on the brightness calibration
+    if !settings.calibrated && golden.is_none() {
+        game_mode = GameMode::Calibration;
+    }
+    if options.skips_menu() {
+        game_mode = GameMode::Game;
+        round.time_left = orchestrator.round_time(*game_type, &settings, &active_mutators);
+        round.reset();
+        mutators::apply_to_players(&active_mutators, &mut players);
+        on_round_start(&mut players);
+        pickups.reset(orchestrator.configs.get(*game_type).paint_bomb_interval);
+        setpieces.reset(&mut ops);
+        spawners.reset();
+        recorder.start();
+    }
 
-        //  rl.is_gamepad_button_down(0, consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP)
-        // println!("{}", );
-        // Update transition
-        if transitioning {
-            if !reversing {
-                trantition_progress += dt * 2.0;
-                if trantition_progress >= 1.0 {
-                    trantition_progress = 1.0;
-                    game_mode = GameMode::Game;
-                    delay_timer = 0.0;
-                    reversing = true;
+    // --connect: the host plays the match, this window follows along
+    let mut session = options.connect.as_ref().map(|address| {
+        let profile = options.profile.as_ref().map(|name| {
+            match profiles
+                .profiles
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name))
+            {
+                Some(profile) => profile,
+                None => {
+                    eprintln!("no saved profile called {}", name);
+                    std::process::exit(1);
+                }
+            }
+        });
+        match Session::connect(address, &map_image, profile) {
+            Ok(session) => session,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    });
+    if session.is_some() {
+        game_mode = GameMode::Game;
+    }
+
+    rl.set_target_fps(60);
+
+    // Esc pauses a match instead of closing the window
+    rl.set_exit_key(None);
+    while !rl.window_should_close() && !quit {
+        if let Some(scene) = golden
+            .as_ref()
+            .filter(|run| run.starting())
+            .and_then(GoldenRun::scene)
+        {
+            // every scene starts from two guests frozen mid-round
+            players_count = 2;
+            for player in &mut players {
+                player.profile = None;
+                skin_textures.apply(player, None);
+                player.respawn(Vector2::new(150.0 + 250.0 * player.number as f32, 300.0));
+                player.invulnerable = 0.0;
+                player.hit_flash = 0.0;
+                player.points = player.number;
+            }
+            map_image = blank_paint_map(settings.quality);
+            recorder.clear_paint();
+            bullets.clear();
+            head_msg = None;
+            round.time_left = 10.0;
+            round.reset();
+            match scene {
+                Scene::Menu => game_mode = GameMode::MainMenu,
+                Scene::ColorTheMap => {
+                    game_mode = GameMode::Game;
+                    game_type = Box::new(MiniGames::ColorTheMap);
+                    // scripted paint: a zig-zag stroke per player
+                    for player in &players[0..players_count] {
+                        for k in 0..60 {
+                            let point = Vector2::new(
+                                100.0 + k as f32 * 15.0,
+                                120.0 + player.number as f32 * 200.0 + (k % 10) as f32 * 8.0,
+                            );
+                            player.paint(&mut map_image, point);
+                        }
+                    }
+                }
+                Scene::Dodge => {
+                    game_mode = GameMode::Game;
+                    game_type = Box::new(MiniGames::Dodge);
+                    for (i, lane) in [50., 200., 350., 500.].into_iter().enumerate() {
+                        bullets.push(Bullet {
+                            rect: Rectangle::new(100.0 + i as f32 * 250.0, lane, 15., 30.),
+                            color: Color::PINK,
+                            speed: Vector2::new(250.0, 0.0),
+                            gravity: 0.0,
+                            time_to_live: 10.,
+                            source: BulletSource::Wave(1),
+                        });
+                    }
+                }
+            }
+        }
+        // keys typed into the console don't reach the game
+        let typing = console.open;
+        // quick snapshot hotkeys, debug builds only so a stray F9 can't
+        // replace a real match
+        let hotkey = if !cfg!(debug_assertions) {
+            None
+        } else if rl.is_key_pressed(consts::KeyboardKey::KEY_F8) {
+            Some(ConsoleCommand::SaveSnapshot(
+                snapshot::QUICK_SNAPSHOT.to_string(),
+            ))
+        } else if rl.is_key_pressed(consts::KeyboardKey::KEY_F9) {
+            Some(ConsoleCommand::LoadSnapshot(
+                snapshot::QUICK_SNAPSHOT.to_string(),
+            ))
+        } else {
+            None
+        };
+        if let Some(command) = console.update(&mut rl).or(hotkey) {
+            match command {
+                ConsoleCommand::Help => console.print_help(),
+                ConsoleCommand::GivePoints { player, points } => {
+                    if player < players_count {
+                        players[player].points += points;
+                        console.print(format!(
+                            "P{} has {} points",
+                            player + 1,
+                            players[player].points
+                        ));
+                    } else {
+                        console.print(format!("only {} players are playing", players_count));
+                    }
+                }
+                ConsoleCommand::SetMode(mode) => {
+                    game_type = Box::new(mode);
+                    bullets.clear();
+                    console.print(format!("mode set to {}", mode.name()));
+                }
+                ConsoleCommand::SpawnBullet { position, speed } => {
+                    bullets.push(Bullet {
+                        rect: Rectangle::new(position.x, position.y, 15., 30.),
+                        color: Color::PINK,
+                        speed,
+                        gravity: 0.0,
+                        time_to_live: 10.,
+                        source: BulletSource::Console,
+                    });
+                }
+                ConsoleCommand::TimeScale(scale) => {
+                    clock.scale = scale;
+                    console.print(format!("time scale {}", scale));
+                }
+                ConsoleCommand::NetSim { lag, loss } => {
+                    net_meter.conditions = LinkConditions { lag, loss };
+                    console.print(format!(
+                        "network meter link: {:.0} ms lag, {:.0}% loss",
+                        lag * 1000.0,
+                        loss * 100.0
+                    ));
+                }
+                ConsoleCommand::ReloadLevel => match load_level().and_then(|level| {
+                    let texture = level.load_background(&mut rl, &thread)?;
+                    let mask = level.load_paint_mask()?;
+                    Ok((level, texture, mask))
+                }) {
+                    Ok((level, texture, mask)) => {
+                        level_texture = texture;
+                        paint_mask = mask;
+                        ops = level.items;
+                        setpieces = SetPieces::new(level.setpieces);
+                        spawners = Spawners::new(level.spawners);
+                        paint_area = coverage::paintable_area(&ops, &paint_mask);
+                        console.print(format!("reloaded level {}", level.name));
+                    }
+                    Err(err) => console.print(err),
+                },
+                ConsoleCommand::SaveSnapshot(name) => {
+                    if game_mode == GameMode::Game {
+                        Snapshot::capture(
+                            *game_type,
+                            round.time_left,
+                            round.done,
+                            level_end_timer,
+                            round.spawn_timer,
+                            round.elapsed,
+                            &head_msg,
+                            &players[0..players_count],
+                            &bullets,
+                            &rng,
+                            &current_rounds,
+                            &mut map_image,
+                        )
+                        .save(&name);
+                        console.print(format!("saved snapshot {}", name));
+                    } else {
+                        console.print("snapshots can only be taken during a match");
+                    }
+                }
+                ConsoleCommand::LoadSnapshot(name) => match Snapshot::load(&name) {
+                    Ok(snapshot) => {
+                        players_count = snapshot.players.len();
+                        snapshot.restore_players(&mut players);
+                        game_type = Box::new(snapshot.game);
+                        round.time_left = snapshot.level_timer;
+                        round.done = snapshot.level_done;
+                        level_end_timer = snapshot.level_end_timer;
+                        round.spawn_timer = snapshot.spawn_timer;
+                        round.elapsed = snapshot.round_elapsed;
+                        head_msg = snapshot.head_msg.clone();
+                        bullets = snapshot.bullets.clone();
+                        rng = snapshot.rng.clone();
+                        current_rounds = snapshot.rounds.clone();
+                        // setpieces due by now go off again on the next update
+                        setpieces.reset(&mut ops);
+                        spawners.reset();
+                        let mut paint = snapshot.paint.to_image(&players);
+                        // nearest-neighbour keeps every pixel a player's color
+                        paint.resize_nn(map_image.width, map_image.height);
+                        map_image = paint;
+                        pause_reason = None;
+                        game_mode = GameMode::Game;
+                        // hold still so it can be stepped through (debug builds)
+                        time_control.freeze();
+                        console.print(format!("loaded snapshot {}", name));
+                    }
+                    Err(err) => console.print(err),
+                },
+            }
+        }
+        let pause_pressed = !typing
+            && !console.open
+            && (rl.is_key_pressed(consts::KeyboardKey::KEY_ESCAPE)
+                || rl.is_key_pressed(consts::KeyboardKey::KEY_P)
+                || (0..4).any(|gamepad| {
+                    rl.is_gamepad_button_pressed(
+                        gamepad,
+                        consts::GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+                    )
+                }));
+        if game_mode == GameMode::Game && golden.is_none() {
+            if pause_reason.is_some() {
+                if pause_pressed {
+                    pause_reason = None;
+                }
+            } else if !rl.is_window_focused() {
+                pause_reason = Some("Window lost focus");
+            } else if pause_pressed {
+                pause_reason = Some("Paused");
+            }
+        } else {
+            pause_reason = None;
+            if game_mode == GameMode::MainMenu
+                && !typing
+                && rl.is_key_pressed(consts::KeyboardKey::KEY_ESCAPE)
+            {
+                break;
+            }
+        }
+        // the lobby seats the connected gamepads first when the menu first
+        // opens and whenever a pad has come or gone since; reopening it keeps
+        // the count picked with +/- or --players
+        if game_mode == GameMode::MainMenu {
+            let pads = lobby::connected_pads(&rl);
+            if lobby_pads.as_ref() != Some(&pads) {
+                for (player, controls) in players.iter_mut().zip(lobby::seat_controls(&pads)) {
+                    player.controls = controls;
+                }
+                if lobby_pads.is_some() || options.players.is_none() {
+                    players_count = lobby::seat_count(&pads);
+                }
+                lobby_pads = Some(pads);
+            }
+        }
+        if game_mode == GameMode::Game && golden.is_none() {
+            pad_watch.update(&rl, &mut players[0..players_count]);
+        } else {
+            pad_watch.reset();
+        }
+        viewport.update(&mut rl);
+        time_control.update(&rl);
+        let paused = pause_reason.is_some() || pad_watch.holds();
+        // the simulation below stands still while paused
+        let dt = if paused || golden.is_some() {
+            0.0
+        } else {
+            clock.tick(time_control.scale(rl.get_frame_time()))
+        };
+        // false while paused, frozen or at time scale 0
+        let simulating = dt > 0.0;
+        let round_was_done = round.done;
+        let take_screenshot = rl.is_key_pressed(consts::KeyboardKey::KEY_F12);
+        if rl.is_key_pressed(consts::KeyboardKey::KEY_F3) {
+            debug.toggle();
+            net_meter.reset();
+        }
+        if debug.enabled
+            && director.shows_debug()
+            && game_mode == GameMode::Game
+            && rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+        {
+            debug.click(
+                director.virtual_to_world(&arena_camera, rl.get_mouse_position()),
+                &players[0..players_count],
+                &bullets,
+                &ops,
+            );
+        }
+
+        #[cfg(feature = "dev")]
+        for path in watcher.poll(dt) {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if PLAYER_SPRITES.contains(&name.as_str()) {
+                skin_textures = SkinTextures::load(&mut rl, &thread, &PLAYER_SPRITES);
+                for player in &mut players {
+                    let profile = player.profile.map(|i| &profiles.profiles[i]);
+                    skin_textures.apply(player, profile);
+                }
+                eprintln!("reloaded {}", name);
+            } else if path.file_name() == level_file.file_name()
+                || path.file_name() == level_background.file_name()
+            {
+                match load_level() {
+                    Ok(level) => {
+                        match level.load_background(&mut rl, &thread) {
+                            Ok(texture) => level_texture = texture,
+                            Err(err) => eprintln!("{}", err),
+                        }
+                        match level.load_paint_mask() {
+                            Ok(mask) => paint_mask = mask,
+                            Err(err) => eprintln!("{}", err),
+                        }
+                        if level.background != level_background {
+                            watcher.watch_file(&level.background);
+                            level_background = level.background.clone();
+                        }
+                        ops = level.items;
+                        setpieces = SetPieces::new(level.setpieces);
+                        spawners = Spawners::new(level.spawners);
+                        paint_area = coverage::paintable_area(&ops, &paint_mask);
+                        eprintln!("reloaded level {}", level.name);
+                    }
+                    Err(err) => eprintln!("keeping the current level: {}", err),
+                }
+            }
+        }
+
+        //  rl.is_gamepad_button_down(0, consts::GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP)
+        // println!("{}", );
+        // Update transition
+        if transitioning {
+            if !reversing {
+                trantition_progress += dt * 2.0;
+                if trantition_progress >= 1.0 {
+                    trantition_progress = 1.0;
+                    if intro_pending {
+                        intro_pending = false;
+                        intro = Some(Intro::new(players_count));
+                        game_mode = GameMode::Intro;
+                    } else {
+                        game_mode = GameMode::Game;
+                    }
+                    reset_match!();
+                    delay_timer = 0.0;
+                    reversing = true;
                 }
             } else {
                 delay_timer += dt;
@@ -781,228 +1765,507 @@ fn main() {
                 }
             }
         }
-        let mut delete_bullets = vec![];
-        for (index, bullet) in bullets.iter_mut().enumerate() {
-            // bullet.update(&rl, dt);
-            bullet.rect.x += bullet.speed.x * dt;
-            bullet.rect.y += bullet.speed.y * dt;
-            bullet.time_to_live -= dt;
-            if bullet.time_to_live <= 0.0 {
-                delete_bullets.push(index);
-            }
-            for player in &mut players[0..players_count] {
-                if let Some(collision_rect) =
-                    player.get_collision_rect().get_collision_rec(&bullet.rect)
-                {
-                    // player.health -= 1;
-                    // delete_bullets.push(index);
-                    player.dead = true;
-                }
-            }
-        }
-        for index in delete_bullets {
-            bullets.remove(index);
-        }
-        let players_clone = players.clone();
-        if (game_mode == GameMode::Game) {
-            for player in &mut players[0..players_count] {
-                let players_clone: Vec<&Player> = players_clone
-                    .iter()
-                    .map(|p| p)
-                    .filter(|p| p.number != player.number)
-                    .collect();
-
-                if !level_done {
-                    player.update(&rl, dt);
-                    let collisions = player.handle_collision(&ops, players_clone);
-                    let is_colliding = !collisions.is_empty();
-
-                    let points: Vec<Vector2> = collisions
-                        .into_iter()
-                        .flat_map(|(_, collision_points)| collision_points)
-                        .collect();
-                    for point in points {
-                        player.paint(&mut map_image, point);
+        if let Some(session) = &mut session {
+            let input = if console.open {
+                PlayerInput::default()
+            } else {
+                players[0].read_input(&rl)
+            };
+            for received in session.update(input, &players, &mut map_image, rl.get_frame_time()) {
+                match received {
+                    // the host keeps no history of its own, every client
+                    // records the match it played
+                    Received::MatchOver(record) => {
+                        let winner = record
+                            .winner()
+                            .map_or("nobody".to_string(), |i| record.players[i].name.clone());
+                        feed.push(format!("{} won the match", winner), Color::GOLD);
+                        history.push(record);
+                        history.save();
                     }
-                    if !is_colliding {
-                        player.is_on_ground = false;
+                    Received::QuickChat { seat, message } => {
+                        events.push(GameEvent::QuickChat {
+                            player: seat,
+                            message,
+                        });
                     }
+                    Received::Nothing | Received::Full => {}
                 }
             }
+            if let Some(count) = session.show(&mut players, &mut bullets) {
+                players_count = count;
+            }
+            if let Some(info) = session.client.round {
+                game_type = Box::new(info.game);
+                round.time_left = info.time_left;
+                round.done = info.done;
+            }
+            if game_mode == GameMode::Game && !round.done {
+                session.predict(
+                    &mut players[0..players_count],
+                    input,
+                    &ops,
+                    orchestrator.configs.get(*game_type).bounds,
+                    &mut map_image,
+                    rl.get_frame_time(),
+                );
+            }
         }
-        let width = map_image.width;
-        let height = map_image.height;
-        let format = map_image.format();
-        let data = unsafe {
-            std::slice::from_raw_parts(
-                map_image.data as *const u8,
-                raylib::texture::get_pixel_data_size(width, height, format)
-                    .try_into()
-                    .unwrap(),
-            )
-        };
-        // let mut reset_game = move || {
-        // };
-
-        map_texture.update_texture(data);
-        if (game_mode == GameMode::Game && !level_done) {
-            level_timer -= dt;
-        }
-        if (level_done) {
-            level_end_timer -= dt;
-        }
-        if (level_end_timer <= 0.0) {
-            level_end_timer = 5.0;
-            level_timer = 15.0;
-            head_msg = None;
-            match *game_type {
-                MiniGames::ColorTheMap => {
-                    game_type = Box::new(MiniGames::Dodge);
+        // the round itself, the same rules the dedicated host plays by
+        if game_mode == GameMode::Game && simulating && session.is_none() {
+            debug.clear_contacts();
+            if round.elapsed == 0.0 {
+                for seat in 0..players_count {
+                    feedback.send(&mut players, seat, Feedback::RoundStart);
                 }
-                MiniGames::Dodge => {
-                    game_type = Box::new(MiniGames::ColorTheMap);
+            }
+            let mut inputs = [None; 4];
+            if !round.done {
+                for (seat, player) in players[0..players_count].iter().enumerate() {
+                    let input = if console.open {
+                        PlayerInput::default()
+                    } else {
+                        player.read_input(&rl)
+                    };
+                    // sitting out a Dodge round dead isn't being away
+                    if !player.dead {
+                        if let Some(event) = afk.update(seat, input, dt) {
+                            events.push(event);
+                        }
+                    }
+                    inputs[seat] = Some(if afk.bots && afk.is_away(seat) {
+                        afk.bot_input(*game_type, player, &bullets, dt)
+                    } else {
+                        input
+                    });
                 }
-                _ => {}
             }
-
-            for player in &mut players {
-                player.dead = false;
-                player.position = Vector2::new(100.0 + 100.0 * player.number as f32, 100.0);
-            }
-            level_done = false;
-        }
-
-        if (*game_type == MiniGames::Dodge && spawn_timer <= 0.0 && level_done == false) {
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 50., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 200., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 350., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 500., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 650., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-            bullets.push(Bullet {
-                rect: Rectangle::new(-20., 800., 15., 30.),
-                color: Color::PINK,
-                speed: Vector2::new(250.0, 0.0),
-                time_to_live: 10.,
-            });
-
-            spawn_timer = 5.0;
-        }
-
-        if (*game_type == MiniGames::Dodge) {
-            spawn_timer -= dt;
-        }
-        if (*game_type == MiniGames::Dodge && level_done == false) {
-            let mut players_alive: Vec<&mut Player> = players
-                .iter_mut()
-                .filter(|p| p.dead == false && p.number < players_count as u32)
-                .collect();
-            if players_alive.len() == 1 {
-                head_msg = Some(format!("Player {} won", players_alive[0].number + 1));
-                level_done = true;
+            let mut stage = Stage {
+                game: *game_type,
+                config: orchestrator.configs.get(*game_type),
+                mutators: &active_mutators,
+                players: &mut players[0..players_count],
+                bullets: &mut bullets,
+                paint: &mut map_image,
+                paint_mask: &mut paint_mask,
+                paint_area,
+                ops: &mut ops,
+                setpieces: &mut setpieces,
+                spawners: &mut spawners,
+                pickups: &mut pickups,
+                rng: &mut rng,
+                script: active_script.map(|i| &mut scripted_games[i]),
+            };
+            let step = sim::step(
+                &mut stage,
+                &mut round,
+                &inputs[0..players_count],
+                clock.in_slow_motion(),
+                dt,
+            );
+            for contact in &step.contacts {
+                debug.record_contact(contact);
+            }
+            for (seat, point, radius) in step.splats {
+                recorder.splat(seat, point, radius);
+                if debug.enabled {
+                    net_meter.predict(&players[seat], point);
+                }
+            }
+            for message in step.messages {
+                feed.push(message, Color::DARKPURPLE);
+            }
+            if step.final_blow {
+                clock.slow_motion(FINAL_BLOW_SCALE, FINAL_BLOW_TIME);
+                arena_camera.focus = step.focus;
+            }
+            for event in step.events {
+                events.push(event);
+            }
+            if let Some(end) = step.ended {
+                head_msg = Some(end.message);
+                for winner in &end.winners {
+                    profiles.record_round_win(&players[*winner], *game_type);
+                }
+                if *game_type == MiniGames::ColorTheMap && active_script.is_none() {
+                    for (i, player) in players[0..players_count].iter().enumerate() {
+                        profiles.record_coverage(player, round.coverage[i]);
+                    }
+                }
+                current_rounds.push(RoundRecord {
+                    game: *game_type,
+                    winners: end.winners,
+                });
+                if players[0..players_count]
+                    .iter()
+                    .any(|p| p.points >= points_to_win)
+                {
+                    game_mode = GameMode::WinScreen;
+                    let record_players: Vec<MatchPlayer> = players[0..players_count]
+                        .iter()
+                        .map(|p| MatchPlayer {
+                            name: match p.profile {
+                                Some(_) => profiles.name(p.profile),
+                                None => format!("P{}", p.number + 1),
+                            },
+                            has_profile: p.profile.is_some(),
+                            points: p.points,
+                        })
+                        .collect();
+                    let record = MatchRecord {
+                        timestamp: history::now(),
+                        players: record_players,
+                        rounds: std::mem::take(&mut current_rounds),
+                    };
+                    new_unlocks =
+                        profiles.record_match(&players[0..players_count], record.winner());
+                    if let Some(daily) = &orchestrator.daily {
+                        daily_log.push(DailyResult {
+                            day: daily.day,
+                            timestamp: record.timestamp,
+                            players: record.players.clone(),
+                        });
+                        daily_log.save();
+                    }
+                    SuspendedMatch::discard();
+                    net_meter.finish_match(&record);
+                    history.push(record);
+                    history.save();
+                }
+                profiles.save();
                 level_end_timer = 5.0;
             }
         }
-        if (level_timer <= 0.0 && level_done == false) {
-            // level += 1;
-            match *game_type {
-                MiniGames::ColorTheMap => {
-                    persents = calculate_winner(
-                        &mut map_image,
-                        2,
-                        &players[0].color,
-                        &players[1].color,
-                        &players[2].color,
-                        &players[3].color,
-                    );
-                    // get index of largest value
-                    let mut index = 0;
-                    for i in 0..persents.len() {
-                        if persents[i] > persents[index] {
-                            index = i;
+        while let Some(event) = events.pop() {
+            match event {
+                // resolved by the step that pushed them
+                GameEvent::BulletHit { .. }
+                | GameEvent::Burned { .. }
+                | GameEvent::Swept { .. } => {}
+                GameEvent::Eliminated { player, by } => {
+                    profiles.record_elimination(&players[player]);
+                    stats_hud.record_elimination(player, by);
+                    if let BulletSource::Thrown(thrower) = by {
+                        if thrower != player && thrower < players_count {
+                            profiles.record_knockout(&players[thrower]);
                         }
                     }
-
-                    match index {
-                        0 => players[0].points += 1,
-                        1 => players[1].points += 1,
-                        2 => players[2].points += 1,
-                        3 => players[3].points += 1,
-                        _ => {}
-                    }
-                    head_msg = Some(format!("player {} won", index + 1));
-
-                    for player in &mut players[0..players_count] {
-                        if player.points >= 5 {
-                            // player.points += 1;
-                            game_mode = GameMode::WinScreen;
-                        }
-                        // player.reset();
+                    feed.push(
+                        format!("P{} eliminated by {}", player + 1, by.describe()),
+                        players[player].color,
+                    );
+                    feedback.send(&mut players, player, Feedback::Eliminated);
+                }
+                GameEvent::PickedUp { player, kind } => {
+                    feed.push(
+                        format!("P{} grabbed {}!", player + 1, kind.name()),
+                        players[player].color,
+                    );
+                    feedback.send(&mut players, player, Feedback::Grabbed);
+                }
+                GameEvent::PaintBomb { player, .. } => {
+                    feedback.send(&mut players, player, Feedback::Splat);
+                }
+                GameEvent::QuickChat { player, message } => {
+                    quick_chat.say(player, message);
+                    // the host passes ours on to everyone else
+                    if let Some(session) =
+                        session.as_ref().filter(|s| s.client.seat == Some(player))
+                    {
+                        session.chat(message);
                     }
                 }
-                MiniGames::Dodge => {
-                    let mut players_alive: Vec<&mut Player> = players
-                        .iter_mut()
-                        .filter(|p| p.dead == false && p.number < players_count as u32)
-                        .collect();
-                    if players_alive.len() == 1 {
-                        head_msg = Some(format!("Player {} won", players_alive[0].number + 1));
+                GameEvent::WentAfk { player } => {
+                    let text = if afk.bots {
+                        format!("P{} is AFK, a bot takes over", player + 1)
                     } else {
-                        head_msg = Some(format!("it's a tie"));
+                        format!("P{} is AFK", player + 1)
+                    };
+                    feed.push(text, players[player].color);
+                }
+                GameEvent::Returned { player } => {
+                    feed.push(format!("P{} is back", player + 1), players[player].color);
+                }
+                GameEvent::RoundWon { winners } => {
+                    for seat in 0..players_count {
+                        let cue = if winners.contains(&seat) {
+                            Feedback::Scored
+                        } else {
+                            Feedback::RoundOver
+                        };
+                        feedback.send(&mut players, seat, cue);
                     }
-
-                    for player in &mut players_alive {
-                        player.points += 1;
+                    for event in streaks.round_won(&winners, players_count) {
+                        events.push(event);
                     }
-                    // for player in &mut players[0..players_count] {
-                    //     if player.points >= 5 {
-                    //         // player.points += 1;
-                    //     }
-                    //     // player.reset();
-                    // }
                 }
-                _ => {}
+                GameEvent::StreakExtended { player, streak } => {
+                    feed.push(
+                        format!("P{} is on a {} round streak", player + 1, streak),
+                        players[player].color,
+                    );
+                }
+                GameEvent::Shutdown {
+                    player,
+                    victim,
+                    streak,
+                } => {
+                    feed.push(
+                        format!(
+                            "Shutdown! P{} ended P{}'s {} round streak",
+                            player + 1,
+                            victim + 1,
+                            streak
+                        ),
+                        players[player].color,
+                    );
+                }
             }
+        }
+        feed.update(dt);
+        feedback.update(dt);
+        if game_mode == GameMode::Game && simulating && !round.done {
+            feedback.warn(&mut players[0..players_count], &bullets, dt);
+        }
+        for player in &mut players {
+            player.tick_timers(dt);
+        }
+        map_texture.update_texture(image_bytes(&map_image));
+        if debug.enabled && game_mode == GameMode::Game && simulating {
+            net_meter.update(
+                dt,
+                &players[0..players_count],
+                &profiles.profiles,
+                &bullets,
+                &mut map_image,
+            );
+        }
+        if game_mode == GameMode::Intro {
+            let skipped =
+                rl.get_key_pressed().is_some() || rl.get_gamepad_button_pressed().is_some();
+            if let Some(current) = &mut intro {
+                arena_camera.intro(
+                    orchestrator.configs.get(*game_type).bounds,
+                    current.progress(),
+                );
+                if current.update(dt) || skipped {
+                    intro = None;
+                    game_mode = GameMode::Game;
+                    // the grace period starts with the round, not the intro
+                    on_round_start(&mut players);
+                }
+            }
+        }
+        if game_mode == GameMode::Game {
+            arena_camera.update(
+                *game_type,
+                orchestrator.configs.get(*game_type),
+                round.elapsed,
+                &players[0..players_count],
+                dt,
+            );
+        }
+        if !typing && (game_mode == GameMode::Game || game_mode == GameMode::Intro) {
+            // runs on wall-clock time, the stream keeps its camera while paused
+            director.update(
+                &rl,
+                &arena_camera,
+                &players[0..players_count],
+                rl.get_frame_time(),
+            );
+        }
+        if (round.done) {
+            level_end_timer -= dt;
+        }
+        if level_end_timer <= 0.0 && session.is_none() {
+            game_type =
+                Box::new(orchestrator.next_game(*game_type, current_rounds.len(), &mut rng));
+            round.time_left = orchestrator.round_time(*game_type, &settings, &active_mutators);
+
+            // hot-seat: the round's lowest scorer hands over the controller
+            if game_mode == GameMode::Game {
+                if let Some((seat, benched)) = hotseat.rotate(&mut players[0..players_count]) {
+                    let profile = players[seat].profile.map(|p| &profiles.profiles[p]);
+                    skin_textures.apply(&mut players[seat], profile);
+                    feed.push(
+                        format!(
+                            "{} sits out, {} takes P{}",
+                            profiles.name(Some(benched)),
+                            profiles.name(players[seat].profile),
+                            seat + 1
+                        ),
+                        players[seat].color,
+                    );
+                }
+            }
+            spawn::respawn_all(
+                &mut players,
+                &bullets,
+                &ops,
+                orchestrator.configs.get(*game_type).bounds,
+            );
+            reset_round!();
+        }
 
-            level_done = true;
-            level_end_timer = 5.0;
-            // level_timer = 5.0;
-            // spown a corotene and after 5 seconds change the game type
-            use std::thread;
-            use std::time::Duration;
-
-            // thread::spawn(move || {
-
-            //     game_type = MiniGames::Dodge;
-            // });
+        if round.done && !round_was_done {
+            round.overtime = false;
+            arena_camera.focus = None;
+            // the round was just decided, keep its last few seconds as a GIF
+            clip.export();
+            window_title.request_attention(&rl);
+        }
+        if game_mode == GameMode::Game && simulating {
+            recorder.record(
+                dt,
+                *game_type,
+                round.time_left,
+                &head_msg,
+                &players[0..players_count],
+                &bullets,
+            );
+            if !round.done && orchestrator.configs.get(*game_type).trails {
+                trails.record(&players[0..players_count], dt);
+            }
+            if !round.done {
+                stats_hud.update(&players[0..players_count], dt);
+            }
+        }
+        // quick chat is open to whoever has nothing else to do: knocked out
+        // players, everyone over the round's results and on the win screen
+        let chat_open = |player: &Player| match game_mode {
+            GameMode::Game => round.done || player.dead,
+            GameMode::WinScreen => true,
+            _ => false,
+        };
+        if console.open {
+            quick_chat.close();
+        } else {
+            for player in &players[0..players_count] {
+                // online only our own seat chats here, with P1's controls
+                let local = match &session {
+                    Some(session) => session.client.seat == Some(player.number as usize),
+                    None => true,
+                };
+                let input = if !local || !chat_open(player) {
+                    PlayerInput::default()
+                } else if session.is_some() {
+                    players[0].read_input(&rl)
+                } else {
+                    player.read_input(&rl)
+                };
+                if let Some(event) = quick_chat.update(player.number as usize, input) {
+                    events.push(event);
+                }
+            }
+        }
+        quick_chat.update_bubbles(rl.get_frame_time());
+        let show_stats = game_mode == GameMode::Game && !console.open && StatsHud::held(&rl);
+        if show_stats
+            && *game_type == MiniGames::ColorTheMap
+            && stats_hud.coverage_due(rl.get_frame_time())
+        {
+            stats_hud.set_coverage(calculate_winner(
+                &mut map_image,
+                paint_area,
+                2,
+                &players[0].color,
+                &players[1].color,
+                &players[2].color,
+                &players[3].color,
+            ));
+        }
+        if let Some(file_name) = replay_to_load.take() {
+            match Replay::load(&file_name)
+                .and_then(|replay| ReplayViewer::new(&mut rl, &thread, replay))
+                .and_then(|mut viewer| {
+                    viewer.load_level(&mut rl, &thread, options.map.as_deref())?;
+                    Ok(viewer)
+                }) {
+                Ok(viewer) => {
+                    replay_viewer = Some(viewer);
+                    game_mode = GameMode::ReplayViewer;
+                }
+                Err(err) => eprintln!("could not open replay: {}", err),
+            }
+        }
+        if game_mode == GameMode::ReplayViewer {
+            if let Some(viewer) = &mut replay_viewer {
+                viewer.update(&rl, dt);
+            }
+        }
+        if let Some(game) = tutorial_to_open.take() {
+            map_image = blank_paint_map(settings.quality);
+            recorder.clear_paint();
+            // seat 1 plays, the ghost looks like seat 2
+            let (seat, others) = players.split_at_mut(1);
+            match Tutorial::new(&mut rl, &thread, game, &others[0], &mut seat[0], &ops) {
+                Ok(opened) => {
+                    tutorial = Some(opened);
+                    game_mode = GameMode::Tutorial;
+                }
+                Err(err) => eprintln!("could not start the tutorial: {}", err),
+            }
+        }
+        if game_mode == GameMode::Tutorial && !console.open {
+            if let Some(tutorial) = &mut tutorial {
+                let was_done = tutorial.done;
+                tutorial.update(&rl, &mut players[0], &ops, &mut map_image, dt);
+                if tutorial.done && !was_done {
+                    profiles.complete_tutorial(&players[0], tutorial.game);
+                    profiles.save();
+                }
+            }
+        }
+        if game_mode == GameMode::Profiles && !console.open {
+            while let Some(c) = rl.get_char_pressed() {
+                if (c.is_alphanumeric() || c == ' ')
+                    && new_profile_name.len() < profiles::MAX_NAME_LEN
+                {
+                    new_profile_name.push(c);
+                }
+            }
+            if rl.is_key_pressed(consts::KeyboardKey::KEY_BACKSPACE) {
+                new_profile_name.pop();
+            }
+            if rl.is_key_pressed(consts::KeyboardKey::KEY_ENTER)
+                && !new_profile_name.trim().is_empty()
+            {
+                profiles.add(new_profile_name.trim());
+                profiles.save();
+                new_profile_name.clear();
+            }
         }
-        println!("{:?}", level_done);
+        let title = if game_mode == GameMode::Game {
+            let round = current_rounds.len() + if round.done { 0 } else { 1 };
+            window::match_title(*game_type, round, &players[0..players_count])
+        } else {
+            base_title.clone()
+        };
+        window_title.update(&mut rl, &thread, &title);
         // --- Drawing ---
-        let mut d = rl.begin_drawing(&thread);
+        post.fit(&mut rl, &thread, &viewport);
+        let mut card_target = None;
+        if std::mem::take(&mut save_card) {
+            match result_card::target(&mut rl, &thread) {
+                Ok(target) => card_target = Some(target),
+                Err(err) => eprintln!("could not make the result card: {}", err),
+            }
+        }
+        let mut screen = rl.begin_drawing(&thread);
+        // the result card goes first, it can't be drawn from inside the
+        // frame's own render texture
+        if let (Some(target), Some(record)) = (&mut card_target, history.matches.last()) {
+            let card = Card {
+                record,
+                players: &players[0..players_count],
+                knockouts: stats_hud.knockouts(),
+                eliminations: stats_hud.eliminations(),
+            };
+            card_path = result_card::save(&mut screen, &thread, target, &card);
+        }
+        // the frame is drawn off-screen, then presented through the gamma shader
+        let mut frame = screen.begin_texture_mode(&thread, &mut post.target);
+        let mut d: &mut RaylibDrawHandle = &mut frame;
         d.clear_background(Color::from_hex("C7DCD0").unwrap());
 
         // Add mouse position logging
@@ -1027,31 +2290,119 @@ fn main() {
         //     }
         // }
 
-        {
-            camera.offset = Vector2::new(
-                (d.get_screen_width() as f32 / 2.0) - SCREEN_WIDTH as f32 / 2.,
-                (d.get_screen_height() as f32 / 2.0) - SCREEN_HEIGHT as f32 / 2.,
-            );
-            let mut d = d.begin_mode2D(camera);
+        if game_mode == GameMode::ReplayViewer {
+            if let Some(viewer) = &replay_viewer {
+                viewer.draw_world(&mut d, &viewport, &level_texture, &ops, &players);
+            }
+        }
+        if game_mode == GameMode::Tutorial {
+            if let Some(tutorial) = &tutorial {
+                tutorial.draw_demo(&mut d, &viewport, &level_texture, &ops, &players[1]);
+            }
+        }
 
-            match game_mode {
-                GameMode::Game => {
-                    d.draw_texture(&level_texture, 0, 0, Color::WHITE);
-                    if (game_type == Box::new(MiniGames::ColorTheMap)) {
-                        d.draw_texture(&map_texture, 0, 0, Color::WHITE);
+        if game_mode == GameMode::Game || game_mode == GameMode::Intro {
+            let mut d = d.begin_mode2D(director.camera(&arena_camera, &viewport));
+            if settings.quality.draws_background() {
+                d.draw_texture(&level_texture, 0, 0, Color::WHITE);
+            }
+            if (game_type == Box::new(MiniGames::ColorTheMap)) {
+                let scale = SCREEN_WIDTH as f32 / map_texture.width as f32;
+                d.draw_texture_ex(&map_texture, Vector2::zero(), 0.0, scale, Color::WHITE);
+            }
+            draw_env_items(&mut d, &ops, Layer::Background);
+            draw_env_items(&mut d, &ops, Layer::Platform);
+            if round.done && orchestrator.configs.get(*game_type).trails {
+                trails.draw(&mut d, &players[0..players_count]);
+            }
+            let time = d.get_time() as f32;
+            for player in players[0..players_count].iter() {
+                player.draw(&mut d);
+                streaks.draw(&mut d, player, time);
+            }
+            if game_type.uses_throwing() && !round.done {
+                for player in players[0..players_count].iter() {
+                    if player.input.primary && !player.dead && player.throw_cooldown <= 0.0 {
+                        player.draw_aim(&mut d, &ops);
                     }
-                    for player in players[0..players_count].iter() {
-                        player.draw(&mut d);
+                }
+            }
+
+            // draw bullets
+            for bullet in bullets.iter() {
+                d.draw_rectangle_rec(bullet.rect, bullet.color);
+            }
+
+            pickups.draw(&mut d, &players[0..players_count], time);
+            setpieces.draw(
+                &mut d,
+                *game_type,
+                round.elapsed,
+                orchestrator.configs.get(*game_type).bounds,
+                &ops,
+                time,
+            );
+            draw_env_items(&mut d, &ops, Layer::Foreground);
+            afk.draw(&mut d, &players[0..players_count]);
+            feedback.draw(&mut d, &players[0..players_count]);
+            let anchors: Vec<Vector2> = players[0..players_count]
+                .iter()
+                .map(|p| p.position)
+                .collect();
+            quick_chat.draw(&mut d, &players[0..players_count], &anchors);
+            if let Some(session) = &session {
+                for seat in session.client.seated() {
+                    let Some(player) = players[0..players_count].get(seat) else {
+                        continue;
+                    };
+                    if player.dead {
+                        continue;
                     }
+                    let name = session.client.name(seat);
+                    let x = player.position.x as i32 - d.measure_text(&name, 14) / 2;
+                    let y = (player.position.y - player.height / 2.0) as i32 - 30;
+                    d.draw_text(&name, x, y, 14, player.color);
+                }
+            }
+            if director.shows_debug() {
+                debug.draw(&mut d, &players[0..players_count], &bullets, &ops);
+            }
+            if debug.enabled && director.shows_debug() {
+                net_meter.draw(&mut d, players_count - 1);
+                net_hud::draw(&mut d, &net_meter.stats(), &players[0..players_count]);
+            }
+        }
+
+        {
+            let mut d = d.begin_mode2D(viewport.camera());
 
-                    // draw bullets
-                    for bullet in bullets.iter() {
-                        d.draw_rectangle_rec(bullet.rect, bullet.color);
+            match game_mode {
+                GameMode::Intro => {
+                    if let Some(intro) = &intro {
+                        let names: Vec<String> = players[0..players_count]
+                            .iter()
+                            .map(|p| profiles.name(p.profile))
+                            .collect();
+                        intro.draw(&mut d, &players[0..players_count], &names);
                     }
+                    // the transition opens over the intro
+                    let screen_center = SCREEN_WIDTH as f32 / 2.0;
+                    let effective_progress = (trantition_progress * 2.0).min(1.0);
+
+                    let left_x =
+                        -trantition_left_image.width as f32 + (effective_progress * screen_center);
+                    let right_x = SCREEN_WIDTH as f32 - (effective_progress * screen_center);
+
+                    d.draw_texture(&trantition_left_texture, left_x as i32, 0, Color::WHITE);
 
-                    // for op in ops.iter() {
-                    //     d.draw_rectangle_rec(op.rect, op.color);
-                    // }
+                    d.draw_texture(&trantition_right_texture, right_x as i32, 0, Color::WHITE);
+                }
+                GameMode::Game => {
+                    if director.shows_debug() {
+                        time_control.draw(&mut d);
+                    }
+                    feed.draw(&mut d);
+                    hotseat.draw(&mut d, &profiles);
 
                     // Keep drawing transition during game mode
                     let screen_center = SCREEN_WIDTH as f32 / 2.0;
@@ -1065,12 +2416,64 @@ fn main() {
 
                     d.draw_texture(&trantition_right_texture, right_x as i32, 0, Color::WHITE);
                     d.draw_text(
-                        &(level_timer as i32).to_string(),
+                        &(round.time_left as i32).to_string(),
                         SCREEN_WIDTH / 2,
                         20,
                         35,
                         Color::BLACK,
                     );
+                    if round.overtime && !round.done {
+                        let text = "OVERTIME - paint flood!";
+                        d.draw_text(
+                            text,
+                            SCREEN_WIDTH / 2 - d.measure_text(text, 25) / 2,
+                            60,
+                            25,
+                            Color::RED,
+                        );
+                    }
+                    if !round.done {
+                        setpieces.draw_warning(&mut d, *game_type, round.elapsed);
+                    }
+                    // online, until the host hands out a seat and while it
+                    // waits for a second player
+                    if let Some(session) = &session {
+                        let stats: Vec<ConnectionStats> =
+                            session.client.stats().into_iter().collect();
+                        net_hud::draw(&mut d, &stats, &players[0..players_count]);
+                        let seated = session.client.seated();
+                        let text = match session.client.seat {
+                            None if session.full => format!("{} has no seat left", session.host),
+                            None => format!("joining {}...", session.host),
+                            Some(_) if seated.len() < 2 => "waiting for another player".to_string(),
+                            Some(_) => String::new(),
+                        };
+                        if !text.is_empty() {
+                            d.draw_text(
+                                &text,
+                                SCREEN_WIDTH / 2 - d.measure_text(&text, 25) / 2,
+                                SCREEN_HEIGHT / 2 - 100,
+                                25,
+                                Color::RED,
+                            );
+                            for (i, seat) in seated.iter().enumerate() {
+                                let you = if session.client.seat == Some(*seat) {
+                                    " (you)"
+                                } else {
+                                    ""
+                                };
+                                let name = format!("{}{}", session.client.name(*seat), you);
+                                d.draw_text(
+                                    &name,
+                                    SCREEN_WIDTH / 2 - d.measure_text(&name, 20) / 2,
+                                    SCREEN_HEIGHT / 2 - 65 + 24 * i as i32,
+                                    20,
+                                    Color::from_hex(PLAYER_COLORS[*seat % PLAYER_COLORS.len()])
+                                        .unwrap(),
+                                );
+                            }
+                        }
+                    }
                     if let Some(msg) = &head_msg {
                         d.draw_text(
                             &msg,
@@ -1082,19 +2485,24 @@ fn main() {
                         // display the persents orders from highest to lowest with the coller of it
                         //
                         if (*game_type == MiniGames::ColorTheMap) {
-                            let mut orderd = persents.clone();
+                            let mut orderd = round.coverage.clone();
                             orderd.sort_by(|a, b| b.partial_cmp(a).unwrap());
                             for (i, order) in orderd.iter().enumerate() {
-                                let og_index: Option<usize> = persents
+                                let og_index: Option<usize> = round
+                                    .coverage
                                     .iter()
                                     .position(|x| *x != 0. && x == order)
                                     .or_else(|| None);
                                 if let Some(index) = og_index {
                                     d.draw_text(
-                                        &format!("{}: {:.1}%", i + 1, order * 100.0),
+                                        &format!("{}: {:.1}% of the map", i + 1, order * 100.0),
                                         SCREEN_WIDTH / 2
                                             - d.measure_text(
-                                                &format!("{}: {:.1}%", i + 1, order * 100.0),
+                                                &format!(
+                                                    "{}: {:.1}% of the map",
+                                                    i + 1,
+                                                    order * 100.0
+                                                ),
                                                 20,
                                             ) / 2,
                                         SCREEN_HEIGHT / 2 + 50 + i as i32 * 20,
@@ -1106,6 +2514,74 @@ fn main() {
                             }
                         }
                     }
+                    if let Some(script) = active_script {
+                        scripted_games[script].draw(&mut d, &players[0..players_count]);
+                    }
+                    if director.enabled {
+                        let names: Vec<String> = players[0..players_count]
+                            .iter()
+                            .map(|p| match p.profile {
+                                Some(_) => profiles.name(p.profile),
+                                None => format!("P{}", p.number + 1),
+                            })
+                            .collect();
+                        director.draw(
+                            &mut d,
+                            *game_type,
+                            round.time_left,
+                            &players[0..players_count],
+                            &names,
+                        );
+                    }
+                    if show_stats {
+                        let names: Vec<String> = players[0..players_count]
+                            .iter()
+                            .map(|p| profiles.name(p.profile))
+                            .collect();
+                        stats_hud.draw(
+                            &mut d,
+                            *game_type,
+                            &players[0..players_count],
+                            &names,
+                            &streaks,
+                        );
+                    }
+                    if let Some(reason) = pause_reason {
+                        d.draw_rectangle(
+                            0,
+                            0,
+                            SCREEN_WIDTH,
+                            SCREEN_HEIGHT,
+                            Color::BLACK.alpha(0.5),
+                        );
+                        d.draw_text(
+                            reason,
+                            SCREEN_WIDTH / 2 - d.measure_text(reason, 35) / 2,
+                            SCREEN_HEIGHT / 2 - 100,
+                            35,
+                            Color::WHITE,
+                        );
+                        let bounds = Rectangle::new(
+                            ((SCREEN_WIDTH / 2) - 60) as f32,
+                            ((SCREEN_HEIGHT / 2) - 25) as f32,
+                            120.0,
+                            50.0,
+                        );
+                        if d.gui_button(bounds, Some(rstr!("Resume"))) {
+                            pause_reason = None;
+                        }
+                        // the match is suspended on the way out, see below the loop
+                        let bounds = Rectangle::new(
+                            ((SCREEN_WIDTH / 2) - 60) as f32,
+                            ((SCREEN_HEIGHT / 2) + 40) as f32,
+                            120.0,
+                            50.0,
+                        );
+                        if d.gui_button(bounds, Some(rstr!("Save & quit"))) {
+                            quit = true;
+                        }
+                    }
+                    pad_watch.draw(&mut d, &players[0..players_count]);
                 }
                 GameMode::WinScreen => {
                     let bounds = Rectangle::new(
@@ -1124,8 +2600,124 @@ fn main() {
                         30,
                         Color::BLACK,
                     );
+                    let time = d.get_time() as f32;
+                    high_score_player.pose.draw(
+                        &mut d,
+                        &high_score_player.texture,
+                        Vector2::new(
+                            SCREEN_WIDTH as f32 / 2.0,
+                            SCREEN_HEIGHT as f32 / 2.0 - 130.0,
+                        ),
+                        time,
+                    );
+                    // the players stand along the bottom for their quick chat
+                    let anchors: Vec<Vector2> = (0..players_count)
+                        .map(|i| {
+                            Vector2::new(
+                                SCREEN_WIDTH as f32 * (i + 1) as f32 / (players_count + 1) as f32,
+                                SCREEN_HEIGHT as f32 - 60.0,
+                            )
+                        })
+                        .collect();
+                    for (player, anchor) in players[0..players_count].iter().zip(&anchors) {
+                        let label = format!("P{}", player.number + 1);
+                        d.draw_text(
+                            &label,
+                            anchor.x as i32 - d.measure_text(&label, 20) / 2,
+                            anchor.y as i32 - 10,
+                            20,
+                            player.color,
+                        );
+                    }
+                    quick_chat.draw(&mut d, &players[0..players_count], &anchors);
+                    for (i, unlock) in new_unlocks.iter().enumerate() {
+                        d.draw_text(
+                            unlock,
+                            SCREEN_WIDTH / 2 - d.measure_text(unlock, 20) / 2,
+                            SCREEN_HEIGHT / 2 + 110 + i as i32 * 24,
+                            20,
+                            Color::DARKGREEN,
+                        );
+                    }
                     if play_button {
+                        // a full new match with the same players, mutators and
+                        // points to win, in a fresh minigame order
                         game_mode = GameMode::Game;
+                        if let Some(daily) = &orchestrator.daily {
+                            rng = Rng::new(daily.seed);
+                        }
+                        game_type = Box::new(orchestrator.rematch(*game_type, &mut rng));
+                        round.time_left =
+                            orchestrator.round_time(*game_type, &settings, &active_mutators);
+                        bullets.clear();
+                        map_image = blank_paint_map(settings.quality);
+                        recorder.clear_paint();
+                        for player in &mut players {
+                            player.points = 0;
+                        }
+                        spawn::respawn_all(
+                            &mut players,
+                            &bullets,
+                            &ops,
+                            orchestrator.configs.get(*game_type).bounds,
+                        );
+                        reset_match!();
+                    }
+
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) - 50) as f32,
+                        ((SCREEN_HEIGHT / 2) + 40) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if replay_saved {
+                        d.draw_text(
+                            "Replay saved",
+                            bounds.x as i32,
+                            bounds.y as i32 + 15,
+                            20,
+                            Color::DARKGRAY,
+                        );
+                    } else if d.gui_button(bounds, Some(rstr!("Save replay"))) {
+                        let names = players[0..players_count]
+                            .iter()
+                            .map(|p| match p.profile {
+                                Some(_) => profiles.name(p.profile),
+                                None => format!("P{}", p.number + 1),
+                            })
+                            .collect();
+                        recorder
+                            .finish(
+                                history::now(),
+                                options.map.clone(),
+                                names,
+                                &players[0..players_count],
+                            )
+                            .save();
+                        replay_saved = true;
+                    }
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) + 60) as f32,
+                        ((SCREEN_HEIGHT / 2) + 40) as f32,
+                        160.0,
+                        50.0,
+                    );
+                    match &card_path {
+                        Some(path) => {
+                            let text = format!("Saved {}", path.display());
+                            d.draw_text(
+                                &text,
+                                bounds.x as i32,
+                                bounds.y as i32 + 15,
+                                20,
+                                Color::DARKGRAY,
+                            );
+                        }
+                        None => {
+                            if d.gui_button(bounds, Some(rstr!("Save result card"))) {
+                                save_card = true;
+                            }
+                        }
                     }
                 }
                 GameMode::MainMenu => {
@@ -1164,6 +2756,303 @@ fn main() {
                     if decrement_button {
                         players_count = (players_count - 1).max(2);
                     }
+
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) - 50) as f32,
+                        ((SCREEN_HEIGHT / 2) - 90) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Profiles"))) && !transitioning {
+                        new_profile_name.clear();
+                        game_mode = GameMode::Profiles;
+                    }
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) + 100) as f32,
+                        ((SCREEN_HEIGHT / 2) - 90) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("History"))) && !transitioning {
+                        game_mode = GameMode::History;
+                    }
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) - 200) as f32,
+                        ((SCREEN_HEIGHT / 2) - 90) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Replays"))) && !transitioning {
+                        replay_files = replay::list();
+                        game_mode = GameMode::Replays;
+                    }
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) - 350) as f32,
+                        ((SCREEN_HEIGHT / 2) - 90) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Tutorial"))) && !transitioning {
+                        // the first one seat 1 hasn't finished yet
+                        let game = MiniGames::ALL
+                            .into_iter()
+                            .find(|game| !profiles.tutorial_done(&players[0], *game))
+                            .unwrap_or(MiniGames::ColorTheMap);
+                        tutorial_to_open = Some(game);
+                    }
+                    let bounds = Rectangle::new(
+                        ((SCREEN_WIDTH / 2) + 250) as f32,
+                        ((SCREEN_HEIGHT / 2) - 90) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Daily"))) && !transitioning {
+                        if suspended.take().is_some() {
+                            SuspendedMatch::discard();
+                        }
+                        let challenge = DailyChallenge::today();
+                        rng = Rng::new(challenge.seed);
+                        active_mutators = challenge.mutators.clone();
+                        game_type = Box::new(challenge.game_for_round(0));
+                        for player in &mut players {
+                            player.points = 0;
+                        }
+                        current_rounds.clear();
+                        orchestrator.daily = Some(challenge);
+                        round.time_left =
+                            orchestrator.round_time(*game_type, &settings, &active_mutators);
+                        intro_pending = true;
+                        transitioning = true;
+                        reversing = false;
+                    }
+                    let mut info = format!(
+                        "Daily {}: {}",
+                        today.date(),
+                        mutators::describe(&today.mutators)
+                    );
+                    let played = daily_log.played_on(today.day);
+                    if let Some(best) = daily_log.best_on(today.day) {
+                        info += &format!(
+                            " - played {}x, best {} ({} pts)",
+                            played, best.name, best.points
+                        );
+                    }
+                    d.draw_text(
+                        &info,
+                        SCREEN_WIDTH / 2 - d.measure_text(&info, 20) / 2,
+                        SCREEN_HEIGHT - 40,
+                        20,
+                        Color::DARKGRAY,
+                    );
+
+                    if d.gui_button(
+                        Rectangle::new(20.0, 20.0, 100.0, 40.0),
+                        Some(rstr!("Settings")),
+                    ) && !transitioning
+                    {
+                        game_mode = GameMode::Settings;
+                    }
+                    // content packs' scripted minigames take over every round
+                    if !scripted_games.is_empty() {
+                        let label = CString::new(format!(
+                            "Minigame: {}",
+                            active_script.map_or("Rotation", |i| scripted_games[i].name.as_str())
+                        ))
+                        .unwrap();
+                        if d.gui_button(
+                            Rectangle::new(130.0, 20.0, 260.0, 40.0),
+                            Some(label.as_c_str()),
+                        ) && !transitioning
+                        {
+                            // Rotation -> each script -> Rotation
+                            active_script = match active_script {
+                                None => Some(0),
+                                Some(i) => Some(i + 1).filter(|i| *i < scripted_games.len()),
+                            };
+                            orchestrator.locked_mode = active_script
+                                .map_or(options.mode, |i| Some(scripted_games[i].base));
+                            *game_type = orchestrator.locked_mode.unwrap_or(*game_type);
+                        }
+                    }
+
+                    // how the next minigame is picked between rounds
+                    let label =
+                        CString::new(format!("Rotation: {}", orchestrator.policy.name())).unwrap();
+                    if d.gui_button(
+                        Rectangle::new(20.0, 80.0, 200.0, 40.0),
+                        Some(label.as_c_str()),
+                    ) && !transitioning
+                    {
+                        orchestrator.policy = orchestrator.policy.next();
+                    }
+                    let label = if streaks.enabled {
+                        rstr!("Streaks: On")
+                    } else {
+                        rstr!("Streaks: Off")
+                    };
+                    if d.gui_button(Rectangle::new(230.0, 80.0, 130.0, 40.0), Some(label))
+                        && !transitioning
+                    {
+                        streaks.enabled = !streaks.enabled;
+                    }
+                    let label = if afk.bots {
+                        rstr!("AFK bots: On")
+                    } else {
+                        rstr!("AFK bots: Off")
+                    };
+                    if d.gui_button(Rectangle::new(370.0, 80.0, 150.0, 40.0), Some(label))
+                        && !transitioning
+                    {
+                        afk.bots = !afk.bots;
+                    }
+                    // more profiles than seats take turns, see hotseat.rs
+                    let label = if hotseat.enabled {
+                        rstr!("Hot-seat: On")
+                    } else {
+                        rstr!("Hot-seat: Off")
+                    };
+                    if d.gui_button(Rectangle::new(530.0, 80.0, 150.0, 40.0), Some(label))
+                        && !transitioning
+                    {
+                        hotseat.enabled = !hotseat.enabled;
+                    }
+                    if active_script.is_some() {
+                        d.draw_text("Scripted minigame", 20, 125, 15, Color::DARKGRAY);
+                    } else if orchestrator.locked_mode.is_some() {
+                        d.draw_text("Locked by --mode", 20, 125, 15, Color::DARKGRAY);
+                    } else if orchestrator.policy == RotationPolicy::Playlist {
+                        for (i, game) in MiniGames::ALL.into_iter().enumerate() {
+                            let label = CString::new(format!("+ {}", game.name())).unwrap();
+                            let bounds = Rectangle::new(20.0, 130.0 + i as f32 * 35.0, 160.0, 30.0);
+                            if d.gui_button(bounds, Some(label.as_c_str())) {
+                                orchestrator.playlist.push(game);
+                            }
+                        }
+                        let bounds = Rectangle::new(190.0, 130.0, 60.0, 30.0);
+                        if d.gui_button(bounds, Some(rstr!("Clear"))) {
+                            orchestrator.playlist.clear();
+                        }
+                        if orchestrator.playlist.is_empty() {
+                            d.draw_text("Empty, alternating", 20, 240, 15, Color::DARKGRAY);
+                        }
+                        for (i, game) in orchestrator.playlist.iter().enumerate().take(8) {
+                            d.draw_text(
+                                &format!("{}. {}", i + 1, game.name()),
+                                20,
+                                240 + i as i32 * 20,
+                                15,
+                                Color::BLACK,
+                            );
+                        }
+                        if orchestrator.playlist.len() > 8 {
+                            let more = format!("+{} more", orchestrator.playlist.len() - 8);
+                            d.draw_text(&more, 20, 400, 15, Color::DARKGRAY);
+                        }
+                    }
+
+                    // lobby slots: pick who is playing in each seat
+                    let taken: Vec<Option<usize>> = players[0..players_count]
+                        .iter()
+                        .map(|p| p.profile)
+                        .collect();
+                    for i in 0..players_count {
+                        let y = (SCREEN_HEIGHT / 2) + 90 + i as i32 * 35;
+                        let prev =
+                            Rectangle::new(((SCREEN_WIDTH / 2) - 150) as f32, y as f32, 30.0, 30.0);
+                        let next =
+                            Rectangle::new(((SCREEN_WIDTH / 2) + 120) as f32, y as f32, 30.0, 30.0);
+                        if d.gui_button(prev, Some(rstr!("<"))) {
+                            players[i].profile = profiles.cycle(players[i].profile, &taken, false);
+                            let profile = players[i].profile.map(|p| &profiles.profiles[p]);
+                            skin_textures.apply(&mut players[i], profile);
+                        }
+                        if d.gui_button(next, Some(rstr!(">"))) {
+                            players[i].profile = profiles.cycle(players[i].profile, &taken, true);
+                            let profile = players[i].profile.map(|p| &profiles.profiles[p]);
+                            skin_textures.apply(&mut players[i], profile);
+                        }
+                        if orchestrator.policy == RotationPolicy::Vote
+                            && orchestrator.locked_mode.is_none()
+                        {
+                            let vote = &mut orchestrator.votes[i];
+                            let label = CString::new(format!(
+                                "Vote: {}",
+                                vote.map_or("Any", |game| game.name())
+                            ))
+                            .unwrap();
+                            let bounds = Rectangle::new(
+                                ((SCREEN_WIDTH / 2) - 320) as f32,
+                                y as f32,
+                                160.0,
+                                30.0,
+                            );
+                            if d.gui_button(bounds, Some(label.as_c_str())) {
+                                // Any -> each minigame -> Any
+                                *vote = match *vote {
+                                    None => Some(MiniGames::ALL[0]),
+                                    Some(game) => MiniGames::ALL.get(game.index() + 1).copied(),
+                                };
+                            }
+                        }
+                        // cosmetics are saved per profile, so guests keep the defaults
+                        if let Some(index) = players[i].profile {
+                            let profile = &mut profiles.profiles[index];
+                            let mut changed = false;
+                            let skin =
+                                CString::new(format!("Skin: {}", profile.skin.name())).unwrap();
+                            let bounds = Rectangle::new(
+                                ((SCREEN_WIDTH / 2) + 160) as f32,
+                                y as f32,
+                                110.0,
+                                30.0,
+                            );
+                            if d.gui_button(bounds, Some(skin.as_c_str())) {
+                                profile.skin = profile.skin.cycle(profile, true);
+                                changed = true;
+                            }
+                            let splat =
+                                CString::new(format!("Splat: {}", profile.splat.name())).unwrap();
+                            let bounds = Rectangle::new(
+                                ((SCREEN_WIDTH / 2) + 275) as f32,
+                                y as f32,
+                                110.0,
+                                30.0,
+                            );
+                            if d.gui_button(bounds, Some(splat.as_c_str())) {
+                                profile.splat = profile.splat.cycle(profile, true);
+                                changed = true;
+                            }
+                            let pose =
+                                CString::new(format!("Pose: {}", profile.pose.name())).unwrap();
+                            let bounds = Rectangle::new(
+                                ((SCREEN_WIDTH / 2) + 390) as f32,
+                                y as f32,
+                                110.0,
+                                30.0,
+                            );
+                            if d.gui_button(bounds, Some(pose.as_c_str())) {
+                                profile.pose = profile.pose.cycle(profile, true);
+                                changed = true;
+                            }
+                            if changed {
+                                skin_textures.apply(&mut players[i], Some(profile));
+                                profiles.save();
+                            }
+                        }
+                        let label = format!(
+                            "P{}: {} ({})",
+                            i + 1,
+                            profiles.name(players[i].profile),
+                            lobby::describe(players[i].controls)
+                        );
+                        d.draw_text(
+                            &label,
+                            SCREEN_WIDTH / 2 - d.measure_text(&label, 20) / 2,
+                            y + 5,
+                            20,
+                            players[i].color,
+                        );
+                    }
                     // Draw transition textures
                     if transitioning {
                         let screen_center = SCREEN_WIDTH as f32 / 2.0;
@@ -1179,17 +3068,528 @@ fn main() {
                     }
 
                     if play_button && !transitioning {
+                        // a fresh match replaces whatever was left suspended
+                        if suspended.take().is_some() {
+                            SuspendedMatch::discard();
+                        }
+                        orchestrator.daily = None;
+                        active_mutators.clear();
+                        game_type = Box::new(orchestrator.first_game(*game_type, &mut rng));
+                        round.time_left =
+                            orchestrator.round_time(*game_type, &settings, &active_mutators);
+                        intro_pending = true;
                         transitioning = true;
                         reversing = false;
                     }
+                    if let Some(saved) = &suspended {
+                        let label = CString::new(format!(
+                            "Continue last match (round {})",
+                            saved.round_number()
+                        ))
+                        .unwrap();
+                        let bounds = Rectangle::new(
+                            ((SCREEN_WIDTH / 2) - 125) as f32,
+                            ((SCREEN_HEIGHT / 2) - 155) as f32,
+                            250.0,
+                            50.0,
+                        );
+                        if d.gui_button(bounds, Some(label.as_c_str())) && !transitioning {
+                            players_count = saved.players.len();
+                            saved.restore_players(&mut players, &profiles, &skin_textures);
+                            game_type = Box::new(saved.game);
+                            round.time_left = saved.level_timer;
+                            orchestrator.daily = saved.daily_day.map(DailyChallenge::for_day);
+                            active_mutators = saved.mutators.clone();
+                            rng = Rng::new(
+                                orchestrator
+                                    .daily
+                                    .as_ref()
+                                    .map_or(history::now(), |d| d.seed),
+                            );
+                            current_rounds = saved.rounds.clone();
+                            if let Some(mut paint) = SuspendedMatch::load_paint() {
+                                // saved at whatever quality was set back then
+                                paint.resize(map_image.width, map_image.height);
+                                map_image = paint;
+                            }
+                            bullets.clear();
+                            head_msg = None;
+                            round.done = false;
+                            suspended = None;
+                            SuspendedMatch::discard();
+                            intro_pending = false;
+                            transitioning = true;
+                            reversing = false;
+                        }
+                    }
+                }
+                GameMode::Replays => {
+                    let title = "Replays";
+                    d.draw_text(
+                        title,
+                        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+                        30,
+                        35,
+                        Color::BLACK,
+                    );
+                    if replay_files.is_empty() {
+                        d.draw_text(
+                            "No saved replays yet, save one from the win screen",
+                            60,
+                            100,
+                            20,
+                            Color::DARKGRAY,
+                        );
+                    }
+                    for (i, file_name) in replay_files.iter().take(12).enumerate() {
+                        let bounds = Rectangle::new(60.0, 90.0 + i as f32 * 40.0, 400.0, 35.0);
+                        let label = CString::new(file_name.trim_end_matches(".json")).unwrap();
+                        if d.gui_button(bounds, Some(label.as_c_str())) {
+                            replay_to_load = Some(file_name.clone());
+                        }
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        game_mode = GameMode::MainMenu;
+                    }
+                }
+                GameMode::Tutorial => {
+                    if let Some(current) = &tutorial {
+                        if current.game == MiniGames::ColorTheMap {
+                            let scale = SCREEN_WIDTH as f32 / map_texture.width as f32;
+                            d.draw_texture_ex(
+                                &map_texture,
+                                Vector2::zero(),
+                                0.0,
+                                scale,
+                                Color::WHITE,
+                            );
+                        }
+                        current.draw(&mut d, &players[0]);
+                        let next =
+                            MiniGames::ALL[(current.game.index() + 1) % MiniGames::ALL.len()];
+                        let label = CString::new(format!("Next: {}", next.name())).unwrap();
+                        let bounds = Rectangle::new(
+                            (SCREEN_WIDTH - 400) as f32,
+                            (SCREEN_HEIGHT - 80) as f32,
+                            220.0,
+                            50.0,
+                        );
+                        if d.gui_button(bounds, Some(label.as_c_str())) {
+                            tutorial_to_open = Some(next);
+                        }
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        tutorial = None;
+                        map_image = blank_paint_map(settings.quality);
+                        recorder.clear_paint();
+                        players[0].respawn(Vector2::new(100.0, 100.0));
+                        game_mode = GameMode::MainMenu;
+                    }
+                }
+                GameMode::ReplayViewer => {
+                    if let Some(viewer) = &replay_viewer {
+                        viewer.draw_ui(&mut d);
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        replay_viewer = None;
+                        game_mode = GameMode::Replays;
+                    }
+                }
+                GameMode::History => {
+                    let title = "History";
+                    d.draw_text(
+                        title,
+                        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+                        30,
+                        35,
+                        Color::BLACK,
+                    );
+                    d.draw_text("Recent matches", 60, 90, 20, Color::DARKGRAY);
+                    for (i, record) in history.recent(12).enumerate() {
+                        let y = 120 + i as i32 * 38;
+                        let scores: Vec<String> = record
+                            .players
+                            .iter()
+                            .map(|p| format!("{} {}", p.name, p.points))
+                            .collect();
+                        let winner = record
+                            .winner()
+                            .map(|w| record.players[w].name.clone())
+                            .unwrap_or_else(|| "draw".to_string());
+                        d.draw_text(
+                            &format!(
+                                "{}  {}  ({} won)",
+                                history::format_date(record.timestamp),
+                                scores.join(" / "),
+                                winner
+                            ),
+                            60,
+                            y,
+                            20,
+                            Color::BLACK,
+                        );
+                        let rounds: Vec<String> = record
+                            .rounds
+                            .iter()
+                            .map(|r| {
+                                let winners: Vec<String> =
+                                    r.winners.iter().map(|w| format!("P{}", w + 1)).collect();
+                                if winners.is_empty() {
+                                    "-".to_string()
+                                } else {
+                                    winners.join("+")
+                                }
+                            })
+                            .collect();
+                        d.draw_text(
+                            &format!("rounds: {}", rounds.join(", ")),
+                            80,
+                            y + 18,
+                            15,
+                            Color::DARKGRAY,
+                        );
+                    }
+
+                    d.draw_text("Head to head", 820, 90, 20, Color::DARKGRAY);
+                    for (i, h) in history.head_to_head().iter().take(15).enumerate() {
+                        d.draw_text(
+                            &format!("{} {} - {} {}", h.a, h.a_wins, h.b_wins, h.b),
+                            820,
+                            120 + i as i32 * 25,
+                            20,
+                            Color::BLACK,
+                        );
+                    }
+
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        export_status = None;
+                        game_mode = GameMode::MainMenu;
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 280) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Export"))) {
+                        export_status = Some(
+                            match export::export_stats(&history, Path::new(export::EXPORT_DIR)) {
+                                Ok(()) => format!("Exported to {}/", export::EXPORT_DIR),
+                                Err(err) => format!("Export failed: {}", err),
+                            },
+                        );
+                    }
+                    if let Some(status) = &export_status {
+                        d.draw_text(
+                            status,
+                            SCREEN_WIDTH - 600,
+                            SCREEN_HEIGHT - 65,
+                            20,
+                            Color::DARKGRAY,
+                        );
+                    }
+                }
+                GameMode::Mods => {
+                    mods::draw_screen(&mut d, &mut content_packs);
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        game_mode = GameMode::Settings;
+                    }
+                }
+                GameMode::Calibration => {
+                    settings.gamma = gamma::draw_calibration(&mut d, settings.gamma);
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 280) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Reset"))) {
+                        settings.gamma = 1.0;
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Done"))) {
+                        settings.calibrated = true;
+                        settings.save();
+                        game_mode = GameMode::MainMenu;
+                    }
+                }
+                GameMode::Settings => {
+                    let title = "Settings";
+                    d.draw_text(
+                        title,
+                        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+                        30,
+                        35,
+                        Color::BLACK,
+                    );
+                    let x = SCREEN_WIDTH / 2 - 250;
+                    d.draw_text("Quality", x, 110, 20, Color::BLACK);
+                    let label = CString::new(settings.quality.name().to_string()).unwrap();
+                    if d.gui_button(
+                        Rectangle::new((x + 250) as f32, 100.0, 150.0, 40.0),
+                        Some(label.as_c_str()),
+                    ) {
+                        settings.quality = settings.quality.next();
+                        settings.save();
+                        // the paint map is re-made at the new resolution
+                        map_image = blank_paint_map(settings.quality);
+                        recorder.clear_paint();
+                        map_texture = d.load_texture_from_image(&thread, &map_image).unwrap();
+                    }
+
+                    d.draw_text("Round length", x, 180, 20, Color::DARKGRAY);
+                    for (i, game) in MiniGames::ALL.into_iter().enumerate() {
+                        let y = 215 + i as i32 * 50;
+                        let default = orchestrator.configs.get(game).round_time;
+                        let current = settings.round_time(game).unwrap_or(default);
+                        d.draw_text(game.name(), x, y + 10, 20, Color::BLACK);
+                        let minus = Rectangle::new((x + 250) as f32, y as f32, 40.0, 40.0);
+                        if d.gui_button(minus, Some(rstr!("-"))) {
+                            settings.step_round_time(game, current, false);
+                            settings.save();
+                        }
+                        let value = match settings.round_time(game) {
+                            Some(_) => format!("{:.0}s", current),
+                            None => format!("{:.0}s (default)", current),
+                        };
+                        d.draw_text(&value, x + 305, y + 10, 20, Color::BLACK);
+                        let plus = Rectangle::new((x + 460) as f32, y as f32, 40.0, 40.0);
+                        if d.gui_button(plus, Some(rstr!("+"))) {
+                            settings.step_round_time(game, current, true);
+                            settings.save();
+                        }
+                    }
+                    d.draw_text(
+                        "The Daily always uses the default lengths",
+                        x,
+                        215 + MiniGames::ALL.len() as i32 * 50,
+                        15,
+                        Color::DARKGRAY,
+                    );
+                    let y = 250 + MiniGames::ALL.len() as i32 * 50;
+                    d.draw_text("Brightness", x, y + 10, 20, Color::BLACK);
+                    if d.gui_button(
+                        Rectangle::new((x + 250) as f32, y as f32, 150.0, 40.0),
+                        Some(rstr!("Calibrate")),
+                    ) {
+                        game_mode = GameMode::Calibration;
+                    }
+                    d.draw_text("Content packs", x, y + 60, 20, Color::BLACK);
+                    if d.gui_button(
+                        Rectangle::new((x + 250) as f32, (y + 50) as f32, 150.0, 40.0),
+                        Some(rstr!("Manage")),
+                    ) {
+                        game_mode = GameMode::Mods;
+                    }
+                    // beeps and jingles for low-vision players, see feedback.rs
+                    d.draw_text("Audio cues", x, y + 110, 20, Color::BLACK);
+                    for seat in 0..4 {
+                        let label = CString::new(format!(
+                            "P{}: {}",
+                            seat + 1,
+                            if settings.audio_cues[seat] {
+                                "On"
+                            } else {
+                                "Off"
+                            }
+                        ))
+                        .unwrap();
+                        let bounds = Rectangle::new(
+                            (x + 250 + seat as i32 * 90) as f32,
+                            (y + 100) as f32,
+                            80.0,
+                            40.0,
+                        );
+                        if d.gui_button(bounds, Some(label.as_c_str())) {
+                            settings.audio_cues[seat] = !settings.audio_cues[seat];
+                            settings.save();
+                            feedback.cues = settings.audio_cues;
+                        }
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 280) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Defaults"))) {
+                        settings.reset_round_times();
+                        settings.save();
+                    }
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        game_mode = GameMode::MainMenu;
+                    }
+                }
+                GameMode::Profiles => {
+                    let title = "Profiles";
+                    d.draw_text(
+                        title,
+                        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+                        30,
+                        35,
+                        Color::BLACK,
+                    );
+                    let columns = [60, 280, 400, 560, 700, 860, 1000, 1120];
+                    let headers = [
+                        "Name",
+                        "Matches",
+                        MiniGames::ColorTheMap.name(),
+                        MiniGames::Dodge.name(),
+                        MiniGames::FloorIsLava.name(),
+                        "Coverage",
+                        "Eliminated",
+                        "KOs",
+                    ];
+                    for (x, header) in columns.iter().zip(headers) {
+                        d.draw_text(header, *x, 90, 20, Color::DARKGRAY);
+                    }
+                    for (i, profile) in profiles.profiles.iter().enumerate() {
+                        let y = 120 + i as i32 * 45;
+                        let cells = [
+                            profile.name.clone(),
+                            profile.matches_played.to_string(),
+                            profile.rounds_won[MiniGames::ColorTheMap.index()].to_string(),
+                            profile.rounds_won[MiniGames::Dodge.index()].to_string(),
+                            profile.rounds_won[MiniGames::FloorIsLava.index()].to_string(),
+                            format!("{:.0}%", profile.total_coverage * 100.0),
+                            profile.times_eliminated.to_string(),
+                            profile.knockouts.to_string(),
+                        ];
+                        for (x, cell) in columns.iter().zip(cells) {
+                            d.draw_text(&cell, *x, y, 20, Color::BLACK);
+                        }
+                        if let Some(hint) = profile.next_unlock() {
+                            d.draw_text(&hint, columns[0], y + 21, 15, Color::DARKGRAY);
+                        }
+                    }
+
+                    d.draw_text(
+                        &format!("New profile: {}_", new_profile_name),
+                        60,
+                        SCREEN_HEIGHT - 110,
+                        20,
+                        Color::BLACK,
+                    );
+                    d.draw_text(
+                        "type a name and press Enter",
+                        60,
+                        SCREEN_HEIGHT - 85,
+                        15,
+                        Color::DARKGRAY,
+                    );
+                    let bounds = Rectangle::new(
+                        (SCREEN_WIDTH - 160) as f32,
+                        (SCREEN_HEIGHT - 80) as f32,
+                        100.0,
+                        50.0,
+                    );
+                    if d.gui_button(bounds, Some(rstr!("Back"))) {
+                        game_mode = GameMode::MainMenu;
+                    }
                 }
             }
+            console.draw(&mut d);
+        }
+        drop(frame);
+        post.set_gamma(settings.gamma);
+        post.present(&mut screen);
+        let d = screen;
+
+        // grab the finished frame before it's presented
+        if game_mode == GameMode::Game
+            && simulating
+            && settings.quality.captures_clips()
+            && clip.due(dt)
+        {
+            clip.push(d.load_image_from_screen(&thread));
+        }
+        if take_screenshot {
+            capture::save_screenshot(d.load_image_from_screen(&thread));
+        }
+        if let Some(run) = &mut golden {
+            if run.frame_drawn() {
+                run.finish_scene(d.load_image_from_screen(&thread));
+                quit = run.scene().is_none();
+            }
         }
     }
+
+    if let Some(run) = &golden {
+        std::process::exit(run.exit_code());
+    }
+
+    // quitting mid-match keeps it around for "Continue last match"
+    if game_mode == GameMode::Game && session.is_none() {
+        // a round that already finished resumes at the start of the next one
+        let (game, timer) = if round.done {
+            let next = orchestrator.next_game(*game_type, current_rounds.len(), &mut rng);
+            (
+                next,
+                orchestrator.round_time(next, &settings, &active_mutators),
+            )
+        } else {
+            (*game_type, round.time_left)
+        };
+        SuspendedMatch::capture(
+            game,
+            timer,
+            round.done,
+            &players[0..players_count],
+            &profiles,
+            &current_rounds,
+            &active_mutators,
+            orchestrator.daily.as_ref().map(|d| d.day),
+        )
+        .save(&map_image);
+    }
 }
 
+// Each player's coverage as a share of `area`, the map's paintable area in
+// virtual screen pixels
 fn calculate_winner(
     image: &mut Image,
+    area: f32,
     players_count: usize,
     player1_color: &Color,
     player2_color: &Color,
@@ -1229,14 +3629,16 @@ fn calculate_winner(
             }
         }
     }
+    // the paint map may be smaller than the screen; splats spilling past the
+    // estimated area must not take anyone over 100%
+    let scale = image.width as f32 / SCREEN_WIDTH as f32;
+    let total = (area * scale * scale)
+        .max((player1_count + player2_count + player3_count + player4_count) as f32)
+        .max(1.0);
     [
-        player1_count as f32
-            / (player1_count + player2_count + player3_count + player4_count) as f32,
-        player2_count as f32
-            / (player1_count + player2_count + player3_count + player4_count) as f32,
-        player3_count as f32
-            / (player1_count + player2_count + player3_count + player4_count) as f32,
-        player4_count as f32
-            / (player1_count + player2_count + player3_count + player4_count) as f32,
+        player1_count as f32 / total,
+        player2_count as f32 / total,
+        player3_count as f32 / total,
+        player4_count as f32 / total,
     ]
 }
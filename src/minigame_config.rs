@@ -0,0 +1,187 @@
+// Per-minigame tuning loaded from static/minigames.json, keyed by the `{:?}`
+// name of the minigame. Anything missing falls back to the built-in defaults,
+// and fields a minigame doesn't use are ignored. A content pack's
+// minigames.json replaces the settings of the minigames it names.
+use crate::{
+    assets,
+    json::Json,
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    MiniGames,
+};
+use raylib::prelude::*;
+use std::fs;
+
+pub const MINIGAMES_FILE: &str = "minigames.json";
+
+// A value that eases from `start` to `end` over the first `ramp` seconds of a
+// round; `exponent` > 1 keeps it gentle early and steep late.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub start: f32,
+    pub end: f32,
+    pub ramp: f32,
+    pub exponent: f32,
+}
+
+impl Curve {
+    pub const fn constant(value: f32) -> Curve {
+        Curve {
+            start: value,
+            end: value,
+            ramp: 0.0,
+            exponent: 1.0,
+        }
+    }
+
+    pub fn at(&self, elapsed: f32) -> f32 {
+        if self.ramp <= 0.0 {
+            return self.end;
+        }
+        let t = (elapsed / self.ramp).clamp(0.0, 1.0).powf(self.exponent);
+        self.start + (self.end - self.start) * t
+    }
+
+    fn from_json(json: Option<&Json>, default: Curve) -> Curve {
+        let Some(json) = json else {
+            return default;
+        };
+        if let Some(value) = json.as_f32() {
+            return Curve::constant(value);
+        }
+        Curve {
+            start: json.f32_or("start", default.start),
+            end: json.f32_or("end", default.end),
+            ramp: json.f32_or("ramp", default.ramp),
+            exponent: json.f32_or("exponent", default.exponent).max(0.01),
+        }
+    }
+}
+
+// How the arena camera moves during a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    // the whole arena, fitted to the screen
+    #[default]
+    Static,
+    // zooms and pans to keep every player still in the round in view
+    FollowAction,
+    // fitted to the arena width, scrolling up from the bottom
+    VerticalScroll,
+}
+
+impl CameraMode {
+    pub fn from_key(key: &str) -> Option<CameraMode> {
+        match key {
+            "static" => Some(CameraMode::Static),
+            "follow" => Some(CameraMode::FollowAction),
+            "scroll" => Some(CameraMode::VerticalScroll),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MiniGameConfig {
+    pub round_time: f32, // seconds
+    // playable area in world coordinates, players are kept inside it
+    pub bounds: Rectangle,
+    pub camera: CameraMode,
+    pub scroll_speed: f32, // VerticalScroll only, world pixels per second
+    // seconds between paint bomb pickups, 0 for none
+    pub paint_bomb_interval: f32,
+    // Dodge: seconds between waves and bullet speed, by time into the round
+    pub spawn_interval: Curve,
+    pub bullet_speed: Curve,
+    // draw everyone's route over the round's results, see trails.rs
+    pub trails: bool,
+}
+
+impl Default for MiniGameConfig {
+    fn default() -> Self {
+        MiniGameConfig {
+            round_time: 45.0,
+            bounds: Rectangle::new(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32),
+            camera: CameraMode::Static,
+            scroll_speed: 40.0,
+            paint_bomb_interval: 0.0,
+            spawn_interval: Curve::constant(5.0),
+            bullet_speed: Curve::constant(250.0),
+            trails: false,
+        }
+    }
+}
+
+impl MiniGameConfig {
+    fn from_json(json: &Json) -> MiniGameConfig {
+        let default = MiniGameConfig::default();
+        MiniGameConfig {
+            round_time: json.f32_or("round_time", default.round_time).max(1.0),
+            bounds: match json.array("bounds") {
+                [x, y, width, height] => Rectangle::new(
+                    x.as_f32().unwrap_or(default.bounds.x),
+                    y.as_f32().unwrap_or(default.bounds.y),
+                    width.as_f32().unwrap_or(default.bounds.width).max(1.0),
+                    height.as_f32().unwrap_or(default.bounds.height).max(1.0),
+                ),
+                _ => default.bounds,
+            },
+            camera: CameraMode::from_key(json.str_or("camera", "")).unwrap_or(default.camera),
+            scroll_speed: json.f32_or("scroll_speed", default.scroll_speed),
+            paint_bomb_interval: json
+                .f32_or("paint_bomb_interval", default.paint_bomb_interval)
+                .max(0.0),
+            spawn_interval: Curve::from_json(json.get("spawn_interval"), default.spawn_interval),
+            bullet_speed: Curve::from_json(json.get("bullet_speed"), default.bullet_speed),
+            trails: json
+                .get("trails")
+                .and_then(Json::as_bool)
+                .unwrap_or(default.trails),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MiniGameConfigs {
+    configs: Vec<MiniGameConfig>, // indexed by MiniGames::index
+}
+
+impl MiniGameConfigs {
+    // The built-in file, or its override in ./static/, under the packs'
+    pub fn load() -> MiniGameConfigs {
+        let json = assets::read_base_to_string(MINIGAMES_FILE).and_then(|text| {
+            Json::parse(&text)
+                .map_err(|err| format!("{} is not valid JSON: {}", MINIGAMES_FILE, err))
+        });
+        let json = json.unwrap_or_else(|err| {
+            eprintln!("using default minigame settings: {}", err);
+            Json::Null
+        });
+        let mut layers = vec![json];
+        for path in assets::pack_files(MINIGAMES_FILE) {
+            let pack = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| Json::parse(&text));
+            match pack {
+                Ok(pack) => layers.push(pack),
+                Err(err) => eprintln!("ignoring {}: {}", path.display(), err),
+            }
+        }
+        MiniGameConfigs {
+            configs: MiniGames::ALL
+                .iter()
+                .map(|game| {
+                    let key = format!("{:?}", game);
+                    layers.iter().rev().find_map(|layer| layer.get(&key))
+                })
+                .map(|config| match config {
+                    Some(config) => MiniGameConfig::from_json(config),
+                    None => MiniGameConfig::default(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, game: MiniGames) -> &MiniGameConfig {
+        &self.configs[game.index()]
+    }
+}
@@ -0,0 +1,411 @@
+// Content packs: folders under ./mods/ that add to or replace the game's
+// assets without a rebuild. A pack is laid out like static/:
+//
+//   mods/<pack>/pack.json        optional: name, author, version, description
+//   mods/<pack>/player1-4.png    skins for the four seats
+//   mods/<pack>/level.json/.png  replaces the default map
+//   mods/<pack>/maps/*.json      extra maps for --map, backgrounds next to them
+//   mods/<pack>/minigames.json   minigame settings, merged per minigame
+//   mods/<pack>/minigames/*.script  new minigames, see scripted.rs
+//   mods/<pack>/sounds/*         sound replacements, kept for when the game
+//                                has audio; nothing plays them yet
+//
+// Which packs are on and in what order is kept in save/mods.json. Packs
+// higher in the list win when two of them ship the same file (or the same
+// minigame's settings), and the Content packs screen lists who overrides
+// whom. Everything is read at startup, so changes there apply on restart.
+use crate::{
+    assets,
+    json::Json,
+    storage,
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+use raylib::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const MODS_DIR: &str = "mods";
+pub const MODS_FILE: &str = "mods.json";
+const PACK_FILE: &str = "pack.json";
+const ROW_HEIGHT: i32 = 60;
+// rows that fit above the conflict list, the rest are scrolled to
+const MAX_ROWS: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct Pack {
+    // the folder name, what save/mods.json refers to
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub version: String,
+    pub description: String,
+    pub enabled: bool,
+    // relative to the pack folder, '/'-separated
+    pub files: Vec<String>,
+    // the minigames whose settings the pack's minigames.json changes
+    minigame_keys: Vec<String>,
+}
+
+impl Pack {
+    fn load(dir: &Path) -> Option<Pack> {
+        let id = dir.file_name()?.to_string_lossy().to_string();
+        let mut files = Vec::new();
+        list_files(dir, "", &mut files);
+        files.retain(|file| file != PACK_FILE);
+        files.sort();
+        let info = match fs::read_to_string(dir.join(PACK_FILE)) {
+            Ok(text) => Json::parse(&text).unwrap_or_else(|err| {
+                eprintln!("ignoring broken {}/{}: {}", id, PACK_FILE, err);
+                Json::Null
+            }),
+            Err(_) => Json::Null,
+        };
+        Some(Pack {
+            name: info.str_or("name", &id).to_string(),
+            author: info.str_or("author", "").to_string(),
+            version: info.str_or("version", "").to_string(),
+            description: info.str_or("description", "").to_string(),
+            enabled: true,
+            minigame_keys: minigame_keys(dir),
+            files,
+            id,
+        })
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        Path::new(MODS_DIR).join(&self.id)
+    }
+
+    // "2 skins, 1 map, minigame settings" for the pack screen
+    pub fn summary(&self) -> String {
+        let count = |filter: &dyn Fn(&str) -> bool| self.files.iter().filter(|f| filter(f)).count();
+        let skins = count(&|f| f.starts_with("player") && f.ends_with(".png"));
+        let maps =
+            count(&|f| (f.starts_with("maps/") && f.ends_with(".json")) || f == "level.json");
+        let scripts = count(&|f| f.starts_with("minigames/") && f.ends_with(".script"));
+        let sounds = count(&|f| f.starts_with("sounds/"));
+        let mut parts = Vec::new();
+        let plural =
+            |n: usize, what: &str| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" });
+        if skins > 0 {
+            parts.push(plural(skins, "skin"));
+        }
+        if maps > 0 {
+            parts.push(plural(maps, "map"));
+        }
+        if self.files.iter().any(|f| f == "minigames.json") {
+            parts.push("minigame settings".to_string());
+        }
+        if scripts > 0 {
+            parts.push(plural(scripts, "scripted minigame"));
+        }
+        if sounds > 0 {
+            parts.push(format!("{} (unused)", plural(sounds, "sound")));
+        }
+        if parts.is_empty() {
+            "nothing the game loads".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+// The minigames whose settings a pack's minigames.json changes
+fn minigame_keys(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join("minigames.json"))
+        .ok()
+        .and_then(|text| Json::parse(&text).ok())
+        .map(|json| match json {
+            Json::Object(fields) => fields.into_iter().map(|(key, _)| key).collect(),
+            _ => Vec::new(),
+        })
+        .unwrap_or_default()
+}
+
+fn list_files(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.path().is_dir() {
+            list_files(&entry.path(), &format!("{}/", name), files);
+        } else {
+            files.push(name);
+        }
+    }
+}
+
+// Two or more enabled packs shipping the same thing
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    // a file, or "minigames.json: <minigame>"
+    pub what: String,
+    // pack names, the one that wins first
+    pub packs: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ContentPacks {
+    // highest priority first
+    pub packs: Vec<Pack>,
+    // the enabled packs when the game started, to tell when a restart is due
+    loaded: Vec<String>,
+    // worked out again only when a pack is toggled or raised
+    conflicts: Vec<Conflict>,
+    // the first pack the screen shows
+    first_row: usize,
+}
+
+impl ContentPacks {
+    pub fn load() -> ContentPacks {
+        let mut found: Vec<Pack> = fs::read_dir(MODS_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| Pack::load(&entry.path()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+
+        // saved order first, packs new since then after them
+        let saved = storage::read_json(MODS_FILE).unwrap_or(Json::Null);
+        let mut packs = Vec::new();
+        for id in saved.array("order").iter().filter_map(Json::as_str) {
+            if let Some(at) = found.iter().position(|pack| pack.id == id) {
+                packs.push(found.remove(at));
+            }
+        }
+        packs.extend(found);
+        for pack in &mut packs {
+            pack.enabled = !saved
+                .array("disabled")
+                .iter()
+                .any(|id| id.as_str() == Some(&pack.id));
+        }
+        let mut content = ContentPacks {
+            packs,
+            ..ContentPacks::default()
+        };
+        content.loaded = content.enabled_ids();
+        content.conflicts = content.find_conflicts();
+        content
+    }
+
+    pub fn save(&self) {
+        let order: Vec<&str> = self.packs.iter().map(|p| p.id.as_str()).collect();
+        let disabled: Vec<&str> = self
+            .packs
+            .iter()
+            .filter(|p| !p.enabled)
+            .map(|p| p.id.as_str())
+            .collect();
+        storage::write_json(
+            MODS_FILE,
+            &Json::object(vec![("order", order.into()), ("disabled", disabled.into())]),
+        );
+    }
+
+    fn enabled_ids(&self) -> Vec<String> {
+        self.packs
+            .iter()
+            .filter(|p| p.enabled)
+            .map(|p| p.id.clone())
+            .collect()
+    }
+
+    // Hands the enabled packs to the asset loader; once, before anything
+    // is loaded
+    pub fn install(&self) {
+        let dirs: Vec<PathBuf> = self
+            .packs
+            .iter()
+            .filter(|p| p.enabled)
+            .map(Pack::dir)
+            .collect();
+        if !dirs.is_empty() {
+            eprintln!("content packs: {}", self.enabled_ids().join(", "));
+        }
+        assets::use_packs(dirs);
+    }
+
+    // Whether the screen changed what a restart would load
+    pub fn restart_needed(&self) -> bool {
+        self.enabled_ids() != self.loaded
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(pack) = self.packs.get_mut(index) {
+            pack.enabled = !pack.enabled;
+            self.conflicts = self.find_conflicts();
+        }
+    }
+
+    // Moves a pack one place up the list, over the one above it
+    pub fn raise(&mut self, index: usize) {
+        if index > 0 && index < self.packs.len() {
+            self.packs.swap(index - 1, index);
+            self.conflicts = self.find_conflicts();
+        }
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    fn find_conflicts(&self) -> Vec<Conflict> {
+        let enabled: Vec<&Pack> = self.packs.iter().filter(|p| p.enabled).collect();
+        let mut claims: Vec<(String, Vec<String>)> = Vec::new();
+        let mut claim =
+            |what: String, pack: &Pack| match claims.iter_mut().find(|(w, _)| *w == what) {
+                Some((_, packs)) => packs.push(pack.name.clone()),
+                None => claims.push((what, vec![pack.name.clone()])),
+            };
+        for pack in &enabled {
+            for file in &pack.files {
+                // minigame settings merge, only the same minigame clashes
+                if file != "minigames.json" {
+                    claim(file.clone(), pack);
+                }
+            }
+            for key in &pack.minigame_keys {
+                claim(format!("minigames.json: {}", key), pack);
+            }
+        }
+        claims
+            .into_iter()
+            .filter(|(_, packs)| packs.len() > 1)
+            .map(|(what, packs)| Conflict { what, packs })
+            .collect()
+    }
+}
+
+// The Content packs screen: every pack with its on/off switch and a button
+// to move it up the priority list, then what overrides what
+pub fn draw_screen(d: &mut RaylibMode2D<'_, RaylibDrawHandle>, content: &mut ContentPacks) {
+    let title = "Content packs";
+    d.draw_text(
+        title,
+        SCREEN_WIDTH / 2 - d.measure_text(title, 35) / 2,
+        30,
+        35,
+        Color::BLACK,
+    );
+    if content.packs.is_empty() {
+        let hint = format!(
+            "No packs found. Put each one in its own folder under ./{}/, laid out like static/",
+            MODS_DIR
+        );
+        d.draw_text(
+            &hint,
+            SCREEN_WIDTH / 2 - d.measure_text(&hint, 20) / 2,
+            120,
+            20,
+            Color::DARKGRAY,
+        );
+        return;
+    }
+    d.draw_text(
+        "Packs higher up win when two ship the same thing",
+        60,
+        80,
+        20,
+        Color::DARKGRAY,
+    );
+
+    let mut changed = false;
+    let last = content.packs.len().min(content.first_row + MAX_ROWS);
+    for (row, i) in (content.first_row..last).enumerate() {
+        let y = 115 + row as i32 * ROW_HEIGHT;
+        let pack = &content.packs[i];
+        let label = if pack.enabled {
+            rstr!("On")
+        } else {
+            rstr!("Off")
+        };
+        if d.gui_button(Rectangle::new(60.0, y as f32, 60.0, 40.0), Some(label)) {
+            content.toggle(i);
+            changed = true;
+        }
+        let raise = Rectangle::new(130.0, y as f32, 40.0, 40.0);
+        if i > 0 && d.gui_button(raise, Some(rstr!("^"))) {
+            content.raise(i);
+            changed = true;
+        }
+        let pack = &content.packs[i];
+        let color = if pack.enabled {
+            Color::BLACK
+        } else {
+            Color::GRAY
+        };
+        let mut heading = pack.name.clone();
+        if !pack.version.is_empty() {
+            heading += &format!(" {}", pack.version);
+        }
+        if !pack.author.is_empty() {
+            heading += &format!(" by {}", pack.author);
+        }
+        d.draw_text(&heading, 190, y, 20, color);
+        let mut details = pack.summary();
+        if !pack.description.is_empty() {
+            details = format!("{} - {}", pack.description, details);
+        }
+        d.draw_text(&details, 190, y + 24, 15, Color::DARKGRAY);
+    }
+    if content.packs.len() > MAX_ROWS {
+        let x = SCREEN_WIDTH as f32 - 100.0;
+        let up = Rectangle::new(x, 115.0, 40.0, 40.0);
+        if content.first_row > 0 && d.gui_button(up, Some(rstr!("^"))) {
+            content.first_row -= 1;
+        }
+        let bottom = 115.0 + (MAX_ROWS - 1) as f32 * ROW_HEIGHT as f32;
+        let down = Rectangle::new(x, bottom, 40.0, 40.0);
+        if last < content.packs.len() && d.gui_button(down, Some(rstr!("v"))) {
+            content.first_row += 1;
+        }
+        let shown = format!(
+            "{}-{} of {}",
+            content.first_row + 1,
+            last,
+            content.packs.len()
+        );
+        d.draw_text(
+            &shown,
+            190,
+            115 + MAX_ROWS as i32 * ROW_HEIGHT,
+            15,
+            Color::DARKGRAY,
+        );
+    }
+    if changed {
+        content.save();
+    }
+
+    let conflicts = content.conflicts();
+    let top = SCREEN_HEIGHT - 190;
+    if !conflicts.is_empty() {
+        d.draw_text("Conflicts", 60, top, 20, Color::BLACK);
+    }
+    for (i, conflict) in conflicts.iter().take(5).enumerate() {
+        let line = format!(
+            "{}: {} over {}",
+            conflict.what,
+            conflict.packs[0],
+            conflict.packs[1..].join(", ")
+        );
+        d.draw_text(&line, 60, top + 26 + i as i32 * 20, 15, Color::MAROON);
+    }
+    if content.restart_needed() {
+        d.draw_text(
+            "Restart the game to load the changes",
+            60,
+            SCREEN_HEIGHT - 60,
+            20,
+            Color::RED,
+        );
+    }
+}
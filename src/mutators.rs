@@ -0,0 +1,94 @@
+// Match-wide rule tweaks, picked by the Daily challenge
+use crate::{Player, GRAVITY, JUMP_FORCE, PLAYER_SPEED};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    LowGravity,
+    Speedy,
+    BigJumps,
+    FastBullets,
+    ScatterShot,
+    ShortRounds,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 6] = [
+        Mutator::LowGravity,
+        Mutator::Speedy,
+        Mutator::BigJumps,
+        Mutator::FastBullets,
+        Mutator::ScatterShot,
+        Mutator::ShortRounds,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mutator::LowGravity => "Low Gravity",
+            Mutator::Speedy => "Speedy",
+            Mutator::BigJumps => "Big Jumps",
+            Mutator::FastBullets => "Fast Bullets",
+            Mutator::ScatterShot => "Scatter Shot",
+            Mutator::ShortRounds => "Short Rounds",
+        }
+    }
+
+    // Inverse of the `{:?}` name used as key in save files
+    pub fn from_key(key: &str) -> Option<Mutator> {
+        Mutator::ALL
+            .into_iter()
+            .find(|mutator| format!("{:?}", mutator) == key)
+    }
+}
+
+pub fn describe(mutators: &[Mutator]) -> String {
+    if mutators.is_empty() {
+        return "no mutators".to_string();
+    }
+    mutators
+        .iter()
+        .map(Mutator::name)
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+// Resets movement stats to the base values, then applies the mutators
+pub fn apply_to_players(mutators: &[Mutator], players: &mut [Player]) {
+    for player in players {
+        player.gravity = GRAVITY;
+        player.speed = PLAYER_SPEED;
+        player.jump_force = JUMP_FORCE;
+        for mutator in mutators {
+            match mutator {
+                Mutator::LowGravity => player.gravity *= 0.55,
+                Mutator::Speedy => player.speed *= 1.35,
+                Mutator::BigJumps => player.jump_force *= 1.25,
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn round_time_scale(mutators: &[Mutator]) -> f32 {
+    if mutators.contains(&Mutator::ShortRounds) {
+        0.6
+    } else {
+        1.0
+    }
+}
+
+pub fn bullet_speed_scale(mutators: &[Mutator]) -> f32 {
+    if mutators.contains(&Mutator::FastBullets) {
+        1.5
+    } else {
+        1.0
+    }
+}
+
+// How far Dodge lanes may drift up or down from their usual height
+pub fn lane_scatter(mutators: &[Mutator]) -> f32 {
+    if mutators.contains(&Mutator::ScatterShot) {
+        60.0
+    } else {
+        0.0
+    }
+}
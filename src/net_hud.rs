@@ -0,0 +1,65 @@
+// Connection quality for networked sessions: every remote player's ping,
+// packet loss and rollback frames in the top-right corner (a joined client
+// has one line, its link to the host), and a banner across the top while
+// one of them has stalled, so a desync has a visible cause instead of
+// players jumping around for no reason.
+use crate::{online::ConnectionStats, viewport::SCREEN_WIDTH, Player};
+use raylib::prelude::*;
+
+const FONT_SIZE: i32 = 18;
+const LINE_HEIGHT: i32 = 22;
+// round trips above these are shown in yellow, then red
+const PING_FAIR: f32 = 0.08;
+const PING_POOR: f32 = 0.15;
+
+fn ping_color(stats: &ConnectionStats) -> Color {
+    if stats.stalled || stats.ping > PING_POOR {
+        Color::RED
+    } else if stats.ping > PING_FAIR || stats.loss > 0.05 {
+        Color::YELLOW
+    } else {
+        Color::LIME
+    }
+}
+
+pub fn draw(
+    d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+    stats: &[ConnectionStats],
+    players: &[Player],
+) {
+    for (i, connection) in stats.iter().enumerate() {
+        let Some(player) = players.get(connection.seat) else {
+            continue;
+        };
+        let text = format!(
+            "{} {:>3} ms {:>2.0}% loss {}f",
+            connection.name,
+            (connection.ping * 1000.0).round(),
+            connection.loss * 100.0,
+            connection.rollback_frames,
+        );
+        let width = d.measure_text(&text, FONT_SIZE);
+        let (x, y) = (SCREEN_WIDTH - width - 30, 10 + LINE_HEIGHT * i as i32);
+        d.draw_circle(x - 10, y + FONT_SIZE / 2, 5.0, ping_color(connection));
+        d.draw_text(&text, x, y, FONT_SIZE, player.color);
+    }
+
+    let stalled: Vec<String> = stats
+        .iter()
+        .filter(|c| c.stalled)
+        .map(|c| c.name.clone())
+        .collect();
+    if stalled.is_empty() {
+        return;
+    }
+    let text = format!("connection unstable: waiting on {}", stalled.join(", "));
+    let width = d.measure_text(&text, 24);
+    d.draw_rectangle(
+        SCREEN_WIDTH / 2 - width / 2 - 16,
+        50,
+        width + 32,
+        40,
+        Color::BLACK.alpha(0.7),
+    );
+    d.draw_text(&text, SCREEN_WIDTH / 2 - width / 2, 58, 24, Color::ORANGE);
+}
@@ -0,0 +1,1345 @@
+// Wire format for online matches: what the host and its clients send each
+// other, sized so four players fit in a household upload. online.rs puts it
+// to use.
+//
+// Entity snapshots go out SNAPSHOT_RATE times a second, unreliably. Each is a
+// delta against the last snapshot the client acknowledged: a player only
+// sends the fields that changed, behind a mask byte, with positions and
+// speeds quantized. Interest management drops what a client can't use: other
+// players' physics tuning only matters for predicting your own seat, a
+// knocked-out player only sends its flags and points, and of the bullets in
+// the arena only the MAX_BULLETS closest to the client's player are sent.
+//
+// Paint is too big to resend and too important to lose, so it has its own
+// reliable channel carrying diffs of a coarse ownership grid. Diffs are
+// resent until acknowledged and applied strictly in order. A client doesn't
+// wait for them to see its own paint: its splats go on its map straight
+// away, and each diff says how far into the client's input it reaches, so
+// once the host has ruled on a splat the prediction is dropped and whatever
+// the host decided shows instead.
+use crate::{
+    cosmetics::SplatStyle,
+    paint_splat,
+    quickchat::MESSAGES,
+    snapshot::{pixel_owner, PlayerState},
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    Bullet, MiniGames, Player, PlayerInput,
+};
+use raylib::prelude::*;
+use std::collections::VecDeque;
+
+pub const SNAPSHOT_RATE: f32 = 20.0;
+// paint pixels per ownership grid cell, each way
+pub const GRID_CELL: i32 = 4;
+// sent and received snapshots kept around for acks to refer to
+const HISTORY: usize = 32;
+// positions go out in quarter pixels
+const POSITION_SCALE: f32 = 4.0;
+// the biggest datagram that gets through without fragmenting
+pub const MAX_PACKET: usize = 1400;
+// what a snapshot and a paint diff chunk leave room for
+const MAX_BULLETS: usize = 30;
+const MAX_DIFF: usize = 800;
+
+// which of a player's fields follow its mask byte
+const POSITION: u8 = 1 << 0;
+const VELOCITY: u8 = 1 << 1;
+// facing, on ground, jumping, dead
+const FLAGS: u8 = 1 << 2;
+const POINTS: u8 = 1 << 3;
+// hit flash, invulnerable, spawn protection
+const TIMERS: u8 = 1 << 4;
+const INPUT: u8 = 1 << 5;
+// speed, jump force, gravity, jump time; the client's own seat only
+const TUNING: u8 = 1 << 6;
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_i16(out: &mut Vec<u8>, value: f32) {
+    let value = value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+    out.extend(value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_f32(out: &mut Vec<u8>, value: f32) {
+    out.extend(value.to_le_bytes());
+}
+
+// LEB128, small numbers take one byte
+fn put_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// Length-prefixed UTF-8
+fn put_text(out: &mut Vec<u8>, text: &str) {
+    put_varint(out, text.len() as u32);
+    out.extend(text.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Option<f32> {
+        Some(i16::from_le_bytes(self.take()?) as f32)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take()?))
+    }
+
+    fn varint(&mut self) -> Option<u32> {
+        let mut value = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte < 0x80 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let len = self.varint()? as usize;
+        let text = String::from_utf8(self.bytes.get(..len)?.to_vec()).ok()?;
+        self.bytes = &self.bytes[len..];
+        Some(text)
+    }
+}
+
+fn blank_player() -> PlayerState {
+    PlayerState {
+        position: Vector2::zero(),
+        velocity: Vector2::zero(),
+        facing: 1.0,
+        speed: 0.0,
+        jump_force: 0.0,
+        gravity: 0.0,
+        is_on_ground: false,
+        is_jumping: false,
+        jump_time: 0.0,
+        points: 0,
+        dead: false,
+        hit_flash: 0.0,
+        invulnerable: 0.0,
+        spawn_protection: 0.0,
+        input: PlayerInput::default(),
+    }
+}
+
+// Timers go out in milliseconds
+fn millis(seconds: f32) -> u16 {
+    (seconds * 1000.0).round().clamp(0.0, u16::MAX as f32) as u16
+}
+
+// What the client will make of `player` after the trip, so host and client
+// hold the same baseline
+fn quantize(player: &PlayerState) -> PlayerState {
+    let round = |v: Vector2, scale: f32| {
+        let q = |x: f32| (x * scale).round().clamp(i16::MIN as f32, i16::MAX as f32);
+        Vector2::new(q(v.x) / scale, q(v.y) / scale)
+    };
+    PlayerState {
+        position: round(player.position, POSITION_SCALE),
+        velocity: round(player.velocity, 1.0),
+        facing: if player.facing < 0.0 { -1.0 } else { 1.0 },
+        points: player.points.min(u16::MAX as u32),
+        hit_flash: millis(player.hit_flash) as f32 / 1000.0,
+        invulnerable: millis(player.invulnerable) as f32 / 1000.0,
+        spawn_protection: millis(player.spawn_protection) as f32 / 1000.0,
+        ..player.clone()
+    }
+}
+
+fn changed_fields(base: &PlayerState, now: &PlayerState, own: bool) -> u8 {
+    let mut mask = 0;
+    let flags = |p: &PlayerState| (p.facing, p.is_on_ground, p.is_jumping, p.dead);
+    if flags(base) != flags(now) {
+        mask |= FLAGS;
+    }
+    if base.points != now.points {
+        mask |= POINTS;
+    }
+    if now.dead {
+        return mask;
+    }
+    if base.position != now.position {
+        mask |= POSITION;
+    }
+    if base.velocity != now.velocity {
+        mask |= VELOCITY;
+    }
+    let timers = |p: &PlayerState| (p.hit_flash, p.invulnerable, p.spawn_protection);
+    if timers(base) != timers(now) {
+        mask |= TIMERS;
+    }
+    if base.input != now.input {
+        mask |= INPUT;
+    }
+    let tuning = |p: &PlayerState| (p.speed, p.jump_force, p.gravity, p.jump_time);
+    if own && tuning(base) != tuning(now) {
+        mask |= TUNING;
+    }
+    mask
+}
+
+fn write_player(out: &mut Vec<u8>, mask: u8, player: &PlayerState) {
+    out.push(mask);
+    if mask & POSITION != 0 {
+        put_i16(out, player.position.x * POSITION_SCALE);
+        put_i16(out, player.position.y * POSITION_SCALE);
+    }
+    if mask & VELOCITY != 0 {
+        put_i16(out, player.velocity.x);
+        put_i16(out, player.velocity.y);
+    }
+    if mask & FLAGS != 0 {
+        out.push(
+            (player.facing < 0.0) as u8
+                | (player.is_on_ground as u8) << 1
+                | (player.is_jumping as u8) << 2
+                | (player.dead as u8) << 3,
+        );
+    }
+    if mask & POINTS != 0 {
+        put_u16(out, player.points as u16);
+    }
+    if mask & TIMERS != 0 {
+        put_u16(out, millis(player.hit_flash));
+        put_u16(out, millis(player.invulnerable));
+        put_u16(out, millis(player.spawn_protection));
+    }
+    if mask & INPUT != 0 {
+        out.push(player.input.to_bits());
+    }
+    if mask & TUNING != 0 {
+        put_f32(out, player.speed);
+        put_f32(out, player.jump_force);
+        put_f32(out, player.gravity);
+        put_f32(out, player.jump_time);
+    }
+}
+
+// Fills in the fields `mask` says were sent, the rest stay as in the baseline
+fn read_player(reader: &mut Reader, player: &mut PlayerState) -> Option<()> {
+    let mask = reader.u8()?;
+    if mask & POSITION != 0 {
+        player.position = Vector2::new(reader.i16()?, reader.i16()?) / POSITION_SCALE;
+    }
+    if mask & VELOCITY != 0 {
+        player.velocity = Vector2::new(reader.i16()?, reader.i16()?);
+    }
+    if mask & FLAGS != 0 {
+        let flags = reader.u8()?;
+        player.facing = if flags & 1 != 0 { -1.0 } else { 1.0 };
+        player.is_on_ground = flags & 2 != 0;
+        player.is_jumping = flags & 4 != 0;
+        player.dead = flags & 8 != 0;
+    }
+    if mask & POINTS != 0 {
+        player.points = reader.u16()? as u32;
+    }
+    if mask & TIMERS != 0 {
+        player.hit_flash = reader.u16()? as f32 / 1000.0;
+        player.invulnerable = reader.u16()? as f32 / 1000.0;
+        player.spawn_protection = reader.u16()? as f32 / 1000.0;
+    }
+    if mask & INPUT != 0 {
+        player.input = PlayerInput::from_bits(reader.u8()?);
+    }
+    if mask & TUNING != 0 {
+        player.speed = reader.f32()?;
+        player.jump_force = reader.f32()?;
+        player.gravity = reader.f32()?;
+        player.jump_time = reader.f32()?;
+    }
+    Some(())
+}
+
+// A bullet as a client draws it; it keeps flying on speed and gravity
+// between snapshots
+#[derive(Debug, Clone)]
+pub struct RemoteBullet {
+    pub rect: Rectangle,
+    pub speed: Vector2,
+    pub gravity: f32,
+    pub color: Color,
+}
+
+fn write_bullet(out: &mut Vec<u8>, bullet: &Bullet) {
+    put_i16(out, bullet.rect.x * POSITION_SCALE);
+    put_i16(out, bullet.rect.y * POSITION_SCALE);
+    out.push(bullet.rect.width.clamp(0.0, 255.0) as u8);
+    out.push(bullet.rect.height.clamp(0.0, 255.0) as u8);
+    put_i16(out, bullet.speed.x);
+    put_i16(out, bullet.speed.y);
+    put_i16(out, bullet.gravity);
+    let Color { r, g, b, a } = bullet.color;
+    out.extend([r, g, b, a]);
+}
+
+fn read_bullet(reader: &mut Reader) -> Option<RemoteBullet> {
+    let x = reader.i16()? / POSITION_SCALE;
+    let y = reader.i16()? / POSITION_SCALE;
+    let (width, height) = (reader.u8()? as f32, reader.u8()? as f32);
+    let speed = Vector2::new(reader.i16()?, reader.i16()?);
+    let gravity = reader.i16()?;
+    let [r, g, b, a] = reader.take::<4>()?;
+    Some(RemoteBullet {
+        rect: Rectangle::new(x, y, width, height),
+        speed,
+        gravity,
+        color: Color::new(r, g, b, a),
+    })
+}
+
+// A snapshot as the client sees it
+#[derive(Debug, Clone)]
+pub struct ClientSnapshot {
+    pub sequence: u16,
+    pub players: Vec<PlayerState>,
+    pub bullets: Vec<RemoteBullet>,
+}
+
+// The host's end of one client's snapshot stream
+pub struct SnapshotSender {
+    // the client's seat, the one it predicts
+    viewer: usize,
+    sequence: u16,
+    sent: VecDeque<(u16, Vec<PlayerState>)>,
+    acked: Option<(u16, Vec<PlayerState>)>,
+}
+
+impl SnapshotSender {
+    pub fn new(viewer: usize) -> SnapshotSender {
+        SnapshotSender {
+            viewer,
+            sequence: 0,
+            sent: VecDeque::new(),
+            acked: None,
+        }
+    }
+
+    // Layout: sequence, baseline sequence (or u16::MAX for none), player
+    // count, the players, bullet count, the bullets
+    pub fn encode(&mut self, players: &[PlayerState], bullets: &[Bullet]) -> Vec<u8> {
+        self.sequence = self.sequence.wrapping_add(1) % u16::MAX;
+        let mut out = Vec::new();
+        put_u16(&mut out, self.sequence);
+        put_u16(
+            &mut out,
+            self.acked.as_ref().map_or(u16::MAX, |(seq, _)| *seq),
+        );
+        out.push(players.len() as u8);
+        let blank = blank_player();
+        let mut sent = Vec::with_capacity(players.len());
+        for (i, player) in players.iter().enumerate() {
+            let now = quantize(player);
+            let own = i == self.viewer;
+            let base = self
+                .acked
+                .as_ref()
+                .and_then(|(_, base)| base.get(i))
+                .unwrap_or(&blank);
+            let mut fields = Vec::new();
+            write_player(&mut fields, changed_fields(base, &now, own), &now);
+            // what the client holds once this lands
+            let mut kept = base.clone();
+            read_player(&mut Reader { bytes: &fields }, &mut kept);
+            sent.push(kept);
+            out.extend(fields);
+        }
+        let arena = Rectangle::new(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        let mut visible: Vec<&Bullet> = bullets
+            .iter()
+            .filter(|b| b.rect.check_collision_recs(&arena))
+            .collect();
+        // the closest to the client's player matter most
+        if let Some(viewer) = players.get(self.viewer) {
+            visible.sort_by(|a, b| {
+                let distance = |bullet: &Bullet| bullet.center().distance_to(viewer.position);
+                distance(a).total_cmp(&distance(b))
+            });
+        }
+        visible.truncate(MAX_BULLETS);
+        out.push(visible.len() as u8);
+        for bullet in visible {
+            write_bullet(&mut out, bullet);
+        }
+        self.sent.push_back((self.sequence, sent));
+        if self.sent.len() > HISTORY {
+            self.sent.pop_front();
+        }
+        out
+    }
+
+    // The last snapshot encoded
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    // The client got `sequence`; later snapshots build on it. Acks older
+    // than the current baseline or out of the history are ignored.
+    pub fn ack(&mut self, sequence: u16) {
+        let Some(at) = self.sent.iter().position(|(seq, _)| *seq == sequence) else {
+            return;
+        };
+        self.acked = self.sent.drain(..=at).last();
+    }
+}
+
+// The client's end: rebuilds full snapshots from deltas
+#[derive(Default)]
+pub struct SnapshotReceiver {
+    received: VecDeque<(u16, Vec<PlayerState>)>,
+    latest: Option<u16>,
+}
+
+impl SnapshotReceiver {
+    // None for a broken packet, one older than the latest, or one whose
+    // baseline has already been forgotten. The returned sequence is the ack.
+    pub fn decode(&mut self, bytes: &[u8]) -> Option<ClientSnapshot> {
+        let mut reader = Reader { bytes };
+        let sequence = reader.u16()?;
+        let baseline = reader.u16()?;
+        if self
+            .latest
+            .is_some_and(|latest| sequence.wrapping_sub(latest) > u16::MAX / 2)
+        {
+            return None;
+        }
+        let base = match baseline {
+            u16::MAX => Vec::new(),
+            baseline => self
+                .received
+                .iter()
+                .find(|(seq, _)| *seq == baseline)?
+                .1
+                .clone(),
+        };
+        let count = reader.u8()? as usize;
+        let mut players = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut player = base.get(i).cloned().unwrap_or_else(blank_player);
+            read_player(&mut reader, &mut player)?;
+            players.push(player);
+        }
+        let count = reader.u8()? as usize;
+        let bullets = (0..count)
+            .map(|_| read_bullet(&mut reader))
+            .collect::<Option<Vec<_>>>()?;
+        self.latest = Some(sequence);
+        self.received.push_back((sequence, players.clone()));
+        if self.received.len() > HISTORY {
+            self.received.pop_front();
+        }
+        Some(ClientSnapshot {
+            sequence,
+            players,
+            bullets,
+        })
+    }
+}
+
+// Who owns each GRID_CELL square of the paint map, sampled at its centre:
+// 0 is bare, n is player n
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnershipGrid {
+    pub width: i32,
+    pub height: i32,
+    pub owners: Vec<u8>,
+}
+
+impl OwnershipGrid {
+    pub fn blank(paint_width: i32, paint_height: i32) -> OwnershipGrid {
+        let (width, height) = (paint_width / GRID_CELL, paint_height / GRID_CELL);
+        OwnershipGrid {
+            width,
+            height,
+            owners: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn capture(image: &mut Image, players: &[Player]) -> OwnershipGrid {
+        let mut grid = OwnershipGrid::blank(image.width, image.height);
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let pixel =
+                    image.get_color(x * GRID_CELL + GRID_CELL / 2, y * GRID_CELL + GRID_CELL / 2);
+                grid.owners[(y * grid.width + x) as usize] = pixel_owner(pixel, players);
+            }
+        }
+        grid
+    }
+
+    // The cells that differ in `newer`, as runs of [cells skipped, run
+    // length, owner] with varint counts. Split into chunks of about
+    // MAX_DIFF bytes that apply on their own, so a repaint of the whole map
+    // still fits in packets.
+    pub fn diff(&self, newer: &OwnershipGrid) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        let mut out = Vec::new();
+        let mut skipped = 0;
+        let mut i = 0;
+        while i < newer.owners.len() {
+            let owner = newer.owners[i];
+            if self.owners.get(i) == Some(&owner) {
+                skipped += 1;
+                i += 1;
+                continue;
+            }
+            if out.len() >= MAX_DIFF {
+                chunks.push(std::mem::take(&mut out));
+                // a chunk counts from the first cell
+                skipped = i as u32;
+            }
+            let run = newer.owners[i..]
+                .iter()
+                .zip(self.owners.get(i..).unwrap_or_default())
+                .take_while(|&(&new, &old)| new == owner && old != new)
+                .count()
+                .max(1);
+            put_varint(&mut out, skipped);
+            put_varint(&mut out, run as u32);
+            out.push(owner);
+            skipped = 0;
+            i += run;
+        }
+        if !out.is_empty() {
+            chunks.push(out);
+        }
+        chunks
+    }
+
+    // Returns the runs of cells that changed, as (first cell, length). A
+    // broken diff may be half applied; the channel never lets one through.
+    pub fn apply(&mut self, diff: &[u8]) -> Option<Vec<(usize, usize)>> {
+        let mut reader = Reader { bytes: diff };
+        let mut changed = Vec::new();
+        let mut i = 0;
+        while !reader.bytes.is_empty() {
+            i += reader.varint()? as usize;
+            let run = reader.varint()? as usize;
+            let owner = reader.u8()?;
+            self.owners.get_mut(i..i + run)?.fill(owner);
+            changed.push((i, run));
+            i += run;
+        }
+        Some(changed)
+    }
+
+    // Paints cells `first..first + count` onto a paint map the size the grid
+    // was captured from
+    fn draw_cells(&self, image: &mut Image, players: &[Player], first: usize, count: usize) {
+        for cell in first..(first + count).min(self.owners.len()) {
+            let color = (self.owners[cell] as usize)
+                .checked_sub(1)
+                .and_then(|p| players.get(p))
+                .map_or(Color::WHITE.alpha(0.0), |p| p.color);
+            let (x, y) = (cell as i32 % self.width, cell as i32 / self.width);
+            image.draw_rectangle(x * GRID_CELL, y * GRID_CELL, GRID_CELL, GRID_CELL, color);
+        }
+    }
+}
+
+// One reliable paint message
+#[derive(Debug, Clone)]
+pub struct PaintDiff {
+    pub sequence: u32,
+    // the newest tick of the receiving client's input the host had taken in
+    pub input_tick: u32,
+    pub cells: Vec<u8>,
+}
+
+// The host's end of the reliable paint channel
+pub struct PaintSender {
+    grid: OwnershipGrid,
+    next: u32,
+    input_tick: u32,
+    unacked: VecDeque<PaintDiff>,
+}
+
+impl PaintSender {
+    pub fn new(blank: OwnershipGrid) -> PaintSender {
+        PaintSender {
+            grid: blank,
+            next: 0,
+            input_tick: 0,
+            unacked: VecDeque::new(),
+        }
+    }
+
+    // Queues what changed since the last queued grid. A diff goes out even
+    // when nothing changed if the client's input moved on, so its
+    // predictions still get ruled on.
+    pub fn update(&mut self, input_tick: u32, grid: OwnershipGrid) {
+        if grid == self.grid && input_tick == self.input_tick {
+            return;
+        }
+        let mut chunks = self.grid.diff(&grid);
+        if chunks.is_empty() {
+            chunks.push(Vec::new());
+        }
+        let last = chunks.len() - 1;
+        for (i, cells) in chunks.into_iter().enumerate() {
+            self.unacked.push_back(PaintDiff {
+                sequence: self.next,
+                // only the whole diff settles the client's predictions
+                input_tick: if i == last {
+                    input_tick
+                } else {
+                    self.input_tick
+                },
+                cells,
+            });
+            self.next += 1;
+        }
+        self.input_tick = input_tick;
+        self.grid = grid;
+    }
+
+    // Everything not acknowledged yet, sent again every tick
+    pub fn outgoing(&self) -> impl Iterator<Item = &PaintDiff> {
+        self.unacked.iter()
+    }
+
+    // Acks are cumulative: the client has applied everything before `next`
+    pub fn ack(&mut self, next: u32) {
+        while self
+            .unacked
+            .front()
+            .is_some_and(|diff| diff.sequence < next)
+        {
+            self.unacked.pop_front();
+        }
+    }
+}
+
+struct PredictedSplat {
+    tick: u32,
+    point: Vector2,
+    color: Color,
+    style: SplatStyle,
+    radius: f32,
+}
+
+impl PredictedSplat {
+    fn draw(&self, image: &mut Image) {
+        paint_splat(image, self.point, self.color, self.style, self.radius);
+    }
+
+    // Rows of grid cells the splat can reach on `image`, as (first, count)
+    // runs; paint_splat takes a contact point offset by the radius
+    fn cells(&self, image: &Image, grid: &OwnershipGrid) -> Vec<(usize, usize)> {
+        let scale = image.width as f32 / SCREEN_WIDTH as f32;
+        let center = (self.point - Vector2::one() * self.radius) * scale;
+        let reach = self.radius * scale + 1.0;
+        let cell = |v: f32, max: i32| ((v / GRID_CELL as f32) as i32).clamp(0, max - 1);
+        let (left, right) = (
+            cell(center.x - reach, grid.width),
+            cell(center.x + reach, grid.width),
+        );
+        let (top, bottom) = (
+            cell(center.y - reach, grid.height),
+            cell(center.y + reach, grid.height),
+        );
+        (top..=bottom)
+            .map(|y| {
+                (
+                    (y * grid.width + left) as usize,
+                    (right - left + 1) as usize,
+                )
+            })
+            .collect()
+    }
+}
+
+// The client's paint map: the host's grid as far as it has arrived, with
+// the client's own splats drawn over it until the host has ruled on them
+pub struct PaintSync {
+    grid: OwnershipGrid,
+    next: u32,
+    predicted: Vec<PredictedSplat>,
+}
+
+impl PaintSync {
+    pub fn new(blank: OwnershipGrid) -> PaintSync {
+        PaintSync {
+            grid: blank,
+            next: 0,
+            predicted: Vec::new(),
+        }
+    }
+
+    // Paints one of the client's own splats, made at input `tick`, right away
+    pub fn predict(
+        &mut self,
+        image: &mut Image,
+        tick: u32,
+        point: Vector2,
+        color: Color,
+        style: SplatStyle,
+        radius: f32,
+    ) {
+        let splat = PredictedSplat {
+            tick,
+            point,
+            color,
+            style,
+            radius,
+        };
+        splat.draw(image);
+        self.predicted.push(splat);
+    }
+
+    // Applies the diff if it is the next one, repeats and early arrivals are
+    // dropped and come round again. The host's cells replace whatever was
+    // predicted under them, then the splats it hasn't seen yet go back on top.
+    pub fn receive(&mut self, image: &mut Image, players: &[Player], diff: &PaintDiff) {
+        if diff.sequence != self.next {
+            return;
+        }
+        let Some(mut changed) = self.grid.apply(&diff.cells) else {
+            return;
+        };
+        self.next += 1;
+        let (ruled, pending): (Vec<_>, Vec<_>) = self
+            .predicted
+            .drain(..)
+            .partition(|splat| splat.tick <= diff.input_tick);
+        for splat in &ruled {
+            changed.extend(splat.cells(image, &self.grid));
+        }
+        for (first, count) in changed {
+            self.grid.draw_cells(image, players, first, count);
+        }
+        for splat in &pending {
+            splat.draw(image);
+        }
+        self.predicted = pending;
+    }
+
+    pub fn ack(&self) -> u32 {
+        self.next
+    }
+
+    pub fn pending(&self) -> usize {
+        self.predicted.len()
+    }
+}
+
+// Every datagram, the first byte says which
+#[derive(Debug, Clone)]
+pub enum Packet {
+    // client to host: asks for a seat, repeated until answered. Carries the
+    // client's profile card as JSON, empty when it plays without one.
+    Hello {
+        profile: String,
+    },
+    // client to host, every frame: its input at `tick` and what it has got
+    Input {
+        tick: u32,
+        input: PlayerInput,
+        snapshot_ack: Option<u16>,
+        paint_ack: u32,
+    },
+    // host to client: the seat it plays
+    Welcome {
+        seat: u8,
+    },
+    // host to client: no seat left
+    Full,
+    // host to client: a snapshot and the unacknowledged paint diffs
+    State {
+        snapshot: Vec<u8>,
+        paint: Vec<PaintDiff>,
+    },
+    // host to client, every second: the profile card of every seated
+    // client, so everyone shows the same names and cosmetics; guests' cards
+    // are empty
+    Roster {
+        cards: Vec<(u8, String)>,
+    },
+    // host to client, resent for a while: the finished match as a history
+    // record in JSON
+    MatchOver {
+        record: String,
+    },
+    // host to client, every second and whenever a round starts or ends: the
+    // minigame being played and its clock
+    Round {
+        game: MiniGames,
+        time_left: f32,
+        done: bool,
+    },
+    // client to host, then host to every client: a seat picked a message off
+    // the quick chat wheel, an index into quickchat::MESSAGES. The host puts
+    // the sender's own seat on what it relays.
+    QuickChat {
+        seat: u8,
+        message: u8,
+    },
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Packet::Hello { profile } => {
+                out.push(0);
+                put_text(&mut out, profile);
+            }
+            Packet::Input {
+                tick,
+                input,
+                snapshot_ack,
+                paint_ack,
+            } => {
+                out.push(1);
+                put_u32(&mut out, *tick);
+                out.push(input.to_bits());
+                put_u16(&mut out, snapshot_ack.unwrap_or(u16::MAX));
+                put_u32(&mut out, *paint_ack);
+            }
+            Packet::Welcome { seat } => out.extend([2, *seat]),
+            Packet::Full => out.push(3),
+            Packet::State { snapshot, paint } => {
+                out.push(4);
+                put_u16(&mut out, snapshot.len() as u16);
+                out.extend(snapshot);
+                // diffs that don't fit wait for the next packet
+                let budget = MAX_PACKET.saturating_sub(out.len() + 1);
+                let mut used = 0;
+                let fitting: Vec<&PaintDiff> = paint
+                    .iter()
+                    .take_while(|diff| {
+                        used += diff.cells.len() + 13;
+                        used <= budget
+                    })
+                    .collect();
+                out.push(fitting.len() as u8);
+                for diff in fitting {
+                    put_u32(&mut out, diff.sequence);
+                    put_u32(&mut out, diff.input_tick);
+                    put_varint(&mut out, diff.cells.len() as u32);
+                    out.extend(&diff.cells);
+                }
+            }
+            Packet::Roster { cards } => {
+                out.push(5);
+                out.push(cards.len() as u8);
+                for (seat, card) in cards {
+                    out.push(*seat);
+                    put_text(&mut out, card);
+                }
+            }
+            Packet::MatchOver { record } => {
+                out.push(6);
+                put_text(&mut out, record);
+            }
+            Packet::Round {
+                game,
+                time_left,
+                done,
+            } => {
+                out.push(7);
+                out.push(MiniGames::ALL.iter().position(|g| g == game).unwrap() as u8);
+                put_f32(&mut out, *time_left);
+                out.push(*done as u8);
+            }
+            Packet::QuickChat { seat, message } => out.extend([8, *seat, *message]),
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Packet> {
+        let mut reader = Reader { bytes };
+        let packet = match reader.u8()? {
+            0 => Packet::Hello {
+                profile: reader.text()?,
+            },
+            1 => Packet::Input {
+                tick: reader.u32()?,
+                input: PlayerInput::from_bits(reader.u8()?),
+                snapshot_ack: Some(reader.u16()?).filter(|ack| *ack != u16::MAX),
+                paint_ack: reader.u32()?,
+            },
+            2 => Packet::Welcome { seat: reader.u8()? },
+            3 => Packet::Full,
+            4 => {
+                let len = reader.u16()? as usize;
+                let snapshot = reader.bytes.get(..len)?.to_vec();
+                reader.bytes = &reader.bytes[len..];
+                let count = reader.u8()?;
+                let mut paint = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let sequence = reader.u32()?;
+                    let input_tick = reader.u32()?;
+                    let len = reader.varint()? as usize;
+                    let cells = reader.bytes.get(..len)?.to_vec();
+                    reader.bytes = &reader.bytes[len..];
+                    paint.push(PaintDiff {
+                        sequence,
+                        input_tick,
+                        cells,
+                    });
+                }
+                Packet::State { snapshot, paint }
+            }
+            5 => {
+                let count = reader.u8()?;
+                let mut cards = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    cards.push((reader.u8()?, reader.text()?));
+                }
+                Packet::Roster { cards }
+            }
+            6 => Packet::MatchOver {
+                record: reader.text()?,
+            },
+            7 => Packet::Round {
+                game: *MiniGames::ALL.get(reader.u8()? as usize)?,
+                time_left: reader.f32()?,
+                done: reader.u8()? != 0,
+            },
+            8 => Packet::QuickChat {
+                seat: reader.u8()?,
+                message: reader.u8().filter(|m| (*m as usize) < MESSAGES.len())?,
+            },
+            _ => return None,
+        };
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rng::Rng, BulletSource};
+
+    fn player(x: f32, y: f32) -> PlayerState {
+        PlayerState {
+            position: Vector2::new(x, y),
+            velocity: Vector2::new(120.0, -40.0),
+            speed: 300.0,
+            jump_force: 500.0,
+            gravity: 900.0,
+            points: 2,
+            input: PlayerInput {
+                right: true,
+                ..PlayerInput::default()
+            },
+            ..blank_player()
+        }
+    }
+
+    fn bullet(x: f32, y: f32) -> Bullet {
+        Bullet {
+            rect: Rectangle::new(x, y, 10.0, 10.0),
+            color: Color::new(200, 10, 10, 255),
+            speed: Vector2::new(-150.0, 0.0),
+            gravity: 0.0,
+            time_to_live: 5.0,
+            source: BulletSource::Wave(1),
+        }
+    }
+
+    // What the client should hold for `sent`, given what it made of it
+    fn assert_same(got: &PlayerState, sent: &PlayerState) {
+        let sent = quantize(sent);
+        assert_eq!(got.position, sent.position);
+        assert_eq!(got.velocity, sent.velocity);
+        assert_eq!(got.facing, sent.facing);
+        assert_eq!(got.points, sent.points);
+        assert_eq!(got.dead, sent.dead);
+        assert_eq!(got.input, sent.input);
+        assert_eq!(got.hit_flash, sent.hit_flash);
+    }
+
+    // Moves everyone along a little, as a tick of the game would
+    fn step(players: &mut [PlayerState], tick: u32) {
+        for (i, player) in players.iter_mut().enumerate() {
+            player.position.x += 1.25 + i as f32;
+            player.velocity.y = tick as f32 * 10.0;
+            player.points = tick / 3;
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let mut sender = SnapshotSender::new(1);
+        let mut receiver = SnapshotReceiver::default();
+        let mut players = vec![player(100.3, 200.7), player(-5.0, 40.0)];
+        players[0].facing = -1.0;
+        players[0].hit_flash = 0.1234;
+        let bullets = [bullet(50.0, 60.0), bullet(-500.0, 60.0)];
+
+        let snapshot = receiver.decode(&sender.encode(&players, &bullets)).unwrap();
+        assert_eq!(snapshot.sequence, sender.sequence());
+        assert_eq!(snapshot.players.len(), 2);
+        for (got, sent) in snapshot.players.iter().zip(&players) {
+            assert_same(got, sent);
+        }
+        // the bullet off the arena is left out
+        assert_eq!(snapshot.bullets.len(), 1);
+        assert_eq!(snapshot.bullets[0].rect, bullets[0].rect);
+        assert_eq!(snapshot.bullets[0].speed, bullets[0].speed);
+        assert_eq!(snapshot.bullets[0].color, bullets[0].color);
+    }
+
+    #[test]
+    fn only_the_viewer_gets_tuning() {
+        let mut sender = SnapshotSender::new(0);
+        let mut receiver = SnapshotReceiver::default();
+        let players = vec![player(10.0, 10.0), player(20.0, 20.0)];
+        let snapshot = receiver.decode(&sender.encode(&players, &[])).unwrap();
+        assert_eq!(snapshot.players[0].speed, 300.0);
+        assert_eq!(snapshot.players[1].speed, 0.0);
+    }
+
+    #[test]
+    fn deltas_build_on_the_acked_snapshot() {
+        let mut sender = SnapshotSender::new(0);
+        let mut receiver = SnapshotReceiver::default();
+        let mut players = vec![player(10.0, 10.0), player(20.0, 20.0)];
+        let full = sender.encode(&players, &[]);
+        let ack = receiver.decode(&full).unwrap().sequence;
+        sender.ack(ack);
+
+        // nothing changed: a mask byte per player
+        let unchanged = sender.encode(&players, &[]);
+        assert_eq!(unchanged.len(), 2 + 2 + 1 + players.len() + 1);
+        let snapshot = receiver.decode(&unchanged).unwrap();
+        assert_same(&snapshot.players[1], &players[1]);
+
+        players[1].position.x += 3.0;
+        let moved = sender.encode(&players, &[]);
+        assert!(moved.len() < full.len());
+        let snapshot = receiver.decode(&moved).unwrap();
+        assert_same(&snapshot.players[0], &players[0]);
+        assert_same(&snapshot.players[1], &players[1]);
+    }
+
+    #[test]
+    fn survives_lost_snapshots_and_acks() {
+        let mut sender = SnapshotSender::new(0);
+        let mut receiver = SnapshotReceiver::default();
+        let mut players = vec![player(10.0, 10.0), player(20.0, 20.0)];
+        let first = receiver.decode(&sender.encode(&players, &[])).unwrap();
+        sender.ack(first.sequence);
+
+        for tick in 0..10 {
+            step(&mut players, tick);
+            let packet = sender.encode(&players, &[]);
+            // every other snapshot is lost, and so is every ack but the third
+            if tick % 2 == 1 {
+                continue;
+            }
+            let snapshot = receiver.decode(&packet).unwrap();
+            for (got, sent) in snapshot.players.iter().zip(&players) {
+                assert_same(got, sent);
+            }
+            if tick % 3 == 0 {
+                sender.ack(snapshot.sequence);
+            }
+        }
+    }
+
+    #[test]
+    fn drops_stale_snapshots_and_forgotten_baselines() {
+        let mut sender = SnapshotSender::new(0);
+        let mut receiver = SnapshotReceiver::default();
+        let players = vec![player(10.0, 10.0)];
+        let old = sender.encode(&players, &[]);
+        let new = sender.encode(&players, &[]);
+        assert!(receiver.decode(&new).is_some());
+        assert!(receiver.decode(&old).is_none());
+
+        // an ack the client's history no longer covers
+        let mut forgetful = SnapshotReceiver::default();
+        let base = sender.encode(&players, &[]);
+        let base = forgetful.decode(&base).unwrap().sequence;
+        sender.ack(base);
+        for _ in 0..HISTORY {
+            forgetful.received.push_back((0, Vec::new()));
+            forgetful.received.pop_front();
+        }
+        assert!(forgetful.decode(&sender.encode(&players, &[])).is_none());
+    }
+
+    #[test]
+    fn sequences_wrap_around() {
+        let mut sender = SnapshotSender::new(0);
+        let mut receiver = SnapshotReceiver::default();
+        let mut players = vec![player(10.0, 10.0), player(20.0, 20.0)];
+        sender.sequence = u16::MAX - 4;
+        let mut sequences = Vec::new();
+        for tick in 0..10 {
+            step(&mut players, tick);
+            let snapshot = receiver.decode(&sender.encode(&players, &[])).unwrap();
+            for (got, sent) in snapshot.players.iter().zip(&players) {
+                assert_same(got, sent);
+            }
+            sender.ack(snapshot.sequence);
+            sequences.push(snapshot.sequence);
+        }
+        // u16::MAX stands for "no baseline" and is never used
+        assert!(!sequences.contains(&u16::MAX));
+        assert!(sequences.contains(&0));
+        // a snapshot from before the wrap is still older
+        let mut late = SnapshotSender::new(0);
+        late.sequence = u16::MAX - 3;
+        assert!(receiver.decode(&late.encode(&players, &[])).is_none());
+    }
+
+    fn random_grid(rng: &mut Rng, width: i32, height: i32, fill: f32) -> OwnershipGrid {
+        let mut grid = OwnershipGrid {
+            width,
+            height,
+            owners: vec![0; (width * height) as usize],
+        };
+        for owner in &mut grid.owners {
+            if rng.next_f32() < fill {
+                *owner = rng.index(5) as u8;
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn grid_diffs_apply_to_the_newer_grid() {
+        let mut rng = Rng::new(7);
+        for fill in [0.0, 0.01, 0.3, 1.0] {
+            let old = random_grid(&mut rng, 80, 45, 0.5);
+            let mut newer = old.clone();
+            for owner in &mut newer.owners {
+                if rng.next_f32() < fill {
+                    *owner = rng.index(5) as u8;
+                }
+            }
+            let chunks = old.diff(&newer);
+            assert_eq!(chunks.is_empty(), old == newer);
+            let mut applied = old.clone();
+            for chunk in &chunks {
+                assert!(chunk.len() < MAX_DIFF + 16);
+                applied.apply(chunk).unwrap();
+            }
+            assert_eq!(applied, newer);
+        }
+    }
+
+    #[test]
+    fn a_whole_repaint_comes_in_chunks_that_apply_alone() {
+        let old = OwnershipGrid::blank(320 * GRID_CELL, 180 * GRID_CELL);
+        let mut newer = old.clone();
+        for (i, owner) in newer.owners.iter_mut().enumerate() {
+            *owner = (i % 4 + 1) as u8;
+        }
+        let chunks = old.diff(&newer);
+        assert!(chunks.len() > 1);
+        // each chunk counts its cells from the start of the grid
+        let mut applied = old.clone();
+        for chunk in chunks.iter().rev() {
+            let runs = applied.apply(chunk).unwrap();
+            assert!(!runs.is_empty());
+        }
+        assert_eq!(applied, newer);
+    }
+
+    #[test]
+    fn the_paint_channel_resends_until_acked() {
+        let blank = OwnershipGrid::blank(40 * GRID_CELL, 20 * GRID_CELL);
+        let mut sender = PaintSender::new(blank.clone());
+        let mut first = blank.clone();
+        first.owners[5] = 1;
+        let mut second = first.clone();
+        second.owners[300..320].fill(2);
+        sender.update(1, first);
+        sender.update(2, second.clone());
+        assert_eq!(sender.outgoing().count(), 2);
+        sender.ack(1);
+        let left: Vec<&PaintDiff> = sender.outgoing().collect();
+        assert_eq!(left.len(), 1);
+        assert_eq!((left[0].sequence, left[0].input_tick), (1, 2));
+        // nothing new but the input moved on: an empty diff still goes out
+        sender.update(3, second);
+        assert!(sender.outgoing().last().unwrap().cells.is_empty());
+    }
+
+    fn samples() -> Vec<Packet> {
+        vec![
+            Packet::Hello {
+                profile: r#"{"name":"Zoë \"Z\""}"#.to_string(),
+            },
+            Packet::Hello {
+                profile: String::new(),
+            },
+            Packet::Input {
+                tick: 123_456,
+                input: PlayerInput::from_bits(0b101011),
+                snapshot_ack: Some(41),
+                paint_ack: 7,
+            },
+            Packet::Input {
+                tick: 0,
+                input: PlayerInput::default(),
+                snapshot_ack: None,
+                paint_ack: 0,
+            },
+            Packet::Welcome { seat: 3 },
+            Packet::Full,
+            Packet::State {
+                snapshot: vec![1, 2, 3, 4, 5],
+                paint: vec![
+                    PaintDiff {
+                        sequence: 9,
+                        input_tick: 300,
+                        cells: vec![0, 200, 1, 3],
+                    },
+                    PaintDiff {
+                        sequence: 10,
+                        input_tick: 301,
+                        cells: Vec::new(),
+                    },
+                ],
+            },
+            Packet::Roster {
+                cards: vec![(1, "{}".to_string()), (2, "card".to_string())],
+            },
+            Packet::MatchOver {
+                record: "{\"rounds\":[]}".to_string(),
+            },
+            Packet::Round {
+                game: MiniGames::Dodge,
+                time_left: 12.5,
+                done: false,
+            },
+            Packet::QuickChat {
+                seat: 2,
+                message: 4,
+            },
+        ]
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        for packet in samples() {
+            let bytes = packet.encode();
+            let decoded = Packet::decode(&bytes).unwrap();
+            assert_eq!(decoded.encode(), bytes, "{:?}", packet);
+        }
+        let Some(Packet::Input {
+            tick,
+            input,
+            snapshot_ack,
+            paint_ack,
+        }) = Packet::decode(&samples()[2].encode())
+        else {
+            panic!("not an input packet");
+        };
+        assert_eq!((tick, snapshot_ack, paint_ack), (123_456, Some(41), 7));
+        assert_eq!(input.to_bits(), 0b101011);
+    }
+
+    #[test]
+    fn state_packets_leave_out_what_does_not_fit() {
+        let diff = |sequence| PaintDiff {
+            sequence,
+            input_tick: 0,
+            cells: vec![1; MAX_DIFF],
+        };
+        let packet = Packet::State {
+            snapshot: vec![0; 200],
+            paint: vec![diff(0), diff(1), diff(2)],
+        };
+        let bytes = packet.encode();
+        assert!(bytes.len() <= MAX_PACKET);
+        let Some(Packet::State { snapshot, paint }) = Packet::decode(&bytes) else {
+            panic!("not a state packet");
+        };
+        assert_eq!(snapshot.len(), 200);
+        assert_eq!(paint.len(), 1);
+        assert_eq!(paint[0].cells, vec![1; MAX_DIFF]);
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        for packet in samples() {
+            let bytes = packet.encode();
+            for len in 0..bytes.len() {
+                assert!(
+                    Packet::decode(&bytes[..len]).is_none(),
+                    "{:?} cut to {} bytes",
+                    packet,
+                    len
+                );
+            }
+        }
+        let mut sender = SnapshotSender::new(0);
+        let bytes = sender.encode(&[player(1.0, 2.0)], &[bullet(5.0, 5.0)]);
+        for len in 0..bytes.len() {
+            assert!(SnapshotReceiver::default().decode(&bytes[..len]).is_none());
+        }
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        let garbage: [&[u8]; 7] = [
+            &[],
+            &[200],
+            // a message that isn't on the wheel
+            &[8, 1, 200],
+            // a varint that never ends
+            &[0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            // text that isn't UTF-8
+            &[0, 2, 0xc3, 0x28],
+            // a length past the end
+            &[6, 100, b'{', b'}'],
+            &[4, 0xff, 0xff, 1],
+        ];
+        for bytes in garbage {
+            assert!(Packet::decode(bytes).is_none(), "{:?}", bytes);
+        }
+        let mut rng = Rng::new(11);
+        for _ in 0..2000 {
+            let len = rng.index(64);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+            // must not panic, whatever it makes of it
+            Packet::decode(&bytes);
+            SnapshotReceiver::default().decode(&bytes);
+        }
+        // runs past the end of the grid
+        let mut grid = OwnershipGrid::blank(8 * GRID_CELL, 8 * GRID_CELL);
+        assert!(grid.apply(&[60, 10, 1]).is_none());
+        assert!(grid.apply(&[0, 0xff]).is_none());
+    }
+}
@@ -0,0 +1,827 @@
+// The two ends of an online match over netcode.rs's packets. A Host seats
+// clients as they say hello, takes in their input and sends each one its
+// own stream of snapshots and paint diffs; it doesn't care what carries the
+// bytes, the dedicated server hands it UDP datagrams. A Client is the other
+// end, join.rs connects one for `--connect`. Clients bring their profile
+// card along; the host passes everyone's on so names and cosmetics match on
+// every screen, and sends the finished match for each client to keep in its
+// own history.
+//
+// With the F3 debug overlay up, NetMeter runs the match being played
+// through a host and a client back to back and shows what it would cost;
+// `net_sim` in the console puts lag and loss on that link.
+use crate::{
+    history::MatchRecord,
+    json::Json,
+    netcode::{
+        ClientSnapshot, OwnershipGrid, Packet, PaintSender, PaintSync, SnapshotReceiver,
+        SnapshotSender, SNAPSHOT_RATE,
+    },
+    profiles::Profile,
+    rng::Rng,
+    snapshot::PlayerState,
+    viewport::SCREEN_HEIGHT,
+    Bullet, MiniGames, Player, PlayerInput,
+};
+use raylib::prelude::*;
+use std::{collections::VecDeque, ops::Range};
+
+// UDP and IP headers, counted by the meter
+const PACKET_OVERHEAD: usize = 28;
+// a client silent for longer than this has stalled
+const STALL_TIME: f32 = 0.3;
+// what the clients simulate a frame as
+const FRAME_TIME: f32 = 1.0 / 60.0;
+// how much of the old ping a new measurement keeps
+const PING_SMOOTHING: f32 = 0.8;
+
+// How a client's connection looks from the host, or the host's from a
+// client (see Client::stats)
+#[derive(Debug, Clone)]
+pub struct ConnectionStats {
+    pub seat: usize,
+    // the client's profile name, "P<n>" for guests
+    pub name: String,
+    // round trip, seconds
+    pub ping: f32,
+    // share of the client's input packets lost over the last second
+    pub loss: f32,
+    // frames the client runs ahead of what it has heard back, the ones it
+    // replays when the host corrects it
+    pub rollback_frames: u32,
+    pub stalled: bool,
+}
+
+struct Remote<A> {
+    address: A,
+    seat: usize,
+    profile: Option<Profile>,
+    snapshots: SnapshotSender,
+    paint: PaintSender,
+    input: PlayerInput,
+    input_tick: u32,
+    last_heard: f32,
+    // snapshots waiting for an ack and when they went out
+    sent_at: VecDeque<(u16, f32)>,
+    ping: f32,
+    loss: f32,
+    // input packets counted since the loss window opened at `window_tick`
+    window_tick: u32,
+    window_received: u32,
+    window_started: f32,
+}
+
+impl<A> Remote<A> {
+    fn name(&self) -> String {
+        self.profile
+            .as_ref()
+            .map_or_else(|| format!("P{}", self.seat + 1), |p| p.name.clone())
+    }
+
+    fn acked(&mut self, sequence: u16, now: f32) {
+        let Some(at) = self.sent_at.iter().position(|(seq, _)| *seq == sequence) else {
+            return;
+        };
+        let (_, sent) = self.sent_at.drain(..=at).last().unwrap();
+        let round_trip = now - sent;
+        self.ping = if self.ping == 0.0 {
+            round_trip
+        } else {
+            self.ping * PING_SMOOTHING + round_trip * (1.0 - PING_SMOOTHING)
+        };
+    }
+
+    fn count_input(&mut self, tick: u32, now: f32) {
+        self.window_received += 1;
+        if now - self.window_started < 1.0 {
+            return;
+        }
+        let expected = tick.saturating_sub(self.window_tick).max(1);
+        self.loss = 1.0 - (self.window_received as f32 / expected as f32).min(1.0);
+        self.window_tick = tick;
+        self.window_received = 0;
+        self.window_started = now;
+    }
+}
+
+pub struct Host<A> {
+    // the seats clients can take, the rest are played on the host
+    seats: Range<usize>,
+    clients: Vec<Remote<A>>,
+    blank: OwnershipGrid,
+    // broadcasts left until the roster goes out again
+    roster_timer: u32,
+    // the last match's MatchOver packet and how many more times it is sent
+    match_over: Option<(Vec<u8>, u32)>,
+    // the round as last announced, and whether it changed since it went out
+    round: Option<RoundInfo>,
+    round_changed: bool,
+    // quick chats to relay with the next broadcast, sent once: a lost
+    // bubble isn't worth resending
+    chats: Vec<Vec<u8>>,
+}
+
+impl<A: PartialEq + Clone> Host<A> {
+    pub fn new(seats: Range<usize>, paint: &Image) -> Host<A> {
+        Host {
+            seats,
+            clients: Vec::new(),
+            blank: OwnershipGrid::blank(paint.width, paint.height),
+            roster_timer: 0,
+            match_over: None,
+            round: None,
+            round_changed: false,
+            chats: Vec::new(),
+        }
+    }
+
+    // Takes in one packet from `from` at `now` seconds, returns the reply
+    // if it needs one
+    pub fn receive(&mut self, from: A, bytes: &[u8], now: f32) -> Option<Vec<u8>> {
+        let known = self.clients.iter().position(|c| c.address == from);
+        match (Packet::decode(bytes)?, known) {
+            (Packet::Hello { .. }, Some(i)) => Some(
+                Packet::Welcome {
+                    seat: self.clients[i].seat as u8,
+                }
+                .encode(),
+            ),
+            (Packet::Hello { profile }, None) => {
+                let Some(seat) = self
+                    .seats
+                    .clone()
+                    .find(|seat| self.clients.iter().all(|c| c.seat != *seat))
+                else {
+                    return Some(Packet::Full.encode());
+                };
+                self.clients.push(Remote {
+                    address: from,
+                    seat,
+                    profile: Profile::from_card(&profile),
+                    snapshots: SnapshotSender::new(seat),
+                    paint: PaintSender::new(self.blank.clone()),
+                    input: PlayerInput::default(),
+                    input_tick: 0,
+                    last_heard: now,
+                    sent_at: VecDeque::new(),
+                    ping: 0.0,
+                    loss: 0.0,
+                    window_tick: 0,
+                    window_received: 0,
+                    window_started: now,
+                });
+                // everyone hears about the newcomer with the next snapshot
+                self.roster_timer = 0;
+                Some(Packet::Welcome { seat: seat as u8 }.encode())
+            }
+            (
+                Packet::Input {
+                    tick,
+                    input,
+                    snapshot_ack,
+                    paint_ack,
+                },
+                Some(i),
+            ) => {
+                let client = &mut self.clients[i];
+                client.last_heard = now;
+                // late packets don't take input back in time
+                if tick > client.input_tick {
+                    client.input = input;
+                    client.input_tick = tick;
+                    client.count_input(tick, now);
+                }
+                if let Some(ack) = snapshot_ack {
+                    client.snapshots.ack(ack);
+                    client.acked(ack, now);
+                }
+                client.paint.ack(paint_ack);
+                None
+            }
+            (Packet::QuickChat { message, .. }, Some(i)) => {
+                self.clients[i].last_heard = now;
+                // a client only speaks for its own seat
+                let seat = self.clients[i].seat as u8;
+                self.chats
+                    .push(Packet::QuickChat { seat, message }.encode());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    // The seat's latest input, None while nobody sits there
+    pub fn input(&self, seat: usize) -> Option<PlayerInput> {
+        self.clients
+            .iter()
+            .find(|c| c.seat == seat)
+            .map(|c| c.input)
+    }
+
+    pub fn seated(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn seat_of(&self, address: &A) -> Option<usize> {
+        self.clients
+            .iter()
+            .find(|c| c.address == *address)
+            .map(|c| c.seat)
+    }
+
+    // The profile the seat's client brought, None for guests and free seats
+    pub fn profile(&self, seat: usize) -> Option<&Profile> {
+        self.clients
+            .iter()
+            .find(|c| c.seat == seat)
+            .and_then(|c| c.profile.as_ref())
+    }
+
+    // Sends the finished match to every client with the next couple of
+    // seconds of snapshots, one of them is bound to get through
+    pub fn finish_match(&mut self, record: &MatchRecord) {
+        let packet = Packet::MatchOver {
+            record: record.to_json().to_string(),
+        };
+        self.match_over = Some((packet.encode(), SNAPSHOT_RATE as u32 * 2));
+    }
+
+    // The round the clients should show. It goes out with the next snapshot
+    // when the minigame changes or the round ends, once a second otherwise.
+    pub fn announce_round(&mut self, round: RoundInfo) {
+        self.round_changed |= self.round.map_or(true, |last| {
+            last.game != round.game || last.done != round.done
+        });
+        self.round = Some(round);
+    }
+
+    pub fn stats(&self, now: f32) -> Vec<ConnectionStats> {
+        self.clients
+            .iter()
+            .map(|c| ConnectionStats {
+                seat: c.seat,
+                name: c.name(),
+                ping: c.ping,
+                loss: c.loss,
+                rollback_frames: (c.ping / FRAME_TIME).ceil() as u32,
+                stalled: now - c.last_heard > STALL_TIME,
+            })
+            .collect()
+    }
+
+    // Frees the seats of clients not heard from in `timeout` seconds
+    pub fn drop_silent(&mut self, now: f32, timeout: f32) -> Vec<usize> {
+        let (gone, kept) = self
+            .clients
+            .drain(..)
+            .partition(|c| now - c.last_heard > timeout);
+        self.clients = kept;
+        gone.iter().map(|c: &Remote<A>| c.seat).collect()
+    }
+
+    // A State packet for every client, SNAPSHOT_RATE times a second
+    pub fn broadcast(
+        &mut self,
+        players: &[Player],
+        bullets: &[Bullet],
+        paint: &mut Image,
+        now: f32,
+    ) -> Vec<(A, Vec<u8>)> {
+        if self.clients.is_empty() {
+            return Vec::new();
+        }
+        let states: Vec<PlayerState> = players.iter().map(PlayerState::capture).collect();
+        let grid = OwnershipGrid::capture(paint, players);
+        let mut extra = Vec::new();
+        if let Some(round) = self
+            .round
+            .filter(|_| self.round_changed || self.roster_timer == 0)
+        {
+            self.round_changed = false;
+            extra.push(
+                Packet::Round {
+                    game: round.game,
+                    time_left: round.time_left,
+                    done: round.done,
+                }
+                .encode(),
+            );
+        }
+        if self.roster_timer == 0 {
+            self.roster_timer = SNAPSHOT_RATE as u32;
+            // guests are listed with an empty card so clients know the seat
+            // is taken
+            let cards = self
+                .clients
+                .iter()
+                .map(|c| {
+                    (
+                        c.seat as u8,
+                        c.profile.as_ref().map_or(String::new(), Profile::card),
+                    )
+                })
+                .collect();
+            extra.push(Packet::Roster { cards }.encode());
+        }
+        self.roster_timer -= 1;
+        extra.append(&mut self.chats);
+        if let Some((packet, left)) = &mut self.match_over {
+            extra.push(packet.clone());
+            *left -= 1;
+            if *left == 0 {
+                self.match_over = None;
+            }
+        }
+        let mut packets: Vec<(A, Vec<u8>)> = self
+            .clients
+            .iter_mut()
+            .map(|client| {
+                // the host has simulated the client's input up to here
+                client.paint.update(client.input_tick, grid.clone());
+                let snapshot = client.snapshots.encode(&states, bullets);
+                client.sent_at.push_back((client.snapshots.sequence(), now));
+                if client.sent_at.len() > SNAPSHOT_RATE as usize * 2 {
+                    client.sent_at.pop_front();
+                }
+                let packet = Packet::State {
+                    snapshot,
+                    paint: client.paint.outgoing().cloned().collect(),
+                };
+                (client.address.clone(), packet.encode())
+            })
+            .collect();
+        for packet in extra {
+            for client in &self.clients {
+                packets.push((client.address.clone(), packet.clone()));
+            }
+        }
+        packets
+    }
+}
+
+// Which minigame the host is playing and how far into the round it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundInfo {
+    pub game: MiniGames,
+    pub time_left: f32,
+    // decided, the next one starts shortly
+    pub done: bool,
+}
+
+// What a packet from the host meant to the client
+pub enum Received {
+    Nothing,
+    // no seat left, stop saying hello
+    Full,
+    // a match just ended, for the local MatchHistory; comes once per match
+    MatchOver(MatchRecord),
+    // another seat picked one of quickchat::MESSAGES
+    QuickChat { seat: usize, message: usize },
+}
+
+pub struct Client {
+    pub seat: Option<usize>,
+    // our own profile card, empty for a guest
+    card: String,
+    // the seated clients' profiles, as the host last listed them
+    roster: Vec<(usize, Option<Profile>)>,
+    // timestamp of the last match recorded, MatchOver comes more than once
+    last_match: u64,
+    tick: u32,
+    snapshots: SnapshotReceiver,
+    snapshot_ack: Option<u16>,
+    paint: PaintSync,
+    pub view: Option<ClientSnapshot>,
+    pub round: Option<RoundInfo>,
+    // seconds since connecting, counted by extrapolate
+    time: f32,
+    last_heard: f32,
+    // inputs waiting for the host to echo their tick and when they went out
+    sent_at: VecDeque<(u32, f32)>,
+    ping: f32,
+    loss: f32,
+    // snapshots counted since the loss window opened at `window_sequence`
+    window_sequence: Option<u16>,
+    window_received: u32,
+    window_started: f32,
+}
+
+impl Client {
+    pub fn new(paint: &Image, profile: Option<&Profile>) -> Client {
+        Client {
+            seat: None,
+            card: profile.map(Profile::card).unwrap_or_default(),
+            roster: Vec::new(),
+            last_match: 0,
+            tick: 0,
+            snapshots: SnapshotReceiver::default(),
+            snapshot_ack: None,
+            paint: PaintSync::new(OwnershipGrid::blank(paint.width, paint.height)),
+            view: None,
+            round: None,
+            time: 0.0,
+            last_heard: 0.0,
+            sent_at: VecDeque::new(),
+            ping: 0.0,
+            loss: 0.0,
+            window_sequence: None,
+            window_received: 0,
+            window_started: 0.0,
+        }
+    }
+
+    // The link to the host, None until it has seated us. The ping is timed
+    // from an input going out to the paint diff that names its tick, so it
+    // includes the wait for the host's next snapshot.
+    pub fn stats(&self) -> Option<ConnectionStats> {
+        Some(ConnectionStats {
+            seat: self.seat?,
+            name: "host".to_string(),
+            ping: self.ping,
+            loss: self.loss,
+            rollback_frames: (self.ping / FRAME_TIME).ceil() as u32,
+            stalled: self.time - self.last_heard > STALL_TIME,
+        })
+    }
+
+    fn echoed(&mut self, tick: u32) {
+        let Some(at) = self.sent_at.iter().position(|(t, _)| *t == tick) else {
+            return;
+        };
+        let (_, sent) = self.sent_at.drain(..=at).last().unwrap();
+        let round_trip = self.time - sent;
+        self.ping = if self.ping == 0.0 {
+            round_trip
+        } else {
+            self.ping * PING_SMOOTHING + round_trip * (1.0 - PING_SMOOTHING)
+        };
+    }
+
+    fn count_snapshot(&mut self, sequence: u16) {
+        self.window_received += 1;
+        let Some(first) = self.window_sequence else {
+            self.window_sequence = Some(sequence);
+            self.window_started = self.time;
+            return;
+        };
+        if self.time - self.window_started < 1.0 {
+            return;
+        }
+        let expected = (sequence.wrapping_sub(first) as u32).max(1);
+        self.loss = 1.0 - (self.window_received as f32 / expected as f32).min(1.0);
+        self.window_sequence = Some(sequence);
+        self.window_received = 0;
+        self.window_started = self.time;
+    }
+
+    pub fn hello(&self) -> Vec<u8> {
+        Packet::Hello {
+            profile: self.card.clone(),
+        }
+        .encode()
+    }
+
+    // The profile of whoever plays `seat`, None for guests and the host's
+    // own players
+    pub fn profile(&self, seat: usize) -> Option<&Profile> {
+        self.roster
+            .iter()
+            .find(|(s, _)| *s == seat)
+            .and_then(|(_, profile)| profile.as_ref())
+    }
+
+    // The seats taken on the host, as it last listed them
+    pub fn seated(&self) -> Vec<usize> {
+        self.roster.iter().map(|(seat, _)| *seat).collect()
+    }
+
+    // The seat's profile name, or P<n>
+    pub fn name(&self, seat: usize) -> String {
+        self.profile(seat)
+            .map_or_else(|| format!("P{}", seat + 1), |p| p.name.clone())
+    }
+
+    // Takes in one packet from the host, painting diffs onto `map`
+    pub fn receive(&mut self, bytes: &[u8], players: &[Player], map: &mut Image) -> Received {
+        let packet = Packet::decode(bytes);
+        if packet.is_some() {
+            self.last_heard = self.time;
+        }
+        match packet {
+            Some(Packet::Welcome { seat }) => self.seat = Some(seat as usize),
+            Some(Packet::Full) => return Received::Full,
+            Some(Packet::State { snapshot, paint }) => {
+                if let Some(snapshot) = self.snapshots.decode(&snapshot) {
+                    self.snapshot_ack = Some(snapshot.sequence);
+                    self.count_snapshot(snapshot.sequence);
+                    self.view = Some(snapshot);
+                }
+                if let Some(tick) = paint.iter().map(|diff| diff.input_tick).max() {
+                    self.echoed(tick);
+                }
+                for diff in &paint {
+                    self.paint.receive(map, players, diff);
+                }
+            }
+            Some(Packet::Roster { cards }) => {
+                self.roster = cards
+                    .iter()
+                    .map(|(seat, card)| (*seat as usize, Profile::from_card(card)))
+                    .collect();
+            }
+            Some(Packet::Round {
+                game,
+                time_left,
+                done,
+            }) => {
+                self.round = Some(RoundInfo {
+                    game,
+                    time_left,
+                    done,
+                })
+            }
+            // our own bubble went up when we sent it
+            Some(Packet::QuickChat { seat, message }) if self.seat != Some(seat as usize) => {
+                return Received::QuickChat {
+                    seat: seat as usize,
+                    message: message as usize,
+                };
+            }
+            Some(Packet::MatchOver { record }) => {
+                let record = Json::parse(&record)
+                    .ok()
+                    .and_then(|json| MatchRecord::from_json(&json));
+                if let Some(record) = record.filter(|r| r.timestamp != self.last_match) {
+                    self.last_match = record.timestamp;
+                    return Received::MatchOver(record);
+                }
+            }
+            _ => {}
+        }
+        Received::Nothing
+    }
+
+    // This frame's input, acknowledging everything received so far
+    pub fn input(&mut self, input: PlayerInput) -> Vec<u8> {
+        self.tick += 1;
+        self.sent_at.push_back((self.tick, self.time));
+        // a second of inputs the host never echoed is a lost cause
+        if self.sent_at.len() > (1.0 / FRAME_TIME) as usize {
+            self.sent_at.pop_front();
+        }
+        Packet::Input {
+            tick: self.tick,
+            input,
+            snapshot_ack: self.snapshot_ack,
+            paint_ack: self.paint.ack(),
+        }
+        .encode()
+    }
+
+    // One of quickchat::MESSAGES from our seat, None until we have one
+    pub fn chat(&self, message: usize) -> Option<Vec<u8>> {
+        Some(
+            Packet::QuickChat {
+                seat: self.seat? as u8,
+                message: message as u8,
+            }
+            .encode(),
+        )
+    }
+
+    // One of our own splats, shown before the host confirms it
+    pub fn predict(&mut self, map: &mut Image, player: &Player, point: Vector2) {
+        self.paint.predict(
+            map,
+            self.tick,
+            point,
+            player.color,
+            player.splat,
+            player.paint_radius,
+        );
+    }
+
+    // Moves the bullets and the round's clock on between packets, and the
+    // clock the link stats are timed by
+    pub fn extrapolate(&mut self, dt: f32) {
+        self.time += dt;
+        if let Some(round) = self.round.as_mut().filter(|r| !r.done) {
+            round.time_left -= dt;
+        }
+        if let Some(view) = &mut self.view {
+            for bullet in &mut view.bullets {
+                bullet.speed.y += bullet.gravity * dt;
+                bullet.rect.x += bullet.speed.x * dt;
+                bullet.rect.y += bullet.speed.y * dt;
+            }
+        }
+    }
+
+    pub fn pending_splats(&self) -> usize {
+        self.paint.pending()
+    }
+}
+
+// One direction of the loopback, with made-up lag and loss
+#[derive(Default)]
+struct Pipe {
+    // delivery time and packet
+    queue: VecDeque<(f32, Vec<u8>)>,
+}
+
+impl Pipe {
+    fn send(&mut self, packet: Vec<u8>, now: f32, conditions: &LinkConditions, rng: &mut Rng) {
+        if rng.next_f32() >= conditions.loss {
+            self.queue.push_back((now + conditions.lag / 2.0, packet));
+        }
+    }
+
+    fn deliver(&mut self, now: f32) -> Vec<Vec<u8>> {
+        let mut arrived = Vec::new();
+        while self.queue.front().is_some_and(|(at, _)| *at <= now) {
+            arrived.push(self.queue.pop_front().unwrap().1);
+        }
+        arrived
+    }
+}
+
+// Lag (round trip, seconds) and loss (0 to 1) put on the meter's loopback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkConditions {
+    pub lag: f32,
+    pub loss: f32,
+}
+
+struct Link {
+    host: Host<()>,
+    client: Client,
+    // the client's copy of the paint map
+    client_map: Image,
+    to_host: Pipe,
+    to_client: Pipe,
+}
+
+// Runs the current match through a loopback host and client, measures the
+// bytes per second a client would cost the host and draws what the client
+// ends up seeing over the real thing. Seat 0 stays on the host.
+pub struct NetMeter {
+    link: Option<Link>,
+    pub conditions: LinkConditions,
+    rng: Rng,
+    time: f32,
+    timer: f32,
+    window: f32,
+    bytes: usize,
+    // last full second, bytes per second
+    rate: f32,
+    // the last match result the client got
+    result: Option<MatchRecord>,
+}
+
+impl Default for NetMeter {
+    fn default() -> NetMeter {
+        NetMeter {
+            link: None,
+            conditions: LinkConditions::default(),
+            // only decides which packets get lost
+            rng: Rng::new(0),
+            time: 0.0,
+            timer: 0.0,
+            window: 0.0,
+            bytes: 0,
+            rate: 0.0,
+            result: None,
+        }
+    }
+}
+
+impl NetMeter {
+    // Starts over, keeping the simulated conditions
+    pub fn reset(&mut self) {
+        *self = NetMeter {
+            conditions: self.conditions,
+            ..NetMeter::default()
+        };
+    }
+
+    pub fn stats(&self) -> Vec<ConnectionStats> {
+        self.link
+            .as_ref()
+            .map_or_else(Vec::new, |link| link.host.stats(self.time))
+    }
+
+    // A splat that the client would have painted itself
+    pub fn predict(&mut self, player: &Player, point: Vector2) {
+        if let Some(link) = &mut self.link {
+            if link.client.seat == Some(player.number as usize) {
+                link.client.predict(&mut link.client_map, player, point);
+            }
+        }
+    }
+
+    // The match the host just recorded, sent on to the client
+    pub fn finish_match(&mut self, record: &MatchRecord) {
+        if let Some(link) = &mut self.link {
+            link.host.finish_match(record);
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        dt: f32,
+        players: &[Player],
+        profiles: &[Profile],
+        bullets: &[Bullet],
+        paint: &mut Image,
+    ) {
+        self.time += dt;
+        let link = self.link.get_or_insert_with(|| {
+            let mut host = Host::new(1..players.len(), paint);
+            // the client plays P2 with P2's profile
+            let profile = players.get(1).and_then(|p| profiles.get(p.profile?));
+            let mut client = Client::new(paint, profile);
+            let mut client_map =
+                Image::gen_image_color(paint.width, paint.height, Color::WHITE.alpha(0.0));
+            if let Some(welcome) = host.receive((), &client.hello(), 0.0) {
+                client.receive(&welcome, players, &mut client_map);
+            }
+            Link {
+                host,
+                client,
+                client_map,
+                to_host: Pipe::default(),
+                to_client: Pipe::default(),
+            }
+        });
+        self.window += dt;
+        if self.window >= 1.0 {
+            self.rate = self.bytes as f32 / self.window;
+            self.window = 0.0;
+            self.bytes = 0;
+        }
+        link.client.extrapolate(dt);
+        if let Some(seat) = link.client.seat {
+            let input = link.client.input(players[seat].input);
+            link.to_host
+                .send(input, self.time, &self.conditions, &mut self.rng);
+        }
+        for packet in link.to_host.deliver(self.time) {
+            link.host.receive((), &packet, self.time);
+        }
+        for packet in link.to_client.deliver(self.time) {
+            // the host has already put the match in this history
+            if let Received::MatchOver(record) =
+                link.client.receive(&packet, players, &mut link.client_map)
+            {
+                self.result = Some(record);
+            }
+        }
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return;
+        }
+        self.timer += 1.0 / SNAPSHOT_RATE;
+        for (_, packet) in link.host.broadcast(players, bullets, paint, self.time) {
+            self.bytes += packet.len() + PACKET_OVERHEAD;
+            link.to_client
+                .send(packet, self.time, &self.conditions, &mut self.rng);
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, clients: usize) {
+        let Some(link) = &self.link else {
+            return;
+        };
+        if let Some(view) = &link.client.view {
+            for (seat, player) in view.players.iter().enumerate().filter(|(_, p)| !p.dead) {
+                let (x, y) = (player.position.x as i32, player.position.y as i32);
+                d.draw_circle_lines(x, y, 6.0, Color::RED);
+                if let Some(profile) = link.client.profile(seat) {
+                    d.draw_text(&profile.name, x + 8, y - 8, 10, Color::RED);
+                }
+            }
+            for bullet in &view.bullets {
+                d.draw_rectangle_lines_ex(bullet.rect, 1.0, bullet.color);
+            }
+        }
+        let text = format!(
+            "net: {:.1} kB/s per client, host upload {:.1} kB/s, {} splats predicted",
+            self.rate / 1000.0,
+            self.rate / 1000.0 * clients as f32,
+            link.client.pending_splats(),
+        );
+        d.draw_text(&text, 10, SCREEN_HEIGHT - 55, 20, Color::RED);
+        // what the client would have written to its history
+        if let Some(record) = &self.result {
+            let winner = record
+                .winner()
+                .map_or("nobody", |i| record.players[i].name.as_str());
+            let text = format!(
+                "client recorded the match: {} rounds, {} won",
+                record.rounds.len(),
+                winner
+            );
+            d.draw_text(&text, 10, SCREEN_HEIGHT - 80, 20, Color::RED);
+        }
+    }
+}
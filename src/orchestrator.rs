@@ -0,0 +1,171 @@
+// Match flow decisions that depend on the kind of match being played: how
+// long a round lasts and which minigame comes next. A Daily challenge pins
+// both so everyone plays the same match; --mode locks the minigame. Otherwise
+// the rotation policy picked in the lobby decides, drawing from the match rng
+// so a seed replays the same order.
+use crate::{
+    daily::DailyChallenge,
+    minigame_config::MiniGameConfigs,
+    mutators::{self, Mutator},
+    rng::Rng,
+    settings::Settings,
+    MiniGames,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationPolicy {
+    // MiniGames::next_in_rotation, the original order
+    #[default]
+    Alternate,
+    // every minigame once, in random order, before any comes up again
+    Shuffle,
+    // random, weighted by the lobby votes
+    Vote,
+    // the host's playlist, looped
+    Playlist,
+}
+
+impl RotationPolicy {
+    pub const ALL: [RotationPolicy; 4] = [
+        RotationPolicy::Alternate,
+        RotationPolicy::Shuffle,
+        RotationPolicy::Vote,
+        RotationPolicy::Playlist,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RotationPolicy::Alternate => "Alternate",
+            RotationPolicy::Shuffle => "Shuffle",
+            RotationPolicy::Vote => "Vote",
+            RotationPolicy::Playlist => "Playlist",
+        }
+    }
+
+    pub fn next(&self) -> RotationPolicy {
+        RotationPolicy::ALL[(*self as usize + 1) % RotationPolicy::ALL.len()]
+    }
+}
+
+pub struct MatchOrchestrator {
+    pub configs: MiniGameConfigs,
+    pub locked_mode: Option<MiniGames>,
+    pub daily: Option<DailyChallenge>,
+    pub policy: RotationPolicy,
+    // one vote per lobby seat, None for no preference
+    pub votes: [Option<MiniGames>; 4],
+    pub playlist: Vec<MiniGames>,
+    // minigames still to come in the current shuffle
+    bag: Vec<MiniGames>,
+}
+
+impl MatchOrchestrator {
+    pub fn new(locked_mode: Option<MiniGames>) -> MatchOrchestrator {
+        MatchOrchestrator {
+            configs: MiniGameConfigs::load(),
+            locked_mode,
+            daily: None,
+            policy: RotationPolicy::default(),
+            votes: [None; 4],
+            playlist: Vec::new(),
+            bag: Vec::new(),
+        }
+    }
+
+    // Minigame for the first round of a fresh match; `current` is kept when
+    // the policy has no opinion
+    pub fn first_game(&mut self, current: MiniGames, rng: &mut Rng) -> MiniGames {
+        self.bag.clear();
+        match (&self.daily, self.locked_mode, self.policy) {
+            (Some(daily), _, _) => daily.game_for_round(0),
+            (None, Some(mode), _) => mode,
+            (None, None, RotationPolicy::Alternate) => current,
+            (None, None, RotationPolicy::Shuffle) => self.draw_from_bag(None, rng),
+            (None, None, RotationPolicy::Vote) => self.draw_voted(rng),
+            (None, None, RotationPolicy::Playlist) => {
+                self.playlist.first().copied().unwrap_or(current)
+            }
+        }
+    }
+
+    // Round length from the minigame config or the player's setting; the
+    // Daily ignores local settings so its rounds are the same for everyone
+    pub fn round_time(&self, game: MiniGames, settings: &Settings, mutators: &[Mutator]) -> f32 {
+        let base = match (&self.daily, settings.round_time(game)) {
+            (None, Some(seconds)) => seconds,
+            _ => self.configs.get(game).round_time,
+        };
+        base * mutators::round_time_scale(mutators)
+    }
+
+    // First minigame of a rematch. Unlike first_game the order is mixed up:
+    // the playlist is reshuffled and the alternation starts at random. A
+    // Daily replays its fixed order.
+    pub fn rematch(&mut self, current: MiniGames, rng: &mut Rng) -> MiniGames {
+        if self.daily.is_none() && self.locked_mode.is_none() {
+            match self.policy {
+                RotationPolicy::Alternate => {
+                    let alternating: Vec<MiniGames> = MiniGames::ALL
+                        .into_iter()
+                        .filter(|game| game.next_in_rotation() != *game)
+                        .collect();
+                    return alternating[rng.index(alternating.len())];
+                }
+                RotationPolicy::Playlist => rng.shuffle(&mut self.playlist),
+                RotationPolicy::Shuffle | RotationPolicy::Vote => {}
+            }
+        }
+        self.first_game(current, rng)
+    }
+
+    pub fn next_game(
+        &mut self,
+        current: MiniGames,
+        rounds_played: usize,
+        rng: &mut Rng,
+    ) -> MiniGames {
+        match (&self.daily, self.locked_mode, self.policy) {
+            (Some(daily), _, _) => daily.game_for_round(rounds_played),
+            (None, Some(mode), _) => mode,
+            (None, None, RotationPolicy::Shuffle) => self.draw_from_bag(Some(current), rng),
+            (None, None, RotationPolicy::Vote) => self.draw_voted(rng),
+            (None, None, RotationPolicy::Playlist) if !self.playlist.is_empty() => {
+                self.playlist[rounds_played % self.playlist.len()]
+            }
+            (None, None, _) => current.next_in_rotation(),
+        }
+    }
+
+    fn draw_from_bag(&mut self, last: Option<MiniGames>, rng: &mut Rng) -> MiniGames {
+        if self.bag.is_empty() {
+            self.bag = MiniGames::ALL.to_vec();
+            rng.shuffle(&mut self.bag);
+            // no back-to-back repeat where one shuffle meets the next
+            if self.bag.last() == last.as_ref() {
+                self.bag.swap(0, MiniGames::ALL.len() - 1);
+            }
+        }
+        self.bag.pop().unwrap()
+    }
+
+    // Each vote adds one to a minigame's weight; with no votes every
+    // minigame is equally likely
+    fn draw_voted(&self, rng: &mut Rng) -> MiniGames {
+        let votes = |game: MiniGames| self.votes.iter().filter(|v| **v == Some(game)).count();
+        let total: usize = MiniGames::ALL.into_iter().map(votes).sum();
+        if total == 0 {
+            return MiniGames::ALL[rng.index(MiniGames::ALL.len())];
+        }
+        let mut pick = rng.index(total);
+        MiniGames::ALL
+            .into_iter()
+            .find(|game| match pick.checked_sub(votes(*game)) {
+                Some(rest) => {
+                    pick = rest;
+                    false
+                }
+                None => true,
+            })
+            .unwrap()
+    }
+}
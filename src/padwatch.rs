@@ -0,0 +1,116 @@
+// Keeps a dead controller battery from deciding a match. When a seated
+// gamepad disappears mid-match the round freezes, timers and slow motion
+// included, since nothing advances the GameClock while it is held. It
+// carries on once every empty seat is claimed again, either by its own pad
+// coming back or by a spare pad pressing A, after a 3-2-1 countdown.
+use crate::{
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    InputType, Player,
+};
+use raylib::prelude::*;
+
+const MAX_PADS: usize = 4;
+const COUNTDOWN: f32 = 3.0;
+const CLAIM_BUTTON: consts::GamepadButton = consts::GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN;
+
+#[derive(Debug, Default)]
+pub struct PadWatch {
+    // seats whose pad was connected at some point this match; seats given
+    // a pad that was never plugged in are not waited for
+    seen: [bool; 4],
+    // seats waiting for a pad, in the order they lost theirs
+    lost: Vec<usize>,
+    // real seconds left before play resumes
+    countdown: Option<f32>,
+}
+
+impl PadWatch {
+    // Between matches, so the next one starts with nobody waited for
+    pub fn reset(&mut self) {
+        *self = PadWatch::default();
+    }
+
+    pub fn update(&mut self, rl: &RaylibHandle, players: &mut [Player]) {
+        for (seat, player) in players.iter().enumerate() {
+            let InputType::Controller(pad) = player.controls else {
+                continue;
+            };
+            let connected = rl.is_gamepad_available(pad as i32);
+            if connected {
+                self.seen[seat % 4] = true;
+            } else if self.seen[seat % 4] && !self.lost.contains(&seat) {
+                self.lost.push(seat);
+                self.countdown = None;
+            }
+        }
+
+        // the seat's own pad coming back, else a spare one pressing A
+        let waiting = self.lost.len();
+        self.lost.retain(|seat| match players[*seat].controls {
+            InputType::Controller(pad) => !rl.is_gamepad_available(pad as i32),
+            InputType::Keyboard(_) => false,
+        });
+        if let Some(&seat) = self.lost.first() {
+            let spare = (0..MAX_PADS).find(|pad| {
+                rl.is_gamepad_available(*pad as i32)
+                    && rl.is_gamepad_button_pressed(*pad as i32, CLAIM_BUTTON)
+                    && !players
+                        .iter()
+                        .any(|p| matches!(p.controls, InputType::Controller(c) if c == *pad))
+            });
+            if let Some(pad) = spare {
+                players[seat].controls = InputType::Controller(pad);
+                self.lost.remove(0);
+            }
+        }
+        if self.lost.is_empty() && waiting > 0 {
+            self.countdown = Some(COUNTDOWN);
+        }
+
+        if let Some(left) = &mut self.countdown {
+            *left -= rl.get_frame_time();
+            if *left <= 0.0 {
+                self.countdown = None;
+            }
+        }
+    }
+
+    // Whether the round has to stay frozen this frame
+    pub fn holds(&self) -> bool {
+        !self.lost.is_empty() || self.countdown.is_some()
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player]) {
+        let (text, hint, color) = match (self.lost.first(), self.countdown) {
+            (Some(&seat), _) => (
+                format!("P{}'s controller disconnected", seat + 1),
+                format!(
+                    "Reconnect it, or press A on another pad to take P{}",
+                    seat + 1
+                ),
+                players.get(seat).map_or(Color::WHITE, |p| p.color),
+            ),
+            (None, Some(left)) => (
+                left.ceil().to_string(),
+                "Get ready".to_string(),
+                Color::WHITE,
+            ),
+            (None, None) => return,
+        };
+        d.draw_rectangle(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT, Color::BLACK.alpha(0.5));
+        d.draw_text(
+            &text,
+            SCREEN_WIDTH / 2 - d.measure_text(&text, 35) / 2,
+            SCREEN_HEIGHT / 2 - 60,
+            35,
+            color,
+        );
+        d.draw_text(
+            &hint,
+            SCREEN_WIDTH / 2 - d.measure_text(&hint, 20) / 2,
+            SCREEN_HEIGHT / 2,
+            20,
+            Color::WHITE,
+        );
+    }
+}
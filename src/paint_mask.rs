@@ -0,0 +1,81 @@
+// Unpaintable parts of a level, e.g. sky and pits nobody can reach, from the
+// level file's "paint_mask" image: black or transparent pixels can't take
+// paint. Paint landing there is wiped before the paint map is shown or
+// scored, so it never counts towards anyone's coverage.
+use crate::viewport::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use raylib::prelude::*;
+
+#[derive(Debug, Default)]
+pub struct PaintMask {
+    // one flag per virtual screen pixel, empty when the whole level takes paint
+    blocked: Vec<bool>,
+    // the blocked pixels as (y, x, width) runs at the paint map's size, which
+    // depends on the quality setting
+    runs: Vec<(i32, i32, i32)>,
+    runs_size: (i32, i32),
+}
+
+impl PaintMask {
+    pub fn new(mut image: Image) -> PaintMask {
+        image.resize_nn(SCREEN_WIDTH, SCREEN_HEIGHT);
+        let mut blocked = Vec::with_capacity((SCREEN_WIDTH * SCREEN_HEIGHT) as usize);
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let color = image.get_color(x, y);
+                let brightness = color.r.max(color.g).max(color.b);
+                blocked.push(color.a < 128 || brightness < 128);
+            }
+        }
+        PaintMask {
+            blocked,
+            ..Default::default()
+        }
+    }
+
+    // Whether the virtual screen position can't take paint
+    pub fn blocks(&self, point: Vector2) -> bool {
+        let (x, y) = (point.x as i32, point.y as i32);
+        x >= 0 && y >= 0 && x < SCREEN_WIDTH && y < SCREEN_HEIGHT && self.blocked_at(x, y)
+    }
+
+    fn blocked_at(&self, x: i32, y: i32) -> bool {
+        self.blocked
+            .get((y * SCREEN_WIDTH + x) as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn update_runs(&mut self, width: i32, height: i32) {
+        if self.runs_size == (width, height) {
+            return;
+        }
+        self.runs.clear();
+        self.runs_size = (width, height);
+        for y in 0..height {
+            let source_y = y * SCREEN_HEIGHT / height;
+            let mut start = None;
+            for x in 0..=width {
+                let blocked = x < width && self.blocked_at(x * SCREEN_WIDTH / width, source_y);
+                match (blocked, start) {
+                    (true, None) => start = Some(x),
+                    (false, Some(from)) => {
+                        self.runs.push((y, from, x - from));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Wipes whatever paint landed on the blocked pixels of the paint map
+    pub fn apply(&mut self, image: &mut Image) {
+        if self.blocked.is_empty() {
+            return;
+        }
+        self.update_runs(image.width, image.height);
+        for (y, x, width) in &self.runs {
+            image.draw_rectangle(*x, *y, *width, 1, Color::WHITE.alpha(0.0));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Pickups: neutral items that appear on top of the level's platforms and are
+// collected by touching them. What a pickup does is reported as game events
+// so the game loop applies it like any other hit or knockout.
+use crate::{events::GameEvent, rng::Rng, EnvItem, Player};
+use raylib::prelude::*;
+
+const PICKUP_SIZE: f32 = 24.0;
+// a pickup nobody grabs disappears again
+const PICKUP_LIFETIME: f32 = 12.0;
+const BOMB_FUSE: f32 = 2.0;
+// size of the bomb's splat against a normal one
+pub const BOMB_PAINT_SCALE: f32 = 24.0;
+// the big brush multiplies its holder's paint radius for the rest of the round
+pub const BIG_BRUSH_SCALE: f32 = 1.6;
+// platforms narrower than this don't get pickups
+const MIN_PLATFORM_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickupKind {
+    // detonates a huge splat of the holder's colour after a short fuse
+    PaintBomb,
+    // paints with bigger splats until the round ends
+    BigBrush,
+}
+
+impl PickupKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PickupKind::PaintBomb => "a paint bomb",
+            PickupKind::BigBrush => "a big brush",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub position: Vector2,
+    pub time_left: f32,
+}
+
+impl Pickup {
+    pub fn rect(&self) -> Rectangle {
+        Rectangle::new(
+            self.position.x - PICKUP_SIZE / 2.0,
+            self.position.y - PICKUP_SIZE / 2.0,
+            PICKUP_SIZE,
+            PICKUP_SIZE,
+        )
+    }
+}
+
+// A grabbed paint bomb counting down on its holder
+#[derive(Debug, Clone)]
+struct LitBomb {
+    player: usize,
+    fuse: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct Pickups {
+    pub items: Vec<Pickup>,
+    bombs: Vec<LitBomb>,
+    spawn_timer: f32,
+}
+
+impl Pickups {
+    // Empties the arena, the next pickup comes `interval` seconds later
+    pub fn reset(&mut self, interval: f32) {
+        self.items.clear();
+        self.bombs.clear();
+        self.spawn_timer = interval;
+    }
+
+    // Spawns a pickup every `interval` seconds (never when it is 0), hands
+    // them to whoever touches them and burns the fuses of lit bombs
+    pub fn update(
+        &mut self,
+        players: &[Player],
+        ops: &[EnvItem],
+        interval: f32,
+        rng: &mut Rng,
+        dt: f32,
+    ) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if interval > 0.0 {
+            self.spawn_timer -= dt;
+            if self.spawn_timer <= 0.0 {
+                self.spawn_timer = interval;
+                if let Some(position) = spawn_point(ops, rng) {
+                    let kind = if rng.index(2) == 0 {
+                        PickupKind::PaintBomb
+                    } else {
+                        PickupKind::BigBrush
+                    };
+                    self.items.push(Pickup {
+                        kind,
+                        position,
+                        time_left: PICKUP_LIFETIME,
+                    });
+                }
+            }
+        }
+
+        for pickup in &mut self.items {
+            pickup.time_left -= dt;
+            let taker = players.iter().position(|p| {
+                !p.dead && p.get_collision_rect().check_collision_recs(&pickup.rect())
+            });
+            if let Some(player) = taker {
+                pickup.time_left = 0.0;
+                events.push(GameEvent::PickedUp {
+                    player,
+                    kind: pickup.kind,
+                });
+                match pickup.kind {
+                    PickupKind::PaintBomb => self.bombs.push(LitBomb {
+                        player,
+                        fuse: BOMB_FUSE,
+                    }),
+                    // applied by the game loop on the PickedUp event
+                    PickupKind::BigBrush => {}
+                }
+            }
+        }
+        self.items.retain(|pickup| pickup.time_left > 0.0);
+
+        for bomb in &mut self.bombs {
+            bomb.fuse -= dt;
+            if bomb.fuse <= 0.0 {
+                events.push(GameEvent::PaintBomb {
+                    player: bomb.player,
+                    position: players[bomb.player].position,
+                });
+            }
+        }
+        self.bombs.retain(|bomb| bomb.fuse > 0.0);
+        events
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player], time: f32) {
+        for pickup in &self.items {
+            // bob up and down, and blink in the last seconds
+            if pickup.time_left < 2.0 && (pickup.time_left * 8.0) as i32 % 2 == 0 {
+                continue;
+            }
+            let center = pickup.position + Vector2::new(0.0, (time * 3.0).sin() * 4.0);
+            d.draw_circle_v(center, PICKUP_SIZE / 2.0, Color::DARKGRAY);
+            match pickup.kind {
+                PickupKind::PaintBomb => {
+                    d.draw_circle_v(center, PICKUP_SIZE / 3.0, Color::MAGENTA);
+                    d.draw_line_ex(
+                        center - Vector2::new(0.0, PICKUP_SIZE / 2.0),
+                        center - Vector2::new(-6.0, PICKUP_SIZE / 2.0 + 8.0),
+                        3.0,
+                        Color::BROWN,
+                    );
+                }
+                PickupKind::BigBrush => {
+                    d.draw_circle_v(center, PICKUP_SIZE / 3.0, Color::SKYBLUE);
+                    d.draw_circle_v(center, PICKUP_SIZE / 6.0, Color::WHITE);
+                }
+            }
+        }
+        for bomb in &self.bombs {
+            let Some(player) = players.get(bomb.player) else {
+                continue;
+            };
+            let text = format!("{:.1}", bomb.fuse);
+            let above = player.position - Vector2::new(0.0, player.height / 2.0 + 40.0);
+            d.draw_circle_v(above, 14.0, player.color);
+            d.draw_text(
+                &text,
+                above.x as i32 - d.measure_text(&text, 15) / 2,
+                above.y as i32 - 7,
+                15,
+                Color::BLACK,
+            );
+        }
+    }
+}
+
+// Somewhere on top of a random platform wide enough to stand on
+fn spawn_point(ops: &[EnvItem], rng: &mut Rng) -> Option<Vector2> {
+    let platforms: Vec<&EnvItem> = ops
+        .iter()
+        .filter(|op| {
+            op.layer.collides()
+                && op.rect.width >= MIN_PLATFORM_WIDTH
+                && op.rect.y > PICKUP_SIZE * 2.0
+        })
+        .collect();
+    if platforms.is_empty() {
+        return None;
+    }
+    let platform = platforms[rng.index(platforms.len())].rect;
+    Some(Vector2::new(
+        rng.range(
+            platform.x + PICKUP_SIZE,
+            platform.x + platform.width - PICKUP_SIZE,
+        ),
+        platform.y - PICKUP_SIZE,
+    ))
+}
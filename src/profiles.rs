@@ -0,0 +1,292 @@
+// Named player profiles with lifetime stats, kept in save/profiles.json
+use crate::{
+    cosmetics::{Cosmetic, Skin, SplatStyle, VictoryPose},
+    json::Json,
+    storage, MiniGames, Player,
+};
+
+pub const PROFILES_FILE: &str = "profiles.json";
+pub const MAX_NAME_LEN: usize = 12;
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub name: String,
+    pub matches_played: u32,
+    pub matches_won: u32,
+    pub rounds_won: [u32; MiniGames::ALL.len()],
+    pub total_coverage: f32, // summed ColorTheMap round shares, 1.0 == a whole round's paint
+    pub times_eliminated: u32, // times knocked out in Dodge
+    pub knockouts: u32,      // other players knocked out with a throw
+    pub unlocked: Vec<String>, // cosmetic keys, kept even if a milestone changes later
+    pub tutorials_done: [bool; MiniGames::ALL.len()],
+    pub skin: Skin,
+    pub splat: SplatStyle,
+    pub pose: VictoryPose,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Profile {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.as_str().into()),
+            ("matches_played", self.matches_played.into()),
+            ("matches_won", self.matches_won.into()),
+            (
+                "rounds_won",
+                Json::Object(
+                    MiniGames::ALL
+                        .iter()
+                        .map(|game| (format!("{:?}", game), self.rounds_won[game.index()].into()))
+                        .collect(),
+                ),
+            ),
+            ("total_coverage", self.total_coverage.into()),
+            ("times_eliminated", self.times_eliminated.into()),
+            ("knockouts", self.knockouts.into()),
+            ("unlocked", self.unlocked.clone().into()),
+            (
+                "tutorials_done",
+                Json::Array(
+                    MiniGames::ALL
+                        .iter()
+                        .filter(|game| self.tutorials_done[game.index()])
+                        .map(|game| format!("{:?}", game).as_str().into())
+                        .collect(),
+                ),
+            ),
+            ("skin", self.skin.key().into()),
+            ("splat", self.splat.key().into()),
+            ("pose", self.pose.key().into()),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Option<Profile> {
+        let mut profile = Profile::new(json.get("name")?.as_str()?);
+        profile.matches_played = json.u32_or("matches_played", 0);
+        profile.matches_won = json.u32_or("matches_won", 0);
+        if let Some(rounds_won) = json.get("rounds_won") {
+            for game in MiniGames::ALL {
+                profile.rounds_won[game.index()] = rounds_won.u32_or(&format!("{:?}", game), 0);
+            }
+        }
+        profile.total_coverage = json.f32_or("total_coverage", 0.0);
+        // saved as "eliminations" before knockouts were counted too
+        profile.times_eliminated = json.u32_or("times_eliminated", json.u32_or("eliminations", 0));
+        profile.knockouts = json.u32_or("knockouts", 0);
+        profile.unlocked = json
+            .array("unlocked")
+            .iter()
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        for key in json.array("tutorials_done") {
+            if let Some(game) = key.as_str().and_then(MiniGames::from_key) {
+                profile.tutorials_done[game.index()] = true;
+            }
+        }
+        profile.skin = Skin::from_key(json.str_or("skin", "")).unwrap_or_default();
+        profile.splat = SplatStyle::from_key(json.str_or("splat", "")).unwrap_or_default();
+        profile.pose = VictoryPose::from_key(json.str_or("pose", "")).unwrap_or_default();
+        Some(profile)
+    }
+
+    // What other players see of the profile online: the name, cosmetics and
+    // match record, not the rest of the save
+    pub fn card(&self) -> String {
+        Json::object(vec![
+            ("name", self.name.as_str().into()),
+            ("matches_played", self.matches_played.into()),
+            ("matches_won", self.matches_won.into()),
+            ("skin", self.skin.key().into()),
+            ("splat", self.splat.key().into()),
+            ("pose", self.pose.key().into()),
+        ])
+        .to_string()
+    }
+
+    // A card from over the network; None for guests and garbage
+    pub fn from_card(card: &str) -> Option<Profile> {
+        let mut profile = Profile::from_json(&Json::parse(card).ok()?)?;
+        profile.name = profile.name.trim().chars().take(MAX_NAME_LEN).collect();
+        if profile.name.is_empty() {
+            return None;
+        }
+        Some(profile)
+    }
+
+    // Unlocks every cosmetic whose milestone has been reached, returning
+    // descriptions of the new ones
+    pub fn refresh_unlocks(&mut self) -> Vec<String> {
+        let mut unlocked = Vec::new();
+        unlocked.extend(self.unlock_all::<Skin>());
+        unlocked.extend(self.unlock_all::<SplatStyle>());
+        unlocked.extend(self.unlock_all::<VictoryPose>());
+        unlocked
+    }
+
+    // "next: <cosmetic> (<milestone>)" for the first cosmetic still locked
+    pub fn next_unlock(&self) -> Option<String> {
+        self.next_locked::<Skin>()
+            .or_else(|| self.next_locked::<SplatStyle>())
+            .or_else(|| self.next_locked::<VictoryPose>())
+    }
+
+    fn next_locked<T: Cosmetic>(&self) -> Option<String> {
+        T::ALL
+            .iter()
+            .find(|cosmetic| !cosmetic.is_unlocked(self))
+            .and_then(|cosmetic| {
+                let milestone = cosmetic.milestone()?;
+                Some(format!(
+                    "next: {} {} ({})",
+                    cosmetic.name(),
+                    T::KIND,
+                    milestone.describe()
+                ))
+            })
+    }
+
+    fn unlock_all<T: Cosmetic>(&mut self) -> Vec<String> {
+        let mut unlocked = Vec::new();
+        for cosmetic in T::ALL {
+            let Some(milestone) = cosmetic.milestone() else {
+                continue;
+            };
+            if milestone.reached(self) && !cosmetic.is_unlocked(self) {
+                self.unlocked.push(cosmetic.key());
+                unlocked.push(format!("{} {}", cosmetic.name(), T::KIND));
+            }
+        }
+        unlocked
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProfileStore {
+    pub profiles: Vec<Profile>,
+}
+
+impl ProfileStore {
+    pub fn load() -> Self {
+        let profiles = storage::read_json(PROFILES_FILE)
+            .map(|json| {
+                json.array("profiles")
+                    .iter()
+                    .filter_map(Profile::from_json)
+                    .collect()
+            })
+            .unwrap_or_default();
+        ProfileStore { profiles }
+    }
+
+    pub fn save(&self) {
+        let profiles: Vec<Json> = self.profiles.iter().map(Profile::to_json).collect();
+        storage::write_json(
+            PROFILES_FILE,
+            &Json::object(vec![("profiles", profiles.into())]),
+        );
+    }
+
+    // Adds a profile, or returns the existing one if the name is taken
+    pub fn add(&mut self, name: &str) -> usize {
+        if let Some(index) = self.profiles.iter().position(|p| p.name == name) {
+            return index;
+        }
+        self.profiles.push(Profile::new(name));
+        self.profiles.len() - 1
+    }
+
+    pub fn name(&self, index: Option<usize>) -> String {
+        index
+            .and_then(|i| self.profiles.get(i))
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "Guest".to_string())
+    }
+
+    // Steps to the next/previous profile for a lobby slot, skipping ones other
+    // slots already picked. `None` (guest) is always available.
+    pub fn cycle(
+        &self,
+        current: Option<usize>,
+        taken: &[Option<usize>],
+        forward: bool,
+    ) -> Option<usize> {
+        let mut options: Vec<Option<usize>> = vec![None];
+        options.extend(
+            (0..self.profiles.len())
+                .map(Some)
+                .filter(|option| *option == current || !taken.contains(option)),
+        );
+        let position = options.iter().position(|o| *o == current).unwrap_or(0);
+        let next = if forward {
+            (position + 1) % options.len()
+        } else {
+            (position + options.len() - 1) % options.len()
+        };
+        options[next]
+    }
+
+    fn get_mut(&mut self, player: &Player) -> Option<&mut Profile> {
+        player.profile.and_then(move |i| self.profiles.get_mut(i))
+    }
+
+    pub fn record_round_win(&mut self, player: &Player, game: MiniGames) {
+        if let Some(profile) = self.get_mut(player) {
+            profile.rounds_won[game.index()] += 1;
+        }
+    }
+
+    pub fn record_coverage(&mut self, player: &Player, coverage: f32) {
+        if let Some(profile) = self.get_mut(player) {
+            profile.total_coverage += coverage;
+        }
+    }
+
+    pub fn record_elimination(&mut self, player: &Player) {
+        if let Some(profile) = self.get_mut(player) {
+            profile.times_eliminated += 1;
+        }
+    }
+
+    // Guests never finish a tutorial, they get offered the first one again
+    pub fn tutorial_done(&self, player: &Player, game: MiniGames) -> bool {
+        player
+            .profile
+            .and_then(|i| self.profiles.get(i))
+            .is_some_and(|profile| profile.tutorials_done[game.index()])
+    }
+
+    pub fn complete_tutorial(&mut self, player: &Player, game: MiniGames) {
+        if let Some(profile) = self.get_mut(player) {
+            profile.tutorials_done[game.index()] = true;
+        }
+    }
+
+    pub fn record_knockout(&mut self, player: &Player) {
+        if let Some(profile) = self.get_mut(player) {
+            profile.knockouts += 1;
+        }
+    }
+
+    // Returns "<name> unlocked <cosmetic>" lines for the results screen
+    pub fn record_match(&mut self, players: &[Player], winner: Option<usize>) -> Vec<String> {
+        let mut unlocks = Vec::new();
+        for (i, player) in players.iter().enumerate() {
+            if let Some(profile) = self.get_mut(player) {
+                profile.matches_played += 1;
+                if winner == Some(i) {
+                    profile.matches_won += 1;
+                }
+                for unlock in profile.refresh_unlocks() {
+                    unlocks.push(format!("{} unlocked {}", profile.name, unlock));
+                }
+            }
+        }
+        unlocks
+    }
+}
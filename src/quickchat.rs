@@ -0,0 +1,161 @@
+// Quick chat: canned messages picked from a wheel, no typing and no voice.
+// Holding `secondary` opens the wheel around the player, the movement keys
+// point at a message and letting go sends it. It is open to players who
+// can't be doing anything else: knocked out (a ghost), over the round's
+// results and on the win screen. A message goes out as a GameEvent and the
+// handler shows it as a speech bubble; online it also goes to the host as a
+// Packet::QuickChat, which the host relays to every client.
+use crate::{events::GameEvent, Player, PlayerInput};
+use raylib::prelude::*;
+
+// clockwise from straight up, one per direction of the movement keys
+pub const MESSAGES: [&str; 8] = [
+    "gg",
+    "nice!",
+    "so close!",
+    "oops",
+    "rematch?",
+    "wow",
+    "hurry up!",
+    "thanks",
+];
+const BUBBLE_TIME: f32 = 2.5;
+const WHEEL_RADIUS: f32 = 80.0;
+const TEXT_SIZE: i32 = 18;
+
+struct Bubble {
+    player: usize,
+    message: usize,
+    time_left: f32,
+}
+
+#[derive(Default)]
+pub struct QuickChat {
+    // the message each open wheel points at, None while it points nowhere
+    wheels: [Option<Option<usize>>; 4],
+    bubbles: Vec<Bubble>,
+}
+
+// Which of MESSAGES the movement keys point at
+fn pointed_at(input: PlayerInput) -> Option<usize> {
+    let x = input.right as i32 - input.left as i32;
+    let y = input.down as i32 - input.up as i32;
+    match (x, y) {
+        (0, -1) => Some(0),
+        (1, -1) => Some(1),
+        (1, 0) => Some(2),
+        (1, 1) => Some(3),
+        (0, 1) => Some(4),
+        (-1, 1) => Some(5),
+        (-1, 0) => Some(6),
+        (-1, -1) => Some(7),
+        _ => None,
+    }
+}
+
+impl QuickChat {
+    // Opens, steers and closes `player`'s wheel; letting go of `secondary`
+    // while pointing at a message sends it
+    pub fn update(&mut self, player: usize, input: PlayerInput) -> Option<GameEvent> {
+        let wheel = &mut self.wheels[player];
+        if input.secondary {
+            let pointed = pointed_at(input);
+            // the last direction sticks, diagonals are hard to let go of cleanly
+            let selected = pointed.or(wheel.flatten());
+            *wheel = Some(selected);
+            return None;
+        }
+        let message = wheel.take().flatten()?;
+        Some(GameEvent::QuickChat { player, message })
+    }
+
+    // Closes the wheels without sending anything
+    pub fn close(&mut self) {
+        self.wheels = [None; 4];
+    }
+
+    pub fn say(&mut self, player: usize, message: usize) {
+        if message >= MESSAGES.len() {
+            return;
+        }
+        // a new message replaces the player's last one
+        self.bubbles.retain(|bubble| bubble.player != player);
+        self.bubbles.push(Bubble {
+            player,
+            message,
+            time_left: BUBBLE_TIME,
+        });
+    }
+
+    pub fn update_bubbles(&mut self, dt: f32) {
+        for bubble in &mut self.bubbles {
+            bubble.time_left -= dt;
+        }
+        self.bubbles.retain(|bubble| bubble.time_left > 0.0);
+    }
+
+    // Wheels and bubbles around each player's anchor point, their sprite in
+    // a round or their spot on the win screen
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        players: &[Player],
+        anchors: &[Vector2],
+    ) {
+        for ((player, anchor), wheel) in players.iter().zip(anchors).zip(&self.wheels) {
+            let Some(selected) = wheel else {
+                continue;
+            };
+            d.draw_circle_v(*anchor, WHEEL_RADIUS + 30.0, Color::BLACK.alpha(0.4));
+            for (i, message) in MESSAGES.iter().enumerate() {
+                let angle = i as f32 * std::f32::consts::TAU / MESSAGES.len() as f32;
+                let at = *anchor + Vector2::new(angle.sin(), -angle.cos()) * WHEEL_RADIUS;
+                let width = d.measure_text(message, TEXT_SIZE);
+                let color = if *selected == Some(i) {
+                    player.color
+                } else {
+                    Color::WHITE
+                };
+                d.draw_text(
+                    message,
+                    at.x as i32 - width / 2,
+                    at.y as i32 - TEXT_SIZE / 2,
+                    TEXT_SIZE,
+                    color,
+                );
+            }
+        }
+        for bubble in &self.bubbles {
+            let (Some(player), Some(anchor)) =
+                (players.get(bubble.player), anchors.get(bubble.player))
+            else {
+                continue;
+            };
+            let text = MESSAGES[bubble.message];
+            let width = d.measure_text(text, TEXT_SIZE) as f32 + 16.0;
+            let height = TEXT_SIZE as f32 + 10.0;
+            let alpha = (bubble.time_left / 0.5).min(1.0);
+            let rect = Rectangle::new(
+                anchor.x - width / 2.0,
+                anchor.y - player.height / 2.0 - height - 20.0,
+                width,
+                height,
+            );
+            d.draw_rectangle_rounded(rect, 0.4, 6, Color::WHITE.alpha(alpha));
+            d.draw_rectangle_rounded_lines(rect, 0.4, 6, 2.0, player.color.alpha(alpha));
+            d.draw_triangle(
+                Vector2::new(anchor.x - 6.0, rect.y + height),
+                Vector2::new(anchor.x, rect.y + height + 10.0),
+                Vector2::new(anchor.x + 6.0, rect.y + height),
+                Color::WHITE.alpha(alpha),
+            );
+            d.draw_text(
+                text,
+                (rect.x + 8.0) as i32,
+                (rect.y + 5.0) as i32,
+                TEXT_SIZE,
+                Color::BLACK.alpha(alpha),
+            );
+        }
+    }
+}
@@ -0,0 +1,621 @@
+// Match recordings: every frame stores player inputs and positions, bullets and
+// the paint splats made that frame. Saved from the WinScreen, watched in the
+// Replays viewer, over the level the match was played on.
+//
+// Inputs alone would be smaller, but playing them back means running the
+// whole match again: waves, spawners, setpieces, scripts and the rng all
+// live in the game loop, and a replay from an older build would drift the
+// moment any of them changed. Recorded positions play back the same on any
+// build, and seeking is a jump to a frame rather than a re-simulation.
+use crate::{
+    cosmetics::{Cosmetic, SplatStyle},
+    draw_env_items, image_bytes,
+    json::Json,
+    level::Level,
+    paint_splat, storage,
+    viewport::{Viewport, SCREEN_HEIGHT, SCREEN_WIDTH},
+    Bullet, EnvItem, Layer, MiniGames, Player, PlayerInput, PAINT_RADIUS,
+};
+use raylib::prelude::*;
+use std::collections::HashMap;
+
+pub const REPLAY_DIR: &str = "replays";
+const SEEK_STEP: f32 = 5.0;
+const CAMERA_SPEED: f32 = 500.0;
+
+#[derive(Debug, Clone)]
+pub struct FramePlayer {
+    pub position: Vector2,
+    pub dead: bool,
+    pub input: PlayerInput,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub time: f32, // seconds since the match started
+    pub game: MiniGames,
+    pub timer: f32,
+    pub message: Option<String>,
+    pub clear_paint: bool,
+    pub players: Vec<FramePlayer>,
+    pub bullets: Vec<Rectangle>,
+    // who painted, where, and the radius they painted with
+    pub splats: Vec<(usize, Vector2, f32)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub timestamp: u64,
+    // the --map it was played on, None for the built-in level
+    pub level: Option<String>,
+    pub names: Vec<String>,
+    pub colors: Vec<Color>,
+    pub splat_styles: Vec<SplatStyle>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+// Keeps file size down: positions are stored to a tenth of a pixel
+fn num(value: f32) -> Json {
+    Json::Number((value as f64 * 10.0).round() / 10.0)
+}
+
+fn read_f32(json: &Json, index: usize) -> f32 {
+    json.as_array()
+        .get(index)
+        .and_then(Json::as_f32)
+        .unwrap_or(0.0)
+}
+
+impl ReplayFrame {
+    fn to_json(&self) -> Json {
+        Json::Array(vec![
+            num(self.time),
+            self.game.index().into(),
+            num(self.timer),
+            match &self.message {
+                Some(message) => message.as_str().into(),
+                None => Json::Null,
+            },
+            self.clear_paint.into(),
+            Json::Array(
+                self.players
+                    .iter()
+                    .map(|p| {
+                        Json::Array(vec![
+                            num(p.position.x),
+                            num(p.position.y),
+                            p.dead.into(),
+                            (p.input.to_bits() as u32).into(),
+                        ])
+                    })
+                    .collect(),
+            ),
+            Json::Array(
+                self.bullets
+                    .iter()
+                    .map(|b| Json::Array(vec![num(b.x), num(b.y), num(b.width), num(b.height)]))
+                    .collect(),
+            ),
+            Json::Array(
+                self.splats
+                    .iter()
+                    .map(|(player, point, radius)| {
+                        Json::Array(vec![
+                            (*player).into(),
+                            num(point.x),
+                            num(point.y),
+                            num(*radius),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<ReplayFrame> {
+        let fields = json.as_array();
+        if fields.len() < 8 {
+            return None;
+        }
+        Some(ReplayFrame {
+            time: fields[0].as_f32()?,
+            game: *MiniGames::ALL.get(fields[1].as_u32()? as usize)?,
+            timer: fields[2].as_f32().unwrap_or(0.0),
+            message: fields[3].as_str().map(str::to_string),
+            clear_paint: fields[4].as_bool().unwrap_or(false),
+            players: fields[5]
+                .as_array()
+                .iter()
+                .map(|p| FramePlayer {
+                    position: Vector2::new(read_f32(p, 0), read_f32(p, 1)),
+                    dead: p.as_array().get(2).and_then(Json::as_bool).unwrap_or(false),
+                    input: PlayerInput::from_bits(read_f32(p, 3) as u8),
+                })
+                .collect(),
+            bullets: fields[6]
+                .as_array()
+                .iter()
+                .map(|b| {
+                    Rectangle::new(
+                        read_f32(b, 0),
+                        read_f32(b, 1),
+                        read_f32(b, 2),
+                        read_f32(b, 3),
+                    )
+                })
+                .collect(),
+            splats: fields[7]
+                .as_array()
+                .iter()
+                .map(|s| {
+                    (
+                        read_f32(s, 0) as usize,
+                        Vector2::new(read_f32(s, 1), read_f32(s, 2)),
+                        // recordings from before per-player radii
+                        s.as_array()
+                            .get(3)
+                            .and_then(Json::as_f32)
+                            .unwrap_or(PAINT_RADIUS),
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+impl Replay {
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|f| f.time).unwrap_or(0.0)
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}/replay-{}.json", REPLAY_DIR, self.timestamp)
+    }
+
+    pub fn save(&self) {
+        let json = Json::object(vec![
+            ("timestamp", self.timestamp.into()),
+            (
+                "level",
+                self.level.as_deref().map_or(Json::Null, Json::from),
+            ),
+            (
+                "names",
+                Json::Array(self.names.iter().map(|n| n.as_str().into()).collect()),
+            ),
+            (
+                "colors",
+                Json::Array(
+                    self.colors
+                        .iter()
+                        .map(|c| Json::from(vec![c.r as u32, c.g as u32, c.b as u32]))
+                        .collect(),
+                ),
+            ),
+            (
+                "splat_styles",
+                Json::Array(self.splat_styles.iter().map(|s| s.key().into()).collect()),
+            ),
+            (
+                "frames",
+                Json::Array(self.frames.iter().map(ReplayFrame::to_json).collect()),
+            ),
+        ]);
+        storage::write_json(&self.file_name(), &json);
+    }
+
+    pub fn load(file_name: &str) -> Result<Replay, String> {
+        let json = storage::read_json(&format!("{}/{}", REPLAY_DIR, file_name))
+            .ok_or_else(|| format!("could not read replay {}", file_name))?;
+        Ok(Replay {
+            timestamp: json.get("timestamp").and_then(Json::as_u64).unwrap_or(0),
+            // replays from before levels were recorded were nearly all on
+            // the built-in one
+            level: json.get("level").and_then(Json::as_str).map(str::to_string),
+            names: json
+                .array("names")
+                .iter()
+                .map(|n| n.as_str().unwrap_or("?").to_string())
+                .collect(),
+            colors: json
+                .array("colors")
+                .iter()
+                .map(|c| {
+                    Color::new(
+                        read_f32(c, 0) as u8,
+                        read_f32(c, 1) as u8,
+                        read_f32(c, 2) as u8,
+                        255,
+                    )
+                })
+                .collect(),
+            splat_styles: json
+                .array("splat_styles")
+                .iter()
+                .map(|s| {
+                    s.as_str()
+                        .and_then(SplatStyle::from_key)
+                        .unwrap_or_default()
+                })
+                .collect(),
+            frames: json
+                .array("frames")
+                .iter()
+                .filter_map(ReplayFrame::from_json)
+                .collect(),
+        })
+    }
+}
+
+// Saved replays, newest first
+pub fn list() -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(storage::save_path(REPLAY_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name.ends_with(".json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files.reverse();
+    files
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayRecorder {
+    pub frames: Vec<ReplayFrame>,
+    time: f32,
+    splats: Vec<(usize, Vector2, f32)>,
+    // who painted each spot last, so standing still doesn't record the same splat every frame
+    last_painter: HashMap<(i32, i32), usize>,
+    clear_paint: bool,
+}
+
+impl ReplayRecorder {
+    pub fn start(&mut self) {
+        *self = ReplayRecorder::default();
+    }
+
+    pub fn splat(&mut self, player: usize, point: Vector2, radius: f32) {
+        let key = (point.x.round() as i32, point.y.round() as i32);
+        if self.last_painter.insert(key, player) != Some(player) {
+            self.splats.push((player, point, radius));
+        }
+    }
+
+    pub fn clear_paint(&mut self) {
+        self.last_painter.clear();
+        self.splats.clear();
+        self.clear_paint = true;
+    }
+
+    pub fn record(
+        &mut self,
+        dt: f32,
+        game: MiniGames,
+        timer: f32,
+        message: &Option<String>,
+        players: &[Player],
+        bullets: &[Bullet],
+    ) {
+        self.time += dt;
+        self.frames.push(ReplayFrame {
+            time: self.time,
+            game,
+            timer,
+            message: message.clone(),
+            clear_paint: std::mem::take(&mut self.clear_paint),
+            players: players
+                .iter()
+                .map(|p| FramePlayer {
+                    position: p.position,
+                    dead: p.dead,
+                    input: p.input,
+                })
+                .collect(),
+            bullets: bullets.iter().map(|b| b.rect).collect(),
+            splats: std::mem::take(&mut self.splats),
+        });
+    }
+
+    pub fn finish(
+        &self,
+        timestamp: u64,
+        level: Option<String>,
+        names: Vec<String>,
+        players: &[Player],
+    ) -> Replay {
+        Replay {
+            timestamp,
+            level,
+            names,
+            colors: players.iter().map(|p| p.color).collect(),
+            splat_styles: players.iter().map(|p| p.splat).collect(),
+            frames: self.frames.clone(),
+        }
+    }
+}
+
+pub struct ReplayViewer {
+    pub replay: Replay,
+    pub clock: f32,
+    pub frame: usize,
+    pub playing: bool,
+    pub speed: f32,
+    pub focus: Vector2,
+    pub zoom: f32,
+    paint: Image,
+    paint_texture: Texture2D,
+    painted_upto: usize, // frames [0, painted_upto) are on the paint image
+    // the replay's own level when it isn't the one loaded, see load_level
+    level: Option<(Texture2D, Vec<EnvItem>)>,
+}
+
+impl ReplayViewer {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        replay: Replay,
+    ) -> Result<Self, String> {
+        if replay.frames.is_empty() {
+            return Err("replay has no frames".to_string());
+        }
+        let paint = Image::gen_image_color(SCREEN_WIDTH, SCREEN_HEIGHT, Color::WHITE.alpha(0.0));
+        let paint_texture = rl.load_texture_from_image(thread, &paint)?;
+        let mut viewer = ReplayViewer {
+            replay,
+            clock: 0.0,
+            frame: 0,
+            playing: true,
+            speed: 1.0,
+            focus: Vector2::zero(),
+            zoom: 1.0,
+            paint,
+            paint_texture,
+            painted_upto: 0,
+            level: None,
+        };
+        viewer.reset_camera();
+        viewer.sync_paint();
+        Ok(viewer)
+    }
+
+    // Loads the level the replay was played on unless it is `current`, the
+    // --map the game has loaded; a replay is no use over the wrong platforms
+    pub fn load_level(
+        &mut self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        current: Option<&str>,
+    ) -> Result<(), String> {
+        if self.replay.level.as_deref() == current {
+            return Ok(());
+        }
+        let level = match &self.replay.level {
+            Some(name) => Level::load(&Level::resolve(name)),
+            None => Level::load_default(),
+        }
+        .and_then(|level| Ok((level.load_background(rl, thread)?, level.items)))
+        .map_err(|err| {
+            let name = self.replay.level.as_deref().unwrap_or("the built-in level");
+            format!("it was played on {}, which can't be loaded: {}", name, err)
+        })?;
+        self.level = Some(level);
+        Ok(())
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.focus = Vector2::new(SCREEN_WIDTH as f32 / 2.0, SCREEN_HEIGHT as f32 / 2.0);
+        self.zoom = 1.0;
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.clock = time.clamp(0.0, self.replay.duration());
+        let frames = &self.replay.frames;
+        self.frame = frames
+            .partition_point(|f| f.time <= self.clock)
+            .saturating_sub(1)
+            .min(frames.len() - 1);
+        self.sync_paint();
+    }
+
+    fn sync_paint(&mut self) {
+        if self.frame + 1 < self.painted_upto {
+            // seeking backwards: repaint from the start
+            self.paint =
+                Image::gen_image_color(SCREEN_WIDTH, SCREEN_HEIGHT, Color::WHITE.alpha(0.0));
+            self.painted_upto = 0;
+        }
+        if self.frame < self.painted_upto {
+            return;
+        }
+        for frame in &self.replay.frames[self.painted_upto..=self.frame] {
+            if frame.clear_paint {
+                self.paint =
+                    Image::gen_image_color(SCREEN_WIDTH, SCREEN_HEIGHT, Color::WHITE.alpha(0.0));
+            }
+            for (player, point, radius) in &frame.splats {
+                if let Some(color) = self.replay.colors.get(*player) {
+                    let style = self
+                        .replay
+                        .splat_styles
+                        .get(*player)
+                        .copied()
+                        .unwrap_or_default();
+                    paint_splat(&mut self.paint, *point, *color, style, *radius);
+                }
+            }
+        }
+        self.painted_upto = self.frame + 1;
+        self.paint_texture.update_texture(image_bytes(&self.paint));
+    }
+
+    pub fn update(&mut self, rl: &RaylibHandle, dt: f32) {
+        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            self.playing = !self.playing;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+            self.seek(self.clock + SEEK_STEP);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+            self.seek(self.clock - SEEK_STEP);
+        }
+        if !self.playing && rl.is_key_pressed(KeyboardKey::KEY_PERIOD) {
+            let next = (self.frame + 1).min(self.replay.frames.len() - 1);
+            self.seek(self.replay.frames[next].time);
+        }
+        if !self.playing && rl.is_key_pressed(KeyboardKey::KEY_COMMA) {
+            let prev = self.frame.saturating_sub(1);
+            self.seek(self.replay.frames[prev].time);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.speed = (self.speed * 2.0).min(8.0);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.speed = (self.speed / 2.0).max(0.125);
+        }
+
+        // free camera
+        let mut pan = Vector2::zero();
+        if rl.is_key_down(KeyboardKey::KEY_W) {
+            pan.y -= 1.0;
+        }
+        if rl.is_key_down(KeyboardKey::KEY_S) {
+            pan.y += 1.0;
+        }
+        if rl.is_key_down(KeyboardKey::KEY_A) {
+            pan.x -= 1.0;
+        }
+        if rl.is_key_down(KeyboardKey::KEY_D) {
+            pan.x += 1.0;
+        }
+        self.focus += pan * (CAMERA_SPEED * dt / self.zoom);
+        self.zoom = (self.zoom * (1.0 + rl.get_mouse_wheel_move() * 0.1)).clamp(0.5, 4.0);
+        if rl.is_key_pressed(KeyboardKey::KEY_R) {
+            self.reset_camera();
+        }
+
+        let timeline = Self::timeline();
+        // the viewport already maps the mouse into virtual coordinates
+        let mouse = rl.get_mouse_position();
+        if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT)
+            && timeline.check_collision_point_rec(mouse)
+        {
+            let t = (mouse.x - timeline.x) / timeline.width;
+            self.seek(t * self.replay.duration());
+        } else if self.playing {
+            self.seek(self.clock + dt * self.speed);
+            if self.clock >= self.replay.duration() {
+                self.playing = false;
+            }
+        }
+    }
+
+    fn timeline() -> Rectangle {
+        Rectangle::new(
+            60.0,
+            SCREEN_HEIGHT as f32 - 40.0,
+            SCREEN_WIDTH as f32 - 260.0,
+            14.0,
+        )
+    }
+
+    pub fn current(&self) -> &ReplayFrame {
+        &self.replay.frames[self.frame]
+    }
+
+    pub fn draw_world(
+        &self,
+        d: &mut RaylibDrawHandle,
+        viewport: &Viewport,
+        level_texture: &Texture2D,
+        ops: &[EnvItem],
+        players: &[Player],
+    ) {
+        let (level_texture, ops) = match &self.level {
+            Some((texture, items)) => (texture, items.as_slice()),
+            None => (level_texture, ops),
+        };
+        let mut d = d.begin_mode2D(viewport.world_camera(self.focus, self.zoom));
+        let frame = self.current();
+        d.draw_texture(level_texture, 0, 0, Color::WHITE);
+        if frame.game == MiniGames::ColorTheMap {
+            d.draw_texture(&self.paint_texture, 0, 0, Color::WHITE);
+        }
+        draw_env_items(&mut d, ops, Layer::Background);
+        draw_env_items(&mut d, ops, Layer::Platform);
+        for (i, state) in frame.players.iter().enumerate() {
+            if let Some(player) = players.get(i) {
+                let mut ghost = player.clone();
+                ghost.position = state.position;
+                ghost.dead = state.dead;
+                ghost.input = state.input;
+                ghost.hit_flash = 0.0;
+                ghost.invulnerable = 0.0;
+                ghost.spawn_protection = 0.0;
+                ghost.shake = 0.0;
+                ghost.draw(&mut d);
+            }
+        }
+        for bullet in &frame.bullets {
+            d.draw_rectangle_rec(*bullet, Color::PINK);
+        }
+        draw_env_items(&mut d, ops, Layer::Foreground);
+    }
+
+    pub fn draw_ui(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
+        let frame = self.current();
+        d.draw_text(
+            &(frame.timer as i32).to_string(),
+            SCREEN_WIDTH / 2,
+            20,
+            35,
+            Color::BLACK,
+        );
+        if let Some(msg) = &frame.message {
+            d.draw_text(
+                msg,
+                SCREEN_WIDTH / 2 - d.measure_text(msg, 35) / 2,
+                SCREEN_HEIGHT / 2 - 35,
+                35,
+                Color::BLACK,
+            );
+        }
+
+        let timeline = Self::timeline();
+        let progress = self.clock / self.replay.duration().max(0.001);
+        d.draw_rectangle_rec(timeline, Color::LIGHTGRAY);
+        d.draw_rectangle_rec(
+            Rectangle::new(
+                timeline.x,
+                timeline.y,
+                timeline.width * progress,
+                timeline.height,
+            ),
+            Color::DARKGRAY,
+        );
+        let status = format!(
+            "{} {:.0}:{:02.0} / {:.0}:{:02.0}  x{}",
+            if self.playing { "playing" } else { "paused" },
+            (self.clock / 60.0).floor(),
+            self.clock % 60.0,
+            (self.replay.duration() / 60.0).floor(),
+            self.replay.duration() % 60.0,
+            self.speed
+        );
+        d.draw_text(&status, 60, SCREEN_HEIGHT - 65, 20, Color::BLACK);
+        d.draw_text(
+            "space pause  <-/-> seek  ,/. step  up/down speed  WASD/wheel camera  R reset",
+            60,
+            SCREEN_HEIGHT - 20,
+            15,
+            Color::DARKGRAY,
+        );
+        for (i, name) in self.replay.names.iter().enumerate() {
+            let color = self.replay.colors.get(i).copied().unwrap_or(Color::BLACK);
+            d.draw_text(name, 20, 20 + i as i32 * 22, 20, color);
+        }
+    }
+}
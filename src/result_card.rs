@@ -0,0 +1,239 @@
+// The result card saved from the WinScreen: the podium with everyone's
+// skin, name and points, who won each round and a few awards, in one PNG
+// under ./captures to drop in a group chat. It is drawn into its own render
+// texture at a fixed size, so it looks the same whatever the window size.
+use crate::{
+    capture,
+    history::{self, MatchRecord},
+    Player,
+};
+use raylib::prelude::*;
+use std::path::PathBuf;
+
+const CARD_WIDTH: i32 = 1200;
+const CARD_HEIGHT: i32 = 675;
+const BLOCK_WIDTH: i32 = 180;
+const BLOCK_BOTTOM: i32 = 560;
+// first, second and third: where their block stands and how tall it is
+const PODIUM: [(i32, i32); 3] = [(370, 200), (170, 140), (570, 100)];
+const SKIN_HEIGHT: f32 = 80.0;
+const MAX_ROUNDS: usize = 14;
+
+pub struct Card<'a> {
+    pub record: &'a MatchRecord,
+    // the match's seats, in the record's order
+    pub players: &'a [Player],
+    pub knockouts: [u32; 4],
+    pub eliminations: [u32; 4],
+}
+
+impl Card<'_> {
+    fn seats(&self) -> usize {
+        self.record.players.len().min(self.players.len()).min(4)
+    }
+
+    // Everyone at `best`, "A & B" on a tie
+    fn leaders(&self, values: &[u32], best: u32) -> String {
+        (0..self.seats())
+            .filter(|seat| values[*seat] == best)
+            .map(|seat| self.record.players[seat].name.as_str())
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    fn awards(&self) -> Vec<(&'static str, String)> {
+        let seats = self.seats();
+        let mut awards = Vec::new();
+        let most = self.knockouts[..seats].iter().copied().max().unwrap_or(0);
+        if most > 0 {
+            let names = self.leaders(&self.knockouts, most);
+            awards.push(("Sharpshooter", format!("{}, {} knockouts", names, most)));
+        }
+        let worst = self.eliminations[..seats]
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0);
+        let fewest = self.eliminations[..seats]
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(0);
+        if worst > fewest {
+            let names = self.leaders(&self.eliminations, fewest);
+            awards.push(("Untouchable", format!("{}, out {} times", names, fewest)));
+        }
+
+        // round wins in a row, and in how many different minigames
+        let mut streak = [0; 4];
+        let mut longest = [0; 4];
+        let mut games = [[false; 3]; 4];
+        for round in &self.record.rounds {
+            for seat in 0..seats {
+                if round.winners.contains(&seat) {
+                    streak[seat] += 1;
+                    longest[seat] = longest[seat].max(streak[seat]);
+                    games[seat][round.game.index()] = true;
+                } else {
+                    streak[seat] = 0;
+                }
+            }
+        }
+        let best = longest[..seats].iter().copied().max().unwrap_or(0);
+        if best > 1 {
+            let names = self.leaders(&longest, best);
+            awards.push(("Hot streak", format!("{}, {} rounds in a row", names, best)));
+        }
+        let variety = games.map(|won| won.iter().filter(|w| **w).count() as u32);
+        let best = variety[..seats].iter().copied().max().unwrap_or(0);
+        if best > 1 {
+            let names = self.leaders(&variety, best);
+            awards.push(("All-rounder", format!("{}, won {} minigames", names, best)));
+        }
+        awards
+    }
+}
+
+pub fn target(rl: &mut RaylibHandle, thread: &RaylibThread) -> Result<RenderTexture2D, String> {
+    rl.load_render_texture(thread, CARD_WIDTH as u32, CARD_HEIGHT as u32)
+}
+
+// Draws the card into `target` and writes it out. Call it outside the
+// frame's own render texture, raylib can't nest them.
+pub fn save(
+    screen: &mut RaylibDrawHandle,
+    thread: &RaylibThread,
+    target: &mut RenderTexture2D,
+    card: &Card,
+) -> Option<PathBuf> {
+    {
+        let mut frame = screen.begin_texture_mode(thread, target);
+        let d: &mut RaylibDrawHandle = &mut frame;
+        let mut d = d.begin_mode2D(Camera2D {
+            offset: Vector2::zero(),
+            target: Vector2::zero(),
+            rotation: 0.0,
+            zoom: 1.0,
+        });
+        draw(&mut d, card);
+    }
+    let mut image = match target.texture().load_image() {
+        Ok(image) => image,
+        Err(err) => {
+            eprintln!("could not read back the result card: {}", err);
+            return None;
+        }
+    };
+    // render textures are stored upside down
+    image.flip_vertical();
+    capture::save_png("result", image)
+}
+
+fn draw(d: &mut RaylibMode2D<'_, RaylibDrawHandle>, card: &Card) {
+    d.clear_background(Color::from_hex("C7DCD0").unwrap());
+    d.draw_text("Match result", 40, 30, 40, Color::BLACK);
+    let date = history::format_date(card.record.timestamp);
+    d.draw_text(
+        &date,
+        CARD_WIDTH - 40 - d.measure_text(&date, 25),
+        40,
+        25,
+        Color::DARKGRAY,
+    );
+
+    // most points first, seat order on a tie
+    let mut standings: Vec<usize> = (0..card.seats()).collect();
+    standings.sort_by_key(|seat| std::cmp::Reverse(card.record.players[*seat].points));
+    for (place, seat) in standings.iter().copied().enumerate() {
+        let player = &card.players[seat];
+        let entry = &card.record.players[seat];
+        let Some(&(x, height)) = PODIUM.get(place) else {
+            let line = format!("{}th  {}  {} pts", place + 1, entry.name, entry.points);
+            d.draw_text(&line, 40, BLOCK_BOTTOM + 60, 20, player.color);
+            continue;
+        };
+        let top = BLOCK_BOTTOM - height;
+        d.draw_rectangle(x, top, BLOCK_WIDTH, height, player.color);
+        let number = (place + 1).to_string();
+        d.draw_text(
+            &number,
+            x + BLOCK_WIDTH / 2 - d.measure_text(&number, 50) / 2,
+            top + 15,
+            50,
+            Color::WHITE,
+        );
+        let center = x as f32 + BLOCK_WIDTH as f32 / 2.0;
+        if place == 0 {
+            player.pose.draw(
+                d,
+                &player.texture,
+                Vector2::new(center, top as f32 - 60.0),
+                0.0,
+            );
+        } else {
+            let scale = SKIN_HEIGHT / player.texture.height.max(1) as f32;
+            let width = player.texture.width as f32 * scale;
+            d.draw_texture_ex(
+                &*player.texture,
+                Vector2::new(center - width / 2.0, top as f32 - SKIN_HEIGHT - 10.0),
+                0.0,
+                scale,
+                Color::WHITE,
+            );
+        }
+        let label = format!("{}  {} pts", entry.name, entry.points);
+        d.draw_text(
+            &label,
+            center as i32 - d.measure_text(&label, 20) / 2,
+            BLOCK_BOTTOM + 15,
+            20,
+            Color::BLACK,
+        );
+    }
+
+    let x = 760;
+    d.draw_text("Rounds", x, 110, 25, Color::BLACK);
+    for (i, round) in card.record.rounds.iter().enumerate().take(MAX_ROUNDS) {
+        let winners: Vec<&str> = round
+            .winners
+            .iter()
+            .filter_map(|w| card.record.players.get(*w))
+            .map(|p| p.name.as_str())
+            .collect();
+        let line = format!(
+            "{}. {}: {}",
+            i + 1,
+            round.game.name(),
+            if winners.is_empty() {
+                "no winner".to_string()
+            } else {
+                winners.join(" & ")
+            }
+        );
+        let color = round
+            .winners
+            .first()
+            .and_then(|w| card.players.get(*w))
+            .map_or(Color::DARKGRAY, |p| p.color);
+        d.draw_text(&line, x, 145 + i as i32 * 22, 20, color);
+    }
+    if card.record.rounds.len() > MAX_ROUNDS {
+        let more = format!("+{} more", card.record.rounds.len() - MAX_ROUNDS);
+        d.draw_text(&more, x, 145 + MAX_ROUNDS as i32 * 22, 20, Color::DARKGRAY);
+    }
+
+    let awards = card.awards();
+    let top = CARD_HEIGHT - 40 - awards.len() as i32 * 24;
+    if !awards.is_empty() {
+        d.draw_text("Awards", x, top - 32, 25, Color::BLACK);
+    }
+    for (i, (title, text)) in awards.iter().enumerate() {
+        d.draw_text(
+            &format!("{}: {}", title, text),
+            x,
+            top + i as i32 * 24,
+            20,
+            Color::BLACK,
+        );
+    }
+}
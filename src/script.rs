@@ -0,0 +1,984 @@
+// A small scripting language for minigames shipped in content packs, see
+// scripted.rs for what the game hands them. Just enough to keep score and
+// push things around:
+//
+//   let laps = 0;                      // top-level lets are globals
+//   fn update(dt) {
+//       for i in 0..player_count() {
+//           if alive(i) && player_y(i) < 100 { add_score(i, dt); }
+//       }
+//       if time() > 10 && laps == 0 { laps = 1; message("halfway!"); }
+//   }
+//
+// Numbers, strings, booleans and nil; `let`, `if`/`else`, `while`,
+// `for i in a..b`, functions, `return`, `//` comments. Anything a script
+// calls that it didn't define goes to the host, so a script can't reach
+// further than the functions the host answers. Every call into the script
+// runs on a step budget and a depth limit: a runaway loop fails the call
+// instead of hanging the game.
+use std::{collections::HashMap, fmt, rc::Rc};
+
+const MAX_STEPS: u32 = 200_000;
+const MAX_DEPTH: usize = 64;
+// how deep brackets, blocks and operator chains may nest; the parser and
+// the interpreter both recurse once per level
+const MAX_NESTING: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Num(f64),
+    Str(Rc<str>),
+}
+
+impl Value {
+    pub fn truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    pub fn num(&self) -> Result<f64, String> {
+        match self {
+            Value::Num(n) => Ok(*n),
+            other => Err(format!("expected a number, got {}", other.kind())),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "a boolean",
+            Value::Num(_) => "a number",
+            Value::Str(_) => "a string",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => f.write_str("nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Num(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Value {
+        Value::Num(n as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+// What the script can call besides its own functions. None when the host
+// doesn't know the name.
+pub trait Host {
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<Result<Value, String>>;
+}
+
+// For running a script's top level, where only the math built-ins exist
+impl Host for () {
+    fn call(&mut self, _: &str, _: &[Value]) -> Option<Result<Value, String>> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Sym(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 22] = [
+    "..", "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*",
+    "/", "%", "<", ">", "!",
+];
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line) = (0, 1);
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_digit() {
+            let start = i;
+            // a `.` only belongs to the number when a digit follows, `0..4`
+            // is a range
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || (chars[i] == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)))
+            {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text
+                .parse()
+                .map_err(|_| format!("line {}: bad number {}", line, text))?;
+            tokens.push((Token::Num(number), line));
+        } else if c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None | Some('\n') => return Err(format!("line {}: unclosed string", line)),
+                    Some('"') => break,
+                    Some('\\') => {
+                        text.push(match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some(other) => *other,
+                            None => return Err(format!("line {}: unclosed string", line)),
+                        });
+                        i += 2;
+                    }
+                    Some(other) => {
+                        text.push(*other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Str(text), line));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| {
+                    symbol
+                        .chars()
+                        .enumerate()
+                        .all(|(k, s)| chars.get(i + k) == Some(&s))
+                })
+                .ok_or_else(|| format!("line {}: unexpected {:?}", line, c))?;
+            i += symbol.len();
+            tokens.push((Token::Sym(symbol), line));
+        }
+    }
+    tokens.push((Token::End, line));
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Value(Value),
+    Var(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug)]
+enum StmtKind {
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    For(String, Expr, Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+struct Stmt {
+    line: usize,
+    kind: StmtKind,
+}
+
+#[derive(Debug)]
+struct Function {
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+const KEYWORDS: [&str; 11] = [
+    "fn", "let", "if", "else", "while", "for", "in", "return", "true", "false", "nil",
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line(), message))
+    }
+
+    // One level deeper, an error past MAX_NESTING; leave() steps back out
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return self.error("nested too deeply");
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Token::Sym(s) if *s == symbol)
+            || matches!(self.peek(), Token::Ident(s) if s == symbol)
+        {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            self.error(&format!("expected {}", symbol))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek().clone() {
+            Token::Ident(name) if !KEYWORDS.contains(&name.as_str()) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.error("expected a name"),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+        self.expect("{")?;
+        self.enter()?;
+        let mut body = Vec::new();
+        while !self.eat("}") {
+            if *self.peek() == Token::End {
+                return self.error("missing }");
+            }
+            body.push(self.statement()?);
+        }
+        self.leave();
+        Ok(body)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, String> {
+        let line = self.line();
+        let kind = if self.eat("let") {
+            let name = self.ident()?;
+            self.expect("=")?;
+            let value = self.expression()?;
+            self.expect(";")?;
+            StmtKind::Let(name, value)
+        } else if self.eat("if") {
+            return self.if_rest(line);
+        } else if self.eat("while") {
+            StmtKind::While(self.expression()?, self.block()?)
+        } else if self.eat("for") {
+            let name = self.ident()?;
+            self.expect("in")?;
+            let from = self.expression()?;
+            self.expect("..")?;
+            let to = self.expression()?;
+            StmtKind::For(name, from, to, self.block()?)
+        } else if self.eat("return") {
+            let value = if self.eat(";") {
+                None
+            } else {
+                let value = self.expression()?;
+                self.expect(";")?;
+                Some(value)
+            };
+            StmtKind::Return(value)
+        } else {
+            let expr = self.expression()?;
+            let kind = if self.eat("=") {
+                let Expr::Var(name) = expr else {
+                    return self.error("can only assign to a variable");
+                };
+                StmtKind::Assign(name, self.expression()?)
+            } else {
+                StmtKind::Expr(expr)
+            };
+            self.expect(";")?;
+            kind
+        };
+        Ok(Stmt { line, kind })
+    }
+
+    // after the `if`, so `else if` chains can come back here
+    fn if_rest(&mut self, line: usize) -> Result<Stmt, String> {
+        let condition = self.expression()?;
+        let then = self.block()?;
+        let otherwise = if self.eat("else") {
+            if self.eat("if") {
+                let line = self.line();
+                vec![self.if_rest(line)?]
+            } else {
+                self.block()?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Stmt {
+            line,
+            kind: StmtKind::If(condition, then, otherwise),
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.enter()?;
+        let expr = self.binary(0)?;
+        self.leave();
+        Ok(expr)
+    }
+
+    // one level of binary operators per entry, loosest first
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["==", "!="],
+            &["<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        let outer = self.depth;
+        loop {
+            let operator = match self.peek() {
+                Token::Sym(s) if operators.contains(s) => *s,
+                _ => {
+                    self.depth = outer;
+                    return Ok(left);
+                }
+            };
+            self.pos += 1;
+            // a chain nests to the left, one level per operator
+            self.enter()?;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        for operator in ["-", "!"] {
+            if self.eat(operator) {
+                self.enter()?;
+                let operand = self.unary()?;
+                self.leave();
+                return Ok(Expr::Unary(operator, Box::new(operand)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Token::Num(n) => Ok(Expr::Value(Value::Num(n))),
+            Token::Str(s) => Ok(Expr::Value(Value::Str(s.into()))),
+            Token::Sym("(") => {
+                let inner = self.expression()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Value(Value::Bool(true))),
+                "false" => Ok(Expr::Value(Value::Bool(false))),
+                "nil" => Ok(Expr::Value(Value::Nil)),
+                _ if KEYWORDS.contains(&name.as_str()) => {
+                    self.pos -= 1;
+                    self.error(&format!("unexpected {}", name))
+                }
+                _ if self.eat("(") => {
+                    let mut args = Vec::new();
+                    if !self.eat(")") {
+                        loop {
+                            args.push(self.expression()?);
+                            if self.eat(")") {
+                                break;
+                            }
+                            self.expect(",")?;
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                }
+                _ => Ok(Expr::Var(name)),
+            },
+            _ => {
+                self.pos = self.pos.saturating_sub(1);
+                self.error("expected a value")
+            }
+        }
+    }
+}
+
+enum Flow {
+    Next,
+    Return(Value),
+}
+
+pub struct Script {
+    globals: HashMap<String, Value>,
+    functions: HashMap<String, Rc<Function>>,
+}
+
+impl Script {
+    // Parses the script and runs its top level
+    pub fn load(src: &str) -> Result<Script, String> {
+        let mut parser = Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+            depth: 0,
+        };
+        let mut script = Script {
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+        };
+        let mut top = Vec::new();
+        while *parser.peek() != Token::End {
+            if parser.eat("fn") {
+                let name = parser.ident()?;
+                parser.expect("(")?;
+                let mut params = Vec::new();
+                if !parser.eat(")") {
+                    loop {
+                        params.push(parser.ident()?);
+                        if parser.eat(")") {
+                            break;
+                        }
+                        parser.expect(",")?;
+                    }
+                }
+                let body = parser.block()?;
+                script
+                    .functions
+                    .insert(name, Rc::new(Function { params, body }));
+            } else {
+                top.push(parser.statement()?);
+            }
+        }
+        let mut run = Run {
+            globals: &mut script.globals,
+            functions: &script.functions,
+            host: &mut (),
+            steps: MAX_STEPS,
+            depth: 0,
+        };
+        run.block(&top, &mut Vec::new())?;
+        Ok(script)
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    pub fn has(&self, function: &str) -> bool {
+        self.functions.contains_key(function)
+    }
+
+    // Calls one of the script's functions; Nil when it doesn't have it
+    pub fn call(
+        &mut self,
+        host: &mut dyn Host,
+        function: &str,
+        args: &[Value],
+    ) -> Result<Value, String> {
+        let Some(function) = self.functions.get(function).cloned() else {
+            return Ok(Value::Nil);
+        };
+        let mut run = Run {
+            globals: &mut self.globals,
+            functions: &self.functions,
+            host,
+            steps: MAX_STEPS,
+            depth: 0,
+        };
+        run.invoke(&function, args.to_vec())
+    }
+}
+
+struct Run<'a> {
+    globals: &'a mut HashMap<String, Value>,
+    functions: &'a HashMap<String, Rc<Function>>,
+    host: &'a mut dyn Host,
+    steps: u32,
+    depth: usize,
+}
+
+// the innermost block last
+type Scopes = Vec<HashMap<String, Value>>;
+
+impl Run<'_> {
+    fn invoke(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("too much recursion".to_string());
+        }
+        let mut scope = HashMap::new();
+        let mut args = args.into_iter();
+        for param in &function.params {
+            scope.insert(param.clone(), args.next().unwrap_or(Value::Nil));
+        }
+        self.depth += 1;
+        let flow = self.block(&function.body, &mut vec![scope]);
+        self.depth -= 1;
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            Flow::Next => Ok(Value::Nil),
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt], scopes: &mut Scopes) -> Result<Flow, String> {
+        // the top level runs without a scope, its lets become globals
+        let top = scopes.is_empty();
+        if !top {
+            scopes.push(HashMap::new());
+        }
+        let mut flow = Ok(Flow::Next);
+        for stmt in body {
+            flow = self
+                .statement(stmt, scopes)
+                .map_err(|err| match err.starts_with("line ") {
+                    true => err,
+                    false => format!("line {}: {}", stmt.line, err),
+                });
+            if !matches!(flow, Ok(Flow::Next)) {
+                break;
+            }
+        }
+        if !top {
+            scopes.pop();
+        }
+        flow
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        if self.steps == 0 {
+            return Err("ran too long".to_string());
+        }
+        self.steps -= 1;
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt, scopes: &mut Scopes) -> Result<Flow, String> {
+        self.step()?;
+        match &stmt.kind {
+            StmtKind::Let(name, value) => {
+                let value = self.eval(value, scopes)?;
+                match scopes.last_mut() {
+                    Some(scope) => scope.insert(name.clone(), value),
+                    None => self.globals.insert(name.clone(), value),
+                };
+            }
+            StmtKind::Assign(name, value) => {
+                let value = self.eval(value, scopes)?;
+                let slot = scopes
+                    .iter_mut()
+                    .rev()
+                    .find_map(|scope| scope.get_mut(name))
+                    .or_else(|| self.globals.get_mut(name))
+                    .ok_or_else(|| format!("{} isn't declared, use let", name))?;
+                *slot = value;
+            }
+            StmtKind::If(condition, then, otherwise) => {
+                let body = if self.eval(condition, scopes)?.truthy() {
+                    then
+                } else {
+                    otherwise
+                };
+                return self.nested(body, scopes);
+            }
+            StmtKind::While(condition, body) => {
+                while self.eval(condition, scopes)?.truthy() {
+                    if let Flow::Return(value) = self.nested(body, scopes)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+            StmtKind::For(name, from, to, body) => {
+                let from = self.eval(from, scopes)?.num()?;
+                let to = self.eval(to, scopes)?.num()?;
+                let mut i = from;
+                while i < to {
+                    // an empty body takes no steps of its own
+                    self.step()?;
+                    scopes.push(HashMap::from([(name.clone(), Value::Num(i))]));
+                    let flow = self.nested(body, scopes);
+                    scopes.pop();
+                    if let Flow::Return(value) = flow? {
+                        return Ok(Flow::Return(value));
+                    }
+                    i += 1.0;
+                }
+            }
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value, scopes)?,
+                    None => Value::Nil,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StmtKind::Expr(expr) => {
+                self.eval(expr, scopes)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    // A block inside a statement; at the top level it gets its own scope
+    // instead of declaring globals
+    fn nested(&mut self, body: &[Stmt], scopes: &mut Scopes) -> Result<Flow, String> {
+        if scopes.is_empty() {
+            let mut scopes = vec![HashMap::new()];
+            self.block(body, &mut scopes)
+        } else {
+            self.block(body, scopes)
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, scopes: &mut Scopes) -> Result<Value, String> {
+        self.step()?;
+        match expr {
+            Expr::Value(value) => Ok(value.clone()),
+            Expr::Var(name) => scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .or_else(|| self.globals.get(name))
+                .cloned()
+                .ok_or_else(|| format!("unknown variable {}", name)),
+            Expr::Unary(operator, inner) => {
+                let value = self.eval(inner, scopes)?;
+                match *operator {
+                    "-" => Ok(Value::Num(-value.num()?)),
+                    _ => Ok(Value::Bool(!value.truthy())),
+                }
+            }
+            Expr::Binary("&&", left, right) => {
+                let left = self.eval(left, scopes)?.truthy();
+                Ok(Value::Bool(left && self.eval(right, scopes)?.truthy()))
+            }
+            Expr::Binary("||", left, right) => {
+                let left = self.eval(left, scopes)?.truthy();
+                Ok(Value::Bool(left || self.eval(right, scopes)?.truthy()))
+            }
+            Expr::Binary(operator, left, right) => {
+                let left = self.eval(left, scopes)?;
+                let right = self.eval(right, scopes)?;
+                binary(operator, left, right)
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, scopes))
+                    .collect::<Result<Vec<Value>, String>>()?;
+                if let Some(function) = self.functions.get(name).cloned() {
+                    return self.invoke(&function, args);
+                }
+                if let Some(result) = math(name, &args) {
+                    return result;
+                }
+                match self.host.call(name, &args) {
+                    Some(result) => result.map_err(|err| format!("{}: {}", name, err)),
+                    None => Err(format!("unknown function {}", name)),
+                }
+            }
+        }
+    }
+}
+
+fn binary(operator: &str, left: Value, right: Value) -> Result<Value, String> {
+    match operator {
+        "==" => return Ok(Value::Bool(left == right)),
+        "!=" => return Ok(Value::Bool(left != right)),
+        // strings join with anything
+        "+" if matches!(left, Value::Str(_)) || matches!(right, Value::Str(_)) => {
+            return Ok(Value::Str(format!("{}{}", left, right).into()));
+        }
+        _ => {}
+    }
+    let (a, b) = (left.num()?, right.num()?);
+    Ok(match operator {
+        "+" => Value::Num(a + b),
+        "-" => Value::Num(a - b),
+        "*" => Value::Num(a * b),
+        "/" if b == 0.0 => return Err("division by zero".to_string()),
+        "/" => Value::Num(a / b),
+        "%" if b == 0.0 => return Err("division by zero".to_string()),
+        "%" => Value::Num(a.rem_euclid(b)),
+        "<" => Value::Bool(a < b),
+        "<=" => Value::Bool(a <= b),
+        ">" => Value::Bool(a > b),
+        _ => Value::Bool(a >= b),
+    })
+}
+
+// Built in everywhere, even at the top level
+fn math(name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+    let arg = |i: usize| {
+        args.get(i)
+            .ok_or_else(|| format!("{} needs {} arguments", name, i + 1))
+            .and_then(Value::num)
+    };
+    let result = match name {
+        "abs" => arg(0).map(f64::abs),
+        "floor" => arg(0).map(f64::floor),
+        "round" => arg(0).map(f64::round),
+        "sqrt" => arg(0).map(|n| n.max(0.0).sqrt()),
+        "sin" => arg(0).map(f64::sin),
+        "cos" => arg(0).map(f64::cos),
+        "min" => arg(0).and_then(|a| Ok(a.min(arg(1)?))),
+        "max" => arg(0).and_then(|a| Ok(a.max(arg(1)?))),
+        "clamp" => arg(0).and_then(|n| Ok(n.max(arg(1)?).min(arg(2)?))),
+        _ => return None,
+    };
+    Some(result.map(Value::Num))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers `log(x)` by keeping x, and `fail()` with an error
+    #[derive(Default)]
+    struct Recorder {
+        logged: Vec<Value>,
+    }
+
+    impl Host for Recorder {
+        fn call(&mut self, name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+            match name {
+                "log" => {
+                    self.logged
+                        .push(args.first().cloned().unwrap_or(Value::Nil));
+                    Some(Ok(Value::Nil))
+                }
+                "fail" => Some(Err("no thanks".to_string())),
+                _ => None,
+            }
+        }
+    }
+
+    fn run(src: &str, function: &str, args: &[Value]) -> Result<Value, String> {
+        Script::load(src)?.call(&mut Recorder::default(), function, args)
+    }
+
+    fn num(n: f64) -> Value {
+        Value::Num(n)
+    }
+
+    #[test]
+    fn top_level_lets_become_globals() {
+        let script = Script::load("let a = 2; let b = a * 3 + 1; let s = \"hi \" + b;").unwrap();
+        assert_eq!(script.global("a"), Some(&num(2.0)));
+        assert_eq!(script.global("b"), Some(&num(7.0)));
+        assert_eq!(script.global("s").and_then(Value::as_str), Some("hi 7"));
+        assert_eq!(script.global("missing"), None);
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        let src = "fn f() { return 1 + 2 * 3 - 8 / 4 % 3; }
+                   fn g() { return -(1 + 2) * 2 < -5 && !(1 == 2) || false; }";
+        assert_eq!(run(src, "f", &[]), Ok(num(5.0)));
+        assert_eq!(run(src, "g", &[]), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn functions_take_arguments_and_recurse() {
+        let src = "fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }
+                   fn pad(a, b) { return b; }";
+        assert_eq!(run(src, "fib", &[num(10.0)]), Ok(num(55.0)));
+        // missing arguments are nil, extra ones are dropped
+        assert_eq!(run(src, "pad", &[num(1.0)]), Ok(Value::Nil));
+        assert_eq!(
+            run(src, "pad", &[num(1.0), num(2.0), num(3.0)]),
+            Ok(num(2.0))
+        );
+        assert_eq!(run(src, "not_there", &[]), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn loops_and_branches() {
+        let src = "fn sum(n) { let total = 0; for i in 0..n { total = total + i; } return total; }
+                   fn count() { let i = 0; while i < 5 { i = i + 1; if i == 3 { return i; } } }
+                   fn sign(n) { if n < 0 { return -1; } else if n == 0 { return 0; } else { return 1; } }";
+        assert_eq!(run(src, "sum", &[num(5.0)]), Ok(num(10.0)));
+        assert_eq!(run(src, "count", &[]), Ok(num(3.0)));
+        assert_eq!(run(src, "sign", &[num(-4.0)]), Ok(num(-1.0)));
+        assert_eq!(run(src, "sign", &[num(0.0)]), Ok(num(0.0)));
+        assert_eq!(run(src, "sign", &[num(9.0)]), Ok(num(1.0)));
+    }
+
+    #[test]
+    fn globals_persist_between_calls() {
+        let mut script =
+            Script::load("let ticks = 0; fn update(dt) { ticks = ticks + dt; }").unwrap();
+        let mut host = Recorder::default();
+        for _ in 0..3 {
+            script.call(&mut host, "update", &[num(0.5)]).unwrap();
+        }
+        assert_eq!(script.global("ticks"), Some(&num(1.5)));
+        assert!(script.has("update"));
+        assert!(!script.has("init"));
+    }
+
+    #[test]
+    fn locals_stay_in_their_block() {
+        let src = "fn f() { if true { let inner = 1; } return inner; }";
+        let err = run(src, "f", &[]).unwrap_err();
+        assert!(err.contains("unknown variable inner"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_calls_go_to_the_host() {
+        let mut script = Script::load("fn f() { log(\"x\" + 1); log(max(2, 3)); }").unwrap();
+        let mut host = Recorder::default();
+        script.call(&mut host, "f", &[]).unwrap();
+        assert_eq!(host.logged, [Value::Str("x1".into()), num(3.0)]);
+
+        let err = run("fn f() { fail(); }", "f", &[]).unwrap_err();
+        assert_eq!(err, "line 1: fail: no thanks");
+        let err = run("fn f() {\n  launch_missiles();\n}", "f", &[]).unwrap_err();
+        assert_eq!(err, "line 2: unknown function launch_missiles");
+    }
+
+    #[test]
+    fn the_top_level_only_reaches_math() {
+        assert!(Script::load("let a = clamp(5, 0, 3);").is_ok());
+        let err = Script::load("log(1);").err().unwrap();
+        assert!(err.contains("unknown function log"), "{}", err);
+    }
+
+    #[test]
+    fn reports_runtime_errors_with_their_line() {
+        let cases = [
+            ("fn f() {\n return 1 / 0;\n}", "line 2: division by zero"),
+            ("fn f() { return 1 % 0; }", "line 1: division by zero"),
+            ("fn f() { x = 1; }", "line 1: x isn't declared, use let"),
+            (
+                "fn f() { return \"a\" * 2; }",
+                "line 1: expected a number, got a string",
+            ),
+            (
+                "fn f() { return -nil; }",
+                "line 1: expected a number, got nil",
+            ),
+            (
+                "fn f() { return sqrt(); }",
+                "line 1: sqrt needs 1 arguments",
+            ),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(run(src, "f", &[]), Err(expected.to_string()), "{}", src);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_syntax() {
+        let bad = [
+            "let = 1;",
+            "let a = 1",
+            "let a = (1 + 2;",
+            "fn f( { }",
+            "fn f() { return 1; ",
+            "1 = 2;",
+            "let s = \"unclosed;",
+            "for i 0..3 { }",
+            "let a = 1 # 2;",
+        ];
+        for src in bad {
+            assert!(Script::load(src).is_err(), "accepted {:?}", src);
+        }
+        let err = Script::load("let a = 1;\n\nlet b = ;").err().unwrap();
+        assert!(err.starts_with("line 3:"), "{}", err);
+    }
+
+    #[test]
+    fn runaway_scripts_stop() {
+        let err = run("fn f() { while true { } }", "f", &[]).unwrap_err();
+        assert!(err.ends_with("ran too long"), "{}", err);
+        let err = run("fn f() { for i in 0..1000000000 { } }", "f", &[]).unwrap_err();
+        assert!(err.ends_with("ran too long"), "{}", err);
+        let err = Script::load("while true { }").err().unwrap();
+        assert!(err.ends_with("ran too long"), "{}", err);
+        let err = run("fn f(n) { return f(n + 1); }", "f", &[num(0.0)]).unwrap_err();
+        assert!(err.ends_with("too much recursion"), "{}", err);
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let deep = [
+            format!("let a = {}1{};", "(".repeat(5000), ")".repeat(5000)),
+            format!("{}{}", "if true { ".repeat(5000), "}".repeat(5000)),
+            format!("let a = 1{};", " + 1".repeat(5000)),
+            format!("let a = {}1;", "-".repeat(5000)),
+        ];
+        for src in &deep {
+            let err = Script::load(src).err().unwrap();
+            assert_eq!(err, "line 1: nested too deeply");
+        }
+        let fine = format!("let a = {}1{};", "(".repeat(20), ")".repeat(20));
+        assert!(Script::load(&fine).is_ok());
+        assert!(Script::load(&format!("let a = 1{};", " + 1".repeat(50))).is_ok());
+    }
+
+    #[test]
+    fn the_budget_is_per_call() {
+        let src = "fn busy() { for i in 0..20000 { } }";
+        let mut script = Script::load(src).unwrap();
+        let mut host = Recorder::default();
+        for _ in 0..20 {
+            script.call(&mut host, "busy", &[]).unwrap();
+        }
+    }
+}
@@ -0,0 +1,391 @@
+// Minigames written as scripts (script.rs), shipped as <name>.script in a
+// content pack's minigames/ folder. A script names itself and the built-in
+// minigame it borrows the arena, camera, movement and round length from:
+//
+//   let name = "King of the Hill";
+//   let base = "Dodge";               // ColorTheMap, Dodge or FloorIsLava
+//
+// then fills in the lifecycle hooks it needs, at least one of:
+//
+//   fn init()        once at the start of every round
+//   fn update(dt)    every simulated frame
+//   fn round_end()   when the clock runs out or end_round() is called, the
+//                    last chance to change the scores
+//
+// The round goes to the highest score, ties share it, nobody wins when
+// nobody scored. The base minigame's own rules (Dodge waves, ColorTheMap
+// scoring) are off while a script runs; rounds are recorded under the base
+// minigame's name. Globals keep their values from round to round.
+//
+// What the game answers:
+//   players   player_count() alive(i) player_x(i) player_y(i) velocity_x(i)
+//             velocity_y(i) on_ground(i) set_velocity(i, x, y) move_to(i, x, y)
+//             eliminate(i)
+//   scoring   score(i) add_score(i, n) set_score(i, n) end_round()
+//   entities  spawn_bullet(x, y, vx, vy, gravity) bullet_count() clear_bullets()
+//   painting  paint(i, x, y) coverage(i), a share from 0 to 1 refreshed once
+//             a second; paint only shows on ColorTheMap-based games
+//   timers    time() time_left() after(seconds, "fn") every(seconds, "fn")
+//   arena     arena_x() arena_y() arena_width() arena_height()
+//   other     random() message(text), plus script.rs's math
+use crate::{
+    assets, calculate_winner,
+    rng::Rng,
+    script::{Host, Script, Value},
+    viewport::SCREEN_WIDTH,
+    Bullet, BulletSource, MiniGames, Player,
+};
+use raylib::prelude::*;
+use std::{fs, path::Path};
+
+pub const SCRIPT_DIR: &str = "minigames";
+const EXTENSION: &str = "script";
+const HOOKS: [&str; 3] = ["init", "update", "round_end"];
+const COVERAGE_REFRESH: f32 = 1.0;
+// per round, past these the calls fail
+const MAX_TIMERS: usize = 64;
+const MAX_BULLETS: usize = 500;
+const BULLET_SIZE: f32 = 14.0;
+const BULLET_TIME: f32 = 10.0;
+
+struct Timer {
+    at: f32,
+    every: Option<f32>,
+    function: String,
+}
+
+#[derive(Default)]
+struct RoundState {
+    running: bool,
+    // round time last seen, it going back means a new round
+    elapsed: f32,
+    scores: [f32; 4],
+    timers: Vec<Timer>,
+    ended: bool,
+    // a hook failed, the script sits out the rest of the round
+    failed: bool,
+    coverage: [f32; 4],
+    coverage_at: Option<f32>,
+}
+
+// The game as a hook sees it
+pub struct World<'a> {
+    pub players: &'a mut [Player],
+    pub bullets: &'a mut Vec<Bullet>,
+    pub paint: &'a mut Image,
+    pub paint_area: f32,
+    pub rng: &'a mut Rng,
+    pub bounds: Rectangle,
+    pub elapsed: f32,
+    pub time_left: f32,
+}
+
+// What the hooks asked of the game this frame
+#[derive(Default)]
+pub struct Effects {
+    pub messages: Vec<String>,
+    // players the script knocked out, already marked dead
+    pub eliminated: Vec<usize>,
+}
+
+struct Api<'w, 'a> {
+    world: &'w mut World<'a>,
+    round: &'w mut RoundState,
+    effects: &'w mut Effects,
+}
+
+impl Host for Api<'_, '_> {
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+        let num = |i: usize| {
+            args.get(i)
+                .ok_or_else(|| format!("needs {} arguments", i + 1))
+                .and_then(Value::num)
+        };
+        let count = self.world.players.len();
+        let player = |i: usize| {
+            let n = num(i)?;
+            if n >= 0.0 && n.fract() == 0.0 && (n as usize) < count {
+                Ok(n as usize)
+            } else {
+                Err(format!("there is no player {}", n))
+            }
+        };
+        let world = &mut *self.world;
+        let result = match name {
+            "player_count" => Ok(Value::Num(count as f64)),
+            "alive" => player(0).map(|i| (!world.players[i].dead).into()),
+            "player_x" => player(0).map(|i| world.players[i].position.x.into()),
+            "player_y" => player(0).map(|i| world.players[i].position.y.into()),
+            "velocity_x" => player(0).map(|i| world.players[i].velocity.x.into()),
+            "velocity_y" => player(0).map(|i| world.players[i].velocity.y.into()),
+            "on_ground" => player(0).map(|i| world.players[i].is_on_ground.into()),
+            "set_velocity" => player(0).and_then(|i| {
+                world.players[i].velocity = Vector2::new(num(1)? as f32, num(2)? as f32);
+                Ok(Value::Nil)
+            }),
+            "move_to" => player(0).and_then(|i| {
+                world.players[i].position = Vector2::new(num(1)? as f32, num(2)? as f32);
+                Ok(Value::Nil)
+            }),
+            "eliminate" => player(0).map(|i| {
+                let hit = world.players[i].hit();
+                if hit {
+                    self.effects.eliminated.push(i);
+                }
+                hit.into()
+            }),
+            "score" => player(0).map(|i| self.round.scores[i].into()),
+            "add_score" => player(0).and_then(|i| {
+                self.round.scores[i] += num(1)? as f32;
+                Ok(self.round.scores[i].into())
+            }),
+            "set_score" => player(0).and_then(|i| {
+                self.round.scores[i] = num(1)? as f32;
+                Ok(Value::Nil)
+            }),
+            "end_round" => {
+                self.round.ended = true;
+                Ok(Value::Nil)
+            }
+            "spawn_bullet" if world.bullets.len() >= MAX_BULLETS => {
+                Err(format!("more than {} bullets", MAX_BULLETS))
+            }
+            "spawn_bullet" => (|| {
+                let gravity = if args.len() > 4 { num(4)? } else { 0.0 };
+                world.bullets.push(Bullet {
+                    rect: Rectangle::new(
+                        num(0)? as f32 - BULLET_SIZE / 2.0,
+                        num(1)? as f32 - BULLET_SIZE / 2.0,
+                        BULLET_SIZE,
+                        BULLET_SIZE,
+                    ),
+                    color: Color::DARKPURPLE,
+                    speed: Vector2::new(num(2)? as f32, num(3)? as f32),
+                    gravity: gravity as f32,
+                    time_to_live: BULLET_TIME,
+                    source: BulletSource::Script,
+                });
+                Ok(Value::Nil)
+            })(),
+            "bullet_count" => Ok(Value::Num(world.bullets.len() as f64)),
+            "clear_bullets" => {
+                world.bullets.clear();
+                Ok(Value::Nil)
+            }
+            "paint" => player(0).and_then(|i| {
+                let point = Vector2::new(num(1)? as f32, num(2)? as f32);
+                world.players[i].paint(world.paint, point);
+                Ok(Value::Nil)
+            }),
+            "coverage" => player(0).map(|i| {
+                let stale = self
+                    .round
+                    .coverage_at
+                    .map_or(true, |at| world.elapsed - at >= COVERAGE_REFRESH);
+                if stale {
+                    let color = |i: usize| world.players.get(i).map_or(Color::BLANK, |p| p.color);
+                    self.round.coverage = calculate_winner(
+                        world.paint,
+                        world.paint_area,
+                        2,
+                        &color(0),
+                        &color(1),
+                        &color(2),
+                        &color(3),
+                    );
+                    self.round.coverage_at = Some(world.elapsed);
+                }
+                self.round.coverage[i].into()
+            }),
+            "time" => Ok(world.elapsed.into()),
+            "time_left" => Ok(world.time_left.into()),
+            "arena_x" => Ok(world.bounds.x.into()),
+            "arena_y" => Ok(world.bounds.y.into()),
+            "arena_width" => Ok(world.bounds.width.into()),
+            "arena_height" => Ok(world.bounds.height.into()),
+            "random" => Ok(world.rng.next_f32().into()),
+            "after" | "every" if self.round.timers.len() >= MAX_TIMERS => {
+                Err(format!("more than {} timers", MAX_TIMERS))
+            }
+            "after" | "every" => num(0).and_then(|seconds| {
+                let function = args
+                    .get(1)
+                    .and_then(Value::as_str)
+                    .ok_or("needs a function name")?;
+                let seconds = (seconds as f32).max(0.05);
+                self.round.timers.push(Timer {
+                    at: world.elapsed + seconds,
+                    every: (name == "every").then_some(seconds),
+                    function: function.to_string(),
+                });
+                Ok(Value::Nil)
+            }),
+            "message" => {
+                let text = args.iter().map(Value::to_string).collect::<Vec<_>>();
+                self.effects.messages.push(text.join(" "));
+                Ok(Value::Nil)
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+pub struct ScriptedGame {
+    pub name: String,
+    pub base: MiniGames,
+    script: Script,
+    round: RoundState,
+}
+
+impl ScriptedGame {
+    pub fn load(path: &Path) -> Result<ScriptedGame, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        let script = Script::load(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if !HOOKS.iter().any(|hook| script.has(hook)) {
+            return Err(format!(
+                "{} defines none of {}",
+                path.display(),
+                HOOKS.join(", ")
+            ));
+        }
+        let name = match script.global("name").and_then(Value::as_str) {
+            Some(name) => name.to_string(),
+            None => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        let base = match script.global("base") {
+            None => MiniGames::ColorTheMap,
+            Some(base) => base
+                .as_str()
+                .and_then(MiniGames::from_key)
+                .ok_or_else(|| format!("{}: unknown base {}", path.display(), base))?,
+        };
+        Ok(ScriptedGame {
+            name,
+            base,
+            script,
+            round: RoundState::default(),
+        })
+    }
+
+    // Runs this frame's hooks, init first on a round's first frame
+    pub fn update(&mut self, world: &mut World, dt: f32) -> Effects {
+        let mut effects = Effects::default();
+        if !self.round.running || world.elapsed < self.round.elapsed {
+            self.round = RoundState {
+                running: true,
+                ..RoundState::default()
+            };
+            self.run(world, &mut effects, "init", &[]);
+        }
+        self.round.elapsed = world.elapsed;
+
+        let mut due = Vec::new();
+        self.round.timers.retain_mut(|timer| {
+            if timer.at > world.elapsed {
+                return true;
+            }
+            due.push(timer.function.clone());
+            match timer.every {
+                Some(every) => {
+                    timer.at += every;
+                    true
+                }
+                None => false,
+            }
+        });
+        for function in due {
+            self.run(world, &mut effects, &function, &[]);
+        }
+        self.run(world, &mut effects, "update", &[dt.into()]);
+        effects
+    }
+
+    // end_round() was called
+    pub fn ended(&self) -> bool {
+        self.round.running && self.round.ended
+    }
+
+    // Calls round_end and hands out the round: the highest score wins
+    pub fn finish(&mut self, world: &mut World) -> (Vec<usize>, Effects) {
+        let mut effects = Effects::default();
+        if self.round.running {
+            self.run(world, &mut effects, "round_end", &[]);
+        }
+        self.round.running = false;
+        let scores = &self.round.scores[..world.players.len().min(4)];
+        let best = scores.iter().copied().fold(0.0, f32::max);
+        let winners = if best > 0.0 {
+            (0..scores.len()).filter(|i| scores[*i] == best).collect()
+        } else {
+            Vec::new()
+        };
+        (winners, effects)
+    }
+
+    fn run(&mut self, world: &mut World, effects: &mut Effects, function: &str, args: &[Value]) {
+        if self.round.failed {
+            return;
+        }
+        let mut api = Api {
+            world,
+            round: &mut self.round,
+            effects,
+        };
+        if let Err(err) = self.script.call(&mut api, function, args) {
+            eprintln!("{}: {} failed: {}", self.name, function, err);
+            effects
+                .messages
+                .push(format!("{} stopped: {}", self.name, err));
+            self.round.failed = true;
+        }
+    }
+
+    // The minigame's name and the round's scores under the clock
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player]) {
+        let width = d.measure_text(&self.name, 25);
+        d.draw_text(
+            &self.name,
+            SCREEN_WIDTH / 2 - width / 2,
+            60,
+            25,
+            Color::BLACK,
+        );
+        let scores: Vec<String> = (0..players.len().min(4))
+            .map(|i| format!("P{} {}", i + 1, self.round.scores[i].floor()))
+            .collect();
+        let widths: Vec<i32> = scores.iter().map(|s| d.measure_text(s, 20) + 20).collect();
+        let mut x = SCREEN_WIDTH / 2 - widths.iter().sum::<i32>() / 2;
+        for ((score, width), player) in scores.iter().zip(&widths).zip(players) {
+            d.draw_text(score, x, 90, 20, player.color);
+            x += width;
+        }
+    }
+}
+
+// Every script in the enabled packs; a name a higher pack already has is
+// skipped
+pub fn load_all() -> Vec<ScriptedGame> {
+    let mut games: Vec<ScriptedGame> = Vec::new();
+    for path in assets::pack_folder(SCRIPT_DIR) {
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        match ScriptedGame::load(&path) {
+            Ok(game) if games.iter().any(|g| g.name == game.name) => {
+                eprintln!(
+                    "skipping {}, {} is already loaded",
+                    path.display(),
+                    game.name
+                )
+            }
+            Ok(game) => games.push(game),
+            Err(err) => eprintln!("skipping minigame script: {}", err),
+        }
+    }
+    games
+}
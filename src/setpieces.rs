@@ -0,0 +1,364 @@
+// Setpieces: timed events a level file schedules during a round, e.g.
+//   "setpieces": [
+//     { "at": 30, "collapse": 13 },
+//     { "at": 20, "turret": [15, 300], "speed": [300, 0], "interval": 2 },
+//     { "at": 40, "lava": 60, "modes": ["floor-is-lava"] },
+//     { "at": 10, "beam": [600, 560], "length": 700, "speed": 1.5, "knockback": 500 }
+//   ]
+// `at` is seconds into the round. A banner warns `warning` seconds (3 by
+// default) before one goes off. `modes` limits it to some minigames, all of
+// them when left out. A beam spins around its middle at `speed` radians a
+// second (negative turns the other way) and knocks out whoever it sweeps,
+// or with `knockback` shoves them away at that speed instead.
+use crate::{
+    collision::OrientedRect, events::GameEvent, viewport::SCREEN_WIDTH, Bullet, BulletSource,
+    EnvItem, MiniGames, Player,
+};
+use raylib::prelude::*;
+
+pub const DEFAULT_WARNING: f32 = 3.0;
+const FALL_GRAVITY: f32 = 900.0;
+// how fast the lava climbs to its new level, per second
+const LAVA_RISE_SPEED: f32 = 40.0;
+const TURRET_SIZE: f32 = 30.0;
+const TURRET_BULLET_TIME: f32 = 10.0;
+const BEAM_THICKNESS: f32 = 16.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetPieceAction {
+    // the level item with this index falls out of the arena
+    Collapse {
+        item: usize,
+    },
+    // fires a bullet from `position` every `interval` seconds
+    Turret {
+        position: Vector2,
+        speed: Vector2,
+        interval: f32,
+    },
+    // lava climbs `step` higher from the bottom of the arena
+    RaiseLava {
+        step: f32,
+    },
+    // a beam `length` long starts spinning around `pivot`
+    Beam {
+        pivot: Vector2,
+        length: f32,
+        speed: f32,
+        knockback: Option<f32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetPiece {
+    pub at: f32,
+    pub warning: f32,
+    pub modes: Vec<MiniGames>,
+    pub action: SetPieceAction,
+}
+
+impl SetPiece {
+    pub fn runs_in(&self, game: MiniGames) -> bool {
+        self.modes.is_empty() || self.modes.contains(&game)
+    }
+
+    fn banner(&self) -> &'static str {
+        match self.action {
+            SetPieceAction::Collapse { .. } => "A platform is about to collapse!",
+            SetPieceAction::Turret { .. } => "A turret is powering up!",
+            SetPieceAction::RaiseLava { .. } => "The lava is rising!",
+            SetPieceAction::Beam { .. } => "A beam is starting to spin!",
+        }
+    }
+}
+
+struct Falling {
+    item: usize,
+    // where the item was before it fell, put back on reset
+    original: Rectangle,
+    speed: f32,
+}
+
+struct Beam {
+    pivot: Vector2,
+    length: f32,
+    speed: f32,
+    knockback: Option<f32>,
+    angle: f32,
+}
+
+impl Beam {
+    fn shape(&self) -> OrientedRect {
+        OrientedRect {
+            center: self.pivot,
+            half: Vector2::new(self.length / 2.0, BEAM_THICKNESS / 2.0),
+            angle: self.angle,
+        }
+    }
+
+    // Which way the beam shoves someone at `position`: along its sweep,
+    // and a bit up so they clear it
+    fn push(&self, position: Vector2, knockback: f32) -> Vector2 {
+        let arm = position - self.pivot;
+        let sweep = Vector2::new(-arm.y, arm.x) * self.speed.signum();
+        (sweep.normalized() + Vector2::new(0.0, -0.5)).normalized() * knockback
+    }
+}
+
+struct Turret {
+    position: Vector2,
+    speed: Vector2,
+    interval: f32,
+    timer: f32,
+}
+
+pub struct SetPieces {
+    // sorted by `at`
+    pieces: Vec<SetPiece>,
+    // pieces before this one already went off this round
+    next: usize,
+    falling: Vec<Falling>,
+    turrets: Vec<Turret>,
+    beams: Vec<Beam>,
+    lava: f32,
+    lava_target: f32,
+}
+
+impl SetPieces {
+    pub fn new(mut pieces: Vec<SetPiece>) -> SetPieces {
+        pieces.sort_by(|a, b| a.at.total_cmp(&b.at));
+        SetPieces {
+            pieces,
+            next: 0,
+            falling: Vec::new(),
+            turrets: Vec::new(),
+            beams: Vec::new(),
+            lava: 0.0,
+            lava_target: 0.0,
+        }
+    }
+
+    // Puts collapsed items back and lines every setpiece up again
+    pub fn reset(&mut self, ops: &mut [EnvItem]) {
+        for falling in self.falling.drain(..) {
+            if let Some(op) = ops.get_mut(falling.item) {
+                op.rect = falling.original;
+            }
+        }
+        self.next = 0;
+        self.turrets.clear();
+        self.beams.clear();
+        self.lava = 0.0;
+        self.lava_target = 0.0;
+    }
+
+    // Setpieces due by `round_elapsed` go off; falling items, turrets, beams
+    // and lava move on. Lava and beams are reported as events, turret shots
+    // go to `bullets`.
+    pub fn update(
+        &mut self,
+        game: MiniGames,
+        round_elapsed: f32,
+        bounds: Rectangle,
+        ops: &mut [EnvItem],
+        players: &[Player],
+        bullets: &mut Vec<Bullet>,
+        dt: f32,
+    ) -> Vec<GameEvent> {
+        while let Some(piece) = self.pieces.get(self.next) {
+            if piece.at > round_elapsed {
+                break;
+            }
+            self.next += 1;
+            if !piece.runs_in(game) {
+                continue;
+            }
+            match piece.action {
+                SetPieceAction::Collapse { item } => {
+                    if let Some(op) = ops.get(item) {
+                        self.falling.push(Falling {
+                            item,
+                            original: op.rect,
+                            speed: 0.0,
+                        });
+                    }
+                }
+                SetPieceAction::Turret {
+                    position,
+                    speed,
+                    interval,
+                } => self.turrets.push(Turret {
+                    position,
+                    speed,
+                    interval,
+                    timer: 0.0,
+                }),
+                SetPieceAction::RaiseLava { step } => self.lava_target += step,
+                SetPieceAction::Beam {
+                    pivot,
+                    length,
+                    speed,
+                    knockback,
+                } => self.beams.push(Beam {
+                    pivot,
+                    length,
+                    speed,
+                    knockback,
+                    angle: 0.0,
+                }),
+            }
+        }
+
+        let bottom = bounds.y + bounds.height;
+        for falling in &mut self.falling {
+            let Some(op) = ops.get_mut(falling.item) else {
+                continue;
+            };
+            if op.rect.y < bottom {
+                falling.speed += FALL_GRAVITY * dt;
+                op.rect.y += falling.speed * dt;
+            }
+        }
+
+        for turret in &mut self.turrets {
+            turret.timer -= dt;
+            if turret.timer <= 0.0 {
+                turret.timer = turret.interval;
+                bullets.push(Bullet {
+                    rect: Rectangle::new(turret.position.x, turret.position.y, 15., 30.),
+                    color: Color::ORANGE,
+                    speed: turret.speed,
+                    gravity: 0.0,
+                    time_to_live: TURRET_BULLET_TIME,
+                    source: BulletSource::Turret,
+                });
+            }
+        }
+
+        self.lava = (self.lava + LAVA_RISE_SPEED * dt).min(self.lava_target);
+        let mut events = Vec::new();
+        for beam in &mut self.beams {
+            beam.angle += beam.speed * dt;
+            let shape = beam.shape();
+            for (player, p) in players.iter().enumerate() {
+                if !p.can_be_hit() || !shape.overlaps_rect(p.get_collision_rect()) {
+                    continue;
+                }
+                events.push(GameEvent::Swept {
+                    player,
+                    knockback: beam.knockback.map(|speed| beam.push(p.position, speed)),
+                });
+            }
+        }
+        if let Some(lava) = self.lava_rect(bounds) {
+            for (player, p) in players.iter().enumerate() {
+                if p.can_be_hit() && p.get_collision_rect().check_collision_recs(&lava) {
+                    events.push(GameEvent::Burned { player });
+                }
+            }
+        }
+        events
+    }
+
+    // What a respawn has to keep away from
+    pub fn hazards(&self, bounds: Rectangle) -> Vec<Rectangle> {
+        self.lava_rect(bounds)
+            .into_iter()
+            .chain(self.beams.iter().map(|beam| beam.shape().bounds()))
+            .collect()
+    }
+
+    fn lava_rect(&self, bounds: Rectangle) -> Option<Rectangle> {
+        if self.lava <= 0.0 {
+            return None;
+        }
+        Some(Rectangle::new(
+            bounds.x,
+            bounds.y + bounds.height - self.lava,
+            bounds.width,
+            self.lava,
+        ))
+    }
+
+    // The warning for the next setpiece about to go off in `game`
+    pub fn warning(&self, game: MiniGames, round_elapsed: f32) -> Option<&'static str> {
+        self.pieces[self.next..]
+            .iter()
+            .find(|piece| piece.runs_in(game) && piece.at - piece.warning <= round_elapsed)
+            .map(SetPiece::banner)
+    }
+
+    // The world side: items about to collapse blink, fallen ones leave a hole
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        game: MiniGames,
+        round_elapsed: f32,
+        bounds: Rectangle,
+        ops: &[EnvItem],
+        time: f32,
+    ) {
+        let blink = (time * 8.0) as i32 % 2 == 0;
+        for piece in &self.pieces[self.next..] {
+            if !piece.runs_in(game) || piece.at - piece.warning > round_elapsed || !blink {
+                continue;
+            }
+            if let SetPieceAction::Collapse { item } = piece.action {
+                if let Some(op) = ops.get(item) {
+                    d.draw_rectangle_lines_ex(op.rect, 3.0, Color::RED);
+                }
+            }
+        }
+        for falling in &self.falling {
+            d.draw_rectangle_rec(falling.original, Color::BLACK.alpha(0.6));
+            if let Some(op) = ops.get(falling.item) {
+                d.draw_rectangle_rec(op.rect, op.color.alpha(1.0));
+            }
+        }
+        for turret in &self.turrets {
+            d.draw_rectangle_rec(
+                // centred on where its bullets come out
+                Rectangle::new(
+                    turret.position.x + 7.5 - TURRET_SIZE / 2.0,
+                    turret.position.y + 15.0 - TURRET_SIZE / 2.0,
+                    TURRET_SIZE,
+                    TURRET_SIZE,
+                ),
+                Color::DARKGRAY,
+            );
+        }
+        for beam in &self.beams {
+            d.draw_rectangle_pro(
+                Rectangle::new(beam.pivot.x, beam.pivot.y, beam.length, BEAM_THICKNESS),
+                Vector2::new(beam.length / 2.0, BEAM_THICKNESS / 2.0),
+                beam.angle.to_degrees(),
+                Color::MAROON,
+            );
+            d.draw_circle_v(beam.pivot, BEAM_THICKNESS, Color::DARKGRAY);
+        }
+        if let Some(lava) = self.lava_rect(bounds) {
+            d.draw_rectangle_rec(lava, Color::ORANGE.alpha(0.8));
+            d.draw_rectangle_rec(Rectangle::new(lava.x, lava.y, lava.width, 6.0), Color::RED);
+        }
+    }
+
+    // The banner, on the HUD under the round timer
+    pub fn draw_warning(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        game: MiniGames,
+        round_elapsed: f32,
+    ) {
+        if let Some(text) = self.warning(game, round_elapsed) {
+            let width = d.measure_text(text, 25);
+            d.draw_rectangle(
+                SCREEN_WIDTH / 2 - width / 2 - 10,
+                92,
+                width + 20,
+                35,
+                Color::BLACK.alpha(0.6),
+            );
+            d.draw_text(text, SCREEN_WIDTH / 2 - width / 2, 97, 25, Color::GOLD);
+        }
+    }
+}
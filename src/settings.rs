@@ -0,0 +1,154 @@
+// Player settings kept in save/settings.json. Quality presets trade looks for
+// frame time so the game holds 60 FPS on weak machines:
+// - paint resolution: the paint map is painted and uploaded at a fraction of
+//   the screen size (re-uploaded every frame, the biggest per-frame cost)
+// - background layer: the level art, or a flat fill on Low
+// - clip capture: the extra screen read-back each 0.1s for the round-end GIF
+// Round lengths can be overridden per minigame; unset ones come from
+// static/minigames.json. Gamma is picked on the calibration screen, see
+// gamma.rs. Audio cues are switched on per seat, see feedback.rs.
+use crate::{json::Json, storage, MiniGames};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] = [Quality::Low, Quality::Medium, Quality::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quality::Low => "Low",
+            Quality::Medium => "Medium",
+            Quality::High => "High",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Quality> {
+        Quality::ALL
+            .into_iter()
+            .find(|quality| quality.name().eq_ignore_ascii_case(key))
+    }
+
+    pub fn next(&self) -> Quality {
+        Quality::ALL[(*self as usize + 1) % Quality::ALL.len()]
+    }
+
+    // Paint map pixels per screen pixel
+    pub fn paint_scale(&self) -> f32 {
+        match self {
+            Quality::Low => 0.5,
+            Quality::Medium => 0.75,
+            Quality::High => 1.0,
+        }
+    }
+
+    pub fn draws_background(&self) -> bool {
+        *self != Quality::Low
+    }
+
+    pub fn captures_clips(&self) -> bool {
+        *self == Quality::High
+    }
+}
+
+// Range and step of the round length setting, in seconds
+pub const ROUND_TIME_MIN: f32 = 15.0;
+pub const ROUND_TIME_MAX: f32 = 180.0;
+pub const ROUND_TIME_STEP: f32 = 15.0;
+
+pub const GAMMA_MIN: f32 = 0.5;
+pub const GAMMA_MAX: f32 = 2.5;
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub quality: Quality,
+    // per minigame, indexed by MiniGames::index; None keeps the minigame's default
+    pub round_times: [Option<f32>; 3],
+    pub gamma: f32,
+    // false until the calibration screen was gone through once
+    pub calibrated: bool,
+    // seats that hear the accessibility audio cues
+    pub audio_cues: [bool; 4],
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            quality: Quality::default(),
+            round_times: [None; 3],
+            gamma: 1.0,
+            calibrated: false,
+            audio_cues: [false; 4],
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let Some(json) = storage::read_json(SETTINGS_FILE) else {
+            return Settings::default();
+        };
+        let mut round_times = [None; 3];
+        for game in MiniGames::ALL {
+            round_times[game.index()] = json
+                .get("round_times")
+                .and_then(|times| times.get(&format!("{:?}", game)))
+                .and_then(Json::as_f32)
+                .map(|seconds| seconds.clamp(ROUND_TIME_MIN, ROUND_TIME_MAX));
+        }
+        let mut audio_cues = [false; 4];
+        for (seat, on) in json.array("audio_cues").iter().take(4).enumerate() {
+            audio_cues[seat] = on.as_bool().unwrap_or(false);
+        }
+        Settings {
+            quality: Quality::from_key(json.str_or("quality", "")).unwrap_or_default(),
+            round_times,
+            gamma: json.f32_or("gamma", 1.0).clamp(GAMMA_MIN, GAMMA_MAX),
+            calibrated: json.bool_or("calibrated", false),
+            audio_cues,
+        }
+    }
+
+    pub fn save(&self) {
+        let round_times = MiniGames::ALL
+            .iter()
+            .filter_map(|game| Some((format!("{:?}", game), self.round_time(*game)?.into())))
+            .collect();
+        storage::write_json(
+            SETTINGS_FILE,
+            &Json::object(vec![
+                ("quality", self.quality.name().into()),
+                ("round_times", Json::Object(round_times)),
+                ("gamma", self.gamma.into()),
+                ("calibrated", self.calibrated.into()),
+                ("audio_cues", self.audio_cues.to_vec().into()),
+            ]),
+        );
+    }
+
+    pub fn round_time(&self, game: MiniGames) -> Option<f32> {
+        self.round_times[game.index()]
+    }
+
+    // Steps a round length up or down, starting from `current` when unset
+    pub fn step_round_time(&mut self, game: MiniGames, current: f32, up: bool) {
+        let step = if up {
+            ROUND_TIME_STEP
+        } else {
+            -ROUND_TIME_STEP
+        };
+        let seconds = (current + step).clamp(ROUND_TIME_MIN, ROUND_TIME_MAX);
+        self.round_times[game.index()] = Some(seconds);
+    }
+
+    pub fn reset_round_times(&mut self) {
+        self.round_times = [None; 3];
+    }
+}
@@ -0,0 +1,448 @@
+// The rules of a round, one step at a time: bullets and what they hit,
+// movement and painting, pickups and setpieces, Dodge waves, a script's
+// hooks and who takes the round once it is over. The game loop and the
+// dedicated host (dedicated.rs) both play their rounds through `step`; what
+// a step means for the screen, the profiles and the history is theirs to
+// handle from what it returns.
+use crate::{
+    calculate_winner,
+    events::{EventQueue, GameEvent},
+    minigame_config::MiniGameConfig,
+    mutators::{self, Mutator},
+    paint_mask::PaintMask,
+    paint_splat,
+    pickups::{self, PickupKind, Pickups},
+    rng::Rng,
+    scripted::{ScriptedGame, World},
+    setpieces::SetPieces,
+    spawn,
+    spawners::Spawners,
+    Bullet, BulletSource, Contact, EnvItem, MiniGames, Player, PlayerInput,
+};
+use raylib::prelude::*;
+
+// A ColorTheMap round closer than this share of the map goes to overtime
+const OVERTIME_MARGIN: f32 = 0.01;
+const OVERTIME_TIME: f32 = 10.0;
+const OVERTIME_PAINT_SCALE: f32 = 2.0;
+// A ColorTheMap round ending closer than this is played out in slow motion
+const FINAL_BLOW_MARGIN: f32 = 0.05;
+// seconds into a Dodge round before the first wave
+const FIRST_WAVE: f32 = 5.0;
+
+// Where the current round stands
+#[derive(Debug, Clone, Default)]
+pub struct Round {
+    // seconds left, set from the orchestrator whenever a round starts
+    pub time_left: f32,
+    // seconds played, drives the difficulty curves
+    pub elapsed: f32,
+    pub done: bool,
+    // seconds until the next Dodge wave
+    pub spawn_timer: f32,
+    // waves spawned so far this round, for kill credit
+    pub wave: u32,
+    // the ColorTheMap round was too close to call and is in its overtime
+    pub overtime: bool,
+    // the round's deciding moment is playing out in slow motion
+    pub final_blow: bool,
+    // every seat's share of the map when the round was last scored
+    pub coverage: [f32; 4],
+    // where the last player was knocked out, the final blow looks there
+    pub last_elimination: Option<Vector2>,
+}
+
+impl Round {
+    // Starts the round over, its clock keeps what it was set to
+    pub fn reset(&mut self) {
+        *self = Round {
+            time_left: self.time_left,
+            spawn_timer: FIRST_WAVE,
+            ..Round::default()
+        };
+    }
+}
+
+// Everything a round is played on, borrowed from whoever runs it
+pub struct Stage<'a> {
+    pub game: MiniGames,
+    pub config: &'a MiniGameConfig,
+    pub mutators: &'a [Mutator],
+    // in seat order
+    pub players: &'a mut [Player],
+    pub bullets: &'a mut Vec<Bullet>,
+    pub paint: &'a mut Image,
+    pub paint_mask: &'a mut PaintMask,
+    pub paint_area: f32,
+    pub ops: &'a mut Vec<EnvItem>,
+    pub setpieces: &'a mut SetPieces,
+    pub spawners: &'a mut Spawners,
+    pub pickups: &'a mut Pickups,
+    pub rng: &'a mut Rng,
+    // replaces the base minigame's rules, only its arena is kept
+    pub script: Option<&'a mut ScriptedGame>,
+}
+
+// What came out of a step
+#[derive(Default)]
+pub struct Step {
+    // knockouts, pickups, bombs and round results for the feed, the
+    // profiles and the cues; hits are resolved by then
+    pub events: Vec<GameEvent>,
+    // seat, contact point and radius of every splat painted
+    pub splats: Vec<(usize, Vector2, f32)>,
+    pub contacts: Vec<Contact>,
+    // what the script said
+    pub messages: Vec<String>,
+    // the deciding moment starts, slow the clock down around `focus`
+    pub final_blow: bool,
+    pub focus: Option<Vector2>,
+    // the round was decided, the winners already have their points
+    pub ended: Option<RoundEnd>,
+}
+
+pub struct RoundEnd {
+    pub winners: Vec<usize>,
+    // what the screen headlines it with
+    pub message: String,
+}
+
+// Plays `dt` seconds of the round. `inputs` go with the players by seat, a
+// seat without one sits the round out. While `slow_motion` holds, a final
+// blow is playing out and the round isn't called yet. The players' own
+// timers (hit flashes, cooldowns) also run between rounds and are left to
+// the caller.
+pub fn step(
+    stage: &mut Stage,
+    round: &mut Round,
+    inputs: &[Option<PlayerInput>],
+    slow_motion: bool,
+    dt: f32,
+) -> Step {
+    let mut step = Step::default();
+    let mut hits = Vec::new();
+    for (index, bullet) in stage.bullets.iter_mut().enumerate() {
+        bullet.step(dt);
+        // a bullet stops at the first player it hits, dead and blinking
+        // players let it through, and nobody is hit by their own throw
+        if let Some(player) = stage.players.iter().position(|p| {
+            p.can_be_hit()
+                && bullet.source != BulletSource::Thrown(p.number as usize)
+                && p.get_collision_rect().check_collision_recs(&bullet.rect)
+        }) {
+            hits.push(GameEvent::BulletHit {
+                bullet: index,
+                player,
+            });
+        }
+    }
+    resolve(stage, round, hits, &mut step);
+    stage.bullets.retain(|bullet| bullet.time_to_live > 0.0);
+    if round.done {
+        return step;
+    }
+
+    move_players(stage, inputs, dt, &mut step);
+    // paint that landed where the level takes none is wiped before it
+    // shows or counts
+    stage.paint_mask.apply(stage.paint);
+    round.time_left -= dt;
+    round.elapsed += dt;
+
+    let mut events = stage.pickups.update(
+        stage.players,
+        stage.ops,
+        stage.config.paint_bomb_interval,
+        stage.rng,
+        dt,
+    );
+    events.extend(stage.setpieces.update(
+        stage.game,
+        round.elapsed,
+        stage.config.bounds,
+        stage.ops,
+        stage.players,
+        stage.bullets,
+        dt,
+    ));
+    if let Some(script) = stage.script.as_deref_mut() {
+        let mut world = World {
+            players: stage.players,
+            bullets: stage.bullets,
+            paint: stage.paint,
+            paint_area: stage.paint_area,
+            rng: stage.rng,
+            bounds: stage.config.bounds,
+            elapsed: round.elapsed,
+            time_left: round.time_left,
+        };
+        let effects = script.update(&mut world, dt);
+        step.messages.extend(effects.messages);
+        for player in effects.eliminated {
+            events.push(GameEvent::Eliminated {
+                player,
+                by: BulletSource::Script,
+            });
+        }
+    } else if stage.game == MiniGames::Dodge {
+        fire_waves(stage, round, dt);
+    }
+    resolve(stage, round, events, &mut step);
+
+    let scripted = stage.script.is_some();
+    let last_standing = stage.game == MiniGames::Dodge
+        && !scripted
+        && stage.players.iter().filter(|p| !p.dead).count() == 1;
+    // the knockout that leaves one player standing plays out slowly before
+    // the round is called
+    if last_standing && !round.final_blow {
+        round.final_blow = true;
+        step.final_blow = true;
+        step.focus = round.last_elimination;
+    }
+    if stage.game == MiniGames::ColorTheMap && !scripted && round.time_left <= 0.0 {
+        // a round that is too close to call gets an overtime where the two
+        // contenders paint with bigger splats, then it is scored again
+        if !round.overtime {
+            round.coverage = coverage(stage);
+            if let Some(contenders) = close_contenders(&round.coverage, OVERTIME_MARGIN, stage) {
+                round.overtime = true;
+                round.time_left = OVERTIME_TIME;
+                for i in contenders {
+                    stage.players[i].paint_radius *= OVERTIME_PAINT_SCALE;
+                }
+            }
+        }
+        // a close finish plays out slowly around the two leaders before it
+        // is scored
+        if round.time_left <= 0.0 && !round.final_blow {
+            round.coverage = coverage(stage);
+            if let Some([leader, runner_up]) =
+                close_contenders(&round.coverage, FINAL_BLOW_MARGIN, stage)
+            {
+                round.final_blow = true;
+                step.final_blow = true;
+                step.focus = Some(
+                    (stage.players[leader].position + stage.players[runner_up].position) * 0.5,
+                );
+            }
+        }
+    }
+
+    let script_ended = stage.script.as_ref().is_some_and(|script| script.ended());
+    let over = round.time_left <= 0.0 || script_ended || last_standing;
+    if over && !slow_motion && !step.final_blow {
+        let (winners, message) = decide(stage, round, &mut step);
+        for winner in &winners {
+            stage.players[*winner].points += 1;
+        }
+        step.events.push(GameEvent::RoundWon {
+            winners: winners.clone(),
+        });
+        step.ended = Some(RoundEnd { winners, message });
+        round.done = true;
+    }
+    step
+}
+
+// Reads and applies the seated players' input: throws, movement, the
+// arena's edges, collisions and the paint they leave
+fn move_players(stage: &mut Stage, inputs: &[Option<PlayerInput>], dt: f32, step: &mut Step) {
+    let seated = |seat: usize| inputs.get(seat).copied().flatten();
+    let bounds = stage.config.bounds;
+    let before = stage.players.to_vec();
+    for (seat, player) in stage.players.iter_mut().enumerate() {
+        let Some(input) = seated(seat) else {
+            continue;
+        };
+        let others: Vec<&Player> = before
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != seat && seated(*i).is_some())
+            .map(|(_, p)| p)
+            .collect();
+        if stage.game.uses_throwing() {
+            stage.bullets.extend(player.release_throw(input));
+        }
+        player.update(input, dt);
+        player.keep_inside(bounds);
+        // fell out of the arena, e.g. through a collapsed platform
+        if player.position.y - player.height / 2.0 > bounds.y + bounds.height {
+            let avoid: Vec<Vector2> = others
+                .iter()
+                .filter(|p| !p.dead)
+                .map(|p| p.position)
+                .collect();
+            let point = spawn::safe_spawn_point(
+                player,
+                &avoid,
+                stage.bullets,
+                stage.ops,
+                &stage.setpieces.hazards(bounds),
+                bounds,
+            );
+            player.respawn(point);
+        }
+        let contacts = player.handle_collision(stage.ops, others);
+        if contacts.is_empty() {
+            player.is_on_ground = false;
+        }
+        for point in contacts.iter().flat_map(|contact| contact.points.iter()) {
+            player.paint(stage.paint, *point);
+            step.splats.push((seat, *point, player.paint_radius));
+        }
+        step.contacts.extend(contacts);
+    }
+}
+
+// Dodge waves come quicker the longer the round goes, spawners with their
+// own interval fire in between
+fn fire_waves(stage: &mut Stage, round: &mut Round, dt: f32) {
+    let speed =
+        stage.config.bullet_speed.at(round.elapsed) * mutators::bullet_speed_scale(stage.mutators);
+    let scatter = mutators::lane_scatter(stage.mutators);
+    if round.spawn_timer <= 0.0 {
+        round.wave += 1;
+        stage
+            .spawners
+            .fire_wave(round.wave, speed, scatter, stage.rng, stage.bullets);
+        round.spawn_timer = stage.config.spawn_interval.at(round.elapsed);
+    }
+    round.spawn_timer -= dt;
+    stage
+        .spawners
+        .update(round.wave, speed, scatter, stage.rng, stage.bullets, dt);
+}
+
+// Does what `events` do to the players and the paint, then passes them on
+// with the knockouts they caused
+fn resolve(stage: &mut Stage, round: &mut Round, events: Vec<GameEvent>, step: &mut Step) {
+    let mut queue = EventQueue::default();
+    for event in events {
+        queue.push(event);
+    }
+    while let Some(event) = queue.pop() {
+        match event {
+            GameEvent::BulletHit { bullet, player } => {
+                stage.bullets[bullet].time_to_live = 0.0;
+                if stage.players[player].hit() {
+                    queue.push(GameEvent::Eliminated {
+                        player,
+                        by: stage.bullets[bullet].source,
+                    });
+                }
+            }
+            GameEvent::Burned { player } => {
+                if stage.players[player].hit() {
+                    queue.push(GameEvent::Eliminated {
+                        player,
+                        by: BulletSource::Lava,
+                    });
+                }
+            }
+            GameEvent::Swept { player, knockback } => match knockback {
+                Some(velocity) => stage.players[player].velocity = velocity,
+                None => {
+                    if stage.players[player].hit() {
+                        queue.push(GameEvent::Eliminated {
+                            player,
+                            by: BulletSource::Beam,
+                        });
+                    }
+                }
+            },
+            GameEvent::Eliminated { player, .. } => {
+                round.last_elimination = Some(stage.players[player].position);
+                step.events.push(event);
+            }
+            GameEvent::PickedUp { player, kind } => {
+                if kind == PickupKind::BigBrush {
+                    stage.players[player].paint_radius *= pickups::BIG_BRUSH_SCALE;
+                }
+                step.events.push(event);
+            }
+            GameEvent::PaintBomb { player, position } => {
+                let player = &stage.players[player];
+                // paint_splat takes a contact point, which is offset by the radius
+                let radius = player.paint_radius * pickups::BOMB_PAINT_SCALE;
+                let point = position + Vector2::one() * radius;
+                paint_splat(stage.paint, point, player.color, player.splat, radius);
+                step.events.push(event);
+            }
+            event => step.events.push(event),
+        }
+    }
+}
+
+// Who took the round that just ended and how to headline it. A script's
+// round_end gets the last word, then the best scores take it; ColorTheMap
+// goes to the most paint, Dodge and FloorIsLava to whoever is still up.
+fn decide(stage: &mut Stage, round: &mut Round, step: &mut Step) -> (Vec<usize>, String) {
+    if let Some(script) = stage.script.as_deref_mut() {
+        let mut world = World {
+            players: stage.players,
+            bullets: stage.bullets,
+            paint: stage.paint,
+            paint_area: stage.paint_area,
+            rng: stage.rng,
+            bounds: stage.config.bounds,
+            elapsed: round.elapsed,
+            time_left: round.time_left,
+        };
+        let (winners, effects) = script.finish(&mut world);
+        step.messages.extend(effects.messages);
+        let message = match winners.as_slice() {
+            [] => "nobody scored".to_string(),
+            [winner] => format!("Player {} won", winner + 1),
+            _ => "it's a tie".to_string(),
+        };
+        return (winners, message);
+    }
+    match stage.game {
+        MiniGames::ColorTheMap => {
+            round.coverage = coverage(stage);
+            let mut winner = 0;
+            for i in 0..stage.players.len() {
+                if round.coverage[i] > round.coverage[winner] {
+                    winner = i;
+                }
+            }
+            (vec![winner], format!("Player {} won", winner + 1))
+        }
+        MiniGames::Dodge | MiniGames::FloorIsLava => {
+            let winners: Vec<usize> = (0..stage.players.len())
+                .filter(|i| !stage.players[*i].dead)
+                .collect();
+            let message = match winners.as_slice() {
+                [winner] => format!("Player {} won", winner + 1),
+                _ => "it's a tie".to_string(),
+            };
+            (winners, message)
+        }
+    }
+}
+
+// Every seat's share of the paintable map
+fn coverage(stage: &mut Stage) -> [f32; 4] {
+    let color = |i: usize| stage.players.get(i).map_or(Color::BLANK, |p| p.color);
+    let colors = [color(0), color(1), color(2), color(3)];
+    calculate_winner(
+        stage.paint,
+        stage.paint_area,
+        2,
+        &colors[0],
+        &colors[1],
+        &colors[2],
+        &colors[3],
+    )
+}
+
+// Leader and runner-up of a ColorTheMap round when they are within `margin`
+fn close_contenders(coverage: &[f32; 4], margin: f32, stage: &Stage) -> Option<[usize; 2]> {
+    let mut order: Vec<usize> = (0..stage.players.len()).collect();
+    order.sort_by(|a, b| coverage[*b].total_cmp(&coverage[*a]));
+    let (leader, runner_up) = (order[0], order[1]);
+    // a round where nothing was painted is never close
+    (coverage[leader] > 0.0 && coverage[leader] - coverage[runner_up] < margin)
+        .then_some([leader, runner_up])
+}
@@ -0,0 +1,398 @@
+// Full simulation snapshots for debugging: players, bullets, timers, the RNG
+// and who owns each paint pixel, written to save/snapshots/<name>.json.
+// Take one the moment a physics bug shows up (`save_snapshot`, or F8 in
+// debug builds), then load it again (`load_snapshot` or F9) and step through
+// it frame by frame.
+use crate::{
+    history::RoundRecord,
+    json::Json,
+    rng::Rng,
+    storage::{self, save_path},
+    Bullet, BulletSource, MiniGames, Player, PlayerInput,
+};
+use raylib::prelude::*;
+
+pub const SNAPSHOT_DIR: &str = "snapshots";
+pub const QUICK_SNAPSHOT: &str = "quick";
+
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    pub facing: f32,
+    pub speed: f32,
+    pub jump_force: f32,
+    pub gravity: f32,
+    pub is_on_ground: bool,
+    pub is_jumping: bool,
+    pub jump_time: f32,
+    pub points: u32,
+    pub dead: bool,
+    pub hit_flash: f32,
+    pub invulnerable: f32,
+    pub spawn_protection: f32,
+    pub input: PlayerInput,
+}
+
+// Paint ownership per paint-map pixel: 0 is bare, n is player n
+#[derive(Debug, Clone)]
+pub struct PaintGrid {
+    pub width: i32,
+    pub height: i32,
+    pub owners: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub game: MiniGames,
+    pub level_timer: f32,
+    pub level_done: bool,
+    pub level_end_timer: f32,
+    pub spawn_timer: f32,
+    pub round_elapsed: f32,
+    pub head_msg: Option<String>,
+    pub players: Vec<PlayerState>,
+    pub bullets: Vec<Bullet>,
+    pub rng: Rng,
+    pub rounds: Vec<RoundRecord>,
+    pub paint: PaintGrid,
+}
+
+fn vec2_json(v: Vector2) -> Json {
+    Json::Array(vec![v.x.into(), v.y.into()])
+}
+
+fn json_vec2(json: &Json, key: &str) -> Option<Vector2> {
+    match json.array(key) {
+        [x, y] => Some(Vector2::new(x.as_f32()?, y.as_f32()?)),
+        _ => None,
+    }
+}
+
+fn file_name(name: &str) -> String {
+    format!("{}/{}.json", SNAPSHOT_DIR, name)
+}
+
+impl PlayerState {
+    pub fn capture(player: &Player) -> PlayerState {
+        PlayerState {
+            position: player.position,
+            velocity: player.velocity,
+            facing: player.facing,
+            speed: player.speed,
+            jump_force: player.jump_force,
+            gravity: player.gravity,
+            is_on_ground: player.is_on_ground,
+            is_jumping: player.is_jumping,
+            jump_time: player.jump_time,
+            points: player.points,
+            dead: player.dead,
+            hit_flash: player.hit_flash,
+            invulnerable: player.invulnerable,
+            spawn_protection: player.spawn_protection,
+            input: player.input,
+        }
+    }
+
+    pub fn restore(&self, player: &mut Player) {
+        player.position = self.position;
+        player.velocity = self.velocity;
+        player.facing = self.facing;
+        player.speed = self.speed;
+        player.jump_force = self.jump_force;
+        player.gravity = self.gravity;
+        player.is_on_ground = self.is_on_ground;
+        player.is_jumping = self.is_jumping;
+        player.jump_time = self.jump_time;
+        player.points = self.points;
+        player.dead = self.dead;
+        player.hit_flash = self.hit_flash;
+        player.invulnerable = self.invulnerable;
+        player.spawn_protection = self.spawn_protection;
+        player.input = self.input;
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("position", vec2_json(self.position)),
+            ("velocity", vec2_json(self.velocity)),
+            ("facing", self.facing.into()),
+            ("speed", self.speed.into()),
+            ("jump_force", self.jump_force.into()),
+            ("gravity", self.gravity.into()),
+            ("is_on_ground", self.is_on_ground.into()),
+            ("is_jumping", self.is_jumping.into()),
+            ("jump_time", self.jump_time.into()),
+            ("points", self.points.into()),
+            ("dead", self.dead.into()),
+            ("hit_flash", self.hit_flash.into()),
+            ("invulnerable", self.invulnerable.into()),
+            ("spawn_protection", self.spawn_protection.into()),
+            ("input", (self.input.to_bits() as u32).into()),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<PlayerState> {
+        Some(PlayerState {
+            position: json_vec2(json, "position")?,
+            velocity: json_vec2(json, "velocity")?,
+            facing: json.f32_or("facing", 1.0),
+            speed: json.get("speed")?.as_f32()?,
+            jump_force: json.get("jump_force")?.as_f32()?,
+            gravity: json.get("gravity")?.as_f32()?,
+            is_on_ground: json.bool_or("is_on_ground", false),
+            is_jumping: json.bool_or("is_jumping", false),
+            jump_time: json.f32_or("jump_time", 0.0),
+            points: json.u32_or("points", 0),
+            dead: json.bool_or("dead", false),
+            hit_flash: json.f32_or("hit_flash", 0.0),
+            invulnerable: json.f32_or("invulnerable", 0.0),
+            spawn_protection: json.f32_or("spawn_protection", 0.0),
+            input: PlayerInput::from_bits(json.u32_or("input", 0) as u8),
+        })
+    }
+}
+
+fn bullet_to_json(bullet: &Bullet) -> Json {
+    let Rectangle {
+        x,
+        y,
+        width,
+        height,
+    } = bullet.rect;
+    let Color { r, g, b, a } = bullet.color;
+    Json::object(vec![
+        ("rect", vec![x, y, width, height].into()),
+        ("color", vec![r as u32, g as u32, b as u32, a as u32].into()),
+        ("speed", vec2_json(bullet.speed)),
+        ("gravity", bullet.gravity.into()),
+        ("time_to_live", bullet.time_to_live.into()),
+        ("source", bullet.source.key().into()),
+    ])
+}
+
+fn bullet_from_json(json: &Json) -> Option<Bullet> {
+    let rect: Vec<f32> = json.array("rect").iter().filter_map(Json::as_f32).collect();
+    let color: Vec<u8> = json
+        .array("color")
+        .iter()
+        .filter_map(|c| Some(c.as_u32()? as u8))
+        .collect();
+    let (&[x, y, width, height], &[r, g, b, a]) = (rect.as_slice(), color.as_slice()) else {
+        return None;
+    };
+    Some(Bullet {
+        rect: Rectangle::new(x, y, width, height),
+        color: Color::new(r, g, b, a),
+        speed: json_vec2(json, "speed")?,
+        gravity: json.f32_or("gravity", 0.0),
+        time_to_live: json.f32_or("time_to_live", 0.0),
+        source: BulletSource::from_key(json.str_or("source", "")).unwrap_or(BulletSource::Console),
+    })
+}
+
+// Which player painted `pixel`: 0 is bare, n is player n
+pub fn pixel_owner(pixel: Color, players: &[Player]) -> u8 {
+    players
+        .iter()
+        .position(|p| {
+            pixel.a > 0 && (p.color.r, p.color.g, p.color.b) == (pixel.r, pixel.g, pixel.b)
+        })
+        .map_or(0, |i| i as u8 + 1)
+}
+
+impl PaintGrid {
+    pub fn capture(image: &mut Image, players: &[Player]) -> PaintGrid {
+        let mut owners = Vec::with_capacity((image.width * image.height) as usize);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                owners.push(pixel_owner(image.get_color(x, y), players));
+            }
+        }
+        PaintGrid {
+            width: image.width,
+            height: image.height,
+            owners,
+        }
+    }
+
+    pub fn to_image(&self, players: &[Player]) -> Image {
+        let mut image = Image::gen_image_color(self.width, self.height, Color::WHITE.alpha(0.0));
+        for (i, owner) in self.owners.iter().enumerate() {
+            if let Some(player) = (*owner as usize)
+                .checked_sub(1)
+                .and_then(|p| players.get(p))
+            {
+                let i = i as i32;
+                image.draw_pixel(i % self.width, i / self.width, player.color);
+            }
+        }
+        image
+    }
+
+    // Run-length encoded as [owner, count, owner, count, ...]
+    fn to_json(&self) -> Json {
+        let mut runs: Vec<u32> = Vec::new();
+        for owner in &self.owners {
+            match runs.len() {
+                n if n >= 2 && runs[n - 2] == *owner as u32 => runs[n - 1] += 1,
+                _ => runs.extend([*owner as u32, 1]),
+            }
+        }
+        Json::object(vec![
+            ("width", (self.width as u32).into()),
+            ("height", (self.height as u32).into()),
+            ("runs", runs.into()),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<PaintGrid> {
+        let width = json.get("width")?.as_u32()? as i32;
+        let height = json.get("height")?.as_u32()? as i32;
+        let mut owners = Vec::with_capacity((width * height) as usize);
+        for run in json.array("runs").chunks(2) {
+            let [owner, count] = run else {
+                return None;
+            };
+            let count = count.as_u32()? as usize;
+            owners.extend(std::iter::repeat(owner.as_u32()? as u8).take(count));
+        }
+        if owners.len() != (width * height) as usize {
+            return None;
+        }
+        Some(PaintGrid {
+            width,
+            height,
+            owners,
+        })
+    }
+}
+
+impl Snapshot {
+    pub fn capture(
+        game: MiniGames,
+        level_timer: f32,
+        level_done: bool,
+        level_end_timer: f32,
+        spawn_timer: f32,
+        round_elapsed: f32,
+        head_msg: &Option<String>,
+        players: &[Player],
+        bullets: &[Bullet],
+        rng: &Rng,
+        rounds: &[RoundRecord],
+        paint: &mut Image,
+    ) -> Snapshot {
+        Snapshot {
+            game,
+            level_timer,
+            level_done,
+            level_end_timer,
+            spawn_timer,
+            round_elapsed,
+            head_msg: head_msg.clone(),
+            players: players.iter().map(PlayerState::capture).collect(),
+            bullets: bullets.to_vec(),
+            rng: rng.clone(),
+            rounds: rounds.to_vec(),
+            paint: PaintGrid::capture(paint, players),
+        }
+    }
+
+    pub fn save(&self, name: &str) {
+        let json = Json::object(vec![
+            ("game", format!("{:?}", self.game).into()),
+            ("level_timer", self.level_timer.into()),
+            ("level_done", self.level_done.into()),
+            ("level_end_timer", self.level_end_timer.into()),
+            ("spawn_timer", self.spawn_timer.into()),
+            ("round_elapsed", self.round_elapsed.into()),
+            (
+                "head_msg",
+                self.head_msg
+                    .as_deref()
+                    .map(Json::from)
+                    .unwrap_or(Json::Null),
+            ),
+            (
+                "players",
+                Json::Array(self.players.iter().map(PlayerState::to_json).collect()),
+            ),
+            (
+                "bullets",
+                Json::Array(self.bullets.iter().map(bullet_to_json).collect()),
+            ),
+            // as text, a JSON number can't hold all 64 bits
+            ("rng", format!("{:016x}", self.rng.state()).into()),
+            (
+                "rounds",
+                Json::Array(self.rounds.iter().map(RoundRecord::to_json).collect()),
+            ),
+            ("paint", self.paint.to_json()),
+        ]);
+        storage::write_json(&file_name(name), &json);
+    }
+
+    pub fn load(name: &str) -> Result<Snapshot, String> {
+        if !save_path(&file_name(name)).exists() {
+            return Err(format!("no snapshot named {}", name));
+        }
+        let json = storage::read_json(&file_name(name))
+            .ok_or_else(|| format!("snapshot {} could not be read", name))?;
+        let broken = || format!("snapshot {} is incomplete", name);
+        let players: Vec<PlayerState> = json
+            .array("players")
+            .iter()
+            .map(PlayerState::from_json)
+            .collect::<Option<_>>()
+            .ok_or_else(broken)?;
+        if !(2..=4).contains(&players.len()) {
+            return Err(broken());
+        }
+        Ok(Snapshot {
+            game: json
+                .get("game")
+                .and_then(Json::as_str)
+                .and_then(MiniGames::from_key)
+                .ok_or_else(broken)?,
+            level_timer: json.f32_or("level_timer", 15.0),
+            level_done: json.bool_or("level_done", false),
+            level_end_timer: json.f32_or("level_end_timer", 5.0),
+            spawn_timer: json.f32_or("spawn_timer", 5.0),
+            round_elapsed: json.f32_or("round_elapsed", 0.0),
+            head_msg: json
+                .get("head_msg")
+                .and_then(Json::as_str)
+                .map(str::to_string),
+            players,
+            bullets: json
+                .array("bullets")
+                .iter()
+                .map(bullet_from_json)
+                .collect::<Option<_>>()
+                .ok_or_else(broken)?,
+            rng: json
+                .get("rng")
+                .and_then(Json::as_str)
+                .and_then(|state| u64::from_str_radix(state, 16).ok())
+                .map(Rng::new)
+                .ok_or_else(broken)?,
+            rounds: json
+                .array("rounds")
+                .iter()
+                .filter_map(RoundRecord::from_json)
+                .collect(),
+            paint: json
+                .get("paint")
+                .and_then(PaintGrid::from_json)
+                .ok_or_else(broken)?,
+        })
+    }
+
+    // Puts the saved state back on the first players.len() seats
+    pub fn restore_players(&self, players: &mut [Player]) {
+        for (player, saved) in players.iter_mut().zip(&self.players) {
+            saved.restore(player);
+        }
+    }
+}
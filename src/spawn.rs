@@ -0,0 +1,128 @@
+// Spawn points. The candidates are the fixed spawn line plus spots on top of
+// every solid platform. Each one is scored by how far it is from the closest
+// danger (other players, hazards, and bullets now and over the next second),
+// and the player's own spot on the spawn line wins whenever it is safe.
+use crate::{Bullet, EnvItem, Player};
+use raylib::prelude::*;
+
+// Further than this from every danger counts as safe
+const SAFE_DISTANCE: f32 = 90.0;
+// Bullets are checked where they will be over this many seconds
+const BULLET_LOOKAHEAD: f32 = 1.0;
+const LOOKAHEAD_STEPS: usize = 4;
+
+// The player's spot on the fixed spawn line
+pub fn spawn_line(number: u32) -> Vector2 {
+    Vector2::new(100.0 + 100.0 * number as f32, 100.0)
+}
+
+// Spots standing on top of the solid platforms
+fn platform_points(player: &Player, ops: &[EnvItem]) -> Vec<Vector2> {
+    let mut points = Vec::new();
+    for op in ops.iter().filter(|op| op.layer.collides()) {
+        if op.rect.width < player.width {
+            continue;
+        }
+        let y = op.rect.y - player.height / 2.0 - 1.0;
+        for fraction in [0.25, 0.5, 0.75] {
+            let x = (op.rect.x + op.rect.width * fraction).clamp(
+                op.rect.x + player.width / 2.0,
+                op.rect.x + op.rect.width - player.width / 2.0,
+            );
+            points.push(Vector2::new(x, y));
+        }
+    }
+    points
+}
+
+fn rect_at(player: &Player, point: Vector2) -> Rectangle {
+    Rectangle::new(
+        point.x - player.width / 2.0,
+        point.y - player.height / 2.0,
+        player.width,
+        player.height,
+    )
+}
+
+// Distance from `point` to the closest danger, capped at SAFE_DISTANCE
+fn danger_distance(
+    point: Vector2,
+    avoid: &[Vector2],
+    bullets: &[Bullet],
+    hazards: &[Rectangle],
+) -> f32 {
+    let mut closest = SAFE_DISTANCE;
+    for other in avoid {
+        closest = closest.min(point.distance_to(*other));
+    }
+    for bullet in bullets {
+        let mut ghost = bullet.clone();
+        for _ in 0..=LOOKAHEAD_STEPS {
+            let center = Vector2::new(
+                ghost.rect.x + ghost.rect.width / 2.0,
+                ghost.rect.y + ghost.rect.height / 2.0,
+            );
+            closest = closest.min(point.distance_to(center));
+            ghost.step(BULLET_LOOKAHEAD / LOOKAHEAD_STEPS as f32);
+        }
+    }
+    for hazard in hazards {
+        let nearest = Vector2::new(
+            point.x.clamp(hazard.x, hazard.x + hazard.width),
+            point.y.clamp(hazard.y, hazard.y + hazard.height),
+        );
+        closest = closest.min(point.distance_to(nearest));
+    }
+    closest
+}
+
+// The safest place for `player` inside `bounds`, away from the positions in
+// `avoid`, the bullets and the hazards
+pub fn safe_spawn_point(
+    player: &Player,
+    avoid: &[Vector2],
+    bullets: &[Bullet],
+    ops: &[EnvItem],
+    hazards: &[Rectangle],
+    bounds: Rectangle,
+) -> Vector2 {
+    let preferred = spawn_line(player.number);
+    let mut best = (preferred, f32::MIN);
+    let line: Vec<Vector2> = (0..4).map(spawn_line).collect();
+    for point in line.iter().copied().chain(platform_points(player, ops)) {
+        let rect = rect_at(player, point);
+        let inside = rect.x >= bounds.x
+            && rect.y >= bounds.y
+            && rect.x + rect.width <= bounds.x + bounds.width
+            && rect.y + rect.height <= bounds.y + bounds.height;
+        // the spawn line may graze a ledge, collision nudges the player out
+        let blocked = !line.contains(&point)
+            && ops
+                .iter()
+                .any(|op| op.layer.collides() && op.rect.check_collision_recs(&rect));
+        if !inside || blocked {
+            continue;
+        }
+        let mut score = danger_distance(point, avoid, bullets, hazards);
+        // among safe points the own spot wins, then the ones closest to it
+        if point == preferred {
+            score += 1.0;
+        } else {
+            score -= point.distance_to(preferred) / 10000.0;
+        }
+        if score > best.1 {
+            best = (point, score);
+        }
+    }
+    best.0
+}
+
+// Respawns everyone for a new round, each away from the ones placed before
+pub fn respawn_all(players: &mut [Player], bullets: &[Bullet], ops: &[EnvItem], bounds: Rectangle) {
+    let mut placed = Vec::new();
+    for player in players {
+        let point = safe_spawn_point(player, &placed, bullets, ops, &[], bounds);
+        player.respawn(point);
+        placed.push(point);
+    }
+}
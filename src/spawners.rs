@@ -0,0 +1,169 @@
+// Dodge's bullet spawners, declared by the level file, e.g.
+//   "spawners": [
+//     { "position": [-20, 50], "direction": [1, 0] },
+//     { "position": [1220, 300], "direction": [-1, 0], "interval": 3, "pattern": "spread" }
+//   ]
+// A spawner without "interval" fires with every Dodge wave, whose timing
+// comes from static/minigames.json; one with it keeps its own timer.
+// "pattern" is "single" (the default), "spread" (a fan of "count" bullets)
+// or "burst" ("count" bullets one behind the other). A level without
+// spawners gets the classic six rows along the left edge.
+use crate::{rng::Rng, Bullet, BulletSource};
+use raylib::prelude::*;
+
+pub const DEFAULT_COUNT: u32 = 3;
+// angle between the bullets of a spread, in radians
+const SPREAD_ANGLE: f32 = 0.25;
+// distance between the bullets of a burst
+const BURST_GAP: f32 = 60.0;
+const BULLET_TIME: f32 = 10.0;
+const DEFAULT_ROWS: [f32; 6] = [50., 200., 350., 500., 650., 800.];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Single,
+    Spread,
+    Burst,
+}
+
+impl Pattern {
+    pub fn from_key(key: &str) -> Option<Pattern> {
+        match key {
+            "single" => Some(Pattern::Single),
+            "spread" => Some(Pattern::Spread),
+            "burst" => Some(Pattern::Burst),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulletSpawner {
+    // top left of the bullets it fires
+    pub position: Vector2,
+    // normalised, bullets fly this way at the minigame's bullet speed
+    pub direction: Vector2,
+    // seconds between shots, None to fire with the Dodge waves
+    pub interval: Option<f32>,
+    pub pattern: Pattern,
+    pub count: u32,
+}
+
+impl BulletSpawner {
+    fn fire(&self, wave: u32, speed: f32, scatter: f32, rng: &mut Rng, bullets: &mut Vec<Bullet>) {
+        // the scatter mutator nudges shots sideways to the direction
+        let side = Vector2::new(-self.direction.y, self.direction.x);
+        let position = if scatter > 0.0 {
+            self.position + side * rng.range(-scatter, scatter)
+        } else {
+            self.position
+        };
+        let count = self.count.max(1);
+        let shots: Vec<(Vector2, Vector2)> = match self.pattern {
+            Pattern::Single => vec![(position, self.direction)],
+            Pattern::Spread => (0..count)
+                .map(|i| {
+                    let angle = (i as f32 - (count - 1) as f32 / 2.0) * SPREAD_ANGLE;
+                    (position, self.direction.rotated(angle))
+                })
+                .collect(),
+            Pattern::Burst => (0..count)
+                .map(|i| {
+                    (
+                        position - self.direction * (i as f32 * BURST_GAP),
+                        self.direction,
+                    )
+                })
+                .collect(),
+        };
+        for (position, direction) in shots {
+            bullets.push(Bullet {
+                rect: Rectangle::new(position.x, position.y, 15., 30.),
+                color: Color::PINK,
+                speed: direction * speed,
+                gravity: 0.0,
+                time_to_live: BULLET_TIME,
+                source: BulletSource::Wave(wave),
+            });
+        }
+    }
+}
+
+// The rows Dodge fired from before levels could declare their own
+fn default_rows() -> Vec<BulletSpawner> {
+    DEFAULT_ROWS
+        .iter()
+        .map(|y| BulletSpawner {
+            position: Vector2::new(-20., *y),
+            direction: Vector2::new(1.0, 0.0),
+            interval: None,
+            pattern: Pattern::Single,
+            count: 1,
+        })
+        .collect()
+}
+
+pub struct Spawners {
+    spawners: Vec<BulletSpawner>,
+    // time to the next shot of the spawners with their own interval
+    timers: Vec<f32>,
+}
+
+impl Spawners {
+    pub fn new(spawners: Vec<BulletSpawner>) -> Spawners {
+        let spawners = if spawners.is_empty() {
+            default_rows()
+        } else {
+            spawners
+        };
+        let mut out = Spawners {
+            timers: vec![0.0; spawners.len()],
+            spawners,
+        };
+        out.reset();
+        out
+    }
+
+    pub fn reset(&mut self) {
+        for (timer, spawner) in self.timers.iter_mut().zip(&self.spawners) {
+            *timer = spawner.interval.unwrap_or(0.0);
+        }
+    }
+
+    // A Dodge wave: every spawner that follows the waves fires
+    pub fn fire_wave(
+        &self,
+        wave: u32,
+        speed: f32,
+        scatter: f32,
+        rng: &mut Rng,
+        bullets: &mut Vec<Bullet>,
+    ) {
+        for spawner in self.spawners.iter().filter(|s| s.interval.is_none()) {
+            spawner.fire(wave, speed, scatter, rng, bullets);
+        }
+    }
+
+    // Spawners with their own interval fire when their timer runs out; the
+    // shots count towards the current wave
+    pub fn update(
+        &mut self,
+        wave: u32,
+        speed: f32,
+        scatter: f32,
+        rng: &mut Rng,
+        bullets: &mut Vec<Bullet>,
+        dt: f32,
+    ) {
+        for (timer, spawner) in self.timers.iter_mut().zip(&self.spawners) {
+            let Some(interval) = spawner.interval else {
+                continue;
+            };
+            *timer -= dt;
+            if *timer <= 0.0 {
+                *timer += interval;
+                spawner.fire(wave.max(1), speed, scatter, rng, bullets);
+            }
+        }
+    }
+}
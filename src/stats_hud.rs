@@ -0,0 +1,131 @@
+// Live stats overlay, shown while Tab (or Select on any gamepad) is held
+// during a round: everyone's coverage, eliminations and knockouts this match,
+// round-win streak and time alive this round. The game keeps running under
+// it. Coverage takes a pass over the whole paint map, so it is refreshed a
+// few times a second while the overlay is up rather than every frame.
+use crate::{
+    streaks::Streaks,
+    viewport::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    BulletSource, MiniGames, Player,
+};
+use raylib::prelude::*;
+
+const COVERAGE_REFRESH: f32 = 0.25;
+const ROW_HEIGHT: i32 = 40;
+const PANEL_WIDTH: i32 = 720;
+
+#[derive(Debug, Default)]
+pub struct StatsHud {
+    // this match
+    eliminations: [u32; 4],
+    knockouts: [u32; 4],
+    // this round
+    alive: [f32; 4],
+    coverage: [f32; 4],
+    coverage_timer: f32,
+}
+
+impl StatsHud {
+    pub fn reset_match(&mut self) {
+        *self = StatsHud::default();
+    }
+
+    pub fn reset_round(&mut self) {
+        self.alive = [0.0; 4];
+        self.coverage = [0.0; 4];
+        self.coverage_timer = 0.0;
+    }
+
+    pub fn held(rl: &RaylibHandle) -> bool {
+        rl.is_key_down(KeyboardKey::KEY_TAB)
+            || (0..4).any(|pad| {
+                rl.is_gamepad_available(pad)
+                    && rl.is_gamepad_button_down(pad, GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT)
+            })
+    }
+
+    // Times each seat was knocked out this match
+    pub fn eliminations(&self) -> [u32; 4] {
+        self.eliminations
+    }
+
+    pub fn knockouts(&self) -> [u32; 4] {
+        self.knockouts
+    }
+
+    // `player` was knocked out, a thrower gets the knockout
+    pub fn record_elimination(&mut self, player: usize, by: BulletSource) {
+        self.eliminations[player] += 1;
+        if let BulletSource::Thrown(thrower) = by {
+            if thrower != player && thrower < 4 {
+                self.knockouts[thrower] += 1;
+            }
+        }
+    }
+
+    pub fn update(&mut self, players: &[Player], dt: f32) {
+        for (alive, player) in self.alive.iter_mut().zip(players) {
+            if !player.dead {
+                *alive += dt;
+            }
+        }
+    }
+
+    // Whether the shown coverage is stale; counts down in real time
+    pub fn coverage_due(&mut self, frame_time: f32) -> bool {
+        self.coverage_timer -= frame_time;
+        if self.coverage_timer > 0.0 {
+            return false;
+        }
+        self.coverage_timer = COVERAGE_REFRESH;
+        true
+    }
+
+    pub fn set_coverage(&mut self, coverage: [f32; 4]) {
+        self.coverage = coverage;
+    }
+
+    pub fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        game: MiniGames,
+        players: &[Player],
+        names: &[String],
+        streaks: &Streaks,
+    ) {
+        let height = ROW_HEIGHT * (players.len() as i32 + 1) + 20;
+        let x = SCREEN_WIDTH / 2 - PANEL_WIDTH / 2;
+        let y = SCREEN_HEIGHT / 2 - height / 2;
+        d.draw_rectangle(x, y, PANEL_WIDTH, height, Color::BLACK.alpha(0.75));
+        let columns = [
+            ("Player", 20),
+            ("Coverage", 250),
+            ("Out", 380),
+            ("KOs", 460),
+            ("Streak", 540),
+            ("Alive", 630),
+        ];
+        for (title, column) in columns {
+            d.draw_text(title, x + column, y + 12, 20, Color::LIGHTGRAY);
+        }
+        for (i, (player, name)) in players.iter().zip(names).enumerate() {
+            let row = y + 12 + ROW_HEIGHT * (i as i32 + 1);
+            let coverage = if game == MiniGames::ColorTheMap {
+                format!("{:.1}%", self.coverage[i] * 100.0)
+            } else {
+                "-".to_string()
+            };
+            let cells = [
+                format!("P{} {}", i + 1, name),
+                coverage,
+                self.eliminations[i].to_string(),
+                self.knockouts[i].to_string(),
+                streaks.count(i).to_string(),
+                format!("{:.0}s", self.alive[i]),
+            ];
+            for ((_, column), text) in columns.iter().zip(&cells) {
+                d.draw_text(text, x + column, row, 20, player.color);
+            }
+        }
+    }
+}
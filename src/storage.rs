@@ -0,0 +1,32 @@
+// Save files live next to the game in ./save
+use crate::json::Json;
+use std::{fs, path::PathBuf};
+
+pub const SAVE_DIR: &str = "save";
+
+pub fn save_path(name: &str) -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(name)
+}
+
+pub fn read_json(name: &str) -> Option<Json> {
+    let text = fs::read_to_string(save_path(name)).ok()?;
+    match Json::parse(&text) {
+        Ok(json) => Some(json),
+        Err(err) => {
+            eprintln!("ignoring broken save file {}: {}", name, err);
+            None
+        }
+    }
+}
+
+pub fn write_json(name: &str, json: &Json) {
+    let path = save_path(name);
+    let dir = path
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from(SAVE_DIR));
+    let result = fs::create_dir_all(dir).and_then(|_| fs::write(&path, json.to_string()));
+    if let Err(err) = result {
+        eprintln!("could not write save file {}: {}", name, err);
+    }
+}
@@ -0,0 +1,87 @@
+// Round win streaks: winning rounds back to back builds a streak, shown over
+// the player with a glow that grows with it. Losing a round ends it, and when
+// a long enough streak is ended by someone winning the round it counts as a
+// shutdown. Streaks only decorate, they never change the score.
+use crate::{events::GameEvent, Player};
+use raylib::prelude::*;
+
+// Smallest streak that is shown and can be shut down
+pub const MIN_STREAK: u32 = 2;
+
+#[derive(Debug)]
+pub struct Streaks {
+    pub enabled: bool,
+    counts: [u32; 4],
+}
+
+impl Streaks {
+    pub fn new(enabled: bool) -> Streaks {
+        Streaks {
+            enabled,
+            counts: [0; 4],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.counts = [0; 4];
+    }
+
+    pub fn count(&self, player: usize) -> u32 {
+        self.counts[player]
+    }
+
+    // Updates the streaks for a finished round and returns the events it
+    // caused; a round nobody won breaks every streak without a shutdown
+    pub fn round_won(&mut self, winners: &[usize], players_count: usize) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if !self.enabled {
+            return events;
+        }
+        for player in 0..players_count {
+            if winners.contains(&player) {
+                self.counts[player] += 1;
+                if self.counts[player] >= MIN_STREAK {
+                    events.push(GameEvent::StreakExtended {
+                        player,
+                        streak: self.counts[player],
+                    });
+                }
+                continue;
+            }
+            let streak = std::mem::take(&mut self.counts[player]);
+            if let (Some(&by), true) = (winners.first(), streak >= MIN_STREAK) {
+                events.push(GameEvent::Shutdown {
+                    player: by,
+                    victim: player,
+                    streak,
+                });
+            }
+        }
+        events
+    }
+
+    // Streak counter and glow over a player, in world coordinates
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, player: &Player, time: f32) {
+        let streak = self.counts[player.number as usize];
+        if !self.enabled || streak < MIN_STREAK || player.dead {
+            return;
+        }
+        let pulse = 0.5 + 0.5 * (time * 6.0).sin();
+        let radius = player.width.max(player.height) * 0.6 + streak as f32 * 3.0 + pulse * 4.0;
+        d.draw_circle_v(player.position, radius, player.color.alpha(0.15));
+        d.draw_circle_lines(
+            player.position.x as i32,
+            player.position.y as i32,
+            radius,
+            Color::GOLD.alpha(0.5 + 0.5 * pulse),
+        );
+        let text = format!("x{}", streak);
+        d.draw_text(
+            &text,
+            player.position.x as i32 - d.measure_text(&text, 20) / 2,
+            (player.position.y - player.height / 2.0) as i32 - 28,
+            20,
+            Color::GOLD,
+        );
+    }
+}
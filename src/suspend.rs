@@ -0,0 +1,180 @@
+// A match left mid-way, saved on quit so the MainMenu can offer "Continue last match"
+use crate::{
+    cosmetics::SkinTextures,
+    history::RoundRecord,
+    json::Json,
+    mutators::Mutator,
+    profiles::ProfileStore,
+    spawn,
+    storage::{self, save_path},
+    MiniGames, Player,
+};
+use raylib::prelude::*;
+use std::fs;
+
+pub const SUSPENDED_FILE: &str = "suspended.json";
+pub const SUSPENDED_PAINT_FILE: &str = "suspended-paint.png";
+
+#[derive(Debug, Clone)]
+pub struct SuspendedPlayer {
+    pub profile: Option<String>, // profile name, None for a guest
+    pub points: u32,
+    pub dead: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SuspendedMatch {
+    pub game: MiniGames,
+    pub level_timer: f32,
+    pub players: Vec<SuspendedPlayer>,
+    pub rounds: Vec<RoundRecord>,
+    pub mutators: Vec<Mutator>,
+    pub daily_day: Option<u64>, // set when the match is a Daily challenge
+}
+
+impl SuspendedMatch {
+    // Captures the match as it stands. For a round that already finished the
+    // caller passes the next round's game and timer; knockouts are then dropped.
+    pub fn capture(
+        game: MiniGames,
+        level_timer: f32,
+        level_done: bool,
+        players: &[Player],
+        profiles: &ProfileStore,
+        rounds: &[RoundRecord],
+        mutators: &[Mutator],
+        daily_day: Option<u64>,
+    ) -> SuspendedMatch {
+        SuspendedMatch {
+            game,
+            level_timer,
+            players: players
+                .iter()
+                .map(|p| SuspendedPlayer {
+                    profile: p.profile.map(|_| profiles.name(p.profile)),
+                    points: p.points,
+                    dead: p.dead && !level_done,
+                })
+                .collect(),
+            rounds: rounds.to_vec(),
+            mutators: mutators.to_vec(),
+            daily_day,
+        }
+    }
+
+    pub fn round_number(&self) -> usize {
+        self.rounds.len() + 1
+    }
+
+    pub fn save(&self, paint: &Image) {
+        let json = Json::object(vec![
+            ("game", format!("{:?}", self.game).into()),
+            ("level_timer", self.level_timer.into()),
+            (
+                "players",
+                Json::Array(
+                    self.players
+                        .iter()
+                        .map(|p| {
+                            Json::object(vec![
+                                (
+                                    "profile",
+                                    p.profile.as_deref().map(Json::from).unwrap_or(Json::Null),
+                                ),
+                                ("points", p.points.into()),
+                                ("dead", p.dead.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "rounds",
+                Json::Array(self.rounds.iter().map(RoundRecord::to_json).collect()),
+            ),
+            (
+                "mutators",
+                Json::Array(
+                    self.mutators
+                        .iter()
+                        .map(|m| format!("{:?}", m).into())
+                        .collect(),
+                ),
+            ),
+            (
+                "daily_day",
+                self.daily_day.map(Json::from).unwrap_or(Json::Null),
+            ),
+        ]);
+        storage::write_json(SUSPENDED_FILE, &json);
+        paint.export_image(&save_path(SUSPENDED_PAINT_FILE).to_string_lossy());
+    }
+
+    pub fn load() -> Option<SuspendedMatch> {
+        let json = storage::read_json(SUSPENDED_FILE)?;
+        let players: Vec<SuspendedPlayer> = json
+            .array("players")
+            .iter()
+            .map(|p| SuspendedPlayer {
+                profile: p.get("profile").and_then(Json::as_str).map(str::to_string),
+                points: p.u32_or("points", 0),
+                dead: p.bool_or("dead", false),
+            })
+            .collect();
+        if !(2..=4).contains(&players.len()) {
+            return None;
+        }
+        let rounds = json
+            .array("rounds")
+            .iter()
+            .filter_map(RoundRecord::from_json)
+            .collect();
+        Some(SuspendedMatch {
+            game: MiniGames::from_key(json.get("game")?.as_str()?)?,
+            level_timer: json.f32_or("level_timer", 15.0),
+            players,
+            rounds,
+            mutators: json
+                .array("mutators")
+                .iter()
+                .filter_map(|m| Mutator::from_key(m.as_str()?))
+                .collect(),
+            daily_day: json.get("daily_day").and_then(Json::as_u64),
+        })
+    }
+
+    // Puts scores, profiles and knockouts back on the first players.len() seats
+    pub fn restore_players(
+        &self,
+        players: &mut [Player],
+        profiles: &ProfileStore,
+        skins: &SkinTextures,
+    ) {
+        for (player, saved) in players.iter_mut().zip(&self.players) {
+            player.profile = saved
+                .profile
+                .as_ref()
+                .and_then(|name| profiles.profiles.iter().position(|p| p.name == *name));
+            skins.apply(player, player.profile.map(|i| &profiles.profiles[i]));
+            player.points = saved.points;
+            player.respawn(spawn::spawn_line(player.number));
+            player.dead = saved.dead;
+        }
+    }
+
+    // The paint left on the map, if it was saved alongside the match
+    pub fn load_paint() -> Option<Image> {
+        let path = save_path(SUSPENDED_PAINT_FILE);
+        if !path.exists() {
+            return None;
+        }
+        Image::load_image(&path.to_string_lossy()).ok()
+    }
+
+    // Called once a match is resumed, finished or replaced by a new one
+    pub fn discard() {
+        for name in [SUSPENDED_FILE, SUSPENDED_PAINT_FILE] {
+            let _ = fs::remove_file(save_path(name));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Each player's route through the round, sampled a few times a second while
+// it runs and drawn as a line in their colour over the round's results, so
+// players can compare how they got where they did. Only minigames with
+// "trails" on in static/minigames.json record them.
+use crate::Player;
+use raylib::prelude::*;
+
+const SAMPLE_TIME: f32 = 0.1;
+// a jump further than this between samples is a respawn, not movement
+const TELEPORT_DISTANCE: f32 = 120.0;
+const TRAIL_THICKNESS: f32 = 3.0;
+
+#[derive(Debug, Default)]
+pub struct Trails {
+    // per player, the runs of points between respawns
+    paths: [Vec<Vec<Vector2>>; 4],
+    timer: f32,
+}
+
+impl Trails {
+    pub fn clear(&mut self) {
+        for path in &mut self.paths {
+            path.clear();
+        }
+        self.timer = 0.0;
+    }
+
+    pub fn record(&mut self, players: &[Player], dt: f32) {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return;
+        }
+        self.timer = SAMPLE_TIME;
+        for (path, player) in self.paths.iter_mut().zip(players) {
+            if player.dead {
+                // whatever comes next starts a new run
+                if path.last().is_some_and(|run| !run.is_empty()) {
+                    path.push(Vec::new());
+                }
+                continue;
+            }
+            let jumped = path
+                .last()
+                .and_then(|run| run.last())
+                .is_some_and(|last| last.distance_to(player.position) > TELEPORT_DISTANCE);
+            if path.is_empty() || jumped {
+                path.push(Vec::new());
+            }
+            if let Some(run) = path.last_mut() {
+                run.push(player.position);
+            }
+        }
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, players: &[Player]) {
+        for (path, player) in self.paths.iter().zip(players) {
+            let color = player.color.alpha(0.8);
+            for run in path {
+                for pair in run.windows(2) {
+                    d.draw_line_ex(pair[0], pair[1], TRAIL_THICKNESS, color);
+                }
+                if let Some(end) = run.last() {
+                    d.draw_circle_v(*end, TRAIL_THICKNESS * 1.5, color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+// Minigame tutorials. A ghost shows the goal by playing a short demo on loop:
+// scripted inputs are run through the normal player physics and recorded
+// into a Replay, which the replay viewer then plays back. Prompts light up
+// the controls the ghost (or the player) is pressing. Seat 1 has to do the
+// goal once; finishing marks the tutorial done on that seat's profile.
+use crate::{
+    replay::{Replay, ReplayRecorder, ReplayViewer},
+    viewport::{Viewport, SCREEN_HEIGHT, SCREEN_WIDTH},
+    Bullet, BulletSource, EnvItem, InputType, KeyboardControls, MiniGames, Player, PlayerInput,
+    PAINT_RADIUS,
+};
+use raylib::prelude::*;
+use std::collections::HashSet;
+
+const DEMO_LENGTH: f32 = 8.0;
+const DEMO_STEP: f32 = 1.0 / 60.0;
+const GHOST_START: Vector2 = Vector2 { x: 750.0, y: 300.0 };
+const PLAYER_START: Vector2 = Vector2 { x: 300.0, y: 300.0 };
+const BULLET_SPEED: f32 = 400.0;
+// Dodge: the ghost jumps at these times, its bullets are timed to meet it
+const DEMO_JUMPS: [f32; 3] = [2.5, 5.0, 7.5];
+// seconds from take-off to the top of a jump, where the bullet passes under
+const JUMP_PEAK: f32 = 0.6;
+const WAVE_INTERVAL: f32 = 2.5;
+// size of the cells counted for the painting goal
+const PAINT_CELL: f32 = 25.0;
+const PAINT_GOAL: usize = 40;
+const DODGE_GOAL: usize = 3;
+const JUMP_GOAL: usize = 3;
+
+fn held(left: bool, right: bool, up: bool) -> PlayerInput {
+    PlayerInput {
+        up,
+        left,
+        right,
+        ..Default::default()
+    }
+}
+
+// The ghost's inputs as (until, input) steps; nothing is held after the last
+fn script(game: MiniGames) -> Vec<(f32, PlayerInput)> {
+    match game {
+        MiniGames::ColorTheMap => vec![
+            (1.0, held(false, false, false)),
+            (2.5, held(false, true, false)),
+            (3.0, held(false, true, true)),
+            (5.0, held(true, false, false)),
+            (5.4, held(true, false, true)),
+            (7.0, held(false, true, false)),
+        ],
+        MiniGames::Dodge => DEMO_JUMPS
+            .iter()
+            .flat_map(|&jump| {
+                [
+                    (jump, held(false, false, false)),
+                    (jump + 0.3, held(false, false, true)),
+                ]
+            })
+            .collect(),
+        MiniGames::FloorIsLava => vec![
+            (1.0, held(false, false, false)),
+            (1.3, held(false, false, true)),
+            (2.5, held(false, true, false)),
+            (2.8, held(false, true, true)),
+            (4.0, held(true, false, false)),
+            (4.3, held(true, false, true)),
+            (5.5, held(false, false, false)),
+            (5.8, held(false, false, true)),
+        ],
+    }
+}
+
+fn input_at(script: &[(f32, PlayerInput)], time: f32) -> PlayerInput {
+    script
+        .iter()
+        .find(|(until, _)| time < *until)
+        .map(|(_, input)| *input)
+        .unwrap_or_default()
+}
+
+pub fn goal(game: MiniGames) -> &'static str {
+    match game {
+        MiniGames::ColorTheMap => "Touch floors and walls to paint them in your colour",
+        MiniGames::Dodge => {
+            "Jump over the bullets, one hit knocks you out. Hold and let go of action to throw"
+        }
+        MiniGames::FloorIsLava => "Keep moving and jump from platform to platform",
+    }
+}
+
+// Key names for move left, move right and jump
+fn control_labels(controls: &InputType) -> [&'static str; 3] {
+    match controls {
+        InputType::Keyboard(KeyboardControls::WASD) => ["A", "D", "W"],
+        InputType::Keyboard(KeyboardControls::ArrowKeys) => ["Left", "Right", "Up"],
+        InputType::Controller(_) => ["D-pad left", "D-pad right", "D-pad up"],
+    }
+}
+
+fn bullet_at(y: f32) -> Bullet {
+    Bullet {
+        rect: Rectangle::new(-20.0, y, 15.0, 30.0),
+        color: Color::PINK,
+        speed: Vector2::new(BULLET_SPEED, 0.0),
+        gravity: 0.0,
+        time_to_live: 10.0,
+        source: BulletSource::Wave(1),
+    }
+}
+
+// Runs the ghost's script through the player physics and records it
+fn record_demo(game: MiniGames, look: &Player, ops: &Vec<EnvItem>) -> Replay {
+    let script = script(game);
+    let mut ghost = look.clone();
+    ghost.respawn(GHOST_START);
+    ghost.invulnerable = 0.0;
+    ghost.hit_flash = 0.0;
+    ghost.spawn_protection = 0.0;
+    // a bullet reaches the ghost as it tops out its jump
+    let travel = (GHOST_START.x + 20.0) / BULLET_SPEED;
+    let mut bullet_times: Vec<f32> = match game {
+        MiniGames::Dodge => DEMO_JUMPS.iter().map(|t| t + JUMP_PEAK - travel).collect(),
+        _ => Vec::new(),
+    };
+    let mut bullets = Vec::new();
+    let mut recorder = ReplayRecorder::default();
+    let mut time = 0.0;
+    while time < DEMO_LENGTH {
+        ghost.update(input_at(&script, time), DEMO_STEP);
+        let contacts = ghost.handle_collision(ops, Vec::new());
+        if contacts.is_empty() {
+            ghost.is_on_ground = false;
+        }
+        if game == MiniGames::ColorTheMap {
+            for point in contacts.into_iter().flat_map(|contact| contact.points) {
+                recorder.splat(0, point, ghost.paint_radius);
+            }
+        }
+        if bullet_times.first().is_some_and(|t| time >= *t) {
+            bullet_times.remove(0);
+            bullets.push(bullet_at(ghost.position.y - 5.0));
+        }
+        for bullet in &mut bullets {
+            bullet.step(DEMO_STEP);
+        }
+        recorder.record(
+            DEMO_STEP,
+            game,
+            DEMO_LENGTH - time,
+            &None,
+            std::slice::from_ref(&ghost),
+            &bullets,
+        );
+        time += DEMO_STEP;
+    }
+    recorder.finish(
+        0,
+        None,
+        vec!["Ghost".to_string()],
+        std::slice::from_ref(&ghost),
+    )
+}
+
+pub struct Tutorial {
+    pub game: MiniGames,
+    demo: ReplayViewer,
+    bullets: Vec<Bullet>,
+    wave_timer: f32,
+    // last height the player stood at, Dodge aims its bullets there
+    lane: f32,
+    painted: HashSet<(i32, i32)>,
+    dodged: usize,
+    jumps: usize,
+    pub done: bool,
+}
+
+impl Tutorial {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        game: MiniGames,
+        ghost_look: &Player,
+        player: &mut Player,
+        ops: &Vec<EnvItem>,
+    ) -> Result<Tutorial, String> {
+        let demo = ReplayViewer::new(rl, thread, record_demo(game, ghost_look, ops))?;
+        player.respawn(PLAYER_START);
+        player.spawn_protection = 0.0;
+        player.paint_radius = PAINT_RADIUS;
+        Ok(Tutorial {
+            game,
+            demo,
+            bullets: Vec::new(),
+            wave_timer: WAVE_INTERVAL,
+            lane: PLAYER_START.y,
+            painted: HashSet::new(),
+            dodged: 0,
+            jumps: 0,
+            done: false,
+        })
+    }
+
+    // Steps the demo and the player; paint goes on `paint`, like in a round
+    pub fn update(
+        &mut self,
+        rl: &RaylibHandle,
+        player: &mut Player,
+        ops: &Vec<EnvItem>,
+        paint: &mut Image,
+        dt: f32,
+    ) {
+        self.demo.seek(self.demo.clock + dt);
+        if self.demo.clock >= self.demo.replay.duration() {
+            self.demo.seek(0.0);
+        }
+
+        if player.dead {
+            player.respawn(PLAYER_START);
+            self.bullets.clear();
+            self.dodged = 0;
+        }
+        let was_jumping = player.is_jumping;
+        player.update(player.read_input(rl), dt);
+        if player.is_jumping && !was_jumping {
+            self.jumps += 1;
+        }
+        let contacts = player.handle_collision(ops, Vec::new());
+        if contacts.is_empty() {
+            player.is_on_ground = false;
+        }
+        if player.is_on_ground {
+            self.lane = player.position.y;
+        }
+        for point in contacts.into_iter().flat_map(|contact| contact.points) {
+            if self.game == MiniGames::ColorTheMap {
+                player.paint(paint, point);
+                self.painted
+                    .insert(((point.x / PAINT_CELL) as i32, (point.y / PAINT_CELL) as i32));
+            }
+        }
+        player.tick_timers(dt);
+
+        if self.game == MiniGames::Dodge {
+            self.wave_timer -= dt;
+            if self.wave_timer <= 0.0 {
+                self.wave_timer = WAVE_INTERVAL;
+                self.bullets.push(bullet_at(self.lane - 5.0));
+            }
+            for bullet in &mut self.bullets {
+                bullet.step(dt);
+                if player
+                    .get_collision_rect()
+                    .check_collision_recs(&bullet.rect)
+                    && player.hit()
+                {
+                    bullet.time_to_live = 0.0;
+                }
+                if bullet.rect.x > SCREEN_WIDTH as f32 {
+                    bullet.time_to_live = 0.0;
+                    self.dodged += 1;
+                }
+            }
+            self.bullets.retain(|bullet| bullet.time_to_live > 0.0);
+        }
+
+        let (count, goal) = self.progress();
+        if count >= goal {
+            self.done = true;
+        }
+    }
+
+    fn progress(&self) -> (usize, usize) {
+        match self.game {
+            MiniGames::ColorTheMap => (self.painted.len(), PAINT_GOAL),
+            MiniGames::Dodge => (self.dodged, DODGE_GOAL),
+            MiniGames::FloorIsLava => (self.jumps, JUMP_GOAL),
+        }
+    }
+
+    // The demo's level, paint, ghost and bullets, drawn by the replay viewer
+    pub fn draw_demo(
+        &self,
+        d: &mut RaylibDrawHandle,
+        viewport: &Viewport,
+        level_texture: &Texture2D,
+        ops: &[EnvItem],
+        ghost_look: &Player,
+    ) {
+        self.demo.draw_world(
+            d,
+            viewport,
+            level_texture,
+            ops,
+            std::slice::from_ref(ghost_look),
+        );
+    }
+
+    pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, player: &Player) {
+        for bullet in &self.bullets {
+            d.draw_rectangle_rec(bullet.rect, bullet.color);
+        }
+        player.draw(d);
+
+        let ghost = &self.demo.current().players[0];
+        d.draw_text(
+            "ghost",
+            ghost.position.x as i32 - d.measure_text("ghost", 15) / 2,
+            ghost.position.y as i32 - 50,
+            15,
+            Color::DARKGRAY,
+        );
+
+        let title = format!("{} tutorial", self.game.name());
+        d.draw_text(
+            &title,
+            SCREEN_WIDTH / 2 - d.measure_text(&title, 35) / 2,
+            40,
+            35,
+            Color::BLACK,
+        );
+        let text = goal(self.game);
+        d.draw_text(
+            text,
+            SCREEN_WIDTH / 2 - d.measure_text(text, 20) / 2,
+            85,
+            20,
+            Color::BLACK,
+        );
+        let (count, goal) = self.progress();
+        let status = if self.done {
+            "Done!".to_string()
+        } else {
+            format!("{} / {}", count.min(goal), goal)
+        };
+        d.draw_text(
+            &status,
+            SCREEN_WIDTH / 2 - d.measure_text(&status, 25) / 2,
+            115,
+            25,
+            if self.done {
+                Color::DARKGREEN
+            } else {
+                Color::DARKGRAY
+            },
+        );
+
+        // a key lights up while the ghost or the player holds it
+        let labels = control_labels(&player.controls);
+        let used = [
+            ghost.input.left || player.input.left,
+            ghost.input.right || player.input.right,
+            ghost.input.up || player.input.up,
+        ];
+        let names = ["move left", "move right", "jump"];
+        for (i, ((label, name), lit)) in labels.iter().zip(names).zip(used).enumerate() {
+            let y = SCREEN_HEIGHT - 170 + i as i32 * 40;
+            let width = d.measure_text(label, 20) + 20;
+            let fill = if lit { Color::GOLD } else { Color::LIGHTGRAY };
+            d.draw_rectangle(30, y, width, 32, fill);
+            d.draw_rectangle_lines(30, y, width, 32, Color::BLACK);
+            d.draw_text(label, 40, y + 6, 20, Color::BLACK);
+            d.draw_text(name, 40 + width, y + 6, 20, Color::BLACK);
+        }
+    }
+}
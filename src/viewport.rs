@@ -0,0 +1,67 @@
+// The game is laid out on a fixed virtual screen of SCREEN_WIDTH x SCREEN_HEIGHT.
+// Viewport places that screen inside the real window and converts between
+// window, virtual and world coordinates. It also sets raylib's mouse
+// offset/scale, so mouse positions (and raygui hit-tests) come out in virtual
+// coordinates.
+use raylib::prelude::*;
+
+pub const SCREEN_WIDTH: i32 = 1200;
+pub const SCREEN_HEIGHT: i32 = 650;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub window: Vector2,
+    pub offset: Vector2, // window position of the virtual screen's top-left corner
+    pub scale: f32,      // window pixels per virtual pixel
+}
+
+impl Viewport {
+    pub fn size() -> Vector2 {
+        Vector2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32)
+    }
+
+    pub fn new(rl: &mut RaylibHandle) -> Self {
+        let mut viewport = Viewport {
+            window: Viewport::size(),
+            offset: Vector2::zero(),
+            scale: 1.0,
+        };
+        viewport.update(rl);
+        viewport
+    }
+
+    // Re-fits to the current window size; call once per frame before input
+    pub fn update(&mut self, rl: &mut RaylibHandle) {
+        self.window = Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+        // drawn 1:1 and centred, a bigger window just shows more border
+        self.scale = 1.0;
+        self.offset = (self.window - Viewport::size() * self.scale) * 0.5;
+        rl.set_mouse_offset(Vector2::new(-self.offset.x, -self.offset.y));
+        rl.set_mouse_scale(1.0 / self.scale, 1.0 / self.scale);
+    }
+
+    // Camera that draws virtual coordinates into the window
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            offset: self.offset,
+            target: Vector2::zero(),
+            rotation: 0.0,
+            zoom: self.scale,
+        }
+    }
+
+    pub fn virtual_to_window(&self, point: Vector2) -> Vector2 {
+        point * self.scale + self.offset
+    }
+
+    // Camera looking at `target` in world space from the middle of the virtual
+    // screen, e.g. the replay viewer's free camera
+    pub fn world_camera(&self, target: Vector2, zoom: f32) -> Camera2D {
+        Camera2D {
+            offset: self.virtual_to_window(Viewport::size() * 0.5),
+            target,
+            rotation: 0.0,
+            zoom: zoom * self.scale,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Window chrome: icon, a title that follows the match, and asking for
+// attention when a round ends while the window is in the background.
+use crate::{assets, MiniGames, Player};
+use raylib::prelude::*;
+
+pub const BASE_TITLE: &str = "Color The Map";
+const ICON_SIZE: i32 = 64;
+// shown in front of the title until the window is focused again
+const ATTENTION_MARKER: &str = "(!) ";
+
+pub fn set_icon(rl: &mut RaylibHandle) {
+    let mut icon = assets::load_image("player1.png");
+    icon.resize(ICON_SIZE, ICON_SIZE);
+    rl.set_window_icon(&icon);
+}
+
+// e.g. "Color The Map — Round 4 — P2 leads"
+pub fn match_title(game: MiniGames, round: usize, players: &[Player]) -> String {
+    let best = players.iter().map(|p| p.points).max().unwrap_or(0);
+    let leaders: Vec<&Player> = players.iter().filter(|p| p.points == best).collect();
+    let standing = match leaders.as_slice() {
+        _ if best == 0 => "no score yet".to_string(),
+        [leader] => format!("P{} leads", leader.number + 1),
+        _ => "tied".to_string(),
+    };
+    format!("{} — Round {} — {}", game.name(), round, standing)
+}
+
+#[derive(Default)]
+pub struct WindowTitle {
+    current: String,
+    attention: bool,
+}
+
+impl WindowTitle {
+    // Only touches the OS window when the text actually changes
+    pub fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, title: &str) {
+        if self.attention && rl.is_window_focused() {
+            self.attention = false;
+        }
+        let title = if self.attention {
+            format!("{}{}", ATTENTION_MARKER, title)
+        } else {
+            title.to_string()
+        };
+        if title != self.current {
+            rl.set_window_title(thread, &title);
+            self.current = title;
+        }
+    }
+
+    pub fn request_attention(&mut self, rl: &RaylibHandle) {
+        if rl.is_window_focused() {
+            return;
+        }
+        self.attention = true;
+        flash_taskbar();
+    }
+}
+
+#[cfg(windows)]
+fn flash_taskbar() {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct FlashWInfo {
+        cb_size: u32,
+        hwnd: *mut c_void,
+        flags: u32,
+        count: u32,
+        timeout: u32,
+    }
+    const FLASHW_ALL: u32 = 0x3;
+    const FLASHW_TIMERNOFG: u32 = 0xC;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn FlashWindowEx(info: *const FlashWInfo) -> i32;
+    }
+
+    let info = FlashWInfo {
+        cb_size: std::mem::size_of::<FlashWInfo>() as u32,
+        hwnd: unsafe { raylib::ffi::GetWindowHandle() },
+        flags: FLASHW_ALL | FLASHW_TIMERNOFG,
+        count: 0,
+        timeout: 0,
+    };
+    unsafe {
+        FlashWindowEx(&info);
+    }
+}
+
+// raylib has no portable attention request, elsewhere the title marker has to do
+#[cfg(not(windows))]
+fn flash_taskbar() {}